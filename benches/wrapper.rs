@@ -0,0 +1,214 @@
+//! Wrapper overhead and tracking throughput
+//!
+//! The wrapper benchmarks never call into cuVSLAM. Tracking benchmarks need a GPU and are only
+//! run when `CUVSLAM_BENCH_GPU` is set:
+//!
+//! ```sh
+//! CUVSLAM_BENCH_GPU=1 cargo bench --bench wrapper
+//! ```
+
+#[path = "../tests/support/mod.rs"]
+mod support;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use cuvslam::{
+    Image, ImageEncoding, PoseEstimate, Preprocessor, RawFormat, RawFrame, Tracker, CUVSLAM_Image, CUVSLAM_Pose,
+    CUVSLAM_PoseEstimate,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// System allocator that counts allocations
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Print the average number of allocations made by one call of `f`
+fn report_allocations(name: &str, mut f: impl FnMut()) {
+    const ITERATIONS: usize = 100;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{name}: {:.2} allocations/iter", allocations as f64 / ITERATIONS as f64);
+}
+
+const WIDTH: i32 = 640;
+const HEIGHT: i32 = 480;
+
+fn image_benchmarks(c: &mut Criterion) {
+    let pixels = support::textured_frame(WIDTH, HEIGHT, 0);
+
+    c.bench_function("image_new", |b| {
+        b.iter_batched(
+            || pixels.clone(),
+            |pixels| Image::new(pixels, WIDTH, HEIGHT, ImageEncoding::Mono8, 0, 0).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let image = Image::new(pixels.clone(), WIDTH, HEIGHT, ImageEncoding::Mono8, 0, 0).unwrap();
+    c.bench_function("image_crop", |b| {
+        b.iter(|| image.crop(black_box(80), black_box(60), 480, 360).unwrap())
+    });
+    report_allocations("image_crop", || {
+        black_box(image.crop(80, 60, 480, 360).unwrap());
+    });
+
+    let [left, right] = support::stereo_frame(WIDTH, HEIGHT, 0, 8, 0);
+    c.bench_function("image_as_inner", |b| {
+        b.iter(|| -> [CUVSLAM_Image; 2] { [*black_box(&left).as_inner(), *black_box(&right).as_inner()] })
+    });
+}
+
+fn preprocess_benchmarks(c: &mut Criterion) {
+    // Four 1280x800 Bayer cameras, halved to 640x400 mono
+    let mosaics: Vec<_> = (0..4).map(|i| support::textured_frame(1280, 800, i)).collect();
+    let frames: Vec<_> = mosaics
+        .iter()
+        .enumerate()
+        .map(|(i, pixels)| RawFrame {
+            pixels,
+            width: 1280,
+            height: 800,
+            pitch: 1280,
+            format: RawFormat::BayerRggb8,
+            camera_index: i as i32,
+            timestamp_ns: 0,
+        })
+        .collect();
+
+    for (name, parallel) in [("preprocess_4x1280x800_serial", false), ("preprocess_4x1280x800_parallel", true)] {
+        let mut preprocessor = Preprocessor::new(2, parallel);
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let outputs = preprocessor.process(black_box(&frames)).unwrap();
+                preprocessor.recycle(outputs.into_iter().map(|(image, _)| image));
+            })
+        });
+    }
+}
+
+fn pose_benchmarks(c: &mut Criterion) {
+    // Quarter turn about z, as returned by cuVSLAM (column-major rotation)
+    let raw = CUVSLAM_PoseEstimate {
+        pose: CUVSLAM_Pose {
+            r: [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            t: [1.0, 2.0, 3.0],
+        },
+        timestamp_ns: 33_333_333,
+        covariance: [0.01; 36],
+    };
+    let prev = PoseEstimate {
+        timestamp_ns: 0,
+        ..PoseEstimate::from(raw)
+    };
+
+    c.bench_function("pose_from_raw", |b| b.iter(|| PoseEstimate::from(black_box(raw))));
+    c.bench_function("pose_to_affine_matrix", |b| {
+        let pose = PoseEstimate::from(raw);
+        b.iter(|| black_box(&pose).to_affine_matrix_4x4())
+    });
+    c.bench_function("pose_velocity", |b| {
+        let curr = PoseEstimate::from(raw);
+        b.iter(|| cuvslam::velocity(black_box(&prev), black_box(&curr)))
+    });
+    report_allocations("pose_from_raw", || {
+        black_box(PoseEstimate::from(black_box(raw)));
+    });
+}
+
+fn track_benchmarks(c: &mut Criterion) {
+    if std::env::var_os("CUVSLAM_BENCH_GPU").is_none() {
+        return;
+    }
+
+    for (width, height) in [(640, 480), (1280, 800)] {
+        // Pre-render a short sequence so frame generation stays out of the measurement
+        let frames: Vec<_> = (0..32)
+            .map(|i| support::stereo_frame(width, height, i, 8, 0))
+            .collect();
+
+        // Same frames in page-locked memory, to compare against pageable uploads
+        #[cfg(feature = "cuda")]
+        {
+            let pinned = frames.iter().map(|frame| frame.each_ref().map(pin)).collect();
+            bench_track(c, &format!("track_{width}x{height}_pinned"), width, height, pinned);
+        }
+
+        bench_track(c, &format!("track_{width}x{height}"), width, height, frames);
+    }
+}
+
+#[cfg(feature = "cuda")]
+fn pin(image: &Image) -> Image {
+    Image::new_pinned(
+        cuvslam::PinnedBuffer::from_slice(image.pixels()),
+        image.width(),
+        image.height(),
+        image.pitch(),
+        image.encoding(),
+        image.camera_index(),
+        image.timestamp_ns(),
+    )
+    .unwrap()
+}
+
+/// Measure `track` latency on a fresh tracker, cycling through `frames`
+fn bench_track(c: &mut Criterion, name: &str, width: i32, height: i32, frames: Vec<[Image; 2]>) {
+    let tracker = Tracker::new(support::stereo_rig(width, height), &cuvslam::init_default_configuration())
+        .expect("tracker creation failed");
+
+    let mut frame = 0usize;
+    let mut timestamp_ns = 0i64;
+    let mut next_images = move || {
+        let [left, right] = &frames[frame % frames.len()];
+        frame += 1;
+        timestamp_ns += 33_333_333;
+        let mut images = [*left.as_inner(), *right.as_inner()];
+        for image in &mut images {
+            image.timestamp_ns = timestamp_ns;
+        }
+        images
+    };
+
+    let mut estimate = PoseEstimate {
+        pose: CUVSLAM_Pose { r: [0.0; 9], t: [0.0; 3] },
+        timestamp_ns: 0,
+        covariance: [0.0; 36],
+    };
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            let images = next_images();
+            // The raw views point into `frames`, which outlive the call
+            let _ = unsafe { tracker.track_raw_into(&images, None, &mut estimate) };
+        })
+    });
+    report_allocations(name, || {
+        let images = next_images();
+        let _ = unsafe { tracker.track_raw_into(&images, None, &mut estimate) };
+    });
+}
+
+criterion_group!(benches, image_benchmarks, preprocess_benchmarks, pose_benchmarks, track_benchmarks);
+criterion_main!(benches);
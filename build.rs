@@ -1,4 +1,14 @@
 fn main() {
     println!("cargo:rustc-link-search=native=lib");
-    println!("cargo:rustc-link-lib=cuvslam");
-}
\ No newline at end of file
+    // With runtime loading the library is opened with dlopen instead of linked
+    if std::env::var_os("CARGO_FEATURE_RUNTIME_LOADING").is_none() {
+        println!("cargo:rustc-link-lib=cuvslam");
+    }
+
+    if std::env::var_os("CARGO_FEATURE_CUDA").is_some() {
+        let cuda_path = std::env::var("CUDA_PATH").unwrap_or_else(|_| "/usr/local/cuda".to_string());
+        println!("cargo:rerun-if-env-changed=CUDA_PATH");
+        println!("cargo:rustc-link-search=native={}/lib64", cuda_path);
+        println!("cargo:rustc-link-lib=cudart");
+    }
+}
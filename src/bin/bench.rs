@@ -0,0 +1,214 @@
+//! Track a sequence as fast as possible and report throughput, latency and memory use
+//!
+//! ```sh
+//! cargo run --release --features datasets --bin bench -- <sequence dir> [--kitti | --tum-vi] [--frames 500] [--json]
+//! cargo run --release --features datasets --bin bench -- --synthetic [--frames 300] [--json]
+//! ```
+//!
+//! The sequence is a EuRoC directory unless `--kitti` or `--tum-vi` says otherwise; `--synthetic`
+//! plays `SyntheticSource` frames instead, so the report runs without any dataset present.
+//! `--frames` stops after that many frames. Frames are tracked back to back with no pacing.
+//!
+//! The report gives the frames played, how many were tracked and dropped (failed to track), the
+//! wall time and frame rate, the mean, p50, p95 and p99 latency of the `track` calls alone, and
+//! the peak process RSS and, with the `cuda` feature, peak GPU memory in use. Memory is sampled
+//! every 100 ms and once more at the end, so peaks between samples can be missed. `--json`
+//! prints the report as one JSON object, for CI to keep and diff between runs.
+
+use cuvslam::datasets::{euroc::EurocSequence, kitti::KittiSequence, tum_vi, StereoSource};
+use cuvslam::{
+    init_default_configuration, FrameSource, LatencyStats, LatencyTracker, MemoryReport, MemorySampler, SyntheticSource,
+    Tracker, CUVSLAM_Configuration,
+};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Frames played by `--synthetic` without `--frames`
+const SYNTHETIC_FRAMES: usize = 300;
+
+/// Time between memory samples
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+enum Input {
+    Euroc(PathBuf),
+    Kitti(PathBuf),
+    TumVi(PathBuf),
+    Synthetic,
+}
+
+struct Options {
+    input: Input,
+    frames: Option<usize>,
+    json: bool,
+}
+
+/// Measurements of one run
+struct Report {
+    frames: usize,
+    tracked: usize,
+    wall_time: Duration,
+    latency: Option<LatencyStats>,
+    peak_rss_bytes: Option<u64>,
+    peak_gpu_used_bytes: Option<u64>,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("usage: bench (<sequence dir> [--kitti | --tum-vi] | --synthetic) [--frames n] [--json]");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&options) {
+        Ok(report) if options.json => {
+            println!("{}", report.to_json());
+            ExitCode::SUCCESS
+        }
+        Ok(report) => {
+            report.print();
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let (mut sequence, mut kitti, mut tum_vi, mut synthetic) = (None, false, false, false);
+    let mut options = Options { input: Input::Synthetic, frames: None, json: false };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => {
+                let frames = args.next().ok_or("--frames needs a value")?;
+                let count = frames.parse().map_err(|_| format!("--frames expects a count, got {}", frames))?;
+                options.frames = Some(count);
+            }
+            "--json" => options.json = true,
+            "--kitti" => kitti = true,
+            "--tum-vi" => tum_vi = true,
+            "--synthetic" => synthetic = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if sequence.is_none() => sequence = Some(PathBuf::from(path)),
+            path => return Err(format!("unexpected argument {}", path)),
+        }
+    }
+    options.input = match (sequence, synthetic) {
+        (None, true) => Input::Synthetic,
+        (Some(_), true) => return Err("--synthetic takes no sequence".to_string()),
+        (None, false) => return Err("missing sequence directory".to_string()),
+        (Some(_), false) if kitti && tum_vi => return Err("--kitti and --tum-vi are exclusive".to_string()),
+        (Some(dir), false) if kitti => Input::Kitti(dir),
+        (Some(dir), false) if tum_vi => Input::TumVi(dir),
+        (Some(dir), false) => Input::Euroc(dir),
+    };
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<Report, Box<dyn Error>> {
+    let limit = options.frames.unwrap_or(usize::MAX);
+    match &options.input {
+        Input::Synthetic => {
+            let source = SyntheticSource::new(options.frames.unwrap_or(SYNTHETIC_FRAMES));
+            bench(source, &init_default_configuration())
+        }
+        Input::Kitti(dir) => {
+            let sequence = KittiSequence::open(dir)?;
+            let frames = &sequence.frames[..limit.min(sequence.frames.len())];
+            bench(StereoSource::new(frames, &sequence.calibration), &sequence.configuration())
+        }
+        Input::Euroc(dir) | Input::TumVi(dir) => {
+            let sequence = match options.input {
+                Input::TumVi(_) => tum_vi::open(dir)?,
+                _ => EurocSequence::open(dir)?,
+            };
+            let frames = &sequence.frames[..limit.min(sequence.frames.len())];
+            bench(StereoSource::new(frames, &sequence.calibration), &sequence.configuration(false))
+        }
+    }
+}
+
+/// Track every frame of `source` back to back
+fn bench(mut source: impl FrameSource, config: &CUVSLAM_Configuration) -> Result<Report, Box<dyn Error>> {
+    let rig = source.calibration().ok_or("the source has no calibration")?;
+    let tracker = Tracker::new(rig, config)?;
+    // Keep every frame's latency, so the percentiles cover the whole run
+    let mut latency = LatencyTracker::new(source.len_hint().unwrap_or(100_000));
+    let mut sampler = MemorySampler::new(MEMORY_SAMPLE_INTERVAL);
+    let mut report = Report {
+        frames: 0,
+        tracked: 0,
+        wall_time: Duration::ZERO,
+        latency: None,
+        peak_rss_bytes: None,
+        peak_gpu_used_bytes: None,
+    };
+
+    let start = Instant::now();
+    while let Some(frame) = source.next_frame() {
+        let frame = frame?;
+        let track_start = Instant::now();
+        let result = tracker.track(&frame.images, None);
+        latency.record_duration(track_start.elapsed());
+        report.frames += 1;
+        report.tracked += usize::from(result.is_ok());
+        if let Some(sample) = sampler.poll(Instant::now(), || tracker.memory_report()) {
+            report.add_memory_sample(&sample?.0);
+        }
+    }
+    report.wall_time = start.elapsed();
+    report.add_memory_sample(&tracker.memory_report()?);
+    report.latency = latency.stats();
+    Ok(report)
+}
+
+impl Report {
+    fn add_memory_sample(&mut self, sample: &MemoryReport) {
+        self.peak_rss_bytes = self.peak_rss_bytes.max(sample.process_rss_bytes);
+        self.peak_gpu_used_bytes = self.peak_gpu_used_bytes.max(sample.gpu_used_bytes());
+    }
+
+    fn fps(&self) -> f64 {
+        self.frames as f64 / self.wall_time.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    fn print(&self) {
+        let mib = |bytes: Option<u64>| {
+            bytes.map_or("n/a".to_string(), |bytes| format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)))
+        };
+        println!("frames:     {} ({} tracked, {} dropped)", self.frames, self.tracked, self.frames - self.tracked);
+        println!("wall time:  {:.2?} ({:.1} fps)", self.wall_time, self.fps());
+        if let Some(latency) = &self.latency {
+            println!(
+                "latency:    mean {:.2?}, p50 {:.2?}, p95 {:.2?}, p99 {:.2?}",
+                latency.mean, latency.p50, latency.p95, latency.p99
+            );
+        }
+        println!("peak RSS:   {}", mib(self.peak_rss_bytes));
+        println!("peak GPU:   {}", mib(self.peak_gpu_used_bytes));
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1e3;
+        serde_json::json!({
+            "frames": self.frames,
+            "tracked": self.tracked,
+            "dropped": self.frames - self.tracked,
+            "wall_time_s": self.wall_time.as_secs_f64(),
+            "fps": self.fps(),
+            "latency_ms": self.latency.map(|latency| serde_json::json!({
+                "mean": ms(latency.mean),
+                "p50": ms(latency.p50),
+                "p95": ms(latency.p95),
+                "p99": ms(latency.p99),
+            })),
+            "peak_rss_bytes": self.peak_rss_bytes,
+            "peak_gpu_used_bytes": self.peak_gpu_used_bytes,
+        })
+    }
+}
@@ -0,0 +1,15 @@
+//! Check that cuVSLAM is installed and working, without any cameras attached
+//!
+//! Exits with a non-zero status if any stage fails.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let report = cuvslam::self_test();
+    println!("{}", report);
+    if report.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
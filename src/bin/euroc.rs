@@ -0,0 +1,434 @@
+//! Track a EuRoC MAV or TUM-VI sequence and evaluate the trajectory against its ground truth
+//!
+//! ```sh
+//! cargo run --release --features datasets --bin euroc -- MH_01_easy [--start 5] [--end 60] [--rate 1] [--imu]
+//!     [--start-frame 1390] [--end-frame 1410] [--interactive] [--output trajectory.tum] [--tum-vi]
+//!     [--ground-truth groundtruth.tum] [--build-map db | --localize db] [--deterministic]
+//! ```
+//!
+//! `--start` and `--end` are seconds from the first frame, and `--start-frame` and `--end-frame`
+//! frame numbers, both inclusive. Frames are played as fast as the tracker allows unless `--rate`
+//! (or its older name `--speed`) gives a multiple of real time. `--interactive` reads keys from the
+//! terminal: space pauses and resumes, `n` steps one frame while paused and `q` stops. `--tum-vi`
+//! reads the directory as a TUM-VI sequence (`dataset-room1_512_16`) instead. The body trajectory
+//! is written in TUM format and, when the sequence has ground truth, its ATE is printed.
+//! `--deterministic` configures cuVSLAM with `ConfigurationBuilder::deterministic`, so repeated
+//! runs over the same frames give the same trajectory.
+//!
+//! `--ground-truth` evaluates against a TUM trajectory file instead of the sequence's own ground
+//! truth, and shows the ATE live in Rerun as the sequence plays: the error of each pose, and the
+//! estimated and aligned ground truth trajectories, realigned every 10 matched poses.
+//!
+//! `--build-map` and `--localize` check map saving and relocalization end to end in two runs.
+//! `--build-map db` tracks the first half of the selected frames with mapping on, saves the SLAM
+//! database to `db` and the last rig pose next to it. `--localize db` tracks the second half: it
+//! relocalizes in the saved map at its first frame, searching around that saved pose, reports
+//! whether that succeeded and how long it took, and continues tracking in the map's frame, so the
+//! ATE printed at the end is the error after relocalization.
+
+use cuvslam::datasets::{euroc::EurocSequence, playback::PlaybackClock, tum_vi, StereoSource};
+use cuvslam::{
+    absolute_trajectory_error, read_kitti, read_tum_positions, write_kitti, write_tum, ConfigurationBuilder,
+    FrameSource, PoseEstimate, RunningAte, Tracker,
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, Stdio};
+use std::sync::mpsc;
+use std::time::Instant;
+
+/// EuRoC ground truth is at 200 Hz and TUM-VI's at 120 Hz, so any frame has a sample within 4.2ms
+const MAX_GROUND_TRUTH_OFFSET_NS: i64 = 5_000_000;
+
+/// Matched poses between realignments of the live ATE
+const REALIGN_EVERY: usize = 10;
+
+/// Distance from the saved pose that `--localize` searches the map within
+const LOCALIZATION_RADIUS_M: f32 = 1.0;
+
+/// File in the map directory holding the rig pose of the last frame `--build-map` tracked
+const HANDOFF_POSE_FILE: &str = "handoff_pose.txt";
+
+/// Half of a two-run map building and relocalization check
+enum Phase {
+    /// Track the first half of the frames and save the map to the directory
+    BuildMap(PathBuf),
+    /// Relocalize in the map in the directory and track the second half
+    Localize(PathBuf),
+}
+
+struct Options {
+    sequence: PathBuf,
+    start_s: f64,
+    end_s: f64,
+    start_frame: usize,
+    end_frame: usize,
+    rate: Option<f64>,
+    interactive: bool,
+    imu: bool,
+    output: PathBuf,
+    tum_vi: bool,
+    ground_truth: Option<PathBuf>,
+    phase: Option<Phase>,
+    deterministic: bool,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!(
+                "usage: euroc <sequence dir> [--start s] [--end s] [--start-frame n] [--end-frame n] [--rate x] \
+                 [--interactive] [--imu] [--output trajectory.tum] [--tum-vi] [--ground-truth groundtruth.tum] \
+                 [--build-map db | --localize db] [--deterministic]"
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut sequence = None;
+    let mut options = Options {
+        sequence: PathBuf::new(),
+        start_s: 0.0,
+        end_s: f64::INFINITY,
+        start_frame: 0,
+        end_frame: usize::MAX,
+        rate: None,
+        interactive: false,
+        imu: false,
+        output: PathBuf::from("trajectory.tum"),
+        tum_vi: false,
+        ground_truth: None,
+        phase: None,
+        deterministic: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        let number = |name: &str, value: String| {
+            value.parse::<f64>().map_err(|_| format!("{} expects a number, got {}", name, value))
+        };
+        let frame = |name: &str, value: String| {
+            value.parse::<usize>().map_err(|_| format!("{} expects a frame number, got {}", name, value))
+        };
+        match arg.as_str() {
+            "--start" => options.start_s = number("--start", value("--start")?)?,
+            "--end" => options.end_s = number("--end", value("--end")?)?,
+            "--start-frame" => options.start_frame = frame("--start-frame", value("--start-frame")?)?,
+            "--end-frame" => options.end_frame = frame("--end-frame", value("--end-frame")?)?,
+            "--rate" | "--speed" => match number(&arg, value(&arg)?)? {
+                rate if rate > 0.0 => options.rate = Some(rate),
+                _ => return Err(format!("{} needs a positive number", arg)),
+            },
+            "--interactive" => options.interactive = true,
+            "--imu" => options.imu = true,
+            "--deterministic" => options.deterministic = true,
+            "--output" => options.output = PathBuf::from(value("--output")?),
+            "--tum-vi" => options.tum_vi = true,
+            "--ground-truth" => options.ground_truth = Some(PathBuf::from(value("--ground-truth")?)),
+            "--build-map" | "--localize" if options.phase.is_some() => {
+                return Err("--build-map and --localize are separate runs".to_string())
+            }
+            "--build-map" => options.phase = Some(Phase::BuildMap(PathBuf::from(value("--build-map")?))),
+            "--localize" => options.phase = Some(Phase::Localize(PathBuf::from(value("--localize")?))),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path => sequence = Some(PathBuf::from(path)),
+        }
+    }
+    options.sequence = sequence.ok_or("missing sequence directory")?;
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let sequence = if options.tum_vi {
+        tum_vi::open(&options.sequence)?
+    } else {
+        EurocSequence::open(&options.sequence)?
+    };
+    let first_ns = sequence.frames.first().ok_or("sequence has no stereo frames")?.timestamp_ns;
+    let seconds = |timestamp_ns: i64| (timestamp_ns - first_ns) as f64 * 1e-9;
+    let start = sequence.frames.partition_point(|frame| seconds(frame.timestamp_ns) < options.start_s);
+    let end = sequence.frames.partition_point(|frame| seconds(frame.timestamp_ns) <= options.end_s);
+    let start = start.max(options.start_frame);
+    let end = end.min(options.end_frame.saturating_add(1));
+    let frames = &sequence.frames[start.min(end)..end];
+    let frames = match options.phase {
+        Some(Phase::BuildMap(_)) => &frames[..frames.len() / 2],
+        Some(Phase::Localize(_)) => &frames[frames.len() / 2..],
+        None => frames,
+    };
+    let Some(start_ns) = frames.first().map(|frame| frame.timestamp_ns) else {
+        return Err("no frames in the selected range".into());
+    };
+
+    let use_imu = options.imu && sequence.imu_calibration.is_some();
+    if options.imu && !use_imu {
+        eprintln!("sequence has no imu0, tracking without IMU");
+    }
+    let ground_truth = match &options.ground_truth {
+        Some(path) => read_tum_positions(BufReader::new(File::open(path)?))
+            .map_err(|error| format!("{}: {}", path.display(), error))?,
+        None => sequence.ground_truth.clone(),
+    };
+    let mut live = match options.ground_truth {
+        Some(_) => Some(LiveAte::new(ground_truth.clone())?),
+        None => None,
+    };
+    let mut source = StereoSource::new(frames, &sequence.calibration);
+    let mut config = ConfigurationBuilder::from(sequence.configuration(use_imu))
+        .deterministic(options.deterministic)
+        .build();
+    if options.phase.is_some() {
+        config.enable_localization_n_mapping = 1;
+    }
+    let guess_pose = match &options.phase {
+        Some(Phase::Localize(db)) => Some(read_handoff_pose(db)?),
+        _ => None,
+    };
+    let tracker = Tracker::new(sequence.calibration.to_rig()?, &config)?;
+
+    let mut imu = sequence.imu.iter().skip_while(|measurement| measurement.timestamp_ns < start_ns).peekable();
+    let mut estimates = Vec::with_capacity(frames.len());
+    let mut failures = 0;
+    let mut rejected_imu = 0;
+    let mut last_rig_pose = None;
+    let mut localized = None;
+    let mut clock = PlaybackClock::new(options.rate.unwrap_or(f64::INFINITY));
+    let keys = if options.interactive { Some(Keys::open()?) } else { None };
+    let wall_start = Instant::now();
+    while let Some(frame) = source.next_frame() {
+        let frame = frame?;
+        if let Some(keys) = &keys {
+            let number = sequence.frames.partition_point(|other| other.timestamp_ns < frame.timestamp_ns);
+            if !keys.control(&mut clock, number, seconds(frame.timestamp_ns)) {
+                println!("stopped at frame {}", number);
+                break;
+            }
+        }
+        std::thread::sleep(clock.delay(frame.timestamp_ns, Instant::now()));
+        while let Some(measurement) = imu.next_if(|measurement| measurement.timestamp_ns <= frame.timestamp_ns) {
+            if use_imu && tracker.register_imu_measurement(0, measurement).is_err() {
+                rejected_imu += 1;
+            }
+        }
+
+        let mut result = tracker.track(&frame.images, None);
+        // Relocalize right after tracking the first frame, which cuVSLAM searches the map for
+        if let (Some(Phase::Localize(db)), Some(guess_pose), None) = (&options.phase, &guess_pose, &localized) {
+            let localize_start = Instant::now();
+            let pose_in_db = tracker.localize_in_db(path_str(db)?, guess_pose, LOCALIZATION_RADIUS_M, &frame.images);
+            match pose_in_db {
+                Ok(pose) => {
+                    println!("localized in {:.2?} at {:?}", localize_start.elapsed(), pose.t);
+                    result = result.map(|estimate| PoseEstimate { pose, ..estimate });
+                }
+                Err(status) => println!("localization failed after {:.2?}: {}", localize_start.elapsed(), status),
+            }
+            localized = Some(pose_in_db.is_ok());
+        }
+
+        match result {
+            Ok(estimate) => {
+                last_rig_pose = Some(estimate.pose);
+                let estimate = PoseEstimate {
+                    pose: sequence.body_pose(&estimate.pose),
+                    ..estimate
+                };
+                if let Some(live) = &mut live {
+                    live.record(estimate.timestamp_ns, estimate.pose.t)?;
+                }
+                estimates.push(estimate);
+            }
+            Err(status) => {
+                failures += 1;
+                eprintln!("{:.3}s: tracking failed: {}", seconds(frame.timestamp_ns), status);
+                if let Some(live) = &mut live {
+                    live.lost();
+                }
+            }
+        }
+    }
+    let elapsed = wall_start.elapsed();
+    println!(
+        "tracked {} of {} frames in {:.2?} ({:.1} fps), {} failed",
+        estimates.len(),
+        frames.len(),
+        elapsed,
+        frames.len() as f64 / elapsed.as_secs_f64(),
+        failures
+    );
+    if rejected_imu > 0 {
+        println!("{} IMU measurements were rejected by the tracker and skipped", rejected_imu);
+    }
+
+    write_tum(BufWriter::new(File::create(&options.output)?), &estimates)?;
+    println!("trajectory written to {}", options.output.display());
+
+    match &options.phase {
+        Some(Phase::BuildMap(db)) => {
+            let last_rig_pose = last_rig_pose.ok_or("no frame was tracked, so there is no map to save")?;
+            let save_start = Instant::now();
+            tracker.save_to_slam_db(path_str(db)?)?;
+            std::fs::create_dir_all(db)?;
+            write_kitti(BufWriter::new(File::create(db.join(HANDOFF_POSE_FILE))?), &[last_rig_pose])?;
+            println!("map saved to {} in {:.2?}", db.display(), save_start.elapsed());
+        }
+        Some(Phase::Localize(_)) if localized != Some(true) => {
+            println!("poses are in a fresh odometry frame since localization failed");
+        }
+        _ => {}
+    }
+
+    if !ground_truth.is_empty() {
+        let positions: Vec<_> =
+            estimates.iter().map(|estimate| (estimate.timestamp_ns, estimate.pose.t.map(f64::from))).collect();
+        match absolute_trajectory_error(&positions, &ground_truth, MAX_GROUND_TRUTH_OFFSET_NS) {
+            Some(ate) => println!(
+                "ATE over {} poses: rmse {:.4} m, mean {:.4} m, max {:.4} m",
+                ate.matched, ate.rmse_m, ate.mean_m, ate.max_m
+            ),
+            None => println!("too few poses overlap the ground truth for ATE"),
+        }
+    }
+    Ok(())
+}
+
+/// The rig pose `--build-map` saved with the map in `db`
+fn read_handoff_pose(db: &Path) -> Result<cuvslam::CUVSLAM_Pose, Box<dyn Error>> {
+    let path = db.join(HANDOFF_POSE_FILE);
+    let file = File::open(&path)
+        .map_err(|error| format!("{}: {} (was the map saved with --build-map?)", path.display(), error))?;
+    let poses = read_kitti(BufReader::new(file)).map_err(|error| format!("{}: {}", path.display(), error))?;
+    Ok(*poses.first().ok_or_else(|| format!("{}: no pose", path.display()))?)
+}
+
+fn path_str(path: &Path) -> Result<&str, String> {
+    path.to_str().ok_or_else(|| format!("{}: path is not valid UTF-8", path.display()))
+}
+
+/// Running ATE of the poses tracked so far, logged to Rerun
+struct LiveAte {
+    rec: rerun::RecordingStream,
+    ate: RunningAte,
+    /// Estimated positions, with a new strip started after every tracking failure
+    strips: Vec<Vec<[f32; 3]>>,
+    ground_truth_ended: bool,
+}
+
+impl LiveAte {
+    fn new(ground_truth: Vec<(i64, [f64; 3])>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            rec: rerun::RecordingStreamBuilder::new("CUVSLAM EuRoC ATE").spawn()?,
+            ate: RunningAte::new(ground_truth, MAX_GROUND_TRUTH_OFFSET_NS, REALIGN_EVERY),
+            strips: vec![Vec::new()],
+            ground_truth_ended: false,
+        })
+    }
+
+    fn record(&mut self, timestamp_ns: i64, position: [f32; 3]) -> rerun::RecordingStreamResult<()> {
+        self.rec.set_time_nanos("capture", timestamp_ns);
+        self.strips.last_mut().unwrap().push(position);
+        let matched = self.ate.matched();
+        let past_end = self.ate.ground_truth_end_ns().is_some_and(|end| timestamp_ns > end);
+        match self.ate.push(timestamp_ns, position.map(f64::from)) {
+            Some(error) => self.rec.log("ate/error", &rerun::Scalar::new(error))?,
+            None if past_end && !self.ground_truth_ended => {
+                self.ground_truth_ended = true;
+                eprintln!("ground truth ends before {:.3}s, later poses are not evaluated", timestamp_ns as f64 * 1e-9);
+            }
+            None => {}
+        }
+        if self.ate.matched() > matched && self.ate.matched() % REALIGN_EVERY == 0 {
+            if let (Some(report), Some(aligned)) = (self.ate.report(), self.ate.aligned_ground_truth()) {
+                let aligned: Vec<[f32; 3]> = aligned.iter().map(|position| position.map(|v| v as f32)).collect();
+                self.rec.log("ate/rmse", &rerun::Scalar::new(report.rmse_m))?;
+                self.rec.log("trajectory/ground_truth", &rerun::LineStrips3D::new([aligned]))?;
+                self.rec.log("trajectory/estimate", &rerun::LineStrips3D::new(self.strips.iter().cloned()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Break the estimated trajectory where tracking failed
+    fn lost(&mut self) {
+        if !self.strips.last().unwrap().is_empty() {
+            self.strips.push(Vec::new());
+        }
+    }
+}
+
+/// Keys pressed in the terminal, read without waiting for Enter while `--interactive` runs
+struct Keys {
+    pressed: mpsc::Receiver<u8>,
+}
+
+impl Keys {
+    fn open() -> Result<Self, Box<dyn Error>> {
+        set_terminal_mode(&["-icanon", "-echo"])?;
+        let (sender, pressed) = mpsc::channel();
+        std::thread::spawn(move || {
+            for byte in std::io::stdin().lock().bytes() {
+                match byte {
+                    Ok(byte) if sender.send(byte).is_ok() => {}
+                    _ => return,
+                }
+            }
+        });
+        eprintln!("space pauses and resumes, n steps one frame while paused, q stops");
+        Ok(Self { pressed })
+    }
+
+    /// Apply the keys pressed before frame `number`, waiting while paused until it is stepped
+    /// to or playback resumes. Returns false once `q` is pressed.
+    fn control(&self, clock: &mut PlaybackClock, number: usize, seconds: f64) -> bool {
+        loop {
+            let key = if clock.is_paused() { self.pressed.recv().ok() } else { self.pressed.try_recv().ok() };
+            match key {
+                Some(b'q') => return false,
+                Some(b' ') if clock.is_paused() => clock.resume(),
+                Some(b' ') => {
+                    clock.pause();
+                    eprintln!("paused before frame {} at {:.3}s", number, seconds);
+                }
+                Some(b'n') if clock.is_paused() => {
+                    eprintln!("frame {} at {:.3}s", number, seconds);
+                    return true;
+                }
+                Some(_) => {}
+                // Nothing pending, or stdin closed while paused
+                None => {
+                    if clock.is_paused() {
+                        clock.resume();
+                    }
+                    return true;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        let _ = set_terminal_mode(&["icanon", "echo"]);
+    }
+}
+
+/// Change the settings of the terminal on stdin with `stty`
+fn set_terminal_mode(settings: &[&str]) -> Result<(), Box<dyn Error>> {
+    let status = Command::new("stty").args(settings).stdin(Stdio::inherit()).status()?;
+    if !status.success() {
+        return Err("--interactive needs a terminal on stdin".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,326 @@
+//! Track a KITTI odometry sequence and write its poses for the devkit
+//!
+//! ```sh
+//! cargo run --release --features datasets --bin kitti -- dataset/sequences/00 [--output 00.txt]
+//!     [--ground-truth dataset/poses/00.txt] [--start-frame 1390] [--end-frame 1410] [--rate 0.25] [--interactive]
+//!     [--deterministic]
+//! ```
+//!
+//! Poses are of the left camera relative to the first frame, one line per frame in the KITTI
+//! 3x4 row-major format. A frame that fails to track repeats the previous pose so line numbers
+//! keep matching frame numbers, counted from `--start-frame` when only part of the sequence is
+//! played (`--start-frame` and `--end-frame` are inclusive).
+//!
+//! Frames are played as fast as the tracker allows unless `--rate` gives a multiple of real time.
+//! `--interactive` reads keys from the terminal: space pauses and resumes, `n` steps one frame
+//! while paused and `q` stops. `--deterministic` configures cuVSLAM with
+//! `ConfigurationBuilder::deterministic`, so repeated runs give the same poses.
+//!
+//! `--ground-truth` reads the sequence's poses from the devkit, one line per frame, and shows
+//! the ATE live in Rerun as the sequence plays: the error of each pose, and the estimated and
+//! aligned ground truth trajectories, realigned every 10 matched poses. The final ATE is printed.
+
+use cuvslam::datasets::{kitti::KittiSequence, playback::PlaybackClock, StereoSource};
+use cuvslam::{
+    absolute_trajectory_error, compose_poses, invert_pose, read_kitti_positions, write_kitti, ConfigurationBuilder,
+    FrameSource, RunningAte, Tracker, CUVSLAM_Pose,
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read};
+use std::path::PathBuf;
+use std::process::{Command, ExitCode, Stdio};
+use std::sync::mpsc;
+use std::time::Instant;
+
+const IDENTITY: CUVSLAM_Pose = CUVSLAM_Pose {
+    r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+    t: [0.0; 3],
+};
+
+/// Ground truth is sampled at the frames themselves, so matches only need to allow for rounding
+const MAX_GROUND_TRUTH_OFFSET_NS: i64 = 1_000_000;
+
+/// Matched poses between realignments of the live ATE
+const REALIGN_EVERY: usize = 10;
+
+struct Options {
+    sequence: PathBuf,
+    output: PathBuf,
+    ground_truth: Option<PathBuf>,
+    start_frame: usize,
+    end_frame: usize,
+    rate: Option<f64>,
+    interactive: bool,
+    deterministic: bool,
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let mut sequence = None;
+    let mut options = Options {
+        sequence: PathBuf::new(),
+        output: PathBuf::from("poses.txt"),
+        ground_truth: None,
+        start_frame: 0,
+        end_frame: usize::MAX,
+        rate: None,
+        interactive: false,
+        deterministic: false,
+    };
+    while let Some(arg) = args.next() {
+        let number = |value: Option<String>| value.and_then(|value| value.parse().ok());
+        match arg.as_str() {
+            "--output" => match args.next() {
+                Some(path) => options.output = PathBuf::from(path),
+                None => return usage("--output needs a value"),
+            },
+            "--ground-truth" => match args.next() {
+                Some(path) => options.ground_truth = Some(PathBuf::from(path)),
+                None => return usage("--ground-truth needs a value"),
+            },
+            "--start-frame" => match number(args.next()) {
+                Some(frame) => options.start_frame = frame,
+                None => return usage("--start-frame needs a frame number"),
+            },
+            "--end-frame" => match number(args.next()) {
+                Some(frame) => options.end_frame = frame,
+                None => return usage("--end-frame needs a frame number"),
+            },
+            "--rate" => match args.next().and_then(|value| value.parse::<f64>().ok()) {
+                Some(rate) if rate > 0.0 => options.rate = Some(rate),
+                _ => return usage("--rate needs a positive number"),
+            },
+            "--interactive" => options.interactive = true,
+            "--deterministic" => options.deterministic = true,
+            flag if flag.starts_with("--") => return usage(&format!("unknown option {}", flag)),
+            path => sequence = Some(PathBuf::from(path)),
+        }
+    }
+    let Some(sequence) = sequence else {
+        return usage("missing sequence directory");
+    };
+    options.sequence = sequence;
+
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage(message: &str) -> ExitCode {
+    eprintln!("{}", message);
+    eprintln!(
+        "usage: kitti <sequence dir> [--output poses.txt] [--ground-truth poses/00.txt] [--start-frame n] \
+         [--end-frame n] [--rate x] [--interactive] [--deterministic]"
+    );
+    ExitCode::FAILURE
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let sequence = KittiSequence::open(&options.sequence)?;
+    let end = sequence.frames.len().min(options.end_frame.saturating_add(1));
+    let frames = &sequence.frames[options.start_frame.min(end)..end];
+    if frames.is_empty() {
+        return Err("no frames in the selected range".into());
+    }
+    let ground_truth_path = options.ground_truth.as_deref();
+    let config = ConfigurationBuilder::from(sequence.configuration()).deterministic(options.deterministic).build();
+    let tracker = Tracker::new(sequence.calibration.to_rig()?, &config)?;
+
+    // One ground truth pose per line, stamped with the frame of the same number
+    let ground_truth = match ground_truth_path {
+        Some(path) => {
+            let positions = read_kitti_positions(BufReader::new(File::open(path)?))
+                .map_err(|error| format!("{}: {}", path.display(), error))?;
+            sequence.frames.iter().map(|frame| frame.timestamp_ns).zip(positions).collect()
+        }
+        None => Vec::new(),
+    };
+    let mut live = match ground_truth_path {
+        Some(_) => Some(LiveAte::new(ground_truth.clone())?),
+        None => None,
+    };
+
+    let mut first_from_world = None;
+    let mut poses = Vec::with_capacity(frames.len());
+    let mut tracked = Vec::with_capacity(frames.len());
+    let mut failures = 0;
+    let mut source = StereoSource::new(frames, &sequence.calibration);
+    let mut clock = PlaybackClock::new(options.rate.unwrap_or(f64::INFINITY));
+    let keys = if options.interactive { Some(Keys::open()?) } else { None };
+    let start = Instant::now();
+    while let Some(frame) = source.next_frame() {
+        let frame = frame?;
+        let number = options.start_frame + poses.len();
+        if let Some(keys) = &keys {
+            let seconds = (frame.timestamp_ns - sequence.frames[0].timestamp_ns) as f64 * 1e-9;
+            if !keys.control(&mut clock, number, seconds) {
+                println!("stopped at frame {}", number);
+                break;
+            }
+        }
+        std::thread::sleep(clock.delay(frame.timestamp_ns, Instant::now()));
+        let pose = match tracker.track(&frame.images, None) {
+            Ok(estimate) => {
+                let first_from_world = first_from_world.get_or_insert_with(|| invert_pose(&estimate.pose));
+                let pose = compose_poses(first_from_world, &estimate.pose);
+                if let Some(live) = &mut live {
+                    live.record(frame.timestamp_ns, pose.t)?;
+                }
+                tracked.push((frame.timestamp_ns, pose.t.map(f64::from)));
+                pose
+            }
+            Err(status) => {
+                failures += 1;
+                eprintln!("frame {}: tracking failed: {}", number, status);
+                if let Some(live) = &mut live {
+                    live.lost();
+                }
+                poses.last().copied().unwrap_or(IDENTITY)
+            }
+        };
+        poses.push(pose);
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "tracked {} frames in {:.2?} ({:.1} fps), {} failed",
+        poses.len(),
+        elapsed,
+        poses.len() as f64 / elapsed.as_secs_f64(),
+        failures
+    );
+
+    write_kitti(BufWriter::new(File::create(&options.output)?), &poses)?;
+    println!("poses written to {}", options.output.display());
+
+    if ground_truth_path.is_some() {
+        match absolute_trajectory_error(&tracked, &ground_truth, MAX_GROUND_TRUTH_OFFSET_NS) {
+            Some(ate) => println!(
+                "ATE over {} poses: rmse {:.4} m, mean {:.4} m, max {:.4} m",
+                ate.matched, ate.rmse_m, ate.mean_m, ate.max_m
+            ),
+            None => println!("too few poses overlap the ground truth for ATE"),
+        }
+    }
+    Ok(())
+}
+
+/// Running ATE of the poses tracked so far, logged to Rerun
+struct LiveAte {
+    rec: rerun::RecordingStream,
+    ate: RunningAte,
+    /// Estimated positions, with a new strip started after every tracking failure
+    strips: Vec<Vec<[f32; 3]>>,
+    ground_truth_ended: bool,
+}
+
+impl LiveAte {
+    fn new(ground_truth: Vec<(i64, [f64; 3])>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            rec: rerun::RecordingStreamBuilder::new("CUVSLAM KITTI ATE").spawn()?,
+            ate: RunningAte::new(ground_truth, MAX_GROUND_TRUTH_OFFSET_NS, REALIGN_EVERY),
+            strips: vec![Vec::new()],
+            ground_truth_ended: false,
+        })
+    }
+
+    fn record(&mut self, timestamp_ns: i64, position: [f32; 3]) -> rerun::RecordingStreamResult<()> {
+        self.rec.set_time_nanos("capture", timestamp_ns);
+        self.strips.last_mut().unwrap().push(position);
+        let matched = self.ate.matched();
+        let past_end = self.ate.ground_truth_end_ns().is_some_and(|end| timestamp_ns > end);
+        match self.ate.push(timestamp_ns, position.map(f64::from)) {
+            Some(error) => self.rec.log("ate/error", &rerun::Scalar::new(error))?,
+            None if past_end && !self.ground_truth_ended => {
+                self.ground_truth_ended = true;
+                eprintln!("ground truth ends before {:.3}s, later poses are not evaluated", timestamp_ns as f64 * 1e-9);
+            }
+            None => {}
+        }
+        if self.ate.matched() > matched && self.ate.matched() % REALIGN_EVERY == 0 {
+            if let (Some(report), Some(aligned)) = (self.ate.report(), self.ate.aligned_ground_truth()) {
+                let aligned: Vec<[f32; 3]> = aligned.iter().map(|position| position.map(|v| v as f32)).collect();
+                self.rec.log("ate/rmse", &rerun::Scalar::new(report.rmse_m))?;
+                self.rec.log("trajectory/ground_truth", &rerun::LineStrips3D::new([aligned]))?;
+                self.rec.log("trajectory/estimate", &rerun::LineStrips3D::new(self.strips.iter().cloned()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Break the estimated trajectory where tracking failed
+    fn lost(&mut self) {
+        if !self.strips.last().unwrap().is_empty() {
+            self.strips.push(Vec::new());
+        }
+    }
+}
+
+/// Keys pressed in the terminal, read without waiting for Enter while `--interactive` runs
+struct Keys {
+    pressed: mpsc::Receiver<u8>,
+}
+
+impl Keys {
+    fn open() -> Result<Self, Box<dyn Error>> {
+        set_terminal_mode(&["-icanon", "-echo"])?;
+        let (sender, pressed) = mpsc::channel();
+        std::thread::spawn(move || {
+            for byte in std::io::stdin().lock().bytes() {
+                match byte {
+                    Ok(byte) if sender.send(byte).is_ok() => {}
+                    _ => return,
+                }
+            }
+        });
+        eprintln!("space pauses and resumes, n steps one frame while paused, q stops");
+        Ok(Self { pressed })
+    }
+
+    /// Apply the keys pressed before frame `number`, waiting while paused until it is stepped
+    /// to or playback resumes. Returns false once `q` is pressed.
+    fn control(&self, clock: &mut PlaybackClock, number: usize, seconds: f64) -> bool {
+        loop {
+            let key = if clock.is_paused() { self.pressed.recv().ok() } else { self.pressed.try_recv().ok() };
+            match key {
+                Some(b'q') => return false,
+                Some(b' ') if clock.is_paused() => clock.resume(),
+                Some(b' ') => {
+                    clock.pause();
+                    eprintln!("paused before frame {} at {:.3}s", number, seconds);
+                }
+                Some(b'n') if clock.is_paused() => {
+                    eprintln!("frame {} at {:.3}s", number, seconds);
+                    return true;
+                }
+                Some(_) => {}
+                // Nothing pending, or stdin closed while paused
+                None => {
+                    if clock.is_paused() {
+                        clock.resume();
+                    }
+                    return true;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        let _ = set_terminal_mode(&["icanon", "echo"]);
+    }
+}
+
+/// Change the settings of the terminal on stdin with `stty`
+fn set_terminal_mode(settings: &[&str]) -> Result<(), Box<dyn Error>> {
+    let status = Command::new("stty").args(settings).stdin(Stdio::inherit()).status()?;
+    if !status.success() {
+        return Err("--interactive needs a terminal on stdin".into());
+    }
+    Ok(())
+}
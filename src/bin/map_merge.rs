@@ -0,0 +1,194 @@
+//! Extend a saved SLAM map with further recorded sessions into one combined database
+//!
+//! ```sh
+//! cargo run --release --features datasets --bin map_merge -- aisle_1_map aisle_2 aisle_3 --output warehouse_map
+//!     [--radius 1] [--tum-vi]
+//! ```
+//!
+//! Databases that were each saved already are merged with `cuvslam::merge_slam_dbs`; this binary
+//! starts from recordings instead and merges them one at a time: each session (a EuRoC or, with
+//! `--tum-vi`, TUM-VI sequence directory) is relocalized in the map built so far, starting from the
+//! base database, and tracked to the end with mapping on. Its map is then saved and merged with
+//! the map so far, which becomes the input of the next; cuVSLAM doesn't promise that a database
+//! saved after relocalizing still holds the map it relocalized in, so it isn't used on its own.
+//! Sessions must start within `--radius` meters (default 1) of the base map's origin, as when
+//! every run begins at the same dock. A session that fails to relocalize within its first frames
+//! is left out, and the merge carries on without it. The size of the last session's map is
+//! printed.
+
+use cuvslam::datasets::{euroc::EurocSequence, tum_vi, StereoSource};
+use cuvslam::{CUVSLAM_Pose, CameraRig, FrameSource, Tracker};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+/// Frames at the start of a session on which relocalization is attempted
+const LOCALIZE_ATTEMPTS: usize = 10;
+
+struct Options {
+    base: PathBuf,
+    sessions: Vec<PathBuf>,
+    output: PathBuf,
+    radius_m: f32,
+    tum_vi: bool,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("usage: map_merge <base db> <session dir>... --output <db> [--radius m] [--tum-vi]");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut output = None;
+    let mut radius_m = 1.0;
+    let mut tum_vi = false;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--output" => output = Some(PathBuf::from(value("--output")?)),
+            "--radius" => {
+                let radius = value("--radius")?;
+                radius_m = radius.parse().map_err(|_| format!("--radius expects a number, got {}", radius))?;
+            }
+            "--tum-vi" => tum_vi = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.len() < 2 {
+        return Err("expected a base map and at least one session".to_string());
+    }
+    let base = paths.remove(0);
+    Ok(Options {
+        base,
+        sessions: paths,
+        output: output.ok_or("missing --output")?,
+        radius_m,
+        tum_vi,
+    })
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    // Each session saves and merges next to the output and replaces it once merged, so a failed
+    // save never leaves a half-written map behind
+    let session_map = options.output.with_extension("session");
+    let staging = options.output.with_extension("partial");
+    let mut map = options.base.clone();
+    let mut merged = 0;
+    let mut metrics = None;
+    for (index, session) in options.sessions.iter().enumerate() {
+        let label = format!("session {} ({})", index + 1, session.display());
+        let Some((tracker, rig)) = extend_map(&map, session, options)? else {
+            println!("{}: not merged", label);
+            continue;
+        };
+        let _ = std::fs::remove_dir_all(&session_map);
+        tracker.save_to_slam_db(path_str(&session_map)?)?;
+        let _ = std::fs::remove_dir_all(&staging);
+        cuvslam::merge_slam_dbs(&rig, &[map.as_path(), session_map.as_path()], &staging)?;
+        std::fs::remove_dir_all(&session_map)?;
+        let _ = std::fs::remove_dir_all(&options.output);
+        std::fs::rename(&staging, &options.output)?;
+        println!("{}: merged", label);
+        map = options.output.clone();
+        merged += 1;
+        metrics = Some(map_metrics(&tracker)?);
+    }
+
+    let metrics = metrics.ok_or("no session relocalized in the base map, nothing was merged")?;
+    println!("merged {} of {} sessions into {}", merged, options.sessions.len(), options.output.display());
+    println!("last session's map: {}", metrics);
+    Ok(())
+}
+
+/// Relocalize `session` in the map saved in `map` and track it to the end with mapping on
+///
+/// Returns the tracker holding the session's map with the rig it was recorded with, or `None` if
+/// the session never relocalized.
+fn extend_map(map: &Path, session: &Path, options: &Options) -> Result<Option<(Tracker, CameraRig)>, Box<dyn Error>> {
+    let sequence = if options.tum_vi {
+        tum_vi::open(session)?
+    } else {
+        EurocSequence::open(session)?
+    };
+    let mut config = sequence.configuration(false);
+    config.enable_localization_n_mapping = 1;
+    config.enable_reading_slam_internals = 1;
+    let tracker = Tracker::new(sequence.calibration.to_rig()?, &config)?;
+    let origin = CUVSLAM_Pose {
+        r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+        t: [0.0; 3],
+    };
+
+    let mut source = StereoSource::new(&sequence.frames, &sequence.calibration);
+    let (mut tracked, mut failures) = (0, 0);
+    let mut localized = false;
+    let mut attempts = 0;
+    while let Some(frame) = source.next_frame() {
+        let frame = frame?;
+        match tracker.track(&frame.images, None) {
+            Ok(_) => tracked += 1,
+            Err(_) => failures += 1,
+        }
+        if localized {
+            continue;
+        }
+        if attempts == LOCALIZE_ATTEMPTS {
+            println!("{}: localization failed on its first {} frames", session.display(), LOCALIZE_ATTEMPTS);
+            return Ok(None);
+        }
+        let localize_start = Instant::now();
+        match tracker.localize_in_db(path_str(map)?, &origin, options.radius_m, &frame.images) {
+            Ok(pose) => {
+                println!(
+                    "{}: localized at frame {} in {:.2?} at {:?}",
+                    session.display(),
+                    attempts,
+                    localize_start.elapsed(),
+                    pose.t
+                );
+                localized = true;
+            }
+            Err(status) => eprintln!("{}: frame {}: localization failed: {}", session.display(), attempts, status),
+        }
+        attempts += 1;
+    }
+    if !localized {
+        println!("{}: ended before it relocalized", session.display());
+        return Ok(None);
+    }
+    println!("{}: tracked {} of {} frames, {} failed", session.display(), tracked, sequence.frames.len(), failures);
+    Ok(Some((tracker, sequence.calibration.to_rig()?)))
+}
+
+/// Size of the map held by `tracker`, for the summary line
+fn map_metrics(tracker: &Tracker) -> Result<String, Box<dyn Error>> {
+    let graph = tracker.get_pose_graph()?;
+    let landmarks = tracker.get_map_landmarks()?;
+    Ok(format!(
+        "{} keyframes, {} pose graph edges, {} landmarks, {:.1} m2 covered",
+        graph.nodes.len(),
+        graph.edges.len(),
+        landmarks.len(),
+        tracker.get_map_coverage_m2()?
+    ))
+}
+
+fn path_str(path: &Path) -> Result<&str, String> {
+    path.to_str().ok_or_else(|| format!("{}: path is not valid UTF-8", path.display()))
+}
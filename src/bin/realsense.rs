@@ -1,6 +1,6 @@
 use cuvslam::{
-    Brown5kParameters, Camera, CameraRig, PoseEstimate, Status, Tracker,
-    CUVSLAM_Configuration, CUVSLAM_Image, CUVSLAM_Pose,
+    Brown5kParameters, CameraRig, Image, ImageEncoding, LatencyTracker, PoseEstimate, Status, Tracker,
+    CUVSLAM_Configuration,
 };
 use realsense_rust::{
     config::Config,
@@ -12,6 +12,8 @@ use realsense_rust::{
 use std::{hash::Hash, time::{Duration, SystemTime, UNIX_EPOCH}};
 use std::collections::HashSet;
 use rerun::{self, LoggableBatch};
+#[cfg(feature = "datasets")]
+use cuvslam::datasets::recorder::SessionRecorder;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize Rerun for visualization
@@ -60,24 +62,42 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create SLAM configuration
     let slam_config = cuvslam::init_default_configuration();
 
-    // Create stereo camera rig
-    let camera_rig = create_stereo_camera_rig();
+    // Record the session for replay when `--record <dir>` is given
+    let recorder = start_recording(&slam_config)?;
+
+    // Create stereo camera rig, from `--calibration <file>` when given
+    let camera_rig = load_calibrated_rig()?.unwrap_or_else(create_stereo_camera_rig);
     
     // Initialize SLAM tracker
     let tracker = match Tracker::new(camera_rig, &slam_config) {
         Ok(tracker) => tracker,
-        Err(status) => {
-            eprintln!("Failed to initialize tracker: {}", status);
+        Err(err) => {
+            eprintln!("Failed to initialize tracker: {}", err);
             return Ok(());
         }
     };
 
     println!("Starting SLAM tracking...");
+
+    // RealSense frame timestamps default to the host-synchronized global time domain, so
+    // capture-to-pose latency is meaningful either way
+    let mut latency = LatencyTracker::new(100);
+    let adapter = InfraredFrameAdapter {
+        encoding: ImageEncoding::Mono8,
+        timestamp_source: if std::env::args().any(|arg| arg == "--system-clock") {
+            TimestampSource::SystemClock
+        } else {
+            TimestampSource::Frame
+        },
+    };
             
     // Main loop
     loop {
         // Wait for next frame
-        let frames = active_pipeline.wait(Some(Duration::from_millis(10000)))?;
+        let frames = {
+            cuvslam::profile_scope!("capture");
+            active_pipeline.wait(Some(Duration::from_millis(10000)))?
+        };
         
         // Get color frames using the CompositeFrame utility
         let infrared_frames: Vec<frame::InfraredFrame> = frames.frames_of_type();
@@ -88,29 +108,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
 
         // Convert to CUVSLAM images
-        let mut images = vec![
-            create_cuvslam_image(&infrared_frames[0]),
-            create_cuvslam_image(&infrared_frames[1]),
+        let images = [
+            adapter.adapt(&infrared_frames[0], 0),
+            adapter.adapt(&infrared_frames[1], 1),
         ];
 
-        images[0].camera_index = 0;
-        images[1].camera_index = 1;
+        if let Some(recorder) = &recorder {
+            recorder.record(images[0].timestamp_ns(), &images);
+        }
 
         // Track frame
         match tracker.track(&images, None) {
             Ok(pose_estimate) => {
+                latency.record_now(pose_estimate.timestamp_ns);
                 print_pose(&pose_estimate);
+                if let Some(stats) = latency.stats() {
+                    println!("Latency: {:?} (p50 {:?}, p95 {:?})", stats.current, stats.p50, stats.p95);
+                }
                 
                 // Log pose to Rerun
                 let t = &pose_estimate.pose.t;
                 let r = &pose_estimate.pose.r;
 
-                // Convert raw pointer to slice for Rerun
-                let width = images[0].width as usize;
-                let height = images[0].height as usize;
-                let image_data = unsafe { 
-                    std::slice::from_raw_parts(images[0].pixels, width * height)
-                };
+                let image_data = images[0].pixels();
                 
                 rec.log("camera_image", &rerun::Image::new(image_data, rerun::ImageFormat::from_color_model([640, 480], rerun::ColorModel::L, rerun::ChannelDatatype::U8)))?;
                 
@@ -138,58 +158,121 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Load the YAML or JSON file passed with `--calibration`, if any
+#[cfg(feature = "calibration")]
+fn load_calibration() -> Result<Option<cuvslam::Calibration>, cuvslam::Error> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--calibration").skip(1);
+    let Some(path) = args.next() else {
+        return Ok(None);
+    };
+    if path.ends_with(".json") {
+        cuvslam::Calibration::from_json(&path).map(Some)
+    } else {
+        cuvslam::Calibration::from_yaml(&path).map(Some)
+    }
+}
+
+/// Load the rig from the YAML or JSON file passed with `--calibration`, if any
+#[cfg(feature = "calibration")]
+fn load_calibrated_rig() -> Result<Option<CameraRig>, cuvslam::Error> {
+    load_calibration()?.map(|calibration| calibration.to_rig()).transpose()
+}
+
+#[cfg(not(feature = "calibration"))]
+fn load_calibrated_rig() -> Result<Option<CameraRig>, cuvslam::Error> {
+    if std::env::args().any(|arg| arg == "--calibration") {
+        eprintln!("--calibration needs the `calibration` feature, using the built-in example rig");
+    }
+    Ok(None)
+}
+
+/// Start recording into the directory passed with `--record`, if any
+///
+/// `--record-seconds <s>` keeps only the last `s` seconds on disk.
+#[cfg(feature = "datasets")]
+fn start_recording(config: &CUVSLAM_Configuration) -> Result<Option<SessionRecorder>, Box<dyn std::error::Error>> {
+    let arg = |name: &str| std::env::args().skip_while(|arg| arg != name).nth(1);
+    let Some(dir) = arg("--record") else {
+        return Ok(None);
+    };
+    let window = match arg("--record-seconds") {
+        Some(seconds) => Some(Duration::try_from_secs_f64(seconds.parse()?)?),
+        None => None,
+    };
+    let recorder = SessionRecorder::create(&dir, load_calibration()?.as_ref(), config, window)?;
+    println!("Recording to {}", dir);
+    Ok(Some(recorder))
+}
+
+/// Stand-in for `SessionRecorder` without the `datasets` feature, which never records
+#[cfg(not(feature = "datasets"))]
+struct SessionRecorder;
+
+#[cfg(not(feature = "datasets"))]
+impl SessionRecorder {
+    fn record(&self, _timestamp_ns: i64, _images: &[Image]) {}
+}
+
+#[cfg(not(feature = "datasets"))]
+fn start_recording(_config: &CUVSLAM_Configuration) -> Result<Option<SessionRecorder>, Box<dyn std::error::Error>> {
+    if std::env::args().any(|arg| arg == "--record") {
+        eprintln!("--record needs the `datasets` feature, the session is not recorded");
+    }
+    Ok(None)
+}
+
 fn create_stereo_camera_rig() -> CameraRig {
-    // Create left camera (values are examples - replace with actual calibration)
-    let left_cam = Camera::new_brown5k(
-        640, 480,
-        Brown5kParameters {
-            cx: 320.0, cy: 240.0,
-            fx: 385.0, fy: 385.0,
-            k1: 0.0, k2: 0.0, k3: 0.0,
-            p1: 0.0, p2: 0.0
-        },
-        CUVSLAM_Pose {
-            r: [1.0, 0.0, 0.0,
-                0.0, 1.0, 0.0,
-                0.0, 0.0, 1.0],
-            t: [0.0, 0.0, 0.0],
-        }
-    );
+    // Values are examples - replace with actual calibration
+    let intrinsics = Brown5kParameters {
+        cx: 320.0, cy: 240.0,
+        fx: 385.0, fy: 385.0,
+        k1: 0.0, k2: 0.0, k3: 0.0,
+        p1: 0.0, p2: 0.0
+    };
+    // 55mm baseline - adjust for your camera
+    CameraRig::stereo_shared_intrinsics(intrinsics, 640, 480, 0.055)
+}
 
-    // Create right camera with baseline offset
-    let right_cam = Camera::new_brown5k(
-        640, 480,
-        Brown5kParameters {
-            cx: 320.0, cy: 240.0,
-            fx: 385.0, fy: 385.0,
-            k1: 0.0, k2: 0.0, k3: 0.0,
-            p1: 0.0, p2: 0.0
-        },
-        CUVSLAM_Pose {
-            r: [1.0, 0.0, 0.0,
-                0.0, 1.0, 0.0,
-                0.0, 0.0, 1.0],
-            t: [0.055, 0.0, 0.0], // 55mm baseline - adjust for your camera
-        }
-    );
+/// Clock used to timestamp adapted frames
+enum TimestampSource {
+    /// The frame's own timestamp as reported by the device
+    Frame,
+    /// The host system clock at the time the frame is adapted
+    SystemClock,
+}
 
-    CameraRig::new(vec![left_cam, right_cam])
+/// Converts RealSense infrared frames into cuVSLAM images
+struct InfraredFrameAdapter {
+    encoding: ImageEncoding,
+    timestamp_source: TimestampSource,
 }
 
-fn create_cuvslam_image(frame: &frame::InfraredFrame) -> CUVSLAM_Image {
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as i64;
-
-    CUVSLAM_Image {
-        width: frame.width() as i32,
-        height: frame.height() as i32,
-        pitch: frame.stride() as i32,
-        pixels: unsafe { frame.get_data() as *const _ as *const u8 },
-        camera_index: 0, // Set appropriate camera index
-        timestamp_ns: timestamp,
-        image_encoding: 0, // Set appropriate encoding
+impl InfraredFrameAdapter {
+    fn adapt(&self, frame: &frame::InfraredFrame, camera_index: i32) -> Image {
+        cuvslam::profile_scope!("convert_infrared");
+        let timestamp_ns = match self.timestamp_source {
+            // RealSense reports milliseconds
+            TimestampSource::Frame => (frame.timestamp() * 1e6) as i64,
+            TimestampSource::SystemClock => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos() as i64,
+        };
+
+        let pixels = unsafe {
+            std::slice::from_raw_parts(frame.get_data() as *const _ as *const u8, frame.get_data_size())
+        };
+
+        Image::new_with_pitch(
+            pixels.to_vec(),
+            frame.width() as i32,
+            frame.height() as i32,
+            frame.stride() as i32,
+            self.encoding,
+            camera_index,
+            timestamp_ns,
+        )
+        .expect("RealSense frame smaller than its reported dimensions")
     }
 }
 
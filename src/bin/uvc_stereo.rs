@@ -0,0 +1,294 @@
+//! Track with two UVC webcams, or one side-by-side stereo webcam, and log poses to Rerun
+//!
+//! ```sh
+//! cargo run --release --features uvc --bin uvc_stereo -- --calibration rig.yaml /dev/video0 /dev/video2
+//! cargo run --release --features uvc --bin uvc_stereo -- --calibration rig.yaml --side-by-side /dev/video0
+//! cargo run --release --features uvc --bin uvc_stereo -- --list-devices
+//! ```
+//!
+//! The calibration is a YAML or JSON file in the `calibration` module's format, and its first
+//! camera's size is the capture resolution (side-by-side devices capture twice its width).
+//! Frames are captured as YUYV and converted to mono. Two separate webcams are free-running, so
+//! their frames are paired by V4L2 timestamp within `--max-skew-ms` (default 10) and a warning is
+//! printed when their frame rates differ.
+
+use cuvslam::{
+    pose_rotation_quaternion, Calibration, PoseEstimate, Preprocessor, RawFormat, RawFrame, StereoPairer, Tracker,
+};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::mpsc;
+use v4l::buffer::Type;
+use v4l::io::traits::CaptureStream;
+use v4l::prelude::*;
+use v4l::video::Capture;
+use v4l::FourCC;
+
+/// The two cameras' rates are compared once this many pairs have been tracked
+const RATE_CHECK_PAIRS: u64 = 90;
+
+enum Devices {
+    Pair(PathBuf, PathBuf),
+    SideBySide(PathBuf),
+}
+
+struct Options {
+    calibration: PathBuf,
+    devices: Devices,
+    max_skew_ns: i64,
+}
+
+/// One captured YUYV frame
+struct CapturedFrame {
+    pixels: Vec<u8>,
+    pitch: i32,
+}
+
+impl CapturedFrame {
+    /// The `width` x `height` region starting `x` pixels into each row, as camera `camera_index`
+    fn region(&self, x: i32, width: i32, height: i32, camera_index: i32, timestamp_ns: i64) -> RawFrame<'_> {
+        RawFrame {
+            // YUYV takes 2 bytes per pixel
+            pixels: &self.pixels[2 * x as usize..],
+            width,
+            height,
+            pitch: self.pitch,
+            format: RawFormat::Yuyv,
+            camera_index,
+            timestamp_ns,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--list-devices") {
+        list_devices();
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(args.into_iter()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!(
+                "usage: uvc_stereo --calibration rig.yaml (<left device> <right device> | --side-by-side <device>) \
+                 [--max-skew-ms 10]\n       uvc_stereo --list-devices"
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut calibration = None;
+    let mut side_by_side = false;
+    let mut max_skew_ns = 10_000_000;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--calibration" => calibration = Some(PathBuf::from(value("--calibration")?)),
+            "--side-by-side" => side_by_side = true,
+            "--max-skew-ms" => {
+                let skew_ms: f64 = value("--max-skew-ms")?.parse().map_err(|_| "--max-skew-ms must be a number")?;
+                max_skew_ns = (skew_ms * 1e6) as i64;
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    let devices = match (side_by_side, <[PathBuf; 2]>::try_from(paths)) {
+        (false, Ok([left, right])) => Devices::Pair(left, right),
+        (true, Err(paths)) if paths.len() == 1 => Devices::SideBySide(paths[0].clone()),
+        (false, _) => return Err("expected a left and a right device".to_string()),
+        (true, _) => return Err("expected one device with --side-by-side".to_string()),
+    };
+    Ok(Options {
+        calibration: calibration.ok_or("missing --calibration")?,
+        devices,
+        max_skew_ns,
+    })
+}
+
+fn list_devices() {
+    let nodes = v4l::context::enum_devices();
+    if nodes.is_empty() {
+        println!("no V4L2 devices found");
+    }
+    for node in nodes {
+        let formats = Device::with_path(node.path())
+            .and_then(|device| device.enum_formats())
+            .map(|formats| formats.iter().map(|format| format.fourcc.to_string()).collect::<Vec<_>>().join(", "))
+            .unwrap_or_else(|error| format!("unavailable: {}", error));
+        println!(
+            "{}  {}  [{}]",
+            node.path().display(),
+            node.name().unwrap_or_default(),
+            formats
+        );
+    }
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let calibration = if options.calibration.extension().is_some_and(|extension| extension == "json") {
+        Calibration::from_json(&options.calibration)?
+    } else {
+        Calibration::from_yaml(&options.calibration)?
+    };
+    let camera = calibration.cameras.first().ok_or("calibration has no cameras")?;
+    let (width, height) = (camera.width, camera.height);
+    let tracker = Tracker::new(calibration.to_rig()?, &cuvslam::init_default_configuration())?;
+    let rec = rerun::RecordingStreamBuilder::new("CUVSLAM UVC Stereo Tracker").spawn()?;
+
+    // Each device is read on its own thread, tagging frames with the camera they belong to
+    let (sender, receiver) = mpsc::sync_channel::<(usize, i64, CapturedFrame)>(8);
+    match &options.devices {
+        Devices::Pair(left, right) => {
+            for (camera_index, path) in [left, right].into_iter().enumerate() {
+                let device = open_device(path, width as u32, height as u32)?;
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    capture(device, |timestamp_ns, frame| sender.send((camera_index, timestamp_ns, frame)).is_ok())
+                });
+            }
+        }
+        Devices::SideBySide(path) => {
+            let device = open_device(path, 2 * width as u32, height as u32)?;
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                capture(device, |timestamp_ns, frame| sender.send((0, timestamp_ns, frame)).is_ok())
+            });
+        }
+    }
+    drop(sender);
+
+    println!("Starting SLAM tracking...");
+    let mut pairer = StereoPairer::new(options.max_skew_ns);
+    let mut preprocessor = Preprocessor::new(1, true);
+    let mut tracked = 0u64;
+    for (camera_index, timestamp_ns, frame) in receiver {
+        if let Devices::SideBySide(_) = options.devices {
+            let halves = [
+                frame.region(0, width, height, 0, timestamp_ns),
+                frame.region(width, width, height, 1, timestamp_ns),
+            ];
+            track_pair(&tracker, &mut preprocessor, &rec, &halves)?;
+            continue;
+        }
+        let Some((timestamp_ns, [left, right])) = pairer.push(camera_index, timestamp_ns, frame) else {
+            continue;
+        };
+        let frames = [
+            left.region(0, width, height, 0, timestamp_ns),
+            right.region(0, width, height, 1, timestamp_ns),
+        ];
+        track_pair(&tracker, &mut preprocessor, &rec, &frames)?;
+
+        tracked += 1;
+        if tracked == RATE_CHECK_PAIRS {
+            if let Some([left_hz, right_hz]) = pairer.rate_mismatch() {
+                eprintln!(
+                    "warning: left camera runs at {:.1} Hz and right at {:.1} Hz, frames of the faster one are dropped",
+                    left_hz, right_hz
+                );
+            }
+        }
+    }
+    Err("camera stream ended".into())
+}
+
+/// Open `path` for YUYV capture at `width` x `height`
+fn open_device(path: &PathBuf, width: u32, height: u32) -> Result<Device, Box<dyn Error>> {
+    let device = Device::with_path(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let format = device.set_format(&v4l::Format::new(width, height, FourCC::new(b"YUYV")))?;
+    if format.fourcc != FourCC::new(b"YUYV") || format.width != width || format.height != height {
+        return Err(format!(
+            "{} does not capture YUYV at {}x{} (got {} at {}x{})",
+            path.display(),
+            width,
+            height,
+            format.fourcc,
+            format.width,
+            format.height
+        )
+        .into());
+    }
+    Ok(device)
+}
+
+/// Stream frames from `device` into `deliver` until it returns false or capture fails
+fn capture(device: Device, mut deliver: impl FnMut(i64, CapturedFrame) -> bool) {
+    let pitch = match device.format() {
+        Ok(format) => format.stride as i32,
+        Err(error) => return eprintln!("failed to read the capture format: {}", error),
+    };
+    let mut stream = match MmapStream::with_buffers(&device, Type::VideoCapture, 4) {
+        Ok(stream) => stream,
+        Err(error) => return eprintln!("failed to start streaming: {}", error),
+    };
+    loop {
+        let (pixels, metadata) = match stream.next() {
+            Ok(frame) => frame,
+            Err(error) => return eprintln!("capture failed: {}", error),
+        };
+        // V4L2 timestamps come from the monotonic clock, which all devices share
+        let timestamp_ns = metadata.timestamp.sec * 1_000_000_000 + metadata.timestamp.usec * 1_000;
+        let frame = CapturedFrame { pixels: pixels[..metadata.bytesused as usize].to_vec(), pitch };
+        if !deliver(timestamp_ns, frame) {
+            return;
+        }
+    }
+}
+
+fn track_pair(
+    tracker: &Tracker,
+    preprocessor: &mut Preprocessor,
+    rec: &rerun::RecordingStream,
+    frames: &[RawFrame; 2],
+) -> Result<(), Box<dyn Error>> {
+    let processed = preprocessor.process(frames)?;
+    let images: Vec<_> = processed.into_iter().map(|(image, _)| image).collect();
+    match tracker.track(&images, None) {
+        Ok(estimate) => log_pose(rec, &estimate, &images[0])?,
+        Err(status) => eprintln!("tracking failed: {}", status),
+    }
+    preprocessor.recycle(images);
+    Ok(())
+}
+
+fn log_pose(
+    rec: &rerun::RecordingStream,
+    estimate: &PoseEstimate,
+    image: &cuvslam::Image,
+) -> rerun::RecordingStreamResult<()> {
+    let t = &estimate.pose.t;
+    let [qx, qy, qz, qw] = pose_rotation_quaternion(&estimate.pose);
+    println!("Position: x={:.3}, y={:.3}, z={:.3} meters", t[0], t[1], t[2]);
+    rec.set_time_nanos("capture", estimate.timestamp_ns);
+    rec.log(
+        "camera_image",
+        &rerun::Image::new(
+            image.pixels(),
+            rerun::ImageFormat::from_color_model(
+                [image.width() as u32, image.height() as u32],
+                rerun::ColorModel::L,
+                rerun::ChannelDatatype::U8,
+            ),
+        ),
+    )?;
+    rec.log(
+        "camera",
+        &rerun::Transform3D::from_translation_rotation(
+            rerun::Vec3D::new(t[0], t[1], t[2]),
+            rerun::Rotation3D::Quaternion(rerun::Quaternion::from_xyzw([qx, qy, qz, qw]).into()),
+        ),
+    )
+}
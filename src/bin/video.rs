@@ -0,0 +1,155 @@
+//! Track a video file, monocular or side-by-side stereo, and write its poses
+//!
+//! ```sh
+//! cargo run --release --features video --bin video -- clip.mp4 --calibration rig.yaml [--side-by-side]
+//!     [--fps 30] [--start 12.5] [--frames 600] [--output poses.tum]
+//! ```
+//!
+//! The calibration is a YAML or JSON file in the `calibration` module's format with one camera,
+//! or two with `--side-by-side`, which splits each frame into its left and right halves. Frames
+//! are timestamped from the container's presentation times; `--fps` stamps them at a fixed rate
+//! instead, for files whose timestamps are missing or wrong. `--start` skips that many seconds
+//! into the file and `--frames` stops after that many frames.
+//!
+//! Poses of camera 0 are written in the TUM format, one line per tracked frame.
+
+use cuvslam::{write_tum, Calibration, FrameSource, Tracker, VideoLayout, VideoSource};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+struct Options {
+    video: PathBuf,
+    calibration: PathBuf,
+    layout: VideoLayout,
+    fps: Option<f64>,
+    start: Duration,
+    frames: Option<usize>,
+    output: PathBuf,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!(
+                "usage: video <file> --calibration rig.yaml [--side-by-side] [--fps rate] [--start seconds] \
+                 [--frames n] [--output poses.tum]"
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let (mut video, mut calibration) = (None, None);
+    let mut options = Options {
+        video: PathBuf::new(),
+        calibration: PathBuf::new(),
+        layout: VideoLayout::Single,
+        fps: None,
+        start: Duration::ZERO,
+        frames: None,
+        output: PathBuf::from("poses.tum"),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--calibration" => calibration = Some(PathBuf::from(value("--calibration")?)),
+            "--side-by-side" => options.layout = VideoLayout::SideBySide,
+            "--fps" => match value("--fps")?.parse::<f64>() {
+                Ok(fps) if fps > 0.0 => options.fps = Some(fps),
+                _ => return Err("--fps needs a positive number".to_string()),
+            },
+            "--start" => match value("--start")?.parse::<f64>() {
+                Ok(seconds) if seconds >= 0.0 => options.start = Duration::from_secs_f64(seconds),
+                _ => return Err("--start needs a number of seconds".to_string()),
+            },
+            "--frames" => {
+                let frames = value("--frames")?;
+                options.frames = Some(frames.parse().map_err(|_| format!("--frames expects a count, got {}", frames))?);
+            }
+            "--output" => options.output = PathBuf::from(value("--output")?),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if video.is_none() => video = Some(PathBuf::from(path)),
+            path => return Err(format!("unexpected argument {}", path)),
+        }
+    }
+    options.video = video.ok_or("missing video file")?;
+    options.calibration = calibration.ok_or("missing --calibration")?;
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let calibration = if options.calibration.extension().is_some_and(|extension| extension == "json") {
+        Calibration::from_json(&options.calibration)?
+    } else {
+        Calibration::from_yaml(&options.calibration)?
+    };
+    let cameras = match options.layout {
+        VideoLayout::Single => 1,
+        VideoLayout::SideBySide => 2,
+    };
+    if calibration.cameras.len() != cameras {
+        return Err(format!(
+            "{} has {} cameras, expected {}",
+            options.calibration.display(),
+            calibration.cameras.len(),
+            cameras
+        )
+        .into());
+    }
+
+    let rig = calibration.to_rig()?;
+    let mut source = VideoSource::open(&options.video, options.layout)?.with_calibration(calibration);
+    match (options.fps, source.frame_rate()) {
+        (Some(fps), _) => {
+            println!("stamping frames at {} fps", fps);
+            source = source.with_frame_rate(fps);
+        }
+        (None, Some(fps)) => println!("video runs at {:.2} fps", fps),
+        (None, None) => println!("video has no frame rate, relying on its timestamps"),
+    }
+    if let Some(frames) = options.frames {
+        source = source.with_max_frames(frames);
+    }
+    if !options.start.is_zero() {
+        source.seek(options.start)?;
+    }
+
+    let mut config = cuvslam::init_default_configuration();
+    if options.layout == VideoLayout::Single {
+        // A monocular rig has no stereo pair to rectify against
+        config.horizontal_stereo_camera = 0;
+    }
+    let tracker = Tracker::new(rig, &config)?;
+
+    let (mut frames, mut estimates) = (0usize, Vec::new());
+    while let Some(frame) = source.next_frame() {
+        let frame = frame?;
+        frames += 1;
+        match tracker.track(&frame.images, None) {
+            Ok(estimate) => estimates.push(estimate),
+            Err(status) => eprintln!("frame at {:.3} s failed to track: {}", frame.timestamp_ns as f64 * 1e-9, status),
+        }
+    }
+    write_tum(BufWriter::new(File::create(&options.output)?), &estimates)?;
+    println!(
+        "tracked {} of {} frames, poses written to {}",
+        estimates.len(),
+        frames,
+        options.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,208 @@
+//! FFI entry points into libcuvslam
+//!
+//! By default the library is linked at build time and this module simply re-exports the
+//! generated bindings. With the `runtime-loading` feature the functions used by the wrapper are
+//! resolved lazily with `dlopen` instead, and calls made while the library is unavailable return
+//! `CUVSLAM_LIBRARY_NOT_LOADED` rather than aborting the process at load time. Entry points
+//! missing from the generated header are resolved optionally, so a library without them still
+//! loads and only those calls fail, with `CUVSLAM_NOT_IMPLEMENTED`.
+
+#![allow(non_snake_case)]
+
+pub(crate) use cuvslam_lib::bindings::*;
+
+// Exported by libcuvslam but missing from its header, so declared by hand. The signature follows
+// the symbol's own handling of its arguments: a rig pointer, an array of `num_databases` path
+// pointers (rejected unless non-null and more than one) and the output path, which is strlen'd.
+#[cfg(not(feature = "runtime-loading"))]
+extern "C" {
+    pub(crate) fn CUVSLAM_MergeDatabases(
+        rig: *const CUVSLAM_CameraRig,
+        databases: *const *const std::os::raw::c_char,
+        num_databases: i32,
+        output_folder: *const std::os::raw::c_char,
+    ) -> CUVSLAM_Status;
+}
+
+#[cfg(feature = "runtime-loading")]
+use std::{
+    ffi::OsStr,
+    os::raw::{c_char, c_void},
+    sync::OnceLock,
+};
+
+/// Status reported by the wrapper when libcuvslam could not be loaded
+pub(crate) const CUVSLAM_LIBRARY_NOT_LOADED: CUVSLAM_Status = CUVSLAM_Status::MAX;
+
+#[cfg(not(feature = "runtime-loading"))]
+pub(crate) fn is_available() -> bool {
+    true
+}
+
+/// Library name resolved through the regular dynamic linker search path
+#[cfg(feature = "runtime-loading")]
+const DEFAULT_LIBRARY: &str = "libcuvslam.so";
+
+/// Declare the dynamically resolved functions along with the value each returns when the
+/// library is unavailable. The generated functions shadow the glob-imported bindings. Functions
+/// marked `optional` don't fail the load when their symbol is missing; calling one then returns
+/// the second value.
+#[cfg(feature = "runtime-loading")]
+macro_rules! dynamic_api {
+    (
+        $(fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)? = $fallback:expr;)*
+        $(optional fn $opt_name:ident($($opt_arg:ident: $opt_ty:ty),* $(,)?) -> $opt_ret:ty
+            = $opt_fallback:expr, $missing:expr;)*
+    ) => {
+        struct Api {
+            $($name: unsafe extern "C" fn($($ty),*) $(-> $ret)?,)*
+            $($opt_name: Option<unsafe extern "C" fn($($opt_ty),*) -> $opt_ret>,)*
+        }
+
+        impl Api {
+            fn load(path: &OsStr) -> Result<Self, libloading::Error> {
+                // The library stays loaded for the lifetime of the process
+                let library: &'static libloading::Library =
+                    Box::leak(Box::new(unsafe { libloading::Library::new(path)? }));
+                unsafe {
+                    Ok(Self {
+                        $($name: *library.get(concat!(stringify!($name), "\0").as_bytes())?,)*
+                        $($opt_name: library
+                            .get(concat!(stringify!($opt_name), "\0").as_bytes())
+                            .ok()
+                            .map(|symbol| *symbol),)*
+                    })
+                }
+            }
+        }
+
+        $(
+            // Signatures mirror the C API
+            #[allow(clippy::too_many_arguments)]
+            pub(crate) unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+                match api() {
+                    Some(api) => (api.$name)($($arg),*),
+                    None => $fallback,
+                }
+            }
+        )*
+
+        $(
+            pub(crate) unsafe fn $opt_name($($opt_arg: $opt_ty),*) -> $opt_ret {
+                match api().map(|api| api.$opt_name) {
+                    Some(Some(function)) => function($($opt_arg),*),
+                    Some(None) => $missing,
+                    None => $opt_fallback,
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "runtime-loading")]
+dynamic_api! {
+    fn CUVSLAM_GetVersion(major: *mut i32, minor: *mut i32, version: *mut *const c_char) = {};
+    fn CUVSLAM_GetDefaultConfiguration() -> CUVSLAM_Configuration = std::mem::zeroed();
+    fn CUVSLAM_WarmUpGPU() = {};
+    fn CUVSLAM_CreateTracker(
+        tracker: *mut CUVSLAM_TrackerHandle,
+        rig: *const CUVSLAM_CameraRig,
+        cfg: *const CUVSLAM_Configuration,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_DestroyTracker(tracker: CUVSLAM_TrackerHandle) = {};
+    fn CUVSLAM_Track(
+        tracker: CUVSLAM_TrackerHandle,
+        images: *const CUVSLAM_Image,
+        num_images: usize,
+        predicted_pose: *const CUVSLAM_Pose,
+        pose_estimate: *mut CUVSLAM_PoseEstimate,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_RegisterImuMeasurement(
+        tracker: CUVSLAM_TrackerHandle,
+        sensor_index: i32,
+        imu: *const CUVSLAM_ImuMeasurement,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_GetOdometryPose(
+        tracker: CUVSLAM_TrackerHandle,
+        pose: *mut CUVSLAM_Pose,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_GetSlamPose(
+        tracker: CUVSLAM_TrackerHandle,
+        pose: *mut CUVSLAM_Pose,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_GetLastLeftObservations(
+        tracker: CUVSLAM_TrackerHandle,
+        observations: *mut CUVSLAM_ObservationVector,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_GetLastLandmarks(
+        tracker: CUVSLAM_TrackerHandle,
+        landmarks: *mut CUVSLAM_LandmarkVector,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_EnableReadingDataLayer(
+        tracker: CUVSLAM_TrackerHandle,
+        layer: CUVSLAM_DataLayer,
+        max_items_count: u32,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_StartReadingLandmarks(
+        tracker: CUVSLAM_TrackerHandle,
+        layer: CUVSLAM_DataLayer,
+    ) -> CUVSLAM_LandmarkInfoArrayRef = std::mem::zeroed();
+    fn CUVSLAM_FinishReadingLandmarks(tracker: CUVSLAM_TrackerHandle, layer: CUVSLAM_DataLayer) = {};
+    fn CUVSLAM_StartReadingPoseGraph(
+        tracker: CUVSLAM_TrackerHandle,
+        layer: CUVSLAM_DataLayer,
+    ) -> CUVSLAM_PoseGraphRef = std::mem::zeroed();
+    fn CUVSLAM_FinishReadingPoseGraph(tracker: CUVSLAM_TrackerHandle, layer: CUVSLAM_DataLayer) = {};
+    fn CUVSLAM_GetAllSlamPoses(
+        tracker: CUVSLAM_TrackerHandle,
+        max_poses_count: u32,
+        poses: *mut CUVSLAM_PoseStamped,
+    ) -> u32 = 0;
+    fn CUVSLAM_SaveToSlamDb(
+        tracker: CUVSLAM_TrackerHandle,
+        folder_name: *const c_char,
+        response: CUVSLAM_SaveToSlamDbResponse,
+        context: *mut c_void,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    fn CUVSLAM_LocalizeInExistDb(
+        tracker: CUVSLAM_TrackerHandle,
+        folder_name: *const c_char,
+        guess_pose: *const CUVSLAM_Pose,
+        radius: f32,
+        images: *const CUVSLAM_Image,
+        num_images: usize,
+        response: CUVSLAM_LocalizeInExistDbResponse,
+        context: *mut c_void,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED;
+    optional fn CUVSLAM_MergeDatabases(
+        rig: *const CUVSLAM_CameraRig,
+        databases: *const *const c_char,
+        num_databases: i32,
+        output_folder: *const c_char,
+    ) -> CUVSLAM_Status = CUVSLAM_LIBRARY_NOT_LOADED, CUVSLAM_NOT_IMPLEMENTED;
+}
+
+#[cfg(feature = "runtime-loading")]
+static API: OnceLock<Api> = OnceLock::new();
+
+/// Get the loaded library, trying the default search path if nothing has been loaded yet
+#[cfg(feature = "runtime-loading")]
+fn api() -> Option<&'static Api> {
+    if API.get().is_none() {
+        let _ = load(OsStr::new(DEFAULT_LIBRARY));
+    }
+    API.get()
+}
+
+#[cfg(feature = "runtime-loading")]
+pub(crate) fn load(path: &OsStr) -> Result<(), libloading::Error> {
+    let api = Api::load(path)?;
+    // Another thread may have won the race, in which case its library is used
+    let _ = API.set(api);
+    Ok(())
+}
+
+#[cfg(feature = "runtime-loading")]
+pub(crate) fn is_available() -> bool {
+    api().is_some()
+}
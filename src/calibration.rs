@@ -0,0 +1,269 @@
+//! Camera rig calibration files (`calibration` feature)
+//!
+//! A calibration lists the rig's cameras in `camera_index` order. In YAML:
+//!
+//! ```yaml
+//! cameras:
+//!   - width: 640
+//!     height: 480
+//!     model: brown5k           # pinhole, brown5k or fisheye4
+//!     cx: 320.5
+//!     cy: 240.2
+//!     fx: 385.1
+//!     fy: 385.1
+//!     distortion: [-0.05, 0.04, 0.0, 0.001, -0.0005]
+//!   - width: 640
+//!     height: 480
+//!     model: brown5k
+//!     cx: 319.8
+//!     cy: 239.6
+//!     fx: 385.4
+//!     fy: 385.4
+//!     distortion: [-0.05, 0.04, 0.0, 0.001, -0.0005]
+//!     extrinsics:
+//!       rotation: [[1, 0, 0], [0, 1, 0], [0, 0, 1]]
+//!       translation: [0.05, 0, 0]
+//! ```
+//!
+//! JSON files use the same structure. `distortion` holds no values for `pinhole`, `k1, k2, k3,
+//! p1, p2` for `brown5k` (note that OpenCV orders these `k1, k2, p1, p2, k3`) and `k1..k4` for
+//! `fisheye4`. `extrinsics` is the camera's pose in the rig frame, mapping camera coordinates
+//! into rig coordinates, with a row-major rotation; it defaults to the identity.
+
+use crate::{
+    Brown5kParameters, Camera, CameraRig, Error, Fisheye4Parameters, PinholeParameters, CUVSLAM_Pose,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Distortion model of a calibrated camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistortionModel {
+    Pinhole,
+    Brown5k,
+    Fisheye4,
+}
+
+impl DistortionModel {
+    /// Number of values expected in `CameraCalibration::distortion`
+    pub fn num_coefficients(self) -> usize {
+        match self {
+            DistortionModel::Pinhole => 0,
+            DistortionModel::Brown5k => 5,
+            DistortionModel::Fisheye4 => 4,
+        }
+    }
+}
+
+/// Pose of a camera in the rig frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Extrinsics {
+    /// Row-major rotation from camera to rig coordinates
+    pub rotation: [[f32; 3]; 3],
+    /// Camera position in the rig frame, in meters
+    pub translation: [f32; 3],
+}
+
+impl Default for Extrinsics {
+    fn default() -> Self {
+        Self {
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            translation: [0.0; 3],
+        }
+    }
+}
+
+impl Extrinsics {
+    /// The same pose in cuVSLAM's layout, with a column-major rotation
+    pub fn to_pose(&self) -> CUVSLAM_Pose {
+        let mut r = [0.0; 9];
+        for (row, values) in self.rotation.iter().enumerate() {
+            for (col, &value) in values.iter().enumerate() {
+                r[col * 3 + row] = value;
+            }
+        }
+        CUVSLAM_Pose { r, t: self.translation }
+    }
+
+    /// Convert a cuVSLAM pose back to row-major extrinsics
+    pub fn from_pose(pose: &CUVSLAM_Pose) -> Self {
+        Self {
+            rotation: std::array::from_fn(|row| std::array::from_fn(|col| pose.r[col * 3 + row])),
+            translation: pose.t,
+        }
+    }
+}
+
+/// Intrinsics, distortion and extrinsics of one camera
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraCalibration {
+    pub width: i32,
+    pub height: i32,
+    pub model: DistortionModel,
+    pub cx: f32,
+    pub cy: f32,
+    pub fx: f32,
+    pub fy: f32,
+    /// Distortion coefficients in cuVSLAM's order for `model`
+    #[serde(default)]
+    pub distortion: Vec<f32>,
+    #[serde(default)]
+    pub extrinsics: Extrinsics,
+}
+
+impl CameraCalibration {
+    /// Build the camera, checking that `distortion` has the right length for `model`
+    pub fn to_camera(&self) -> Result<Camera, Error> {
+        let expected = self.model.num_coefficients();
+        if self.distortion.len() != expected {
+            return Err(Error::Calibration(format!(
+                "{:?} expects {} distortion coefficients, got {}",
+                self.model,
+                expected,
+                self.distortion.len()
+            )));
+        }
+
+        let (cx, cy, fx, fy) = (self.cx, self.cy, self.fx, self.fy);
+        let k = &self.distortion;
+        let pose = self.extrinsics.to_pose();
+        Ok(match self.model {
+            DistortionModel::Pinhole => {
+                Camera::new_pinhole(self.width, self.height, PinholeParameters { cx, cy, fx, fy }, pose)
+            }
+            DistortionModel::Brown5k => Camera::new_brown5k(
+                self.width,
+                self.height,
+                Brown5kParameters { cx, cy, fx, fy, k1: k[0], k2: k[1], k3: k[2], p1: k[3], p2: k[4] },
+                pose,
+            ),
+            DistortionModel::Fisheye4 => Camera::new_fisheye4(
+                self.width,
+                self.height,
+                Fisheye4Parameters { cx, cy, fx, fy, k1: k[0], k2: k[1], k3: k[2], k4: k[3] },
+                pose,
+            ),
+        })
+    }
+}
+
+/// Calibration of a whole rig, loaded from YAML or JSON (see the module docs for the schema)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    /// Cameras in `camera_index` order
+    pub cameras: Vec<CameraCalibration>,
+}
+
+impl Calibration {
+    /// Load a calibration from a YAML file
+    #[must_use = "the calibration or the reason it could not be loaded is only in the Result"]
+    pub fn from_yaml<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_yaml_str(&read(path.as_ref())?)
+    }
+
+    /// Parse a calibration from YAML text
+    #[must_use = "the calibration or the reason it could not be loaded is only in the Result"]
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(yaml).map_err(|error| Error::Calibration(error.to_string()))
+    }
+
+    /// Load a calibration from a JSON file
+    #[must_use = "the calibration or the reason it could not be loaded is only in the Result"]
+    pub fn from_json<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_json_str(&read(path.as_ref())?)
+    }
+
+    /// Parse a calibration from JSON text
+    #[must_use = "the calibration or the reason it could not be loaded is only in the Result"]
+    pub fn from_json_str(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|error| Error::Calibration(error.to_string()))
+    }
+
+    /// Build a camera rig, ready for `Tracker::new`
+    #[must_use = "the rig or the reason it could not be built is only in the Result"]
+    pub fn to_rig(&self) -> Result<CameraRig, Error> {
+        if self.cameras.is_empty() {
+            return Err(Error::Calibration("no cameras".to_string()));
+        }
+        let cameras = self
+            .cameras
+            .iter()
+            .enumerate()
+            .map(|(index, camera)| {
+                camera.to_camera().map_err(|error| match error {
+                    Error::Calibration(reason) => Error::Calibration(format!("camera {}: {}", index, reason)),
+                    error => error,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(CameraRig::new(cameras))
+    }
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|error| Error::Calibration(format!("{}: {}", path.display(), error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = "
+cameras:
+  - width: 640
+    height: 480
+    model: brown5k
+    cx: 320.5
+    cy: 240.25
+    fx: 385.0
+    fy: 386.0
+    distortion: [-0.05, 0.04, 0.0, 0.001, -0.0005]
+  - width: 640
+    height: 480
+    model: pinhole
+    cx: 319.5
+    cy: 239.5
+    fx: 385.0
+    fy: 385.0
+    extrinsics:
+      rotation: [[0, -1, 0], [1, 0, 0], [0, 0, 1]]
+      translation: [0.05, 0, 0]
+";
+
+    #[test]
+    fn test_calibration_from_yaml_and_json() {
+        let calibration = Calibration::from_yaml_str(YAML).unwrap();
+        let json = serde_json::to_string(&calibration).unwrap();
+        assert_eq!(Calibration::from_json_str(&json).unwrap(), calibration);
+
+        let rig = calibration.to_rig().unwrap();
+        let inner = rig.as_inner();
+        assert_eq!(inner.num_cameras, 2);
+
+        let cameras = unsafe { std::slice::from_raw_parts(inner.cameras, 2) };
+        let parameters = unsafe { std::slice::from_raw_parts(cameras[0].parameters, 9) };
+        assert_eq!(parameters, [320.5, 240.25, 385.0, 386.0, -0.05, 0.04, 0.0, 0.001, -0.0005]);
+        assert_eq!(cameras[0].pose.r, [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+
+        // Row-major in the file, column-major in cuVSLAM
+        assert_eq!(cameras[1].num_parameters, 4);
+        assert_eq!(cameras[1].pose.r, [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(cameras[1].pose.t, [0.05, 0.0, 0.0]);
+        assert_eq!(crate::validate_rig(&rig), Ok(()));
+    }
+
+    #[test]
+    fn test_calibration_errors() {
+        let mut calibration = Calibration::from_yaml_str(YAML).unwrap();
+        calibration.cameras[1].model = DistortionModel::Fisheye4;
+        assert_eq!(
+            calibration.to_rig().err(),
+            Some(Error::Calibration("camera 1: Fisheye4 expects 4 distortion coefficients, got 0".to_string()))
+        );
+
+        assert_eq!(Calibration { cameras: vec![] }.to_rig().err(), Some(Error::Calibration("no cameras".to_string())));
+        assert!(matches!(Calibration::from_yaml_str("cameras: [{ width: 640 }]"), Err(Error::Calibration(_))));
+        assert!(matches!(Calibration::from_json("/nonexistent/calibration.json"), Err(Error::Calibration(_))));
+    }
+}
@@ -0,0 +1,250 @@
+//! Builder for tracker configurations
+
+use crate::{init_default_configuration, CUVSLAM_Configuration};
+
+/// Starting points for common platforms, applied by `ConfigurationBuilder::preset`
+///
+/// Each preset only changes the fields listed on it; everything else keeps cuVSLAM's defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigurationPreset {
+    /// Hand-held stereo camera: motion model on, planar constraints off
+    Handheld,
+    /// Aerial vehicle: motion model on, image denoising on against vibration-induced noise,
+    /// planar constraints off
+    Drone,
+    /// Wheeled robot on flat floors: motion model on, planar constraints on
+    GroundRobot,
+    /// Slow indoor mapping: localization and mapping on with loop closure run on every frame
+    /// (`slam_sync_mode` on, throttling off) and an unlimited keyframe count
+    IndoorHighAccuracy,
+    /// Fast outdoor motion: motion model on, localization and mapping off to keep per-frame
+    /// latency low (along with reading SLAM internals, which requires it), planar constraints off
+    OutdoorHighSpeed,
+}
+
+/// Builder for a `CUVSLAM_Configuration`, starting from cuVSLAM's defaults
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigurationBuilder {
+    config: CUVSLAM_Configuration,
+}
+
+impl ConfigurationBuilder {
+    /// Start from the library's default configuration
+    pub fn new() -> Self {
+        Self::from(init_default_configuration())
+    }
+
+    /// Start from the library's default configuration with `preset` applied
+    pub fn preset(preset: ConfigurationPreset) -> Self {
+        Self::new().with_preset(preset)
+    }
+
+    /// Apply the field changes documented on `preset`
+    fn with_preset(mut self, preset: ConfigurationPreset) -> Self {
+        let config = &mut self.config;
+        match preset {
+            ConfigurationPreset::Handheld => {
+                config.use_motion_model = 1;
+                config.planar_constraints = 0;
+            }
+            ConfigurationPreset::Drone => {
+                config.use_motion_model = 1;
+                config.use_denoising = 1;
+                config.planar_constraints = 0;
+            }
+            ConfigurationPreset::GroundRobot => {
+                config.use_motion_model = 1;
+                config.planar_constraints = 1;
+            }
+            ConfigurationPreset::IndoorHighAccuracy => {
+                config.enable_localization_n_mapping = 1;
+                config.slam_sync_mode = 1;
+                config.slam_throttling_time_ms = 0;
+                config.slam_max_map_size = 0;
+            }
+            ConfigurationPreset::OutdoorHighSpeed => {
+                config.use_motion_model = 1;
+                config.enable_localization_n_mapping = 0;
+                config.enable_reading_slam_internals = 0;
+                config.planar_constraints = 0;
+            }
+        }
+        self
+    }
+
+    /// Set an upper bound on the keyframes retained in the SLAM map, 0 for unlimited
+    ///
+    /// Sets `slam_max_map_size`. Only used with `enable_localization_n_mapping`.
+    pub fn max_keyframes(mut self, n: u32) -> Self {
+        self.config.slam_max_map_size = n;
+        self
+    }
+
+    /// Turn cuVSLAM's constant-velocity motion model on or off
+    ///
+    /// The motion model predicts each frame's pose from the previous ones to seed feature
+    /// matching. A `predicted_pose` passed to `Tracker::track` replaces that prediction outright
+    /// for the frame; cuVSLAM has no setting to weight one against the other. With good external
+    /// predictions, such as wheel odometry, pass them on every frame; the motion model then only
+    /// matters for frames tracked without one.
+    pub fn motion_model(mut self, enabled: bool) -> Self {
+        self.config.use_motion_model = enabled as i32;
+        self
+    }
+
+    /// Configure cuVSLAM for run-to-run reproducible trajectories
+    ///
+    /// Runs SLAM synchronously inside `track` instead of on a background thread, and disables
+    /// time-based SLAM throttling so the work done per frame no longer depends on wall-clock
+    /// timing. cuVSLAM has no seed or thread-count settings, and GPU kernels may still reorder
+    /// floating point reductions, so trajectories can differ in the last bits between runs.
+    /// Passing `false` switches SLAM back to asynchronous mode.
+    pub fn deterministic(mut self, enabled: bool) -> Self {
+        self.config.slam_sync_mode = enabled as i32;
+        if enabled {
+            self.config.slam_throttling_time_ms = 0;
+        }
+        self
+    }
+
+    /// Check the configuration built so far (see `validate_configuration`)
+    pub fn validate(&self) -> Result<(), String> {
+        validate(&self.config)
+    }
+
+    /// Finish building the configuration
+    pub fn build(self) -> CUVSLAM_Configuration {
+        self.config
+    }
+}
+
+impl Default for ConfigurationBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<CUVSLAM_Configuration> for ConfigurationBuilder {
+    /// Start from an existing configuration
+    fn from(config: CUVSLAM_Configuration) -> Self {
+        Self { config }
+    }
+}
+
+/// Rig-independent configuration rules, documented on `validate_configuration`
+pub(crate) fn validate(config: &CUVSLAM_Configuration) -> Result<(), String> {
+    if config.enable_imu_fusion != 0 {
+        let imu = &config.imu_calibration;
+        if imu.frequency <= 0.0 {
+            return Err("enable_imu_fusion requires an IMU calibration with a positive frequency".to_string());
+        }
+        let noise = [
+            ("gyroscope_noise_density", imu.gyroscope_noise_density),
+            ("gyroscope_random_walk", imu.gyroscope_random_walk),
+            ("accelerometer_noise_density", imu.accelerometer_noise_density),
+            ("accelerometer_random_walk", imu.accelerometer_random_walk),
+        ];
+        if let Some((name, _)) = noise.iter().find(|(_, value)| value.is_nan() || *value <= 0.0) {
+            return Err(format!("enable_imu_fusion requires a positive IMU {}", name));
+        }
+    }
+    if config.enable_reading_slam_internals != 0 && config.enable_localization_n_mapping == 0 {
+        return Err("enable_reading_slam_internals requires enable_localization_n_mapping".to_string());
+    }
+    if config.map_cell_size < 0.0 {
+        return Err("map_cell_size must not be negative".to_string());
+    }
+    if config.max_frame_delta_s < 0.0 {
+        return Err("max_frame_delta_s must not be negative".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_keyframes() {
+        // Plain data with nullable pointers, so all-zero is a valid value
+        let base: CUVSLAM_Configuration = unsafe { std::mem::zeroed() };
+        let config = ConfigurationBuilder::from(base).max_keyframes(300).build();
+        assert_eq!(config.slam_max_map_size, 300);
+        assert_eq!(config.enable_localization_n_mapping, base.enable_localization_n_mapping);
+    }
+
+    #[test]
+    fn test_motion_model() {
+        let base: CUVSLAM_Configuration = unsafe { std::mem::zeroed() };
+        let config = ConfigurationBuilder::from(base).motion_model(true).build();
+        assert_eq!(config.use_motion_model, 1);
+        assert_eq!(ConfigurationBuilder::from(config).motion_model(false).build().use_motion_model, 0);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let mut base: CUVSLAM_Configuration = unsafe { std::mem::zeroed() };
+        base.slam_throttling_time_ms = 500;
+
+        let config = ConfigurationBuilder::from(base).deterministic(true).build();
+        assert_eq!((config.slam_sync_mode, config.slam_throttling_time_ms), (1, 0));
+
+        let config = ConfigurationBuilder::from(config).deterministic(false).build();
+        assert_eq!(config.slam_sync_mode, 0);
+    }
+
+    #[test]
+    fn test_presets() {
+        let base: CUVSLAM_Configuration = unsafe { std::mem::zeroed() };
+        let preset = |preset| ConfigurationBuilder::from(base).with_preset(preset).build();
+
+        let ground = preset(ConfigurationPreset::GroundRobot);
+        assert_eq!((ground.use_motion_model, ground.planar_constraints), (1, 1));
+        assert_eq!(ground.enable_localization_n_mapping, 0);
+
+        let drone = preset(ConfigurationPreset::Drone);
+        assert_eq!((drone.use_denoising, drone.planar_constraints), (1, 0));
+
+        let indoor = preset(ConfigurationPreset::IndoorHighAccuracy);
+        assert_eq!((indoor.enable_localization_n_mapping, indoor.slam_sync_mode), (1, 1));
+
+        // Every preset yields a configuration that passes validation
+        for preset in [
+            ConfigurationPreset::Handheld,
+            ConfigurationPreset::Drone,
+            ConfigurationPreset::GroundRobot,
+            ConfigurationPreset::IndoorHighAccuracy,
+            ConfigurationPreset::OutdoorHighSpeed,
+        ] {
+            assert_eq!(ConfigurationBuilder::from(base).with_preset(preset).validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let base: CUVSLAM_Configuration = unsafe { std::mem::zeroed() };
+        assert_eq!(ConfigurationBuilder::from(base).validate(), Ok(()));
+
+        let mut imu = base;
+        imu.enable_imu_fusion = 1;
+        imu.imu_calibration.frequency = 200.0;
+        assert_eq!(
+            validate(&imu),
+            Err("enable_imu_fusion requires a positive IMU gyroscope_noise_density".to_string())
+        );
+        imu.imu_calibration.gyroscope_noise_density = 1e-4;
+        imu.imu_calibration.gyroscope_random_walk = 1e-5;
+        imu.imu_calibration.accelerometer_noise_density = 1e-3;
+        imu.imu_calibration.accelerometer_random_walk = f32::NAN;
+        assert!(validate(&imu).is_err());
+        imu.imu_calibration.accelerometer_random_walk = 1e-4;
+        assert_eq!(validate(&imu), Ok(()));
+
+        let mut internals = base;
+        internals.enable_reading_slam_internals = 1;
+        assert!(validate(&internals).is_err());
+        internals.enable_localization_n_mapping = 1;
+        assert_eq!(validate(&internals), Ok(()));
+    }
+}
@@ -0,0 +1,143 @@
+//! Minimal CUDA runtime queries used by the `cuda` feature
+
+use std::ops::{Deref, DerefMut};
+use std::os::raw::{c_int, c_uint, c_void};
+use std::ptr::NonNull;
+
+extern "C" {
+    fn cudaGetDeviceCount(count: *mut c_int) -> c_int;
+    fn cudaHostAlloc(ptr: *mut *mut c_void, size: usize, flags: c_uint) -> c_int;
+    fn cudaFreeHost(ptr: *mut c_void) -> c_int;
+    fn cudaMemGetInfo(free: *mut usize, total: *mut usize) -> c_int;
+}
+
+/// `cudaHostAllocDefault`
+const HOST_ALLOC_DEFAULT: c_uint = 0;
+
+/// Check that at least one CUDA device is usable, returning the device count
+pub(crate) fn probe() -> Result<i32, String> {
+    let mut count = 0;
+    let error = unsafe { cudaGetDeviceCount(&mut count) };
+    if error != 0 {
+        return Err(format!("cudaGetDeviceCount failed with error code {}", error));
+    }
+    if count == 0 {
+        return Err("no CUDA devices found".to_string());
+    }
+    Ok(count)
+}
+
+/// Free and total memory of the current device, in bytes
+pub(crate) fn mem_info() -> Result<(u64, u64), String> {
+    let (mut free, mut total) = (0, 0);
+    let error = unsafe { cudaMemGetInfo(&mut free, &mut total) };
+    if error != 0 {
+        return Err(format!("cudaMemGetInfo failed with error code {}", error));
+    }
+    Ok((free as u64, total as u64))
+}
+
+/// Zero-initialized host buffer in page-locked memory, for faster uploads to the GPU
+///
+/// If CUDA cannot allocate pinned memory (e.g. no device is present) the buffer silently falls
+/// back to a regular heap allocation; `is_pinned` reports which one was used.
+pub struct PinnedBuffer {
+    storage: Storage,
+}
+
+enum Storage {
+    Pinned { ptr: NonNull<u8>, len: usize },
+    Heap(Box<[u8]>),
+}
+
+// The pinned allocation is uniquely owned, like the boxed slice
+unsafe impl Send for PinnedBuffer {}
+unsafe impl Sync for PinnedBuffer {}
+
+impl PinnedBuffer {
+    /// Allocate `len` zeroed bytes
+    pub fn new(len: usize) -> Self {
+        let storage = Self::alloc_pinned(len).unwrap_or_else(|| Storage::Heap(vec![0; len].into_boxed_slice()));
+        Self { storage }
+    }
+
+    /// Copy `data` into a new buffer
+    pub fn from_slice(data: &[u8]) -> Self {
+        let mut buffer = Self::new(data.len());
+        buffer.copy_from_slice(data);
+        buffer
+    }
+
+    /// Whether the buffer lives in page-locked memory
+    pub fn is_pinned(&self) -> bool {
+        matches!(self.storage, Storage::Pinned { .. })
+    }
+
+    fn alloc_pinned(len: usize) -> Option<Storage> {
+        if len == 0 {
+            return None;
+        }
+        let mut ptr = std::ptr::null_mut();
+        let error = unsafe { cudaHostAlloc(&mut ptr, len, HOST_ALLOC_DEFAULT) };
+        let ptr = NonNull::new(ptr.cast::<u8>()).filter(|_| error == 0)?;
+        unsafe { ptr.as_ptr().write_bytes(0, len) };
+        Some(Storage::Pinned { ptr, len })
+    }
+}
+
+impl Deref for PinnedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.storage {
+            Storage::Pinned { ptr, len } => unsafe { std::slice::from_raw_parts(ptr.as_ptr(), *len) },
+            Storage::Heap(data) => data,
+        }
+    }
+}
+
+impl DerefMut for PinnedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.storage {
+            Storage::Pinned { ptr, len } => unsafe { std::slice::from_raw_parts_mut(ptr.as_ptr(), *len) },
+            Storage::Heap(data) => data,
+        }
+    }
+}
+
+impl Drop for PinnedBuffer {
+    fn drop(&mut self) {
+        if let Storage::Pinned { ptr, .. } = self.storage {
+            unsafe { cudaFreeHost(ptr.as_ptr().cast()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_buffer_contents() {
+        let mut buffer = PinnedBuffer::new(16);
+        assert_eq!(buffer.len(), 16);
+        assert!(buffer.iter().all(|&b| b == 0));
+        buffer[3] = 7;
+        assert_eq!(PinnedBuffer::from_slice(&buffer)[3], 7);
+        assert!(!PinnedBuffer::new(0).is_pinned());
+    }
+
+    #[test]
+    fn test_pinned_buffer_drop_on_panic() {
+        // Unwinding drops each buffer exactly once; a double free would abort the test
+        for _ in 0..8 {
+            let result = std::panic::catch_unwind(|| {
+                let buffer = PinnedBuffer::new(1 << 20);
+                let moved = buffer;
+                assert_eq!(moved.len(), 1 << 20);
+                panic!("dropped while unwinding");
+            });
+            assert!(result.is_err());
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Readers for public SLAM benchmark datasets (`datasets` feature)
+//!
+//! Each reader turns a sequence on disk into a camera rig, a configuration and per-frame images
+//! ready for `Tracker::track`, plus any IMU measurements and ground truth it provides. Pair them
+//! with `write_tum` and `absolute_trajectory_error` to evaluate the tracker. Recordings in no
+//! particular layout can be read with `folder::FolderSource`, and live sessions recorded in
+//! that layout with `recorder::SessionRecorder`.
+
+pub mod euroc;
+pub mod folder;
+pub mod kitti;
+pub mod playback;
+pub mod recorder;
+pub mod tum_vi;
+
+use crate::{compose_poses, Calibration, CameraRig, Error, FrameSet, FrameSource, Image, CUVSLAM_Pose};
+use std::path::{Path, PathBuf};
+
+/// One frame of a stereo sequence, with the image files of cameras 0 and 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StereoFrame {
+    /// Timestamp used for both images
+    pub timestamp_ns: i64,
+    /// Image files of cameras 0 and 1
+    pub paths: [PathBuf; 2],
+}
+
+impl StereoFrame {
+    /// Load both images, ready for `Tracker::track`
+    #[must_use = "the images or the reason they could not be loaded are only in the Result"]
+    pub fn load(&self) -> Result<Vec<Image>, Error> {
+        self.paths
+            .iter()
+            .zip(0..)
+            .map(|(path, camera_index)| {
+                Image::open(path, camera_index, self.timestamp_ns)
+                    .map_err(|error| Error::Dataset(format!("{}: {}", path.display(), error)))
+            })
+            .collect()
+    }
+}
+
+/// `FrameSource` over stereo frames, loading each pair when it is reached
+pub struct StereoSource<'a> {
+    frames: std::slice::Iter<'a, StereoFrame>,
+    calibration: &'a Calibration,
+}
+
+impl<'a> StereoSource<'a> {
+    /// Play `frames` in order, reporting `calibration` as the rig
+    pub fn new(frames: &'a [StereoFrame], calibration: &'a Calibration) -> Self {
+        Self { frames: frames.iter(), calibration }
+    }
+}
+
+impl FrameSource for StereoSource<'_> {
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>> {
+        let frame = self.frames.next()?;
+        Some(frame.load().map(|images| FrameSet { timestamp_ns: frame.timestamp_ns, images }))
+    }
+
+    fn calibration(&self) -> Option<CameraRig> {
+        self.calibration.to_rig().ok()
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        Some(self.frames.len())
+    }
+}
+
+/// Half turn about x between OpenCV camera axes (x right, y down, z forward), which dataset
+/// calibrations use, and cuVSLAM's (x right, y up, z backward); it is its own inverse
+const OPTICAL_FLIP: CUVSLAM_Pose = CUVSLAM_Pose {
+    r: [1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0],
+    t: [0.0; 3],
+};
+
+/// Re-express `camera_from_x`, given for a camera in OpenCV axes, in the camera's cuVSLAM axes
+fn from_optical(camera_from_x: &CUVSLAM_Pose) -> CUVSLAM_Pose {
+    compose_poses(&OPTICAL_FLIP, camera_from_x)
+}
+
+/// Read the rows of a comma-separated file, skipping blank lines and `#` comments
+fn read_csv(path: &Path) -> Result<Vec<Vec<String>>, Error> {
+    let text = read_to_string(path)?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split(',').map(|field| field.trim().to_string()).collect())
+        .collect())
+}
+
+/// Parse one field of a row read by `read_csv`
+fn parse_field<T: std::str::FromStr>(path: &Path, row: &[String], index: usize) -> Result<T, Error> {
+    row.get(index)
+        .and_then(|field| field.parse().ok())
+        .ok_or_else(|| Error::Dataset(format!("{}: bad row {:?}", path.display(), row.join(","))))
+}
+
+fn read_to_string(path: &Path) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|error| Error::Dataset(format!("{}: {}", path.display(), error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_csv() {
+        let path = std::env::temp_dir().join(format!("cuvslam-csv-{}.csv", std::process::id()));
+        std::fs::write(&path, "#timestamp [ns],filename\n1403636579763555584, 1403636579763555584.png\n\n2,b\n")
+            .unwrap();
+        let rows = read_csv(&path).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(parse_field::<i64>(&path, &rows[0], 0).unwrap(), 1_403_636_579_763_555_584);
+        assert_eq!(rows[0][1], "1403636579763555584.png");
+        assert!(matches!(parse_field::<i64>(&path, &rows[1], 1), Err(Error::Dataset(_))));
+        assert!(matches!(parse_field::<i64>(&path, &rows[1], 2), Err(Error::Dataset(_))));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(read_csv(&path), Err(Error::Dataset(_))));
+    }
+}
@@ -0,0 +1,357 @@
+//! EuRoC MAV sequences in the ASL folder layout
+//!
+//! A sequence directory (or its `mav0` subdirectory) holds `cam0` and `cam1` with a `data.csv`
+//! of `timestamp [ns],filename` rows, PNGs under `data/` and a `sensor.yaml` calibration, and
+//! optionally `imu0` and `state_groundtruth_estimate0`. The rig frame is `cam0`'s, so its pose in
+//! the body frame is kept in `rig_in_body` for comparing against ground truth. `T_BS` gives the
+//! cameras in OpenCV axes (y down, z forward); they are turned into cuVSLAM's (y up, z backward).
+
+use super::{from_optical, parse_field, read_csv, read_to_string, StereoFrame, StereoSource};
+use crate::{
+    compose_poses, invert_pose, Calibration, CameraCalibration, ConfigurationBuilder, DistortionModel, Error,
+    Extrinsics, CUVSLAM_Configuration, CUVSLAM_ImuCalibration, CUVSLAM_ImuMeasurement, CUVSLAM_Pose,
+};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Largest timestamp difference for `cam0` and `cam1` images to be paired into one frame
+const PAIRING_TOLERANCE_NS: i64 = 1_000_000;
+
+/// A parsed EuRoC sequence
+#[derive(Debug, Clone)]
+pub struct EurocSequence {
+    /// Stereo frames in timestamp order, stamped with the `cam0` timestamp
+    pub frames: Vec<StereoFrame>,
+    /// Calibration of `cam0` and `cam1` in the `cam0` frame
+    pub calibration: Calibration,
+    /// Pose of the rig (`cam0` in cuVSLAM axes) in the body frame used by the ground truth
+    pub rig_in_body: CUVSLAM_Pose,
+    /// IMU noise parameters and pose in the rig frame, if the sequence has `imu0`
+    pub imu_calibration: Option<CUVSLAM_ImuCalibration>,
+    /// `imu0` measurements in timestamp order
+    pub imu: Vec<CUVSLAM_ImuMeasurement>,
+    /// Ground truth body positions in timestamp order, empty if the sequence has none
+    pub ground_truth: Vec<(i64, [f64; 3])>,
+}
+
+impl EurocSequence {
+    /// Parse the sequence at `dir`, without loading any images
+    #[must_use = "the sequence or the reason it could not be read is only in the Result"]
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let root = if dir.join("mav0").is_dir() { dir.join("mav0") } else { dir.to_path_buf() };
+
+        let (cam0, body_from_cam0) = read_camera(&root.join("cam0/sensor.yaml"))?;
+        let (mut cam1, body_from_cam1) = read_camera(&root.join("cam1/sensor.yaml"))?;
+        let (rig_from_body, rig_from_cam1) = rig_poses(&body_from_cam0, &body_from_cam1);
+        cam1.extrinsics = Extrinsics::from_pose(&rig_from_cam1);
+
+        let imu_dir = root.join("imu0");
+        let (imu_calibration, imu) = if imu_dir.is_dir() {
+            let sensor = read_imu(&imu_dir.join("sensor.yaml"))?;
+            let calibration = CUVSLAM_ImuCalibration {
+                left_from_imu: compose_poses(&rig_from_body, &sensor.body_from_imu),
+                ..sensor.calibration
+            };
+            (Some(calibration), read_imu_measurements(&imu_dir.join("data.csv"))?)
+        } else {
+            (None, Vec::new())
+        };
+
+        let ground_truth_path = root.join("state_groundtruth_estimate0/data.csv");
+        let ground_truth = if ground_truth_path.is_file() {
+            read_csv(&ground_truth_path)?
+                .iter()
+                .map(|row| {
+                    let position = [1, 2, 3].map(|i| parse_field::<f64>(&ground_truth_path, row, i));
+                    let [x, y, z] = position;
+                    Ok((parse_field(&ground_truth_path, row, 0)?, [x?, y?, z?]))
+                })
+                .collect::<Result<_, Error>>()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            frames: pair_frames(&read_images(&root.join("cam0"))?, &read_images(&root.join("cam1"))?),
+            calibration: Calibration { cameras: vec![cam0, cam1] },
+            rig_in_body: invert_pose(&rig_from_body),
+            imu_calibration,
+            imu,
+            ground_truth,
+        })
+    }
+
+    /// Tracker configuration for this sequence, with IMU fusion if `use_imu` and it has an IMU
+    ///
+    /// EuRoC images are not rectified, so `horizontal_stereo_camera` is off.
+    pub fn configuration(&self, use_imu: bool) -> CUVSLAM_Configuration {
+        let mut config = ConfigurationBuilder::new().build();
+        config.horizontal_stereo_camera = 0;
+        if let (true, Some(imu_calibration)) = (use_imu, self.imu_calibration) {
+            config.imu_calibration = imu_calibration;
+            config.enable_imu_fusion = 1;
+        }
+        config
+    }
+
+    /// Frames of the sequence as a `FrameSource`
+    pub fn source(&self) -> StereoSource<'_> {
+        StereoSource::new(&self.frames, &self.calibration)
+    }
+
+    /// Convert a tracked rig pose to the body pose the ground truth describes
+    pub fn body_pose(&self, rig_pose: &CUVSLAM_Pose) -> CUVSLAM_Pose {
+        compose_poses(rig_pose, &invert_pose(&self.rig_in_body))
+    }
+}
+
+/// The rig's pose relative to the body and `cam1`'s in the rig, in cuVSLAM axes, from both
+/// cameras' `T_BS`
+fn rig_poses(body_from_cam0: &CUVSLAM_Pose, body_from_cam1: &CUVSLAM_Pose) -> (CUVSLAM_Pose, CUVSLAM_Pose) {
+    let rig_from_body = from_optical(&invert_pose(body_from_cam0));
+    let cam1_from_body = from_optical(&invert_pose(body_from_cam1));
+    (rig_from_body, compose_poses(&rig_from_body, &invert_pose(&cam1_from_body)))
+}
+
+/// 4x4 row-major matrix as written in `sensor.yaml`
+#[derive(Deserialize)]
+struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f32>,
+}
+
+impl Matrix {
+    fn to_pose(&self, path: &Path) -> Result<CUVSLAM_Pose, Error> {
+        if (self.rows, self.cols, self.data.len()) != (4, 4, 16) {
+            return Err(Error::Dataset(format!("{}: T_BS is not a 4x4 matrix", path.display())));
+        }
+        Ok(CUVSLAM_Pose {
+            r: std::array::from_fn(|i| self.data[(i % 3) * 4 + i / 3]),
+            t: std::array::from_fn(|row| self.data[row * 4 + 3]),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct CameraSensor {
+    #[serde(rename = "T_BS")]
+    body_from_sensor: Matrix,
+    resolution: [i32; 2],
+    camera_model: String,
+    intrinsics: [f32; 4],
+    distortion_model: String,
+    distortion_coefficients: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct ImuSensor {
+    #[serde(rename = "T_BS")]
+    body_from_sensor: Matrix,
+    rate_hz: f32,
+    gyroscope_noise_density: f32,
+    gyroscope_random_walk: f32,
+    accelerometer_noise_density: f32,
+    accelerometer_random_walk: f32,
+}
+
+struct ImuParameters {
+    body_from_imu: CUVSLAM_Pose,
+    calibration: CUVSLAM_ImuCalibration,
+}
+
+/// Parse a `sensor.yaml`, dropping the `%YAML:1.0` directive OpenCV writes, which isn't valid YAML
+pub(super) fn read_sensor<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, Error> {
+    let text = read_to_string(path)?;
+    let yaml: String = text.lines().filter(|line| !line.starts_with('%')).flat_map(|line| [line, "\n"]).collect();
+    serde_yaml::from_str(&yaml).map_err(|error| Error::Dataset(format!("{}: {}", path.display(), error)))
+}
+
+/// Import a camera's calibration, with its pose in the body frame
+fn read_camera(path: &Path) -> Result<(CameraCalibration, CUVSLAM_Pose), Error> {
+    let sensor: CameraSensor = read_sensor(path)?;
+    let calibration = camera_calibration(
+        path,
+        &sensor.camera_model,
+        &sensor.distortion_model,
+        &sensor.distortion_coefficients,
+        sensor.intrinsics,
+        sensor.resolution,
+    )?;
+    Ok((calibration, sensor.body_from_sensor.to_pose(path)?))
+}
+
+/// Build a camera from the fields EuRoC and Kalibr calibrations share, with identity extrinsics
+///
+/// `intrinsics` are `[fu, fv, cu, cv]`, `resolution` is `[width, height]`.
+pub(super) fn camera_calibration(
+    path: &Path,
+    camera_model: &str,
+    distortion_model: &str,
+    coefficients: &[f32],
+    intrinsics: [f32; 4],
+    resolution: [i32; 2],
+) -> Result<CameraCalibration, Error> {
+    if camera_model != "pinhole" {
+        return Err(Error::Dataset(format!("{}: unsupported camera model {}", path.display(), camera_model)));
+    }
+    let k = coefficients;
+    let (model, distortion) = match (distortion_model, k.len()) {
+        // k1, k2, p1, p2 in OpenCV's order, with no k3
+        ("radial-tangential", 4) => (DistortionModel::Brown5k, vec![k[0], k[1], 0.0, k[2], k[3]]),
+        ("equidistant", 4) => (DistortionModel::Fisheye4, k.to_vec()),
+        (model, count) => {
+            return Err(Error::Dataset(format!(
+                "{}: unsupported distortion model {} with {} coefficients",
+                path.display(),
+                model,
+                count
+            )))
+        }
+    };
+
+    let [fx, fy, cx, cy] = intrinsics;
+    Ok(CameraCalibration {
+        width: resolution[0],
+        height: resolution[1],
+        model,
+        cx,
+        cy,
+        fx,
+        fy,
+        distortion,
+        extrinsics: Extrinsics::default(),
+    })
+}
+
+fn read_imu(path: &Path) -> Result<ImuParameters, Error> {
+    let sensor: ImuSensor = read_sensor(path)?;
+    let body_from_imu = sensor.body_from_sensor.to_pose(path)?;
+    Ok(ImuParameters {
+        body_from_imu,
+        calibration: CUVSLAM_ImuCalibration {
+            left_from_imu: body_from_imu,
+            gyroscope_noise_density: sensor.gyroscope_noise_density,
+            gyroscope_random_walk: sensor.gyroscope_random_walk,
+            accelerometer_noise_density: sensor.accelerometer_noise_density,
+            accelerometer_random_walk: sensor.accelerometer_random_walk,
+            frequency: sensor.rate_hz,
+        },
+    })
+}
+
+/// Rows of `timestamp [ns], w_x, w_y, w_z [rad/s], a_x, a_y, a_z [m/s^2]`
+pub(super) fn read_imu_measurements(path: &Path) -> Result<Vec<CUVSLAM_ImuMeasurement>, Error> {
+    read_csv(path)?
+        .iter()
+        .map(|row| {
+            let values = [1, 2, 3, 4, 5, 6].map(|i| parse_field::<f32>(path, row, i));
+            let [wx, wy, wz, ax, ay, az] = values;
+            Ok(CUVSLAM_ImuMeasurement {
+                timestamp_ns: parse_field(path, row, 0)?,
+                linear_accelerations: [ax?, ay?, az?],
+                angular_velocities: [wx?, wy?, wz?],
+            })
+        })
+        .collect()
+}
+
+/// Timestamps and image paths from a camera's `data.csv`
+pub(super) fn read_images(camera_dir: &Path) -> Result<Vec<(i64, PathBuf)>, Error> {
+    let path = camera_dir.join("data.csv");
+    read_csv(&path)?
+        .iter()
+        .map(|row| {
+            let file = row.get(1).ok_or_else(|| Error::Dataset(format!("{}: missing filename", path.display())))?;
+            Ok((parse_field(&path, row, 0)?, camera_dir.join("data").join(file)))
+        })
+        .collect()
+}
+
+/// Match up images from the two cameras, dropping any without a partner
+pub(super) fn pair_frames(cam0: &[(i64, PathBuf)], cam1: &[(i64, PathBuf)]) -> Vec<StereoFrame> {
+    let mut frames = Vec::with_capacity(cam0.len().min(cam1.len()));
+    let (mut left, mut right) = (cam0.iter().peekable(), cam1.iter().peekable());
+    while let (Some((t0, path0)), Some((t1, path1))) = (left.peek(), right.peek()) {
+        if (t0 - t1).abs() <= PAIRING_TOLERANCE_NS {
+            frames.push(StereoFrame { timestamp_ns: *t0, paths: [path0.clone(), path1.clone()] });
+            left.next();
+            right.next();
+        } else if t0 < t1 {
+            left.next();
+        } else {
+            right.next();
+        }
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A camera a quarter turn about z from the body and offset along all three axes
+    const SENSOR_YAML: &str = "%YAML:1.0
+sensor_type: camera
+T_BS:
+  cols: 4
+  rows: 4
+  data: [0.0, -1.0, 0.0, -0.02,
+         1.0, 0.0, 0.0, -0.06,
+         0.0, 0.0, 1.0, 0.01,
+         0.0, 0.0, 0.0, 1.0]
+rate_hz: 20
+resolution: [752, 480]
+camera_model: pinhole
+intrinsics: [458.654, 457.296, 367.215, 248.375] #fu, fv, cu, cv
+distortion_model: radial-tangential
+distortion_coefficients: [-0.2834, 0.07396, 0.0001936, 1.762e-05]
+";
+
+    #[test]
+    fn test_pair_frames() {
+        let files = |timestamps: &[i64]| -> Vec<(i64, PathBuf)> {
+            timestamps.iter().map(|&t| (t, PathBuf::from(format!("{}.png", t)))).collect()
+        };
+        // cam1 is missing the second frame and has a stray early image
+        let frames = pair_frames(
+            &files(&[0, 50_000_000, 100_000_000]),
+            &files(&[-30_000_000, 200, 100_000_500]),
+        );
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].timestamp_ns, 0);
+        assert_eq!(frames[0].paths[1], PathBuf::from("200.png"));
+        assert_eq!(frames[1].timestamp_ns, 100_000_000);
+    }
+
+    #[test]
+    fn test_read_camera() {
+        let path = std::env::temp_dir().join(format!("cuvslam-euroc-sensor-{}.yaml", std::process::id()));
+        std::fs::write(&path, SENSOR_YAML).unwrap();
+        let (camera, body_from_camera) = read_camera(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((camera.width, camera.height, camera.model), (752, 480, DistortionModel::Brown5k));
+        assert_eq!((camera.fx, camera.fy, camera.cx, camera.cy), (458.654, 457.296, 367.215, 248.375));
+        assert_eq!(camera.distortion, [-0.2834, 0.07396, 0.0, 0.0001936, 1.762e-05]);
+        assert_eq!(body_from_camera.r, [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(body_from_camera.t, [-0.02, -0.06, 0.01]);
+    }
+
+    #[test]
+    fn test_rig_poses_use_cuvslam_axes() {
+        let path = std::env::temp_dir().join(format!("cuvslam-euroc-rig-{}.yaml", std::process::id()));
+        std::fs::write(&path, SENSOR_YAML).unwrap();
+        let (_, body_from_cam1) = read_camera(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // With cam0 at the body, the rig is the body with y and z flipped
+        let identity = CUVSLAM_Pose { r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], t: [0.0; 3] };
+        let (rig_from_body, rig_from_cam1) = rig_poses(&identity, &body_from_cam1);
+        assert_eq!(rig_from_body.r, [1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0]);
+        // The baseline's y and z change sign and the quarter turn about z reverses
+        assert_eq!(rig_from_cam1.t, [-0.02, 0.06, -0.01]);
+        assert_eq!(rig_from_cam1.r, [0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,356 @@
+//! Folders of images with timestamps from a file or from the file names
+//!
+//! Each camera is given as a path whose file name may contain `*` and `?` wildcards, such as
+//! `left/*.png`. Images are timestamped either from a text file, line by line in file name order,
+//! or by parsing the file names. Cameras are then paired by nearest timestamp: every image of
+//! camera 0 takes the closest unused image of each other camera within a maximum skew, and is
+//! dropped if any camera has none, so missing frames on either side don't shift the pairing.
+
+use super::read_csv;
+use crate::{Calibration, CameraRig, Error, FrameSet, FrameSource, Image};
+use std::path::{Path, PathBuf};
+
+/// Where a `FolderSource` takes image timestamps from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Timestamps {
+    /// A text file with one line per image, applied to each camera's images in file name order
+    ///
+    /// The first comma-separated field of each line is the timestamp: integers are nanoseconds,
+    /// decimals (`1403636579.763555`, `4.144e+01`) seconds. Blank lines and `#` comments are skipped.
+    File(PathBuf),
+    /// A pattern matched against each file name, with strftime-like fields
+    ///
+    /// `%Y` (4 digits), `%m`, `%d`, `%H`, `%M` and `%S` (2 digits each) give a UTC date and time,
+    /// `%s` whole seconds since the Unix epoch, `%f` a fraction of a second of any length and `%N`
+    /// nanoseconds since the epoch. `*` matches any text and `%%` a literal `%`. For example
+    /// `%N.png` reads EuRoC names and `frame_%Y%m%d_%H%M%S.%f.png` names like
+    /// `frame_20240131_120000.250.png`.
+    FileName(String),
+}
+
+/// `FrameSource` over per-camera image folders
+pub struct FolderSource {
+    frames: std::vec::IntoIter<(i64, Vec<PathBuf>)>,
+    calibration: Option<Calibration>,
+}
+
+impl FolderSource {
+    /// List and pair the images matching `cameras`, one pattern per camera in camera index order
+    ///
+    /// Images are paired when their timestamps are at most `max_skew_ns` apart. No image is
+    /// loaded until it is played.
+    #[must_use = "the source or the reason it could not be opened is only in the Result"]
+    pub fn open<P: AsRef<Path>>(cameras: &[P], timestamps: &Timestamps, max_skew_ns: i64) -> Result<Self, Error> {
+        let cameras = cameras
+            .iter()
+            .map(|pattern| {
+                let paths = list_matching(pattern.as_ref())?;
+                let mut images = timestamp_images(paths, timestamps)?;
+                images.sort_by_key(|(timestamp_ns, _)| *timestamp_ns);
+                Ok(images)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Self { frames: pair_nearest(&cameras, max_skew_ns).into_iter(), calibration: None })
+    }
+
+    /// Report `calibration` as the rig the images were captured with
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+}
+
+impl FrameSource for FolderSource {
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>> {
+        let (timestamp_ns, paths) = self.frames.next()?;
+        let images = paths
+            .iter()
+            .zip(0..)
+            .map(|(path, camera_index)| {
+                Image::open(path, camera_index, timestamp_ns)
+                    .map_err(|error| Error::Dataset(format!("{}: {}", path.display(), error)))
+            })
+            .collect::<Result<_, _>>();
+        Some(images.map(|images| FrameSet { timestamp_ns, images }))
+    }
+
+    fn calibration(&self) -> Option<CameraRig> {
+        self.calibration.as_ref()?.to_rig().ok()
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        Some(self.frames.len())
+    }
+}
+
+/// Files matching a path with wildcards in its file name, sorted by name
+fn list_matching(pattern: &Path) -> Result<Vec<PathBuf>, Error> {
+    let bad_pattern = |reason: &str| Error::Dataset(format!("{}: {}", pattern.display(), reason));
+    let name = pattern.file_name().and_then(|name| name.to_str()).ok_or_else(|| bad_pattern("no file name"))?;
+    let dir = match pattern.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if dir.to_string_lossy().contains(['*', '?']) {
+        return Err(bad_pattern("wildcards are only supported in the file name"));
+    }
+
+    let entries = std::fs::read_dir(dir).map_err(|error| Error::Dataset(format!("{}: {}", dir.display(), error)))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let file_name = path.file_name().and_then(|name| name.to_str());
+            path.is_file() && file_name.is_some_and(|file_name| wildcard_match(name.as_bytes(), file_name.as_bytes()))
+        })
+        .collect();
+    if paths.is_empty() {
+        return Err(bad_pattern("no matching files"));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern, name) {
+        ([], []) => true,
+        ([b'*', rest @ ..], _) => (0..=name.len()).any(|skip| wildcard_match(rest, &name[skip..])),
+        ([b'?', pattern @ ..], [_, name @ ..]) => wildcard_match(pattern, name),
+        ([expected, pattern @ ..], [found, name @ ..]) => expected == found && wildcard_match(pattern, name),
+        _ => false,
+    }
+}
+
+/// Attach a timestamp to each of one camera's images, given in file name order
+fn timestamp_images(paths: Vec<PathBuf>, timestamps: &Timestamps) -> Result<Vec<(i64, PathBuf)>, Error> {
+    match timestamps {
+        Timestamps::File(file) => {
+            let rows = read_csv(file)?;
+            if rows.len() < paths.len() {
+                return Err(Error::Dataset(format!(
+                    "{}: {} timestamps for {} images",
+                    file.display(),
+                    rows.len(),
+                    paths.len()
+                )));
+            }
+            rows.iter()
+                .zip(paths)
+                .map(|(row, path)| {
+                    let field = row[0].as_str();
+                    let timestamp_ns = field
+                        .parse::<i64>()
+                        .ok()
+                        .or_else(|| field.parse::<f64>().ok().map(|seconds| (seconds * 1e9).round() as i64))
+                        .ok_or_else(|| Error::Dataset(format!("{}: bad timestamp {:?}", file.display(), field)))?;
+                    Ok((timestamp_ns, path))
+                })
+                .collect()
+        }
+        Timestamps::FileName(pattern) => paths
+            .into_iter()
+            .map(|path| {
+                let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+                let timestamp_ns = parse_file_timestamp(pattern, name).ok_or_else(|| {
+                    Error::Dataset(format!("{}: name does not match {:?}", path.display(), pattern))
+                })?;
+                Ok((timestamp_ns, path))
+            })
+            .collect(),
+    }
+}
+
+/// Date and time fields read from a file name
+#[derive(Debug, Clone, Copy, Default)]
+struct TimeFields {
+    /// Year, month, day, hour, minute and second
+    civil: [Option<i64>; 6],
+    unix_s: Option<i64>,
+    fraction_ns: i64,
+    ns: Option<i64>,
+}
+
+impl TimeFields {
+    fn timestamp_ns(&self) -> Option<i64> {
+        if let Some(ns) = self.ns {
+            return Some(ns);
+        }
+        let seconds = match (self.unix_s, self.civil) {
+            (Some(seconds), _) => seconds,
+            (None, [None, None, None, None, None, None]) => return None,
+            (None, [year, month, day, hour, minute, second]) => {
+                let days = days_from_civil(year.unwrap_or(1970), month.unwrap_or(1), day.unwrap_or(1));
+                days * 86_400 + hour.unwrap_or(0) * 3600 + minute.unwrap_or(0) * 60 + second.unwrap_or(0)
+            }
+        };
+        Some(seconds * 1_000_000_000 + self.fraction_ns)
+    }
+}
+
+/// Timestamp in nanoseconds encoded in `name`, per the `Timestamps::FileName` pattern syntax
+fn parse_file_timestamp(pattern: &str, name: &str) -> Option<i64> {
+    match_fields(pattern.as_bytes(), name.as_bytes(), TimeFields::default())?.timestamp_ns()
+}
+
+fn match_fields(pattern: &[u8], name: &[u8], mut fields: TimeFields) -> Option<TimeFields> {
+    match pattern {
+        [] => name.is_empty().then_some(fields),
+        [b'*', rest @ ..] => (0..=name.len()).find_map(|skip| match_fields(rest, &name[skip..], fields)),
+        [b'%', b'%', rest @ ..] => match_fields(rest, name.strip_prefix(b"%")?, fields),
+        [b'%', spec, rest @ ..] => {
+            let width = match spec {
+                b'Y' => Some(4),
+                b'm' | b'd' | b'H' | b'M' | b'S' => Some(2),
+                b's' | b'f' | b'N' => None,
+                _ => return None,
+            };
+            let digits = name.iter().take_while(|c| c.is_ascii_digit()).count();
+            let digits = width.unwrap_or(digits);
+            if digits == 0 || !name.get(..digits)?.iter().all(u8::is_ascii_digit) {
+                return None;
+            }
+            let text = std::str::from_utf8(&name[..digits]).ok()?;
+            match spec {
+                b'f' => {
+                    let kept = &text[..digits.min(9)];
+                    fields.fraction_ns = kept.parse::<i64>().ok()? * 10_i64.pow(9 - kept.len() as u32);
+                }
+                b's' => fields.unix_s = Some(text.parse().ok()?),
+                b'N' => fields.ns = Some(text.parse().ok()?),
+                _ => {
+                    let slot = b"YmdHMS".iter().position(|c| c == spec)?;
+                    fields.civil[slot] = Some(text.parse().ok()?);
+                }
+            }
+            match_fields(rest, &name[digits..], fields)
+        }
+        [expected, rest @ ..] => match_fields(rest, name.strip_prefix(&[*expected])?, fields),
+    }
+}
+
+/// Days from 1970-01-01 to a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Give every image of camera 0 the nearest unused image of each other camera, within `max_skew_ns`
+///
+/// Each camera's images must be sorted by timestamp. Frames use camera 0's timestamp.
+fn pair_nearest(cameras: &[Vec<(i64, PathBuf)>], max_skew_ns: i64) -> Vec<(i64, Vec<PathBuf>)> {
+    let Some((reference, others)) = cameras.split_first() else {
+        return Vec::new();
+    };
+    // First image of each other camera not yet paired
+    let mut unused = vec![0; others.len()];
+    let mut frames = Vec::with_capacity(reference.len());
+    for (timestamp_ns, path) in reference {
+        let matches: Option<Vec<usize>> = others
+            .iter()
+            .zip(&unused)
+            .map(|(images, &first)| {
+                let after = first + images[first..].partition_point(|(t, _)| t < timestamp_ns);
+                [after.checked_sub(1).filter(|&i| i >= first), Some(after).filter(|&i| i < images.len())]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|&i| (images[i].0 - timestamp_ns).abs())
+                    .filter(|&i| (images[i].0 - timestamp_ns).abs() <= max_skew_ns)
+            })
+            .collect();
+        let Some(matches) = matches else {
+            continue;
+        };
+        let mut paths = vec![path.clone()];
+        for ((images, unused), index) in others.iter().zip(&mut unused).zip(matches) {
+            paths.push(images[index].1.clone());
+            *unused = index + 1;
+        }
+        frames.push((*timestamp_ns, paths));
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_timestamp() {
+        assert_eq!(parse_file_timestamp("%N.png", "1403636579763555584.png"), Some(1_403_636_579_763_555_584));
+        assert_eq!(parse_file_timestamp("%s.%f.png", "1700000000.25.png"), Some(1_700_000_000_250_000_000));
+        // 2024-01-31 12:00:00 UTC is 1706702400
+        assert_eq!(
+            parse_file_timestamp("frame_%Y%m%d_%H%M%S.%f.png", "frame_20240131_120000.250.png"),
+            Some(1_706_702_400_250_000_000)
+        );
+        assert_eq!(parse_file_timestamp("*_%s.png", "left_cam_12.png"), Some(12_000_000_000));
+        // Fractions beyond nanoseconds are truncated
+        assert_eq!(parse_file_timestamp("%s.%f", "1.1234567899"), Some(1_123_456_789));
+        assert_eq!(parse_file_timestamp("100%%_%s", "100%_3"), Some(3_000_000_000));
+
+        assert_eq!(parse_file_timestamp("%N.png", "1403636579763555584.jpg"), None);
+        assert_eq!(parse_file_timestamp("%Y%m%d", "2024131"), None);
+        assert_eq!(parse_file_timestamp("frame.png", "frame.png"), None);
+        assert_eq!(parse_file_timestamp("%q", "1"), None);
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+    }
+
+    #[test]
+    fn test_pair_nearest() {
+        let images = |camera: &str, timestamps: &[i64]| -> Vec<(i64, PathBuf)> {
+            timestamps.iter().map(|&t| (t, PathBuf::from(format!("{}/{}.png", camera, t)))).collect()
+        };
+        // Camera 1 misses the frame at 200 and camera 2 the one at 100; camera 1 has a stray image
+        let frames = pair_nearest(
+            &[images("a", &[0, 100, 200, 300]), images("b", &[2, 98, 150, 303]), images("c", &[-3, 201, 296])],
+            5,
+        );
+        let timestamps: Vec<i64> = frames.iter().map(|(t, _)| *t).collect();
+        assert_eq!(timestamps, [0, 300]);
+        assert_eq!(frames[1].1, [PathBuf::from("a/300.png"), PathBuf::from("b/303.png"), PathBuf::from("c/296.png")]);
+
+        // An image is only used once, even if it is the nearest for two frames
+        let frames = pair_nearest(&[images("a", &[0, 4]), images("b", &[2])], 5);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].1[1], PathBuf::from("b/2.png"));
+
+        assert!(pair_nearest(&[], 5).is_empty());
+        assert_eq!(pair_nearest(&[images("a", &[0, 1])], 5).len(), 2);
+    }
+
+    #[test]
+    fn test_open_folders() {
+        let dir = std::env::temp_dir().join(format!("cuvslam-folder-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (camera, names) in [("left", ["0001.png", "0002.png"]), ("right", ["0001.png", "0002.png"])] {
+            std::fs::create_dir_all(dir.join(camera)).unwrap();
+            for name in names {
+                image::GrayImage::new(4, 2).save(dir.join(camera).join(name)).unwrap();
+            }
+            std::fs::write(dir.join(camera).join("notes.txt"), "").unwrap();
+        }
+        std::fs::write(dir.join("times.csv"), "# seconds\n0.5\n1.5\n").unwrap();
+
+        let patterns = [dir.join("left/*.png"), dir.join("right/*.png")];
+        let mut source = FolderSource::open(&patterns, &Timestamps::File(dir.join("times.csv")), 1000).unwrap();
+        assert_eq!(source.len_hint(), Some(2));
+        assert!(source.calibration().is_none());
+
+        let frame = source.next_frame().unwrap().unwrap();
+        assert_eq!(frame.timestamp_ns, 500_000_000);
+        assert_eq!(frame.images.iter().map(Image::camera_index).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!((frame.images[1].width(), frame.images[1].timestamp_ns()), (4, 500_000_000));
+        assert!(source.next_frame().unwrap().is_ok());
+        assert!(source.next_frame().is_none());
+
+        let by_name = FolderSource::open(&patterns[..1], &Timestamps::FileName("%s.png".to_string()), 0).unwrap();
+        assert_eq!(by_name.len_hint(), Some(2));
+        let missing = FolderSource::open(&[dir.join("left/*.jpg")], &Timestamps::FileName("%s.jpg".to_string()), 0);
+        assert!(matches!(missing, Err(Error::Dataset(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
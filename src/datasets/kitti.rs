@@ -0,0 +1,143 @@
+//! KITTI odometry sequences
+//!
+//! A sequence directory holds the grayscale pair in `image_0` (left) and `image_1` (right) as
+//! `000000.png` onwards, `times.txt` with one timestamp in seconds per frame, and `calib.txt`
+//! with the rectified projection matrices. Images are already rectified, so both cameras are
+//! undistorted pinholes and the baseline is the only extrinsic, recovered from `P1`. Image sizes
+//! differ between sequences (1241x376, 1226x370, 1242x375) and are read from the first frame.
+
+use super::{read_to_string, StereoFrame, StereoSource};
+use crate::{
+    Calibration, CameraCalibration, ConfigurationBuilder, DistortionModel, Error, Extrinsics, CUVSLAM_Configuration,
+};
+use std::path::Path;
+
+/// A parsed KITTI odometry sequence
+#[derive(Debug, Clone)]
+pub struct KittiSequence {
+    /// Stereo frames in order, one per line of `times.txt`
+    pub frames: Vec<StereoFrame>,
+    /// Calibration of `image_0` and `image_1` in the `image_0` frame
+    pub calibration: Calibration,
+}
+
+impl KittiSequence {
+    /// Parse the sequence at `dir`, reading only the size of the first image
+    #[must_use = "the sequence or the reason it could not be read is only in the Result"]
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let times_path = dir.join("times.txt");
+        let frames = read_to_string(&times_path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                let seconds: f64 = line
+                    .parse()
+                    .map_err(|_| Error::Dataset(format!("{}: bad timestamp {:?}", times_path.display(), line)))?;
+                let file = format!("{:06}.png", index);
+                Ok(StereoFrame {
+                    timestamp_ns: (seconds * 1e9).round() as i64,
+                    paths: [dir.join("image_0").join(&file), dir.join("image_1").join(&file)],
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let first = frames.first().ok_or_else(|| Error::Dataset(format!("{}: no frames", times_path.display())))?;
+        let (width, height) = image::image_dimensions(&first.paths[0])
+            .map_err(|error| Error::Dataset(format!("{}: {}", first.paths[0].display(), error)))?;
+
+        let calib_path = dir.join("calib.txt");
+        let calibration = parse_calibration(&read_to_string(&calib_path)?, width as i32, height as i32)
+            .map_err(|reason| Error::Dataset(format!("{}: {}", calib_path.display(), reason)))?;
+        Ok(Self { frames, calibration })
+    }
+
+    /// Tracker configuration for this sequence, with `horizontal_stereo_camera` on
+    pub fn configuration(&self) -> CUVSLAM_Configuration {
+        let mut config = ConfigurationBuilder::new().build();
+        config.horizontal_stereo_camera = 1;
+        config
+    }
+
+    /// Frames of the sequence as a `FrameSource`
+    pub fn source(&self) -> StereoSource<'_> {
+        StereoSource::new(&self.frames, &self.calibration)
+    }
+}
+
+/// Build the rig for `width` x `height` images from the text of a `calib.txt`
+///
+/// Intrinsics come from `P0`. `P1` shares them and its fourth column holds `-fx * baseline`, which
+/// places the right camera at `baseline` meters along x. Other lines (`P2`, `P3`, `Tr`) are ignored.
+pub fn parse_calibration(text: &str, width: i32, height: i32) -> Result<Calibration, String> {
+    let matrix = |name: &str| -> Result<[f64; 12], String> {
+        let line = text
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(name)?.strip_prefix(':'))
+            .ok_or_else(|| format!("missing {}", name))?;
+        let values: Vec<f64> = line
+            .split_whitespace()
+            .map(|value| value.parse().map_err(|_| format!("bad {} value {:?}", name, value)))
+            .collect::<Result<_, _>>()?;
+        values.try_into().map_err(|values: Vec<f64>| format!("{} has {} values, expected 12", name, values.len()))
+    };
+    let (p0, p1) = (matrix("P0")?, matrix("P1")?);
+    let (fx, cx, fy, cy) = (p0[0], p0[2], p0[5], p0[6]);
+    if fx <= 0.0 || fy <= 0.0 {
+        return Err("P0 has a non-positive focal length".to_string());
+    }
+    let baseline = -p1[3] / p1[0];
+
+    let camera = |translation: [f32; 3]| CameraCalibration {
+        width,
+        height,
+        model: DistortionModel::Pinhole,
+        cx: cx as f32,
+        cy: cy as f32,
+        fx: fx as f32,
+        fy: fy as f32,
+        distortion: Vec::new(),
+        extrinsics: Extrinsics { translation, ..Extrinsics::default() },
+    };
+    Ok(Calibration { cameras: vec![camera([0.0; 3]), camera([baseline as f32, 0.0, 0.0])] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `calib.txt` of sequence 00
+    const CALIB_00: &str = "\
+P0: 7.188560000000e+02 0.000000000000e+00 6.071928000000e+02 0.000000000000e+00 0.000000000000e+00 7.188560000000e+02 1.852157000000e+02 0.000000000000e+00 0.000000000000e+00 0.000000000000e+00 1.000000000000e+00 0.000000000000e+00
+P1: 7.188560000000e+02 0.000000000000e+00 6.071928000000e+02 -3.861448000000e+02 0.000000000000e+00 7.188560000000e+02 1.852157000000e+02 0.000000000000e+00 0.000000000000e+00 0.000000000000e+00 1.000000000000e+00 0.000000000000e+00
+P2: 7.188560000000e+02 0.000000000000e+00 6.071928000000e+02 4.538225000000e+01 0.000000000000e+00 7.188560000000e+02 1.852157000000e+02 -1.130887000000e-01 0.000000000000e+00 0.000000000000e+00 1.000000000000e+00 3.779761000000e-03
+P3: 7.188560000000e+02 0.000000000000e+00 6.071928000000e+02 -3.372877000000e+02 0.000000000000e+00 7.188560000000e+02 1.852157000000e+02 2.369057000000e+00 0.000000000000e+00 0.000000000000e+00 1.000000000000e+00 4.915215000000e-03
+Tr: 4.276802385584e-04 -9.999672484946e-01 -8.084491683471e-03 -1.198459927713e-02 -7.210626507497e-03 8.081198471645e-03 -9.999413164504e-01 -5.403984729748e-02 9.999738645903e-01 4.859485810390e-04 -7.206933692422e-03 -2.921968648686e-01
+";
+
+    #[test]
+    fn test_parse_calibration() {
+        let calibration = parse_calibration(CALIB_00, 1241, 376).unwrap();
+        let [left, right] = &calibration.cameras[..] else {
+            panic!("expected two cameras");
+        };
+        assert_eq!((left.width, left.height, left.model), (1241, 376, DistortionModel::Pinhole));
+        assert_eq!((left.fx, left.fy, left.cx, left.cy), (718.856, 718.856, 607.1928, 185.2157));
+        assert_eq!(right.fx, left.fx);
+        assert!(right.distortion.is_empty());
+        assert_eq!(left.extrinsics.translation, [0.0; 3]);
+
+        // 386.1448 / 718.856
+        let [baseline, y, z] = right.extrinsics.translation;
+        assert!((baseline - 0.537_165).abs() < 1e-5, "{}", baseline);
+        assert_eq!((y, z), (0.0, 0.0));
+        assert_eq!(right.extrinsics.rotation, Extrinsics::default().rotation);
+        assert_eq!(crate::validate_rig(&calibration.to_rig().unwrap()), Ok(()));
+
+        assert_eq!(parse_calibration("P0: 1 2 3", 10, 10).err(), Some("P0 has 3 values, expected 12".to_string()));
+        let without_p1 = CALIB_00.lines().filter(|line| !line.starts_with("P1")).collect::<Vec<_>>().join("\n");
+        assert_eq!(parse_calibration(&without_p1, 10, 10).err(), Some("missing P1".to_string()));
+    }
+}
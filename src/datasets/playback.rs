@@ -0,0 +1,120 @@
+//! Pacing of recorded frames for the dataset players
+//!
+//! Sequences normally play as fast as the tracker allows. `PlaybackClock` instead maps dataset
+//! timestamps to wall-clock times at a rate factor, so a sequence can be watched at a fraction
+//! of real time around a failure, and paused and resumed without playback racing to catch up.
+
+use std::time::{Duration, Instant};
+
+/// Schedules frames by their dataset timestamps at a multiple of real time
+#[derive(Debug, Clone)]
+pub struct PlaybackClock {
+    rate: f64,
+    /// Dataset timestamp and wall-clock time the schedule starts from
+    anchor: Option<(i64, Instant)>,
+    paused: bool,
+}
+
+impl PlaybackClock {
+    /// Play at `rate` times real time, for example 0.25 for quarter speed
+    ///
+    /// `f64::INFINITY` plays as fast as possible. Panics unless `rate` is positive.
+    pub fn new(rate: f64) -> Self {
+        assert!(rate > 0.0, "playback rate must be positive, got {}", rate);
+        Self { rate, anchor: None, paused: false }
+    }
+
+    /// Time to wait at `now` before playing the frame stamped `timestamp_ns`
+    ///
+    /// The first frame, and the first after `resume`, is due at once and anchors the schedule.
+    /// Later frames are due once their time since the anchor, divided by the rate, has passed on
+    /// the wall clock. Frames that are late, or stamped before the anchor, are due at once, and
+    /// the schedule stays put so playback catches up after a slow frame. A frame too far ahead to
+    /// schedule, at a tiny rate, waits `Duration::MAX`.
+    pub fn delay(&mut self, timestamp_ns: i64, now: Instant) -> Duration {
+        let (anchor_ns, anchor_time) = *self.anchor.get_or_insert((timestamp_ns, now));
+        let elapsed_s = timestamp_ns.saturating_sub(anchor_ns).max(0) as f64 * 1e-9 / self.rate;
+        Duration::try_from_secs_f64(elapsed_s)
+            .ok()
+            .and_then(|elapsed| anchor_time.checked_add(elapsed))
+            .map_or(Duration::MAX, |due| due.saturating_duration_since(now))
+    }
+
+    /// Pause playback; frames stepped through while paused are due at once
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume playback, scheduling from the next frame rather than from before the pause
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.anchor = None;
+    }
+
+    /// Whether `pause` was called without a `resume` since
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Multiple of real time frames are played at
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+    const MS_NS: i64 = 1_000_000;
+
+    #[test]
+    fn test_delay_at_rate() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new(0.25);
+        assert_eq!(clock.delay(5_000 * MS_NS, start), Duration::ZERO);
+        // 10ms of data takes 40ms at quarter speed
+        assert_eq!(clock.delay(5_010 * MS_NS, start), 40 * MS);
+        assert_eq!(clock.delay(5_010 * MS_NS, start + 30 * MS), 10 * MS);
+        // Late and out of order frames are due at once
+        assert_eq!(clock.delay(5_010 * MS_NS, start + 50 * MS), Duration::ZERO);
+        assert_eq!(clock.delay(4_000 * MS_NS, start + 50 * MS), Duration::ZERO);
+
+        let mut fastest = PlaybackClock::new(f64::INFINITY);
+        assert_eq!(fastest.delay(0, start), Duration::ZERO);
+        assert_eq!(fastest.delay(60_000 * MS_NS, start), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new(1.0);
+        clock.delay(0, start);
+        clock.pause();
+        assert!(clock.is_paused());
+        // A frame stepped through a second into the pause is overdue
+        assert_eq!(clock.delay(50 * MS_NS, start + 1_000 * MS), Duration::ZERO);
+
+        // After resuming, the next frame anchors the schedule instead of being played late
+        clock.resume();
+        assert!(!clock.is_paused());
+        let resumed = start + 5_000 * MS;
+        assert_eq!(clock.delay(100 * MS_NS, resumed), Duration::ZERO);
+        assert_eq!(clock.delay(150 * MS_NS, resumed), 50 * MS);
+    }
+
+    #[test]
+    fn test_delay_saturates() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new(1e-12);
+        assert_eq!(clock.delay(i64::MIN, start), Duration::ZERO);
+        assert_eq!(clock.delay(i64::MAX, start), Duration::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "playback rate must be positive")]
+    fn test_rejects_zero_rate() {
+        PlaybackClock::new(0.0);
+    }
+}
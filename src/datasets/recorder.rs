@@ -0,0 +1,427 @@
+//! Recording live sessions as folder datasets
+//!
+//! `SessionRecorder` writes the frames handed to it as PNG files that `folder::FolderSource` can
+//! play back, so a failure seen on hardware can be reproduced from the exact frames. Encoding and
+//! writing happen on a background thread fed through a bounded queue; when the disk falls behind,
+//! frames are dropped from the recording rather than stalling the tracking loop.
+//!
+//! A recording directory holds:
+//! - `cam<N>/<timestamp_ns>.png` for each camera, the timestamp zero-padded to 20 digits so file
+//!   name order is time order,
+//! - `timestamps.csv`, one frame timestamp in nanoseconds per line,
+//! - `imu.csv` with `timestamp_ns,wx,wy,wz,ax,ay,az` rows, if IMU samples were recorded,
+//! - `calibration.yaml`, if a calibration was given, readable by `Calibration::from_yaml`,
+//! - `configuration.toml` with the tracker configuration's scalar fields.
+//!
+//! The CSV files are written by `finish`. Images are on disk as soon as they are written, so a
+//! recording cut short can still be played with `Timestamps::FileName("%N.png")`.
+
+use super::folder::{FolderSource, Timestamps};
+use crate::{Calibration, CUVSLAM_Configuration, CUVSLAM_ImuMeasurement, Error, FrameSet, FrameSource, Image};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Frames and IMU samples that may wait for the writer before new ones are dropped
+const QUEUE_CAPACITY: usize = 64;
+
+/// What a finished recording holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingSummary {
+    /// Frames left on disk, after any older than the window were deleted
+    pub frames: usize,
+    /// IMU samples written to `imu.csv`
+    pub imu_samples: usize,
+    /// Frames and IMU samples dropped because the writer fell behind
+    pub dropped: u64,
+}
+
+/// Tees frames and IMU samples to a folder dataset on a background thread
+pub struct SessionRecorder {
+    dir: PathBuf,
+    sender: Option<SyncSender<Item>>,
+    writer: Option<JoinHandle<Result<RecordingSummary, Error>>>,
+    dropped: AtomicU64,
+}
+
+enum Item {
+    Frame(FrameSet),
+    Imu(CUVSLAM_ImuMeasurement),
+}
+
+impl SessionRecorder {
+    /// Start recording into `dir`, creating it if needed
+    ///
+    /// The calibration and configuration files are written before this returns. With a `window`,
+    /// only frames and IMU samples from the last `window` of sensor time are kept, older frames
+    /// being deleted as new ones are written, so disk usage stays bounded on long sessions.
+    #[must_use = "the recorder or the reason it could not be started is only in the Result"]
+    pub fn create<P: AsRef<Path>>(
+        dir: P,
+        calibration: Option<&Calibration>,
+        configuration: &CUVSLAM_Configuration,
+        window: Option<Duration>,
+    ) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir).map_err(|error| io_error(&dir, error))?;
+        if let Some(calibration) = calibration {
+            let yaml = serde_yaml::to_string(calibration).map_err(|error| Error::Calibration(error.to_string()))?;
+            write_file(&dir.join("calibration.yaml"), &yaml)?;
+        }
+        write_file(&dir.join("configuration.toml"), &configuration_toml(configuration))?;
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let state = Writer {
+            dir: dir.clone(),
+            window_ns: window.map(|window| window.as_nanos().min(i64::MAX as u128) as i64),
+            frames: VecDeque::new(),
+            imu: VecDeque::new(),
+        };
+        let writer = thread::Builder::new()
+            .name("cuvslam-recorder".to_string())
+            .spawn(move || state.run(receiver))
+            .map_err(|error| io_error(&dir, error))?;
+        Ok(Self { dir, sender: Some(sender), writer: Some(writer), dropped: AtomicU64::new(0) })
+    }
+
+    /// Directory the session is recorded into
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Queue a copy of a frame's images for writing, stamped `timestamp_ns`
+    ///
+    /// Never blocks: if the writer is behind, or has stopped on a write error, the frame is
+    /// dropped and counted. Each camera's images are stored under their `camera_index`.
+    pub fn record(&self, timestamp_ns: i64, images: &[Image]) {
+        let images = images.iter().filter_map(copy_image).collect();
+        self.send(Item::Frame(FrameSet { timestamp_ns, images }));
+    }
+
+    /// Queue an IMU sample for `imu.csv`, dropping it like `record` if the writer is behind
+    pub fn record_imu(&self, measurement: &CUVSLAM_ImuMeasurement) {
+        self.send(Item::Imu(*measurement));
+    }
+
+    /// Frames and IMU samples dropped so far
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Record every frame `source` yields on the way to its consumer
+    pub fn tee<S: FrameSource>(self, source: S) -> RecordingSource<S> {
+        RecordingSource { source, recorder: self }
+    }
+
+    /// Wait for queued frames to be written, then write the CSV files
+    ///
+    /// Returns the first error the writer ran into, after which it had stopped writing.
+    #[must_use = "write errors are only reported through the Result"]
+    pub fn finish(mut self) -> Result<RecordingSummary, Error> {
+        self.stop()
+    }
+
+    /// Open the recording as a `FolderSource` of `cameras` cameras, pairing images by file name
+    ///
+    /// The recording may be finished or still being written.
+    #[must_use = "the source or the reason it could not be opened is only in the Result"]
+    pub fn replay(dir: &Path, cameras: usize) -> Result<FolderSource, Error> {
+        let patterns: Vec<PathBuf> =
+            (0..cameras).map(|camera| dir.join(format!("cam{}", camera)).join("*.png")).collect();
+        let source = FolderSource::open(&patterns, &Timestamps::FileName("%N.png".to_string()), 0)?;
+        let calibration = dir.join("calibration.yaml");
+        if !calibration.is_file() {
+            return Ok(source);
+        }
+        Ok(source.with_calibration(Calibration::from_yaml(calibration)?))
+    }
+
+    fn send(&self, item: Item) {
+        let Some(sender) = &self.sender else { return };
+        if sender.try_send(item).is_err() {
+            if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                log::warn!("{}: recorder is behind, dropping frames", self.dir.display());
+            }
+        }
+    }
+
+    fn stop(&mut self) -> Result<RecordingSummary, Error> {
+        self.sender = None;
+        let writer = self.writer.take().ok_or_else(|| Error::Dataset("recorder already stopped".to_string()))?;
+        let mut summary = writer.join().map_err(|_| Error::Dataset("recorder thread panicked".to_string()))??;
+        summary.dropped = self.dropped();
+        Ok(summary)
+    }
+}
+
+impl Drop for SessionRecorder {
+    /// Finish the recording, logging any write error
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            if let Err(error) = self.stop() {
+                log::warn!("{}: recording failed: {}", self.dir.display(), error);
+            }
+        }
+    }
+}
+
+/// `FrameSource` that records the frames of another as they are played
+pub struct RecordingSource<S> {
+    source: S,
+    recorder: SessionRecorder,
+}
+
+impl<S> RecordingSource<S> {
+    /// Stop recording and return the source
+    #[must_use = "write errors are only reported through the Result"]
+    pub fn finish(self) -> (S, Result<RecordingSummary, Error>) {
+        (self.source, self.recorder.finish())
+    }
+}
+
+impl<S: FrameSource> FrameSource for RecordingSource<S> {
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>> {
+        let frame = self.source.next_frame()?;
+        if let Ok(frame) = &frame {
+            self.recorder.record(frame.timestamp_ns, &frame.images);
+        }
+        Some(frame)
+    }
+
+    fn calibration(&self) -> Option<crate::CameraRig> {
+        self.source.calibration()
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        self.source.len_hint()
+    }
+}
+
+/// State of the background thread
+struct Writer {
+    dir: PathBuf,
+    window_ns: Option<i64>,
+    /// Timestamps and files of the frames on disk, oldest first
+    frames: VecDeque<(i64, Vec<PathBuf>)>,
+    imu: VecDeque<CUVSLAM_ImuMeasurement>,
+}
+
+impl Writer {
+    /// Write items until the recorder hangs up, then the CSV files
+    fn run(mut self, receiver: Receiver<Item>) -> Result<RecordingSummary, Error> {
+        while let Ok(item) = receiver.recv() {
+            match item {
+                Item::Frame(frame) => self.write_frame(frame)?,
+                Item::Imu(measurement) => self.imu.push_back(measurement),
+            }
+            self.trim()?;
+        }
+
+        let mut timestamps = String::from("# timestamp_ns\n");
+        for (timestamp_ns, _) in &self.frames {
+            let _ = writeln!(timestamps, "{}", timestamp_ns);
+        }
+        write_file(&self.dir.join("timestamps.csv"), &timestamps)?;
+        if !self.imu.is_empty() {
+            let mut imu = String::from("# timestamp_ns,wx,wy,wz,ax,ay,az\n");
+            for m in &self.imu {
+                let [wx, wy, wz] = m.angular_velocities;
+                let [ax, ay, az] = m.linear_accelerations;
+                let _ = writeln!(imu, "{},{},{},{},{},{},{}", m.timestamp_ns, wx, wy, wz, ax, ay, az);
+            }
+            write_file(&self.dir.join("imu.csv"), &imu)?;
+        }
+        Ok(RecordingSummary {
+            frames: self.frames.len(),
+            imu_samples: self.imu.len(),
+            // Counted by the recorder
+            dropped: 0,
+        })
+    }
+
+    fn write_frame(&mut self, frame: FrameSet) -> Result<(), Error> {
+        let mut paths = Vec::with_capacity(frame.images.len());
+        for image in &frame.images {
+            let camera_dir = self.dir.join(format!("cam{}", image.camera_index()));
+            std::fs::create_dir_all(&camera_dir).map_err(|error| io_error(&camera_dir, error))?;
+            let path = camera_dir.join(format!("{:020}.png", frame.timestamp_ns));
+            image.save_png(&path).map_err(|error| Error::Dataset(format!("{}: {}", path.display(), error)))?;
+            paths.push(path);
+        }
+        self.frames.push_back((frame.timestamp_ns, paths));
+        Ok(())
+    }
+
+    /// Delete frames and forget IMU samples older than the window before the latest frame
+    fn trim(&mut self) -> Result<(), Error> {
+        let (Some(window_ns), Some(&(latest_ns, _))) = (self.window_ns, self.frames.back()) else {
+            return Ok(());
+        };
+        let oldest_ns = latest_ns.saturating_sub(window_ns);
+        while self.frames.front().is_some_and(|(timestamp_ns, _)| *timestamp_ns < oldest_ns) {
+            let (_, paths) = self.frames.pop_front().unwrap_or_default();
+            for path in paths {
+                std::fs::remove_file(&path).map_err(|error| io_error(&path, error))?;
+            }
+        }
+        while self.imu.front().is_some_and(|measurement| measurement.timestamp_ns < oldest_ns) {
+            self.imu.pop_front();
+        }
+        Ok(())
+    }
+}
+
+/// Copy of an image for the writer thread, keeping its pitch
+fn copy_image(image: &Image) -> Option<Image> {
+    Image::new_with_pitch(
+        image.pixels().to_vec(),
+        image.width(),
+        image.height(),
+        image.pitch(),
+        image.encoding(),
+        image.camera_index(),
+        image.timestamp_ns(),
+    )
+    .ok()
+}
+
+/// The configuration's scalar fields as a TOML document
+fn configuration_toml(config: &CUVSLAM_Configuration) -> String {
+    let imu = &config.imu_calibration;
+    let mut toml = String::from("[tracker]\n");
+    let fields: [(&str, String); 15] = [
+        ("use_motion_model", config.use_motion_model.to_string()),
+        ("use_denoising", config.use_denoising.to_string()),
+        ("use_gpu", config.use_gpu.to_string()),
+        ("horizontal_stereo_camera", config.horizontal_stereo_camera.to_string()),
+        ("enable_observations_export", config.enable_observations_export.to_string()),
+        ("enable_landmarks_export", config.enable_landmarks_export.to_string()),
+        ("enable_localization_n_mapping", config.enable_localization_n_mapping.to_string()),
+        ("enable_reading_slam_internals", config.enable_reading_slam_internals.to_string()),
+        ("enable_imu_fusion", config.enable_imu_fusion.to_string()),
+        ("planar_constraints", config.planar_constraints.to_string()),
+        ("slam_sync_mode", config.slam_sync_mode.to_string()),
+        ("slam_throttling_time_ms", config.slam_throttling_time_ms.to_string()),
+        ("slam_max_map_size", config.slam_max_map_size.to_string()),
+        ("map_cell_size", toml_float(config.map_cell_size)),
+        ("max_frame_delta_s", toml_float(config.max_frame_delta_s)),
+    ];
+    for (name, value) in fields {
+        let _ = writeln!(toml, "{} = {}", name, value);
+    }
+    toml.push_str("\n[imu]\n");
+    for (name, value) in [
+        ("gyroscope_noise_density", imu.gyroscope_noise_density),
+        ("gyroscope_random_walk", imu.gyroscope_random_walk),
+        ("accelerometer_noise_density", imu.accelerometer_noise_density),
+        ("accelerometer_random_walk", imu.accelerometer_random_walk),
+        ("frequency", imu.frequency),
+    ] {
+        let _ = writeln!(toml, "{} = {}", name, toml_float(value));
+    }
+    toml
+}
+
+/// A float as a TOML float, which needs a decimal point and spells out special values
+fn toml_float(value: f32) -> String {
+    match value {
+        value if value.is_nan() => "nan".to_string(),
+        value if value.is_infinite() => if value > 0.0 { "inf" } else { "-inf" }.to_string(),
+        value => format!("{:?}", value),
+    }
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), Error> {
+    std::fs::write(path, contents).map_err(|error| io_error(path, error))
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> Error {
+    Error::Dataset(format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{init_default_configuration, ImageEncoding};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cuvslam-recorder-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Stereo frame of 5x3 images whose pixels depend on the frame and camera
+    fn synthetic_frame(frame: u8) -> Vec<Image> {
+        (0..2)
+            .map(|camera| {
+                let pixels = (0..15).map(|i| i * 16 + frame * 3 + camera as u8).collect();
+                Image::new(pixels, 5, 3, ImageEncoding::Mono8, camera, 0).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recording_replays_identical_images() {
+        let dir = temp_dir("replay");
+        let recorder = SessionRecorder::create(&dir, None, &init_default_configuration(), None).unwrap();
+        let timestamps_ns = [5_000_000, 38_000_000, 71_000_000];
+        for (frame, timestamp_ns) in timestamps_ns.into_iter().enumerate() {
+            recorder.record(timestamp_ns, &synthetic_frame(frame as u8));
+        }
+        recorder.record_imu(&CUVSLAM_ImuMeasurement {
+            timestamp_ns: 6_000_000,
+            linear_accelerations: [0.0, 9.8, 0.0],
+            angular_velocities: [0.5, 0.0, 0.0],
+        });
+        let summary = recorder.finish().unwrap();
+        assert_eq!(summary, RecordingSummary { frames: 3, imu_samples: 1, dropped: 0 });
+
+        let mut source = SessionRecorder::replay(&dir, 2).unwrap();
+        assert_eq!(source.len_hint(), Some(3));
+        for (frame, timestamp_ns) in timestamps_ns.into_iter().enumerate() {
+            let replayed = source.next_frame().unwrap().unwrap();
+            assert_eq!(replayed.timestamp_ns, timestamp_ns);
+            for (replayed, recorded) in replayed.images.iter().zip(synthetic_frame(frame as u8)) {
+                assert_eq!(replayed.camera_index(), recorded.camera_index());
+                assert_eq!(replayed.pixels(), recorded.pixels());
+            }
+        }
+        assert!(source.next_frame().is_none());
+
+        let timestamps = std::fs::read_to_string(dir.join("timestamps.csv")).unwrap();
+        assert_eq!(timestamps, "# timestamp_ns\n5000000\n38000000\n71000000\n");
+        let imu = std::fs::read_to_string(dir.join("imu.csv")).unwrap();
+        assert_eq!(imu.lines().nth(1), Some("6000000,0.5,0,0,0,9.8,0"));
+        let toml = std::fs::read_to_string(dir.join("configuration.toml")).unwrap();
+        assert!(toml.starts_with("[tracker]\nuse_motion_model = "));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_window_bounds_the_recording() {
+        let dir = temp_dir("window");
+        let window = Some(Duration::from_millis(100));
+        let recorder = SessionRecorder::create(&dir, None, &init_default_configuration(), window).unwrap();
+        for frame in 0..10 {
+            recorder.record(frame as i64 * 50_000_000, &synthetic_frame(frame));
+        }
+        // Frames at 350, 400 and 450ms are within 100ms of the last
+        assert_eq!(recorder.finish().unwrap().frames, 3);
+        assert_eq!(std::fs::read_dir(dir.join("cam1")).unwrap().count(), 3);
+        let source = SessionRecorder::replay(&dir, 2).unwrap();
+        assert_eq!(source.len_hint(), Some(3));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_toml_float() {
+        assert_eq!(toml_float(0.5), "0.5");
+        assert_eq!(toml_float(2.0), "2.0");
+        assert_eq!(toml_float(f32::NAN), "nan");
+        assert_eq!(toml_float(f32::NEG_INFINITY), "-inf");
+    }
+}
@@ -0,0 +1,201 @@
+//! TUM-VI sequences in their EuRoC-style export
+//!
+//! Images and IMU measurements use the EuRoC layout under `mav0`, but the calibration is Kalibr's:
+//! `dso/camchain.yaml` describes the fisheye cameras (equidistant distortion, imported as
+//! `fisheye4`) with their `T_cam_imu`, and `dso/imu_config.yaml` the IMU noise. The body frame is
+//! the IMU's. Ground truth is the pose of the mocap marker in `mav0/mocap0/data.csv`; if either
+//! YAML file has a `T_imu_marker`, it is applied so the ground truth follows the IMU rather than
+//! the marker. The 16-bit PNGs of the `_16` exports are scaled to 8 bits by `Image::open`.
+//! Kalibr's camera frames use OpenCV axes (y down, z forward) and are turned into cuVSLAM's.
+
+use super::euroc::{camera_calibration, pair_frames, read_images, read_imu_measurements, read_sensor, EurocSequence};
+use super::{from_optical, parse_field, read_csv};
+use crate::{
+    compose_poses, invert_pose, pose_from_quaternion, Calibration, Error, Extrinsics, CUVSLAM_ImuCalibration,
+    CUVSLAM_Pose,
+};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Deserialize)]
+struct KalibrCamera {
+    #[serde(rename = "T_cam_imu")]
+    cam_from_imu: [[f32; 4]; 4],
+    camera_model: String,
+    distortion_model: String,
+    distortion_coeffs: Vec<f32>,
+    intrinsics: [f32; 4],
+    resolution: [i32; 2],
+}
+
+#[derive(Deserialize)]
+struct Camchain {
+    cam0: KalibrCamera,
+    cam1: KalibrCamera,
+    #[serde(rename = "T_imu_marker")]
+    imu_from_marker: Option<[[f32; 4]; 4]>,
+}
+
+#[derive(Deserialize)]
+struct ImuConfig {
+    accelerometer_noise_density: f32,
+    accelerometer_random_walk: f32,
+    gyroscope_noise_density: f32,
+    gyroscope_random_walk: f32,
+    update_rate: f32,
+    #[serde(rename = "T_imu_marker")]
+    imu_from_marker: Option<[[f32; 4]; 4]>,
+}
+
+/// Parse the TUM-VI sequence at `dir`, without loading any images
+///
+/// The result is an `EurocSequence` whose `rig_in_body` is `cam0`'s pose in the IMU frame.
+#[must_use = "the sequence or the reason it could not be read is only in the Result"]
+pub fn open<P: AsRef<Path>>(dir: P) -> Result<EurocSequence, Error> {
+    let dir = dir.as_ref();
+    let camchain_path = dir.join("dso/camchain.yaml");
+    let camchain: Camchain = read_sensor(&camchain_path)?;
+    let imu_config_path = dir.join("dso/imu_config.yaml");
+    let imu_config: ImuConfig = read_sensor(&imu_config_path)?;
+
+    let camera = |camera: &KalibrCamera| {
+        camera_calibration(
+            &camchain_path,
+            &camera.camera_model,
+            &camera.distortion_model,
+            &camera.distortion_coeffs,
+            camera.intrinsics,
+            camera.resolution,
+        )
+    };
+    let (cam0_from_imu, rig_from_cam1) =
+        rig_poses(&to_pose(&camchain.cam0.cam_from_imu), &to_pose(&camchain.cam1.cam_from_imu));
+    let (cam0, mut cam1) = (camera(&camchain.cam0)?, camera(&camchain.cam1)?);
+    cam1.extrinsics = Extrinsics::from_pose(&rig_from_cam1);
+
+    let mav0 = dir.join("mav0");
+    let marker_from_imu = camchain.imu_from_marker.or(imu_config.imu_from_marker).map(|m| invert_pose(&to_pose(&m)));
+    let mocap_path = mav0.join("mocap0/data.csv");
+    let ground_truth = if mocap_path.is_file() {
+        read_csv(&mocap_path)?
+            .iter()
+            .map(|row| {
+                let values = [1, 2, 3, 4, 5, 6, 7].map(|i| parse_field::<f32>(&mocap_path, row, i));
+                let [x, y, z, qw, qx, qy, qz] = values;
+                let world_from_marker = pose_from_quaternion([qx?, qy?, qz?, qw?], [x?, y?, z?]);
+                let world_from_body = match &marker_from_imu {
+                    Some(marker_from_imu) => compose_poses(&world_from_marker, marker_from_imu),
+                    None => world_from_marker,
+                };
+                Ok((parse_field(&mocap_path, row, 0)?, world_from_body.t.map(f64::from)))
+            })
+            .collect::<Result<_, Error>>()?
+    } else {
+        Vec::new()
+    };
+
+    Ok(EurocSequence {
+        frames: pair_frames(&read_images(&mav0.join("cam0"))?, &read_images(&mav0.join("cam1"))?),
+        calibration: Calibration { cameras: vec![cam0, cam1] },
+        rig_in_body: invert_pose(&cam0_from_imu),
+        imu_calibration: Some(CUVSLAM_ImuCalibration {
+            left_from_imu: cam0_from_imu,
+            gyroscope_noise_density: imu_config.gyroscope_noise_density,
+            gyroscope_random_walk: imu_config.gyroscope_random_walk,
+            accelerometer_noise_density: imu_config.accelerometer_noise_density,
+            accelerometer_random_walk: imu_config.accelerometer_random_walk,
+            frequency: imu_config.update_rate,
+        }),
+        imu: read_imu_measurements(&mav0.join("imu0/data.csv"))?,
+        ground_truth,
+    })
+}
+
+/// `cam0`'s pose relative to the IMU and `cam1`'s in the rig, in cuVSLAM axes, from both
+/// cameras' `T_cam_imu`
+fn rig_poses(cam0_from_imu: &CUVSLAM_Pose, cam1_from_imu: &CUVSLAM_Pose) -> (CUVSLAM_Pose, CUVSLAM_Pose) {
+    let (cam0_from_imu, cam1_from_imu) = (from_optical(cam0_from_imu), from_optical(cam1_from_imu));
+    (cam0_from_imu, compose_poses(&cam0_from_imu, &invert_pose(&cam1_from_imu)))
+}
+
+/// Convert a row-major 4x4 Kalibr transform
+fn to_pose(matrix: &[[f32; 4]; 4]) -> CUVSLAM_Pose {
+    CUVSLAM_Pose {
+        r: std::array::from_fn(|i| matrix[i % 3][i / 3]),
+        t: std::array::from_fn(|row| matrix[row][3]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rig_poses_use_cuvslam_axes() {
+        let identity = CUVSLAM_Pose { r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], t: [0.0; 3] };
+        let cam1_from_imu = CUVSLAM_Pose { t: [-0.1, 0.01, 0.02], ..identity };
+        let (cam0_from_imu, rig_from_cam1) = rig_poses(&identity, &cam1_from_imu);
+
+        // The IMU keeps its axes, so only the camera side flips
+        assert_eq!(cam0_from_imu.r, [1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0]);
+        assert_eq!(cam0_from_imu.t, [0.0, 0.0, 0.0]);
+        // cam1 is at (0.1, -0.01, -0.02) in OpenCV axes
+        assert_eq!(rig_from_cam1.r, identity.r);
+        assert_eq!(rig_from_cam1.t, [0.1, 0.01, 0.02]);
+    }
+
+    #[test]
+    fn test_kalibr_camchain() {
+        // cam0 of the 512x512 TUM-VI calibration
+        let camchain: Camchain = serde_yaml::from_str(
+            "
+cam0:
+  T_cam_imu:
+  - [-0.9995250378696743, 0.0075019185074052044, -0.02989013031643309, 0.045574835649698026]
+  - [0.029615343885863205, -0.03439736061393144, -0.9989694637087135, -0.071161801837997044]
+  - [-0.008522328211654736, -0.9993800792498829, 0.03415885127385616, -0.044681254117144367]
+  - [0.0, 0.0, 0.0, 1.0]
+  camera_model: pinhole
+  distortion_coeffs: [0.0034823894022493434, 0.0007150348452162257, -0.0020532361418706202,
+    0.00020293673591811182]
+  distortion_model: equidistant
+  intrinsics: [190.97847715128717, 190.9733070521226, 254.93170605935475, 256.8974428996504]
+  resolution: [512, 512]
+  rostopic: /cam0/image_raw
+cam1:
+  T_cam_imu:
+  - [1.0, 0.0, 0.0, -0.1]
+  - [0.0, 1.0, 0.0, 0.0]
+  - [0.0, 0.0, 1.0, 0.0]
+  - [0.0, 0.0, 0.0, 1.0]
+  camera_model: pinhole
+  distortion_coeffs: [0.0, 0.0, 0.0, 0.0]
+  distortion_model: equidistant
+  intrinsics: [190.0, 190.0, 256.0, 256.0]
+  resolution: [512, 512]
+",
+        )
+        .unwrap();
+        assert!(camchain.imu_from_marker.is_none());
+
+        // Rows of T_cam_imu become columns of `r`
+        let pose = to_pose(&camchain.cam0.cam_from_imu);
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert!(close(pose.r[1], 0.029_615_34) && close(pose.r[3], 0.007_501_92), "{:?}", pose.r);
+        assert!(close(pose.t[0], 0.045_574_84) && close(pose.t[2], -0.044_681_25), "{:?}", pose.t);
+
+        let cam0 = &camchain.cam0;
+        let camera = camera_calibration(
+            Path::new("camchain.yaml"),
+            &cam0.camera_model,
+            &cam0.distortion_model,
+            &cam0.distortion_coeffs,
+            cam0.intrinsics,
+            cam0.resolution,
+        )
+        .unwrap();
+        assert_eq!(camera.model, crate::DistortionModel::Fisheye4);
+        assert_eq!(camera.distortion, cam0.distortion_coeffs);
+        assert_eq!((camera.fx, camera.cx, camera.width), (cam0.intrinsics[0], cam0.intrinsics[2], 512));
+    }
+}
@@ -0,0 +1,136 @@
+//! Timestamp-driven frame rate reduction
+
+use std::collections::VecDeque;
+
+/// Number of kept frames the achieved rate is measured over
+const RATE_WINDOW: usize = 30;
+
+/// Decides which frames to keep so the output approximates a target rate
+///
+/// Decisions are based on sensor timestamps rather than frame counts, so dropped input frames
+/// and timestamp jitter do not skew the output rate. Kept frames follow a fixed schedule of one
+/// every `1 / target_hz` seconds; a frame is kept once it is no more than a quarter period
+/// early for the next slot. After a gap longer than a period the schedule restarts at the next
+/// frame instead of catching up with a burst.
+pub struct FrameDecimator {
+    period_ns: i64,
+    next_due_ns: Option<i64>,
+    kept: VecDeque<i64>,
+}
+
+impl FrameDecimator {
+    /// Create a decimator targeting `target_hz` output frames per second
+    ///
+    /// A non-positive target keeps every frame.
+    pub fn new(target_hz: f64) -> Self {
+        let period_ns = if target_hz > 0.0 { (1e9 / target_hz) as i64 } else { 0 };
+        Self {
+            period_ns,
+            next_due_ns: None,
+            kept: VecDeque::with_capacity(RATE_WINDOW),
+        }
+    }
+
+    /// Decide whether to keep the frame captured at `timestamp_ns`
+    pub fn keep(&mut self, timestamp_ns: i64) -> bool {
+        let keep = match self.next_due_ns {
+            None => true,
+            Some(due) => timestamp_ns >= due - self.period_ns / 4,
+        };
+        if !keep {
+            return false;
+        }
+
+        self.next_due_ns = Some(match self.next_due_ns {
+            Some(due) if timestamp_ns - due <= self.period_ns => due + self.period_ns,
+            _ => timestamp_ns + self.period_ns,
+        });
+        if self.kept.len() == RATE_WINDOW {
+            self.kept.pop_front();
+        }
+        self.kept.push_back(timestamp_ns);
+        true
+    }
+
+    /// Output rate over the most recently kept frames, in Hz
+    pub fn achieved_hz(&self) -> Option<f64> {
+        let (first, last) = (*self.kept.front()?, *self.kept.back()?);
+        (last > first).then(|| (self.kept.len() - 1) as f64 * 1e9 / (last - first) as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_HZ: f64 = 30.0;
+
+    /// Deterministic jitter in `[-amplitude, amplitude]`
+    fn jitter(seed: &mut u64, amplitude_ns: i64) -> i64 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((*seed >> 33) as i64 % (2 * amplitude_ns + 1)) - amplitude_ns
+    }
+
+    /// Rate of kept frames over `[start_ns, end_ns)`
+    fn output_rate(timestamps: &[i64], start_ns: i64, end_ns: i64) -> f64 {
+        let mut decimator = FrameDecimator::new(TARGET_HZ);
+        let kept = timestamps
+            .iter()
+            .filter(|&&t| decimator.keep(t) && (start_ns..end_ns).contains(&t))
+            .count();
+        kept as f64 * 1e9 / (end_ns - start_ns) as f64
+    }
+
+    fn assert_near_target(rate: f64) {
+        assert!((rate - TARGET_HZ).abs() <= TARGET_HZ * 0.1, "rate {rate}");
+    }
+
+    #[test]
+    fn test_decimator_jittered_input() {
+        let mut seed = 1;
+        for input_hz in [60.0, 45.0, 90.0] {
+            let period = (1e9 / input_hz) as i64;
+            let timestamps: Vec<_> = (0..600).map(|i| i * period + jitter(&mut seed, period / 5)).collect();
+            assert_near_target(output_rate(&timestamps, 0, 599 * period));
+        }
+    }
+
+    #[test]
+    fn test_decimator_bursts_and_gaps() {
+        let mut seed = 7;
+        let mut timestamps = Vec::new();
+        let mut t = 0;
+        for i in 0..1000 {
+            // Pairs of frames arrive 2ms apart, every other 60Hz slot drops a frame, and there
+            // is a one second outage in the middle
+            t += match i % 4 {
+                0 | 2 => 2_000_000,
+                _ => 31_000_000,
+            } + jitter(&mut seed, 1_000_000);
+            if i == 500 {
+                t += 1_000_000_000;
+            }
+            timestamps.push(t);
+        }
+
+        let gap_start = timestamps[499];
+        let gap_end = timestamps[500];
+        let kept_before = output_rate(&timestamps, 0, gap_start) * gap_start as f64;
+        let kept_after = output_rate(&timestamps, gap_end, t + 1) * (t + 1 - gap_end) as f64;
+        assert_near_target((kept_before + kept_after) / (gap_start + t + 1 - gap_end) as f64);
+    }
+
+    #[test]
+    fn test_decimator_achieved_rate() {
+        let mut decimator = FrameDecimator::new(TARGET_HZ);
+        assert_eq!(decimator.achieved_hz(), None);
+        for i in 0..120 {
+            decimator.keep(i * 16_666_667);
+        }
+        assert_near_target(decimator.achieved_hz().unwrap());
+
+        // Slower input than the target passes through untouched
+        let mut decimator = FrameDecimator::new(TARGET_HZ);
+        assert!((0..10).all(|i| decimator.keep(i * 50_000_000)));
+    }
+}
@@ -0,0 +1,423 @@
+//! MCAP recordings with Foxglove message schemas (`mcap` feature)
+//!
+//! Messages are JSON encoded, which Foxglove Studio reads with the `jsonschema` schemas written
+//! alongside each channel. Byte fields such as image data are base64 strings, and timestamps are
+//! `{ "sec", "nsec" }` objects.
+
+use crate::frame_source::StampMatcher;
+use crate::image_io::convert_pixels;
+use crate::mcap_file::McapFile;
+use crate::{
+    pose_from_quaternion, pose_rotation_quaternion, Calibration, CameraRig, Error, FrameSet, FrameSource, Image,
+    PoseEstimate, CUVSLAM_ImuMeasurement, CUVSLAM_Pose,
+};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const RAW_IMAGE: &str = "foxglove.RawImage";
+const COMPRESSED_IMAGE: &str = "foxglove.CompressedImage";
+const POSE_IN_FRAME: &str = "foxglove.PoseInFrame";
+const IMU_MEASUREMENT: &str = "cuvslam.ImuMeasurement";
+
+/// Records tracker inputs and outputs into a single MCAP file for replay and visualization
+///
+/// Images are written as `foxglove.RawImage` on `image_topic(camera_index)`, IMU samples on
+/// `IMU_TOPIC` and pose estimates as `foxglove.PoseInFrame` in the `world` frame on `POSE_TOPIC`
+/// (the covariance is not kept). Messages are grouped in zstd-compressed chunks with an index,
+/// so the file opens directly in Foxglove Studio and can be played back with `McapSource`.
+pub struct McapRecorder {
+    writer: mcap::Writer<BufWriter<File>>,
+    schemas: HashMap<&'static str, u16>,
+    channels: HashMap<String, u16>,
+    sequence: u32,
+}
+
+impl McapRecorder {
+    /// Topic of the IMU samples, with the `cuvslam.ImuMeasurement` schema
+    pub const IMU_TOPIC: &'static str = "/imu";
+    /// Topic of the pose estimates
+    pub const POSE_TOPIC: &'static str = "/pose";
+
+    /// Topic of the images of camera `camera_index`
+    pub fn image_topic(camera_index: i32) -> String {
+        format!("/camera_{}/image", camera_index)
+    }
+
+    /// Create or truncate the file at `path`
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let writer = mcap::Writer::new(BufWriter::new(File::create(path)?)).map_err(to_io)?;
+        Ok(Self { writer, schemas: HashMap::new(), channels: HashMap::new(), sequence: 0 })
+    }
+
+    /// Record the images of one frame, as passed to `Tracker::track`
+    pub fn record_frame(&mut self, images: &[Image]) -> io::Result<()> {
+        for image in images {
+            let encoding: &str = image.encoding().into();
+            let message = json!({
+                "timestamp": timestamp(image.timestamp_ns()),
+                "frame_id": format!("camera_{}", image.camera_index()),
+                "width": image.width(),
+                "height": image.height(),
+                "encoding": encoding,
+                "step": image.pitch(),
+                "data": BASE64.encode(image.pixels()),
+            });
+            let topic = Self::image_topic(image.camera_index());
+            self.write(&topic, RAW_IMAGE, image.timestamp_ns(), &message)?;
+        }
+        Ok(())
+    }
+
+    /// Record an IMU sample, as passed to `Tracker::register_imu_measurement`
+    pub fn record_imu(&mut self, measurement: &CUVSLAM_ImuMeasurement) -> io::Result<()> {
+        let [wx, wy, wz] = measurement.angular_velocities;
+        let [ax, ay, az] = measurement.linear_accelerations;
+        let message = json!({
+            "timestamp": timestamp(measurement.timestamp_ns),
+            "angular_velocity": { "x": wx, "y": wy, "z": wz },
+            "linear_acceleration": { "x": ax, "y": ay, "z": az },
+        });
+        self.write(Self::IMU_TOPIC, IMU_MEASUREMENT, measurement.timestamp_ns, &message)
+    }
+
+    /// Record a pose estimate returned by the tracker
+    pub fn record_pose(&mut self, estimate: &PoseEstimate) -> io::Result<()> {
+        let [x, y, z] = estimate.pose.t;
+        let [qx, qy, qz, qw] = pose_rotation_quaternion(&estimate.pose);
+        let message = json!({
+            "timestamp": timestamp(estimate.timestamp_ns),
+            "frame_id": "world",
+            "pose": {
+                "position": { "x": x, "y": y, "z": z },
+                "orientation": { "x": qx, "y": qy, "z": qz, "w": qw },
+            },
+        });
+        self.write(Self::POSE_TOPIC, POSE_IN_FRAME, estimate.timestamp_ns, &message)
+    }
+
+    /// Write the index and summary and flush the file
+    ///
+    /// Dropping the recorder also finishes the file, but ignores any error.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.finish().map_err(to_io)?;
+        self.writer.into_inner().flush()
+    }
+
+    fn write(&mut self, topic: &str, schema_name: &'static str, timestamp_ns: i64, message: &Value) -> io::Result<()> {
+        let channel_id = match self.channels.get(topic) {
+            Some(&channel_id) => channel_id,
+            None => {
+                let schema_id = match self.schemas.get(schema_name) {
+                    Some(&schema_id) => schema_id,
+                    None => {
+                        let schema = serde_json::to_vec(&json_schema(schema_name))?;
+                        let schema_id = self.writer.add_schema(schema_name, "jsonschema", &schema).map_err(to_io)?;
+                        *self.schemas.entry(schema_name).or_insert(schema_id)
+                    }
+                };
+                let channel_id =
+                    self.writer.add_channel(schema_id, topic, "json", &Default::default()).map_err(to_io)?;
+                *self.channels.entry(topic.to_string()).or_insert(channel_id)
+            }
+        };
+
+        let log_time = timestamp_ns.max(0) as u64;
+        let header =
+            mcap::records::MessageHeader { channel_id, sequence: self.sequence, log_time, publish_time: log_time };
+        self.sequence = self.sequence.wrapping_add(1);
+        self.writer.write_to_known_channel(&header, &serde_json::to_vec(message)?).map_err(to_io)
+    }
+}
+
+/// `FrameSource` over the image channels of an MCAP file, such as one written by `McapRecorder`
+///
+/// Reads JSON-encoded `foxglove.RawImage` and `foxglove.CompressedImage` (PNG or JPEG) channels;
+/// protobuf-encoded channels are not supported. Images are matched across topics by timestamp
+/// like `Rosbag2Source`, and the file is streamed a chunk at a time.
+pub struct McapSource {
+    path: PathBuf,
+    file: McapFile,
+    topic_schemas: HashMap<String, String>,
+    image_topics: Vec<String>,
+    imu_topic: Option<String>,
+    matcher: StampMatcher<Value>,
+    imu: Vec<CUVSLAM_ImuMeasurement>,
+    last_timestamp_ns: Option<i64>,
+    calibration: Option<Calibration>,
+}
+
+impl McapSource {
+    /// Open the file at `path` to play `image_topics` as cameras 0, 1, ...
+    ///
+    /// Images whose timestamps differ by more than `max_skew_ns` are not grouped into a frame.
+    #[must_use = "the source or the reason it could not be opened is only in the Result"]
+    pub fn open<P: AsRef<Path>>(path: P, image_topics: &[&str], max_skew_ns: i64) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file =
+            McapFile::open(path, "json").map_err(|reason| Error::Dataset(format!("{}: {}", path.display(), reason)))?;
+        let topic_schemas = file.topic_schemas();
+        for topic in image_topics {
+            match topic_schemas.get(*topic).map(String::as_str) {
+                Some(RAW_IMAGE | COMPRESSED_IMAGE) => {}
+                Some(other) => return Err(Error::Dataset(format!("{}: {} is not an image topic", topic, other))),
+                None => return Err(Error::Dataset(format!("{}: no JSON topic {}", path.display(), topic))),
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            topic_schemas,
+            image_topics: image_topics.iter().map(|topic| topic.to_string()).collect(),
+            imu_topic: None,
+            matcher: StampMatcher::new(image_topics.len(), max_skew_ns),
+            imu: Vec::new(),
+            last_timestamp_ns: None,
+            calibration: None,
+        })
+    }
+
+    /// Also read `cuvslam.ImuMeasurement` messages from `topic`, to be collected with `drain_imu`
+    #[must_use = "the source or the reason the topic can't be read is only in the Result"]
+    pub fn with_imu_topic(mut self, topic: &str) -> Result<Self, Error> {
+        match self.topic_schemas.get(topic).map(String::as_str) {
+            Some(IMU_MEASUREMENT) => {}
+            Some(other) => return Err(Error::Dataset(format!("{}: {} is not an IMU topic", topic, other))),
+            None => return Err(Error::Dataset(format!("no JSON topic {}", topic))),
+        }
+        self.imu_topic = Some(topic.to_string());
+        Ok(self)
+    }
+
+    /// Report `calibration` as the rig the file was recorded with
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    /// Take the IMU measurements read so far that are no later than the last frame returned
+    pub fn drain_imu(&mut self) -> Vec<CUVSLAM_ImuMeasurement> {
+        let Some(until_ns) = self.last_timestamp_ns else { return Vec::new() };
+        self.imu.sort_by_key(|measurement| measurement.timestamp_ns);
+        let split = self.imu.partition_point(|measurement| measurement.timestamp_ns <= until_ns);
+        let later = self.imu.split_off(split);
+        std::mem::replace(&mut self.imu, later)
+    }
+
+    /// Read every `foxglove.PoseInFrame` on `topic`, e.g. the estimates `McapRecorder` recorded
+    ///
+    /// Reads the file independently of the frames played so far.
+    #[must_use = "the poses or the reason they could not be read are only in the Result"]
+    pub fn read_poses(&self, topic: &str) -> Result<Vec<(i64, CUVSLAM_Pose)>, Error> {
+        if self.topic_schemas.get(topic).map(String::as_str) != Some(POSE_IN_FRAME) {
+            return Err(Error::Dataset(format!("{}: no {} topic {}", self.path.display(), POSE_IN_FRAME, topic)));
+        }
+        let mut file = McapFile::open(&self.path, "json").map_err(Error::Dataset)?;
+        let mut poses = Vec::new();
+        while let Some((_, data)) = file.next_message(&[topic]).map_err(mcap_error)? {
+            poses.push(
+                parse_json(&data).and_then(|message| parse_pose(&message)).map_err(|error| on_topic(topic, error))?,
+            );
+        }
+        Ok(poses)
+    }
+
+    /// Decode a matched set of image messages
+    fn decode_frame(&mut self, timestamp_ns: i64, messages: Vec<Value>) -> Result<FrameSet, Error> {
+        self.last_timestamp_ns = Some(timestamp_ns);
+        let images = messages
+            .iter()
+            .enumerate()
+            .map(|(camera, message)| {
+                let topic = &self.image_topics[camera];
+                decode_image(message, self.topic_schemas[topic].as_str(), camera as i32, timestamp_ns)
+                    .map_err(|error| on_topic(topic, error))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(FrameSet { timestamp_ns, images })
+    }
+}
+
+impl FrameSource for McapSource {
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>> {
+        let topics: Vec<String> = self.image_topics.iter().chain(&self.imu_topic).cloned().collect();
+        let topics: Vec<&str> = topics.iter().map(String::as_str).collect();
+        loop {
+            if let Some((timestamp_ns, messages)) = self.matcher.pop() {
+                return Some(self.decode_frame(timestamp_ns, messages));
+            }
+
+            let (topic, data) = match self.file.next_message(&topics) {
+                Ok(Some(message)) => message,
+                Ok(None) => return None,
+                Err(error) => return Some(Err(mcap_error(error))),
+            };
+            let result = parse_json(&data).and_then(|message| {
+                match self.image_topics.iter().position(|image_topic| *image_topic == topic) {
+                    Some(camera) => {
+                        let timestamp_ns = parse_timestamp(&message["timestamp"])?;
+                        self.matcher.push(camera, timestamp_ns, message);
+                    }
+                    None => self.imu.push(parse_imu(&message)?),
+                }
+                Ok(())
+            });
+            if let Err(error) = result {
+                return Some(Err(on_topic(&topic, error)));
+            }
+        }
+    }
+
+    fn calibration(&self) -> Option<CameraRig> {
+        self.calibration.as_ref()?.to_rig().ok()
+    }
+}
+
+fn to_io(error: mcap::McapError) -> io::Error {
+    match error {
+        mcap::McapError::Io(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+fn mcap_error(error: mcap::McapError) -> Error {
+    Error::Dataset(format!("mcap: {}", error))
+}
+
+/// Name the topic a message came from in its decoding error
+fn on_topic(topic: &str, error: Error) -> Error {
+    match error {
+        Error::Dataset(reason) => Error::Dataset(format!("{}: {}", topic, reason)),
+        error => error,
+    }
+}
+
+fn timestamp(timestamp_ns: i64) -> Value {
+    json!({ "sec": timestamp_ns.div_euclid(1_000_000_000), "nsec": timestamp_ns.rem_euclid(1_000_000_000) })
+}
+
+/// JSON schema of the messages written for `schema_name`
+fn json_schema(schema_name: &str) -> Value {
+    let number = json!({ "type": "number" });
+    let vector3 = json!({ "type": "object", "properties": { "x": number, "y": number, "z": number } });
+    let time = json!({
+        "type": "object",
+        "properties": { "sec": { "type": "integer" }, "nsec": { "type": "integer", "minimum": 0, "maximum": 999_999_999 } },
+    });
+    let properties = match schema_name {
+        RAW_IMAGE => json!({
+            "timestamp": time,
+            "frame_id": { "type": "string" },
+            "width": { "type": "integer" },
+            "height": { "type": "integer" },
+            "encoding": { "type": "string" },
+            "step": { "type": "integer" },
+            "data": { "type": "string", "contentEncoding": "base64" },
+        }),
+        POSE_IN_FRAME => json!({
+            "timestamp": time,
+            "frame_id": { "type": "string" },
+            "pose": {
+                "type": "object",
+                "properties": {
+                    "position": vector3,
+                    "orientation": {
+                        "type": "object",
+                        "properties": { "x": number, "y": number, "z": number, "w": number },
+                    },
+                },
+            },
+        }),
+        _ => json!({ "timestamp": time, "angular_velocity": vector3, "linear_acceleration": vector3 }),
+    };
+    json!({ "title": schema_name, "type": "object", "properties": properties })
+}
+
+fn parse_json(data: &[u8]) -> Result<Value, Error> {
+    serde_json::from_slice(data).map_err(|error| Error::Dataset(format!("invalid JSON message: {}", error)))
+}
+
+fn field_error(name: &str) -> Error {
+    Error::Dataset(format!("missing or invalid field {}", name))
+}
+
+fn parse_timestamp(value: &Value) -> Result<i64, Error> {
+    let sec = value["sec"].as_i64().ok_or_else(|| field_error("timestamp.sec"))?;
+    let nsec = value["nsec"].as_i64().ok_or_else(|| field_error("timestamp.nsec"))?;
+    Ok(sec * 1_000_000_000 + nsec)
+}
+
+fn parse_vector<const N: usize>(value: &Value, name: &str, axes: [&str; N]) -> Result<[f32; N], Error> {
+    let mut vector = [0.0; N];
+    for (component, axis) in vector.iter_mut().zip(axes) {
+        *component = value[axis].as_f64().ok_or_else(|| field_error(&format!("{}.{}", name, axis)))? as f32;
+    }
+    Ok(vector)
+}
+
+fn parse_imu(message: &Value) -> Result<CUVSLAM_ImuMeasurement, Error> {
+    Ok(CUVSLAM_ImuMeasurement {
+        timestamp_ns: parse_timestamp(&message["timestamp"])?,
+        linear_accelerations: parse_vector(&message["linear_acceleration"], "linear_acceleration", ["x", "y", "z"])?,
+        angular_velocities: parse_vector(&message["angular_velocity"], "angular_velocity", ["x", "y", "z"])?,
+    })
+}
+
+fn parse_pose(message: &Value) -> Result<(i64, CUVSLAM_Pose), Error> {
+    let pose = &message["pose"];
+    let position = parse_vector(&pose["position"], "pose.position", ["x", "y", "z"])?;
+    let orientation = parse_vector(&pose["orientation"], "pose.orientation", ["x", "y", "z", "w"])?;
+    Ok((parse_timestamp(&message["timestamp"])?, pose_from_quaternion(orientation, position)))
+}
+
+fn decode_image(message: &Value, schema_name: &str, camera_index: i32, timestamp_ns: i64) -> Result<Image, Error> {
+    let data = message["data"].as_str().ok_or_else(|| field_error("data"))?;
+    let data = BASE64.decode(data).map_err(|_| field_error("data"))?;
+    if schema_name == COMPRESSED_IMAGE {
+        let format = message["format"].as_str().unwrap_or_default();
+        return Image::decode(&data, camera_index, timestamp_ns)
+            .map_err(|error| Error::Dataset(format!("{} image: {}", format, error)));
+    }
+
+    let dimension = |name: &str| message[name].as_u64().map(|value| value as usize).ok_or_else(|| field_error(name));
+    let (width, height, step) = (dimension("width")?, dimension("height")?, dimension("step")?);
+    let encoding = message["encoding"].as_str().ok_or_else(|| field_error("encoding"))?;
+    let (pixels, image_encoding) =
+        convert_pixels(&data, encoding, width, height, step, false).map_err(Error::Dataset)?;
+    Image::new(pixels, width as i32, height as i32, image_encoding, camera_index, timestamp_ns).map_err(Error::Status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_messages() {
+        assert_eq!(timestamp(-1), json!({ "sec": -1, "nsec": 999_999_999 }));
+        assert_eq!(parse_timestamp(&timestamp(1_500_000_001)).unwrap(), 1_500_000_001);
+        assert!(matches!(parse_timestamp(&json!({ "sec": 1 })), Err(Error::Dataset(_))));
+
+        let imu = json!({
+            "timestamp": timestamp(5),
+            "angular_velocity": { "x": 0.1, "y": 0.2, "z": 0.3 },
+            "linear_acceleration": { "x": 0.0, "y": 9.8, "z": 0.0 },
+        });
+        let measurement = parse_imu(&imu).unwrap();
+        assert_eq!(measurement.timestamp_ns, 5);
+        assert_eq!(measurement.angular_velocities, [0.1, 0.2, 0.3]);
+        assert_eq!(measurement.linear_accelerations, [0.0, 9.8, 0.0]);
+
+        let raw = json!({
+            "width": 2, "height": 1, "step": 8, "encoding": "bgra8",
+            "data": BASE64.encode([1, 2, 3, 255, 4, 5, 6, 255]),
+        });
+        let image = decode_image(&raw, RAW_IMAGE, 1, 7).unwrap();
+        assert_eq!((image.camera_index(), image.timestamp_ns()), (1, 7));
+        assert_eq!(image.pixels(), [3, 2, 1, 6, 5, 4]);
+        assert!(matches!(decode_image(&json!({ "data": "?" }), RAW_IMAGE, 0, 0), Err(Error::Dataset(_))));
+    }
+}
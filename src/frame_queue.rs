@@ -0,0 +1,236 @@
+//! Bounded hand-off queue between a capture thread and a tracking thread
+
+use crate::profile_scope;
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// What to do when a frame is submitted to a full queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Wait for the consumer to make room
+    Block,
+    /// Discard the oldest queued frame to make room for the new one
+    DropOldest,
+    /// Discard the submitted frame
+    DropNewest,
+}
+
+/// Counters describing queue usage since creation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Frames passed to `push`
+    pub frames_submitted: u64,
+    /// Frames discarded because the queue was full
+    pub frames_dropped: u64,
+    /// Largest number of frames queued at once
+    pub max_queue_depth: usize,
+}
+
+struct State<T> {
+    frames: VecDeque<T>,
+    stats: QueueStats,
+    closed: bool,
+}
+
+/// Bounded frame queue with an explicit policy for a full queue
+///
+/// Frames discarded by `DropOldest` or `DropNewest` are passed to the optional drop callback,
+/// which lets the capture side react (e.g. skip preprocessing) as soon as drops start.
+pub struct FrameQueue<T> {
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    policy: QueuePolicy,
+    on_drop: Option<Box<dyn Fn(T) + Send + Sync>>,
+}
+
+impl<T> FrameQueue<T> {
+    /// Create a queue holding at most `capacity` frames (at least one)
+    pub fn new(capacity: usize, policy: QueuePolicy) -> Self {
+        Self {
+            state: Mutex::new(State {
+                frames: VecDeque::with_capacity(capacity.max(1)),
+                stats: QueueStats::default(),
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity: capacity.max(1),
+            policy,
+            on_drop: None,
+        }
+    }
+
+    /// Call `on_drop` with every frame discarded by the queue policy
+    pub fn with_drop_callback<F: Fn(T) + Send + Sync + 'static>(mut self, on_drop: F) -> Self {
+        self.on_drop = Some(Box::new(on_drop));
+        self
+    }
+
+    /// The policy applied when the queue is full
+    pub fn policy(&self) -> QueuePolicy {
+        self.policy
+    }
+
+    /// Submit a frame, returning false if it was not enqueued
+    ///
+    /// With `QueuePolicy::Block` this waits for space, and only fails once the queue is closed.
+    pub fn push(&self, frame: T) -> bool {
+        profile_scope!("queue_push");
+        let mut state = self.state.lock().unwrap();
+        state.stats.frames_submitted += 1;
+        if self.policy == QueuePolicy::Block {
+            while state.frames.len() >= self.capacity && !state.closed {
+                state = self.not_full.wait(state).unwrap();
+            }
+        }
+        if state.closed {
+            return false;
+        }
+
+        let dropped = if state.frames.len() < self.capacity {
+            None
+        } else if self.policy == QueuePolicy::DropNewest {
+            state.stats.frames_dropped += 1;
+            drop(state);
+            self.report_drop(frame);
+            return false;
+        } else {
+            state.stats.frames_dropped += 1;
+            state.frames.pop_front()
+        };
+
+        state.frames.push_back(frame);
+        state.stats.max_queue_depth = state.stats.max_queue_depth.max(state.frames.len());
+        drop(state);
+        self.not_empty.notify_one();
+
+        if let Some(dropped) = dropped {
+            self.report_drop(dropped);
+        }
+        true
+    }
+
+    /// Take the oldest frame, waiting up to `timeout` for one to arrive
+    ///
+    /// Returns `None` on timeout or once the queue is closed and drained.
+    pub fn pop(&self, timeout: Duration) -> Option<T> {
+        profile_scope!("queue_pop");
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(frame) = state.frames.pop_front() {
+                drop(state);
+                self.not_full.notify_one();
+                return Some(frame);
+            }
+            if state.closed {
+                return None;
+            }
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            state = self.not_empty.wait_timeout(state, remaining).unwrap().0;
+        }
+    }
+
+    /// Number of frames currently queued
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().frames.len()
+    }
+
+    /// Whether no frames are currently queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Usage counters since the queue was created
+    pub fn stats(&self) -> QueueStats {
+        self.state.lock().unwrap().stats
+    }
+
+    /// Reject further frames and wake up all waiting producers and consumers
+    ///
+    /// Frames already queued can still be popped.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    fn report_drop(&self, frame: T) {
+        if let Some(on_drop) = &self.on_drop {
+            on_drop(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    const FRAMES: u32 = 10;
+
+    /// Push frames into a queue with no consumer, as if the tracker were stalled
+    fn flood(policy: QueuePolicy) -> (FrameQueue<u32>, Vec<u32>, Arc<Mutex<Vec<u32>>>) {
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let queue = {
+            let dropped = dropped.clone();
+            FrameQueue::new(3, policy).with_drop_callback(move |frame| dropped.lock().unwrap().push(frame))
+        };
+        for frame in 0..FRAMES {
+            queue.push(frame);
+        }
+        queue.close();
+        let remaining = std::iter::from_fn(|| queue.pop(Duration::ZERO)).collect();
+        (queue, remaining, dropped)
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_latest_frames() {
+        let (queue, remaining, dropped) = flood(QueuePolicy::DropOldest);
+        assert_eq!(remaining, vec![7, 8, 9]);
+        assert_eq!(*dropped.lock().unwrap(), (0..7).collect::<Vec<_>>());
+        assert_eq!(
+            queue.stats(),
+            QueueStats { frames_submitted: 10, frames_dropped: 7, max_queue_depth: 3 }
+        );
+    }
+
+    #[test]
+    fn test_drop_newest_keeps_earliest_frames() {
+        let (queue, remaining, dropped) = flood(QueuePolicy::DropNewest);
+        assert_eq!(remaining, vec![0, 1, 2]);
+        assert_eq!(*dropped.lock().unwrap(), (3..10).collect::<Vec<_>>());
+        assert_eq!(queue.stats().frames_dropped, 7);
+    }
+
+    #[test]
+    fn test_block_delivers_every_frame() {
+        let queue = Arc::new(FrameQueue::new(2, QueuePolicy::Block));
+        let producer = {
+            let queue = queue.clone();
+            thread::spawn(move || {
+                for frame in 0..FRAMES {
+                    assert!(queue.push(frame));
+                }
+                queue.close();
+            })
+        };
+
+        // Slow consumer standing in for the tracker
+        let mut received = Vec::new();
+        while let Some(frame) = queue.pop(Duration::from_secs(5)) {
+            thread::sleep(Duration::from_millis(2));
+            received.push(frame);
+        }
+        producer.join().unwrap();
+
+        assert_eq!(received, (0..FRAMES).collect::<Vec<_>>());
+        let stats = queue.stats();
+        assert_eq!(stats.frames_dropped, 0);
+        assert!(stats.max_queue_depth <= 2);
+    }
+}
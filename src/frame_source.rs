@@ -0,0 +1,172 @@
+//! Common interface for recorded and live frame providers
+
+use crate::{CameraRig, Error, FrameDecimator, Image, PoseEstimate, Status};
+use std::collections::VecDeque;
+
+/// Relative difference between two cameras' frame rates reported as a mismatch
+const RATE_MISMATCH: f64 = 0.1;
+
+/// The images of one multi-camera frame
+pub struct FrameSet {
+    /// Timestamp shared by all images
+    pub timestamp_ns: i64,
+    /// One image per camera, in camera index order
+    pub images: Vec<Image>,
+}
+
+/// A frame's timestamp and its tracking result, as returned by `Tracker::track_source`
+pub type TrackedFrame = (i64, Result<PoseEstimate, Status>);
+
+/// Something that yields frames in time order, such as a dataset sequence or a folder of images
+///
+/// `Tracker::track_source` tracks a whole source, and the dataset players are written against
+/// this trait, so supporting a new recording layout only takes an implementation of it.
+pub trait FrameSource {
+    /// Load the next frame, or `None` once the source is exhausted
+    ///
+    /// A frame that fails to load is reported as an error without ending the source.
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>>;
+
+    /// Camera rig the frames were captured with, if the source knows it
+    fn calibration(&self) -> Option<CameraRig>;
+
+    /// Number of frames left, if known up front
+    fn len_hint(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Groups per-camera items whose timestamps agree within a tolerance, for recordings that store
+/// each camera's images as separate messages
+pub(crate) struct StampMatcher<T> {
+    pending: Vec<VecDeque<(i64, T)>>,
+    max_skew_ns: i64,
+}
+
+impl<T> StampMatcher<T> {
+    pub(crate) fn new(cameras: usize, max_skew_ns: i64) -> Self {
+        Self { pending: (0..cameras).map(|_| VecDeque::new()).collect(), max_skew_ns }
+    }
+
+    /// Queue an item of `camera`, which must arrive in timestamp order for that camera
+    pub(crate) fn push(&mut self, camera: usize, timestamp_ns: i64, item: T) {
+        self.pending[camera].push_back((timestamp_ns, item));
+    }
+
+    /// Take the oldest complete set, stamped with camera 0's timestamp
+    ///
+    /// Items too old to ever be matched are dropped on the way.
+    pub(crate) fn pop(&mut self) -> Option<(i64, Vec<T>)> {
+        loop {
+            let heads = self.pending.iter().map(|queue| queue.front().map(|(timestamp_ns, _)| *timestamp_ns));
+            let heads: Vec<i64> = heads.collect::<Option<_>>()?;
+            let oldest = (0..heads.len()).min_by_key(|&camera| heads[camera])?;
+            let newest = heads.iter().max()?;
+            if newest - heads[oldest] <= self.max_skew_ns {
+                let items = self.pending.iter_mut().filter_map(|queue| queue.pop_front()).map(|(_, item)| item);
+                return Some((heads[0], items.collect()));
+            }
+            self.pending[oldest].pop_front();
+        }
+    }
+}
+
+/// Pairs the frames of two free-running cameras by timestamp, such as two USB webcams used as a
+/// stereo rig
+///
+/// Frames are matched when their timestamps are within `max_skew_ns`; a frame too old to ever be
+/// matched is dropped, so a camera running faster than the other has its extra frames dropped
+/// rather than queued. Both cameras' timestamps must come from the same clock. Each camera's
+/// delivered rate is measured over its last 30 frames with a `FrameDecimator` that keeps
+/// everything, to report cameras that don't run at the same rate.
+pub struct StereoPairer<T> {
+    matcher: StampMatcher<T>,
+    meters: [FrameDecimator; 2],
+}
+
+impl<T> StereoPairer<T> {
+    /// Create a pairer matching frames at most `max_skew_ns` apart
+    pub fn new(max_skew_ns: i64) -> Self {
+        Self {
+            matcher: StampMatcher::new(2, max_skew_ns),
+            meters: [FrameDecimator::new(0.0), FrameDecimator::new(0.0)],
+        }
+    }
+
+    /// Add a frame of camera 0 or 1, returning the newest complete pair it finished
+    ///
+    /// Pairs are stamped with camera 0's timestamp. Frames of each camera must be added in
+    /// timestamp order. Returns `None` for a `camera_index` other than 0 or 1.
+    pub fn push(&mut self, camera_index: usize, timestamp_ns: i64, frame: T) -> Option<(i64, [T; 2])> {
+        self.meters.get_mut(camera_index)?.keep(timestamp_ns);
+        self.matcher.push(camera_index, timestamp_ns, frame);
+        let (timestamp_ns, frames) = std::iter::from_fn(|| self.matcher.pop()).last()?;
+        let frames: [T; 2] = frames.try_into().ok()?;
+        Some((timestamp_ns, frames))
+    }
+
+    /// Measured frame rate of each camera in Hz, once it has delivered two frames
+    pub fn rates_hz(&self) -> [Option<f64>; 2] {
+        [self.meters[0].achieved_hz(), self.meters[1].achieved_hz()]
+    }
+
+    /// Both cameras' rates when they differ by more than 10%
+    pub fn rate_mismatch(&self) -> Option<[f64; 2]> {
+        let [Some(left), Some(right)] = self.rates_hz() else {
+            return None;
+        };
+        ((left - right).abs() > RATE_MISMATCH * left.max(right)).then_some([left, right])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp_matcher() {
+        let mut matcher = StampMatcher::new(2, 2);
+        assert_eq!(matcher.pop(), None);
+        matcher.push(0, 10, "left 10");
+        matcher.push(0, 20, "left 20");
+        assert_eq!(matcher.pop(), None);
+        // The left image at 10 has no match and is dropped
+        matcher.push(1, 21, "right 21");
+        matcher.push(1, 30, "right 30");
+        assert_eq!(matcher.pop(), Some((20, vec!["left 20", "right 21"])));
+        assert_eq!(matcher.pop(), None);
+        matcher.push(0, 29, "left 29");
+        assert_eq!(matcher.pop(), Some((29, vec!["left 29", "right 30"])));
+    }
+
+    #[test]
+    fn test_stereo_pairer() {
+        const PERIOD_NS: i64 = 33_333_333;
+        let mut pairer = StereoPairer::new(5_000_000);
+        assert_eq!(pairer.push(2, 0, 0), None);
+
+        // The left camera runs at 30 Hz, the right one at 15 Hz and 2ms late
+        let mut pairs = Vec::new();
+        for frame in 0..60 {
+            pairs.extend(pairer.push(0, frame * PERIOD_NS, frame));
+            if frame % 2 == 0 {
+                pairs.extend(pairer.push(1, frame * PERIOD_NS + 2_000_000, 100 + frame));
+            }
+        }
+        assert_eq!(pairs.len(), 30);
+        assert!(pairs
+            .iter()
+            .all(|(timestamp_ns, [left, right])| *timestamp_ns == left * PERIOD_NS && *right == 100 + left));
+
+        let [Some(left_hz), Some(right_hz)] = pairer.rates_hz() else { panic!("rates not measured") };
+        assert!((left_hz - 30.0).abs() < 0.01 && (right_hz - 15.0).abs() < 0.01);
+        assert!(pairer.rate_mismatch().is_some());
+
+        let mut matched = StereoPairer::new(5_000_000);
+        for frame in 0..10 {
+            matched.push(0, frame * PERIOD_NS, ());
+            matched.push(1, frame * PERIOD_NS + 1_000_000, ());
+        }
+        assert_eq!(matched.rate_mismatch(), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Image files (`image` feature)
+
+use crate::{Image, ImageEncoding};
+use image::{ExtendedColorType, ImageEncoder, ImageError};
+use std::io;
+use std::path::Path;
+
+impl Image {
+    /// Load a PNG file as a tracker input
+    ///
+    /// Grayscale files become `Mono8` images, with 16-bit ones scaled down to 8 bits; anything
+    /// else is converted to `Rgb8`. Files that can't be decoded are reported as
+    /// `io::ErrorKind::InvalidData`.
+    pub fn open<P: AsRef<Path>>(path: P, camera_index: i32, timestamp_ns: i64) -> io::Result<Image> {
+        from_decoded(image::open(path), camera_index, timestamp_ns)
+    }
+
+    /// Decode an encoded image held in memory, such as a PNG or a JPEG from a compressed stream
+    ///
+    /// Converts pixels and reports errors like `Image::open`. JPEG support is only compiled in
+    /// with the `rosbag2` feature.
+    pub fn decode(bytes: &[u8], camera_index: i32, timestamp_ns: i64) -> io::Result<Image> {
+        from_decoded(image::load_from_memory(bytes), camera_index, timestamp_ns)
+    }
+
+    /// Encode the image as a PNG, grayscale for `Mono8` and RGB for `Rgb8`
+    ///
+    /// Row padding from the pitch is dropped, so the PNG holds exactly `width` x `height` pixels.
+    pub fn to_png_bytes(&self) -> Result<Vec<u8>, ImageError> {
+        let row_len = self.width() as usize * self.encoding().bytes_per_pixel();
+        let mut pixels = Vec::with_capacity(row_len * self.height() as usize);
+        for row in self.pixels().chunks(self.pitch() as usize).take(self.height() as usize) {
+            pixels.extend_from_slice(&row[..row_len]);
+        }
+        let color = match self.encoding() {
+            ImageEncoding::Mono8 => ExtendedColorType::L8,
+            ImageEncoding::Rgb8 => ExtendedColorType::Rgb8,
+        };
+
+        let mut png = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut png).write_image(
+            &pixels,
+            self.width() as u32,
+            self.height() as u32,
+            color,
+        )?;
+        Ok(png)
+    }
+
+    /// Write the image to `path` as a PNG, e.g. to inspect the frames passed to the tracker
+    pub fn save_png(&self, path: &Path) -> Result<(), ImageError> {
+        std::fs::write(path, self.to_png_bytes()?).map_err(ImageError::IoError)
+    }
+}
+
+/// Convert a decoded file to `Mono8` or `Rgb8`
+fn from_decoded(
+    decoded: image::ImageResult<image::DynamicImage>,
+    camera_index: i32,
+    timestamp_ns: i64,
+) -> io::Result<Image> {
+    let decoded = decoded.map_err(|error| match error {
+        image::ImageError::IoError(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    })?;
+    let (width, height) = (decoded.width() as i32, decoded.height() as i32);
+    let (pixels, encoding) = match decoded {
+        image::DynamicImage::ImageLuma8(gray) => (gray.into_raw(), ImageEncoding::Mono8),
+        gray @ image::DynamicImage::ImageLuma16(_) => (gray.into_luma8().into_raw(), ImageEncoding::Mono8),
+        other => (other.into_rgb8().into_raw(), ImageEncoding::Rgb8),
+    };
+    Image::new(pixels, width, height, encoding, camera_index, timestamp_ns)
+        .map_err(|status| io::Error::new(io::ErrorKind::InvalidData, status.to_string()))
+}
+
+/// Convert raw pixels in a ROS/Foxglove `encoding` to `Mono8` or `Rgb8`, dropping row padding
+///
+/// `mono8`, `rgb8` and `bgr8` keep their pixels (BGR swapped to RGB); `mono16` keeps its high
+/// byte and the alpha channel of `rgba8`/`bgra8` is dropped.
+#[cfg(any(feature = "mcap", feature = "rosbag2"))]
+pub(crate) fn convert_pixels(
+    data: &[u8],
+    encoding: &str,
+    width: usize,
+    height: usize,
+    step: usize,
+    big_endian: bool,
+) -> Result<(Vec<u8>, ImageEncoding), String> {
+    let (bytes_per_pixel, image_encoding) = match encoding {
+        "mono8" | "8UC1" => (1, ImageEncoding::Mono8),
+        "mono16" | "16UC1" => (2, ImageEncoding::Mono8),
+        "rgb8" | "bgr8" | "8UC3" => (3, ImageEncoding::Rgb8),
+        "rgba8" | "bgra8" => (4, ImageEncoding::Rgb8),
+        encoding => return Err(format!("unsupported image encoding {}", encoding)),
+    };
+    if step < width * bytes_per_pixel || data.len() < step * height {
+        return Err(format!(
+            "{}x{} {} image with {} bytes per row has {} bytes",
+            width,
+            height,
+            encoding,
+            step,
+            data.len()
+        ));
+    }
+
+    let rows = data.chunks(step.max(1)).take(height).map(|row| &row[..width * bytes_per_pixel]);
+    let pixels = match encoding {
+        "bgr8" | "8UC3" => rows.flat_map(|row| row.chunks(3).flat_map(|bgr| [bgr[2], bgr[1], bgr[0]])).collect(),
+        "rgba8" => rows.flat_map(|row| row.chunks(4).flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])).collect(),
+        "bgra8" => rows.flat_map(|row| row.chunks(4).flat_map(|bgra| [bgra[2], bgra[1], bgra[0]])).collect(),
+        "mono16" | "16UC1" => {
+            let high = usize::from(!big_endian);
+            rows.flat_map(|row| row.chunks(2).map(|pixel| pixel[high])).collect()
+        }
+        _ => rows.flatten().copied().collect(),
+    };
+    Ok((pixels, image_encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_open_png() {
+        let dir = std::env::temp_dir().join(format!("cuvslam-image-io-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let gray_path = dir.join("gray.png");
+        image::GrayImage::from_fn(4, 3, |x, y| image::Luma([(x + y * 4) as u8])).save(&gray_path).unwrap();
+        let gray = Image::open(&gray_path, 1, 42).unwrap();
+        assert_eq!((gray.width(), gray.height(), gray.encoding()), (4, 3, ImageEncoding::Mono8));
+        assert_eq!((gray.camera_index(), gray.timestamp_ns()), (1, 42));
+        assert_eq!(gray.pixels(), (0..12).collect::<Vec<u8>>());
+
+        let gray16_path = dir.join("gray16.png");
+        image::ImageBuffer::<image::Luma<u16>, _>::from_raw(3, 1, vec![0u16, 0x8080, 0xffff])
+            .unwrap()
+            .save(&gray16_path)
+            .unwrap();
+        let gray16 = Image::open(&gray16_path, 0, 0).unwrap();
+        assert_eq!(gray16.encoding(), ImageEncoding::Mono8);
+        assert_eq!(gray16.pixels(), [0, 0x80, 0xff]);
+
+        let rgba_path = dir.join("rgba.png");
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([10, 20, 30, 255])).save(&rgba_path).unwrap();
+        let rgb = Image::open(&rgba_path, 0, 0).unwrap();
+        assert_eq!(rgb.encoding(), ImageEncoding::Rgb8);
+        assert_eq!(&rgb.pixels()[..3], &[10, 20, 30]);
+
+        let kind = |path: PathBuf| Image::open(path, 0, 0).err().map(|error| error.kind());
+        assert_eq!(kind(dir.join("missing.png")), Some(io::ErrorKind::NotFound));
+        std::fs::write(dir.join("garbage.png"), b"not a png").unwrap();
+        assert_eq!(kind(dir.join("garbage.png")), Some(io::ErrorKind::InvalidData));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cuvslam-image-png-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Rows of 3 pixels padded to 4 bytes, the padding must not end up in the file
+        let padded = vec![1, 2, 3, 0xff, 4, 5, 6, 0xff];
+        let gray = Image::new_with_pitch(padded, 3, 2, 4, ImageEncoding::Mono8, 0, 7).unwrap();
+        gray.save_png(&dir.join("gray.png")).unwrap();
+        let loaded = Image::open(dir.join("gray.png"), 0, 7).unwrap();
+        assert_eq!((loaded.width(), loaded.height(), loaded.encoding()), (3, 2, ImageEncoding::Mono8));
+        assert_eq!(loaded.pixels(), [1, 2, 3, 4, 5, 6]);
+
+        let rgb = Image::new((0..12).collect(), 2, 2, ImageEncoding::Rgb8, 1, 0).unwrap();
+        let png = rgb.to_png_bytes().unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        let decoded = image::load_from_memory(&png).unwrap().into_rgb8();
+        assert_eq!(decoded.into_raw(), (0..12).collect::<Vec<u8>>());
+        let decoded = Image::decode(&png, 1, 5).unwrap();
+        assert_eq!((decoded.encoding(), decoded.camera_index(), decoded.timestamp_ns()), (ImageEncoding::Rgb8, 1, 5));
+        assert_eq!(decoded.pixels(), rgb.pixels());
+
+        assert!(matches!(gray.save_png(&dir.join("missing/gray.png")), Err(ImageError::IoError(_))));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
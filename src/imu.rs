@@ -0,0 +1,250 @@
+//! Lock-free hand-off of IMU measurements from a sensor thread to the tracking thread
+
+use crate::{bindings::CUVSLAM_ImuMeasurement, Status, Tracker};
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Counters describing IMU channel usage since creation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImuStats {
+    /// Measurements accepted by `ImuSender::push`
+    pub accepted: u64,
+    /// Measurements discarded because the channel was full
+    pub dropped_overflow: u64,
+    /// Measurements discarded because their timestamp did not increase
+    pub dropped_out_of_order: u64,
+    /// Measurements passed to the tracker by `ImuChannel::drain_into`
+    pub registered: u64,
+    /// Measurements `ImuChannel::drain_into` took from the queue but the tracker rejected
+    pub rejected: u64,
+}
+
+/// Single-producer single-consumer ring buffer
+struct Ring {
+    slots: Box<[UnsafeCell<CUVSLAM_ImuMeasurement>]>,
+    // Both indices only ever increase; slots are addressed modulo the capacity
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    accepted: AtomicU64,
+    dropped_overflow: AtomicU64,
+    dropped_out_of_order: AtomicU64,
+    registered: AtomicU64,
+    rejected: AtomicU64,
+}
+
+// Each slot is written only by the producer before publishing it with `tail`, and read only by
+// the consumer before releasing it with `head`
+unsafe impl Send for Ring {}
+unsafe impl Sync for Ring {}
+
+/// Producer half of an `ImuChannel`, owned by the IMU thread
+pub struct ImuSender {
+    ring: Arc<Ring>,
+    last_timestamp_ns: Option<i64>,
+}
+
+/// Bounded IMU queue drained into the tracker between `track` calls
+///
+/// The IMU thread pushes through its `ImuSender` without locking or calling into cuVSLAM, and
+/// the tracking thread forwards queued measurements in order with `drain_into`. Timestamps must
+/// strictly increase; anything else is dropped and counted, as are measurements pushed while
+/// the channel is full.
+pub struct ImuChannel {
+    ring: Arc<Ring>,
+}
+
+impl ImuChannel {
+    /// Create a channel holding at most `capacity` measurements (at least one)
+    pub fn new(capacity: usize) -> (ImuSender, ImuChannel) {
+        // Plain data, so all-zero is a valid placeholder
+        let empty: CUVSLAM_ImuMeasurement = unsafe { std::mem::zeroed() };
+        let ring = Arc::new(Ring {
+            slots: (0..capacity.max(1)).map(|_| UnsafeCell::new(empty)).collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            accepted: AtomicU64::new(0),
+            dropped_overflow: AtomicU64::new(0),
+            dropped_out_of_order: AtomicU64::new(0),
+            registered: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        });
+        let sender = ImuSender {
+            ring: ring.clone(),
+            last_timestamp_ns: None,
+        };
+        (sender, ImuChannel { ring })
+    }
+
+    /// Take the oldest queued measurement
+    pub fn pop(&mut self) -> Option<CUVSLAM_ImuMeasurement> {
+        let ring = &self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        if head == ring.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let measurement = unsafe { *ring.slots[head % ring.slots.len()].get() };
+        ring.head.store(head + 1, Ordering::Release);
+        Some(measurement)
+    }
+
+    /// Register every queued measurement with `tracker`, oldest first
+    ///
+    /// Returns the number of measurements registered, stopping at the first error. The rejected
+    /// measurement has already left the queue, so it is lost; it is counted in
+    /// `ImuStats::rejected`, and later measurements stay queued for the next call.
+    #[must_use = "registration failures are only reported through the Result"]
+    pub fn drain_into(&mut self, tracker: &Tracker, sensor_index: i32) -> Result<usize, Status> {
+        self.drain_with(|measurement| tracker.register_imu_measurement(sensor_index, measurement))
+    }
+
+    /// `drain_into` with the registration call supplied by the caller
+    fn drain_with(
+        &mut self,
+        mut register: impl FnMut(&CUVSLAM_ImuMeasurement) -> Result<(), Status>,
+    ) -> Result<usize, Status> {
+        let mut registered = 0;
+        while let Some(measurement) = self.pop() {
+            if let Err(status) = register(&measurement) {
+                self.ring.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(status);
+            }
+            self.ring.registered.fetch_add(1, Ordering::Relaxed);
+            registered += 1;
+        }
+        Ok(registered)
+    }
+
+    /// Number of measurements currently queued
+    pub fn len(&self) -> usize {
+        self.ring.tail.load(Ordering::Acquire) - self.ring.head.load(Ordering::Acquire)
+    }
+
+    /// Whether no measurements are currently queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Usage counters since the channel was created
+    pub fn imu_stats(&self) -> ImuStats {
+        self.ring.stats()
+    }
+}
+
+impl ImuSender {
+    /// Queue a measurement, returning false if it was dropped
+    pub fn push(&mut self, measurement: CUVSLAM_ImuMeasurement) -> bool {
+        let ring = &self.ring;
+        if self.last_timestamp_ns.is_some_and(|last| measurement.timestamp_ns <= last) {
+            ring.dropped_out_of_order.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        let tail = ring.tail.load(Ordering::Relaxed);
+        if tail - ring.head.load(Ordering::Acquire) == ring.slots.len() {
+            ring.dropped_overflow.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        unsafe { *ring.slots[tail % ring.slots.len()].get() = measurement };
+        ring.tail.store(tail + 1, Ordering::Release);
+
+        ring.accepted.fetch_add(1, Ordering::Relaxed);
+        self.last_timestamp_ns = Some(measurement.timestamp_ns);
+        true
+    }
+
+    /// Usage counters since the channel was created
+    pub fn imu_stats(&self) -> ImuStats {
+        self.ring.stats()
+    }
+}
+
+impl Ring {
+    fn stats(&self) -> ImuStats {
+        ImuStats {
+            accepted: self.accepted.load(Ordering::Relaxed),
+            dropped_overflow: self.dropped_overflow.load(Ordering::Relaxed),
+            dropped_out_of_order: self.dropped_out_of_order.load(Ordering::Relaxed),
+            registered: self.registered.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn measurement(timestamp_ns: i64) -> CUVSLAM_ImuMeasurement {
+        CUVSLAM_ImuMeasurement {
+            timestamp_ns,
+            linear_accelerations: [0.0, 0.0, 9.81],
+            angular_velocities: [0.0; 3],
+        }
+    }
+
+    #[test]
+    fn test_imu_channel_overflow_and_ordering() {
+        let (mut sender, mut channel) = ImuChannel::new(4);
+        for timestamp_ns in [1, 2, 2, 3, 1, 4, 5, 6] {
+            sender.push(measurement(timestamp_ns));
+        }
+        let received: Vec<_> = std::iter::from_fn(|| channel.pop()).map(|m| m.timestamp_ns).collect();
+        assert_eq!(received, vec![1, 2, 3, 4]);
+        assert_eq!(
+            channel.imu_stats(),
+            ImuStats { accepted: 4, dropped_overflow: 2, dropped_out_of_order: 2, registered: 0, rejected: 0 }
+        );
+    }
+
+    #[test]
+    fn test_drain_counts_rejected_measurements() {
+        let (mut sender, mut channel) = ImuChannel::new(8);
+        for timestamp_ns in 1..=4 {
+            sender.push(measurement(timestamp_ns));
+        }
+        let register = |m: &CUVSLAM_ImuMeasurement| if m.timestamp_ns == 2 { Err(Status::InvalidArg) } else { Ok(()) };
+        assert_eq!(channel.drain_with(register), Err(Status::InvalidArg));
+        // The rejected measurement is gone and counted, the rest wait for the next drain
+        assert_eq!(channel.len(), 2);
+        assert_eq!(channel.drain_with(register), Ok(2));
+        let stats = channel.imu_stats();
+        assert_eq!((stats.registered, stats.rejected), (3, 1));
+    }
+
+    #[test]
+    fn test_imu_channel_concurrent_bursts() {
+        const BURSTS: i64 = 100;
+        const BURST_LEN: i64 = 50;
+        let (mut sender, mut channel) = ImuChannel::new(64);
+
+        let producer = thread::spawn(move || {
+            for burst in 0..BURSTS {
+                for i in 0..BURST_LEN {
+                    sender.push(measurement(burst * BURST_LEN + i));
+                }
+                // A stale sample after every burst, as from a glitching driver
+                sender.push(measurement(0));
+                thread::yield_now();
+            }
+        });
+
+        let mut received = Vec::new();
+        while !producer.is_finished() || !channel.is_empty() {
+            while let Some(m) = channel.pop() {
+                received.push(m.timestamp_ns);
+            }
+        }
+        producer.join().unwrap();
+
+        assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+        let stats = channel.imu_stats();
+        assert_eq!(stats.accepted, received.len() as u64);
+        assert_eq!(stats.dropped_out_of_order, BURSTS as u64);
+        assert_eq!(
+            stats.accepted + stats.dropped_overflow + stats.dropped_out_of_order,
+            (BURSTS * (BURST_LEN + 1)) as u64
+        );
+    }
+}
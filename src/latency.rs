@@ -0,0 +1,126 @@
+//! Capture-to-pose latency accounting
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Latency of the most recent pose and percentiles over the rolling window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Latency of the most recently recorded pose
+    pub current: Duration,
+    /// Median latency over the window
+    pub p50: Duration,
+    /// 95th percentile latency over the window
+    pub p95: Duration,
+    /// 99th percentile latency over the window
+    pub p99: Duration,
+    /// Mean latency over the window
+    pub mean: Duration,
+}
+
+/// Rolling statistics of the time from frame capture to pose availability
+///
+/// Sensor timestamps are interpreted as nanoseconds since the Unix epoch, so they must come from
+/// a clock synchronized with the system clock. Poses that appear to be produced before their
+/// frame was captured (clock skew) count as zero latency.
+pub struct LatencyTracker {
+    window: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl LatencyTracker {
+    /// Create a tracker keeping the latencies of the last `capacity` poses (at least one)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            window: VecDeque::with_capacity(capacity.max(1)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Record a pose for the frame captured at `sensor_timestamp_ns`, produced at `produced_at`
+    ///
+    /// Returns the latency of this pose.
+    pub fn record(&mut self, sensor_timestamp_ns: i64, produced_at: SystemTime) -> Duration {
+        let captured_at = UNIX_EPOCH + Duration::from_nanos(sensor_timestamp_ns.max(0) as u64);
+        let latency = produced_at.duration_since(captured_at).unwrap_or_default();
+        self.record_duration(latency);
+        latency
+    }
+
+    /// Record a latency measured some other way, such as the duration of a `track` call
+    pub fn record_duration(&mut self, latency: Duration) {
+        if self.window.len() == self.capacity {
+            self.window.pop_front();
+        }
+        self.window.push_back(latency);
+    }
+
+    /// Record a pose for the frame captured at `sensor_timestamp_ns`, produced just now
+    pub fn record_now(&mut self, sensor_timestamp_ns: i64) -> Duration {
+        self.record(sensor_timestamp_ns, SystemTime::now())
+    }
+
+    /// Statistics over the window, or `None` if nothing has been recorded
+    pub fn stats(&self) -> Option<LatencyStats> {
+        let current = *self.window.back()?;
+        let mut sorted: Vec<_> = self.window.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest-rank percentile
+        let percentile = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        Some(LatencyStats {
+            current,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            mean: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_NS: i64 = 33_333_333;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut tracker = LatencyTracker::new(100);
+        assert!(tracker.stats().is_none());
+
+        // Pose i is produced (i + 1) ms after its frame
+        let start_ns = 1_700_000_000_000_000_000;
+        for i in 0..100 {
+            let sensor_ns = start_ns + i * FRAME_NS;
+            let produced_at = UNIX_EPOCH + Duration::from_nanos((sensor_ns + (i + 1) * 1_000_000) as u64);
+            assert_eq!(tracker.record(sensor_ns, produced_at), Duration::from_millis(i as u64 + 1));
+        }
+
+        let stats = tracker.stats().unwrap();
+        assert_eq!(stats.current, Duration::from_millis(100));
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p95, Duration::from_millis(95));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.mean, Duration::from_micros(50_500));
+    }
+
+    #[test]
+    fn test_latency_window_and_skew() {
+        let mut tracker = LatencyTracker::new(2);
+        let at = |ms: u64| UNIX_EPOCH + Duration::from_millis(ms);
+
+        tracker.record(0, at(100));
+        tracker.record(0, at(10));
+        // Produced "before" capture
+        assert_eq!(tracker.record(50_000_000, at(20)), Duration::ZERO);
+
+        let stats = tracker.stats().unwrap();
+        assert_eq!(stats.current, Duration::ZERO);
+        assert_eq!(stats.p95, Duration::from_millis(10));
+
+        tracker.record_duration(Duration::from_millis(4));
+        let stats = tracker.stats().unwrap();
+        assert_eq!((stats.current, stats.p99), (Duration::from_millis(4), Duration::from_millis(4)));
+        assert_eq!(stats.mean, Duration::from_millis(2));
+    }
+}
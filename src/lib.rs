@@ -1,13 +1,118 @@
-use cuvslam_lib::bindings;
+mod bindings;
+#[cfg(feature = "calibration")]
+mod calibration;
+mod configuration;
+#[cfg(feature = "cuda")]
+mod cuda;
+#[cfg(feature = "datasets")]
+pub mod datasets;
+mod decimator;
+mod frame_queue;
+#[cfg(feature = "mcap")]
+mod foxglove;
+mod frame_source;
+#[cfg(feature = "image")]
+mod image_io;
+mod imu;
+mod latency;
+#[cfg(any(feature = "mcap", feature = "rosbag2"))]
+mod mcap_file;
+mod memory;
+mod occupancy;
+mod pose_cache;
+mod pose_graph;
+mod preprocess;
+#[cfg(feature = "profiling")]
+mod profiling;
+mod recovery;
+#[cfg(any(feature = "rosbag1", feature = "rosbag2"))]
+mod ros;
+#[cfg(feature = "rosbag1")]
+mod rosbag1;
+#[cfg(feature = "rosbag2")]
+mod rosbag2;
+#[doc(hidden)]
+pub mod self_test;
+#[cfg(feature = "serde")]
+mod serialization;
+mod stats;
+pub mod stereo_utils;
+pub mod stress;
+mod trajectory;
+#[cfg(feature = "video")]
+mod video;
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::ffi::CString;
+use std::mem::MaybeUninit;
 
 // Re-export key types
 pub use cuvslam_lib::bindings::{
-    CUVSLAM_Camera, CUVSLAM_CameraRig, CUVSLAM_Configuration, CUVSLAM_Image,
-    CUVSLAM_Pose, CUVSLAM_PoseEstimate, CUVSLAM_Status, CUVSLAM_TrackerHandle,
+    CUVSLAM_Camera, CUVSLAM_CameraRig, CUVSLAM_Configuration, CUVSLAM_Image, CUVSLAM_ImuCalibration,
+    CUVSLAM_ImuMeasurement, CUVSLAM_Landmark,
+    CUVSLAM_LandmarkInfo,
+    CUVSLAM_PoseGraphEdge, CUVSLAM_PoseGraphNode,
+    CUVSLAM_Observation, CUVSLAM_Pose, CUVSLAM_PoseEstimate, CUVSLAM_Status, CUVSLAM_TrackerHandle,
+};
+#[cfg(feature = "cuda")]
+pub use cuda::PinnedBuffer;
+#[cfg(feature = "calibration")]
+pub use calibration::{Calibration, CameraCalibration, DistortionModel, Extrinsics};
+pub use configuration::{ConfigurationBuilder, ConfigurationPreset};
+pub use decimator::FrameDecimator;
+pub use frame_queue::{FrameQueue, QueuePolicy, QueueStats};
+#[cfg(feature = "mcap")]
+pub use foxglove::{McapRecorder, McapSource};
+pub use frame_source::{FrameSet, FrameSource, StereoPairer, TrackedFrame};
+pub use imu::{ImuChannel, ImuSender, ImuStats};
+pub use latency::{LatencyStats, LatencyTracker};
+pub use memory::{MemoryReport, MemorySampler};
+pub use occupancy::{landmarks_to_occupancy, OccupancyGrid};
+pub use pose_cache::{PoseCache, PoseReceiver};
+pub use pose_graph::PoseGraph;
+pub use preprocess::{CameraHealth, PreprocessStats, Preprocessor, RawFormat, RawFrame};
+#[cfg(feature = "profiling")]
+pub use profiling::{Profiler, SpanGuard};
+pub use recovery::{DeviceRecovery, RecoveryEvent, RecoveryState};
+#[cfg(any(feature = "rosbag1", feature = "rosbag2"))]
+pub use ros::CameraInfo;
+#[cfg(feature = "rosbag1")]
+pub use rosbag1::{RosBagReader, StereoImagePair};
+#[cfg(feature = "rosbag2")]
+pub use rosbag2::Rosbag2Source;
+pub use self_test::{self_test, SelfTestReport, SelfTestStage, SyntheticSource};
+pub use stats::RunningStatistics;
+pub use trajectory::{
+    absolute_trajectory_error, compose_poses, invert_pose, log_trajectory_to_rrd, pose_to_ros, read_kitti,
+    read_kitti_positions, read_tum_positions, write_kitti, write_ros_poses_csv, write_tum, AteReport,
+    GroundTruthInterpolator, RunningAte,
 };
+#[cfg(feature = "video")]
+pub use video::{VideoLayout, VideoSource};
+
+/// Time the rest of the enclosing scope as a span named `$name` in `Profiler::global()`
+///
+/// Expands to nothing unless the `profiling` feature is enabled.
+#[cfg(feature = "profiling")]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_span = $crate::Profiler::global().span($name);
+    };
+}
+
+/// Time the rest of the enclosing scope as a span named `$name` in `Profiler::global()`
+///
+/// Expands to nothing unless the `profiling` feature is enabled.
+#[cfg(not(feature = "profiling"))]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {};
+}
 
 /// Distortion model parameters for brown5k model (9 parameters)
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Brown5kParameters {
     pub cx: f32,  // Principal point x
     pub cy: f32,  // Principal point y 
@@ -21,6 +126,7 @@ pub struct Brown5kParameters {
 }
 
 /// Distortion model parameters for pinhole model (4 parameters)
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PinholeParameters {
     pub cx: f32,  // Principal point x
     pub cy: f32,  // Principal point y
@@ -29,6 +135,7 @@ pub struct PinholeParameters {
 }
 
 /// Distortion model parameters for fisheye4 model (8 parameters)
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Fisheye4Parameters {
     pub cx: f32,  // Principal point x
     pub cy: f32,  // Principal point y
@@ -137,6 +244,99 @@ impl Camera {
         }
     }
 
+    /// Replace the distortion model with fisheye4, keeping the intrinsics, size and pose
+    ///
+    /// Useful for upgrading a pinhole calibration once fisheye coefficients are known.
+    pub fn into_fisheye4(self, k1: f32, k2: f32, k3: f32, k4: f32) -> Camera {
+        // Every model starts with cx, cy, fx, fy
+        let params = Fisheye4Parameters {
+            cx: self._parameters[0],
+            cy: self._parameters[1],
+            fx: self._parameters[2],
+            fy: self._parameters[3],
+            k1, k2, k3, k4,
+        };
+        Camera::new_fisheye4(self.inner.width, self.inner.height, params, self.inner.pose)
+    }
+
+    /// Unit-length ray in camera coordinates through the pixel `(px, py)`
+    ///
+    /// Inverts the distortion model: pinhole directly, fisheye4 by solving the equidistant
+    /// polynomial for the incidence angle, and brown5k by fixed-point undistortion. Camera frames
+    /// share cuVSLAM's x right, y up, z backward axes, so the camera looks along -z and the
+    /// principal point maps to `[0, 0, -1]`.
+    pub fn pixel_to_bearing_vector(&self, px: f32, py: f32) -> [f32; 3] {
+        let p: Vec<f64> = self._parameters.iter().map(|&p| p as f64).collect();
+        // Every model starts with cx, cy, fx, fy
+        let x = (px as f64 - p[0]) / p[2];
+        let y = (py as f64 - p[1]) / p[3];
+
+        let ray = match self._distortion_model.to_bytes() {
+            b"fisheye4" => {
+                let theta_d = x.hypot(y);
+                if theta_d < 1e-12 {
+                    [0.0, 0.0, 1.0]
+                } else {
+                    // Newton's method on theta_d = theta * (1 + k1 theta^2 + k2 theta^4 + k3 theta^6 + k4 theta^8)
+                    let (k1, k2, k3, k4) = (p[4], p[5], p[6], p[7]);
+                    let mut theta = theta_d;
+                    for _ in 0..20 {
+                        let t2 = theta * theta;
+                        let f = theta * (1.0 + t2 * (k1 + t2 * (k2 + t2 * (k3 + t2 * k4)))) - theta_d;
+                        let df = 1.0 + t2 * (3.0 * k1 + t2 * (5.0 * k2 + t2 * (7.0 * k3 + t2 * 9.0 * k4)));
+                        theta -= f / df;
+                    }
+                    let scale = theta.sin() / theta_d;
+                    [x * scale, y * scale, theta.cos()]
+                }
+            }
+            b"brown5k" => {
+                let (k1, k2, k3, p1, p2) = (p[4], p[5], p[6], p[7], p[8]);
+                let (mut ux, mut uy) = (x, y);
+                for _ in 0..20 {
+                    let r2 = ux * ux + uy * uy;
+                    let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+                    let dx = 2.0 * p1 * ux * uy + p2 * (r2 + 2.0 * ux * ux);
+                    let dy = p1 * (r2 + 2.0 * uy * uy) + 2.0 * p2 * ux * uy;
+                    ux = (x - dx) / radial;
+                    uy = (y - dy) / radial;
+                }
+                [ux, uy, 1.0]
+            }
+            _ => [x, y, 1.0],
+        };
+
+        // The models work in optical axes, y down and z forward
+        let norm = (ray[0] * ray[0] + ray[1] * ray[1] + ray[2] * ray[2]).sqrt();
+        [ray[0] / norm, -ray[1] / norm, -ray[2] / norm].map(|v| v as f32)
+    }
+
+    /// Pinhole projection matrix `P = K [R | t]` from rig coordinates to pixels
+    ///
+    /// `K` is built from fx, fy, cx and cy, and `[R | t]` is the inverse of the camera's pose, as
+    /// in `CameraRig::transform_points`, so `P * [x, y, z, 1]` is the homogeneous pixel of the rig
+    /// point `(x, y, z)`. The camera looks along its -z axis with y up, as in `pose_look_at`, so
+    /// points in front of it have `w > 0`. Non-linear distortion is ignored: for brown5k and
+    /// fisheye4 cameras the matrix holds for undistorted pixel coordinates, not the raw image.
+    pub fn projection_matrix(&self) -> [[f32; 4]; 3] {
+        // Every model starts with cx, cy, fx, fy
+        let (cx, cy, fx, fy) = (self._parameters[0], self._parameters[1], self._parameters[2], self._parameters[3]);
+        let CUVSLAM_Pose { r, t } = self.inner.pose;
+        // Rotation is column-major, so row i of R^T is column i of R
+        let extrinsics: [[f32; 4]; 3] = std::array::from_fn(|i| {
+            let row = [r[3 * i], r[3 * i + 1], r[3 * i + 2]];
+            [row[0], row[1], row[2], -(row[0] * t[0] + row[1] * t[1] + row[2] * t[2])]
+        });
+        // Flip y and z into optical axes, y down and z forward
+        let [x, y, z] = extrinsics;
+        let (y, z) = (y.map(|v| -v), z.map(|v| -v));
+        [
+            std::array::from_fn(|j| fx * x[j] + cx * z[j]),
+            std::array::from_fn(|j| fy * y[j] + cy * z[j]),
+            z,
+        ]
+    }
+
     /// Get a reference to the underlying CUVSLAM_Camera
     pub fn as_inner(&self) -> &CUVSLAM_Camera {
         &self.inner
@@ -148,12 +348,14 @@ pub struct CameraRig {
     _inner_cameras: Vec<CUVSLAM_Camera>,
     _cameras: Vec<Camera>,
     inner: CUVSLAM_CameraRig,
+    imu_pose: Option<CUVSLAM_Pose>,
+    time_offsets_ns: Vec<i64>,
 }
 
 impl CameraRig {
     /// Create a new camera rig from a vector of cameras
     pub fn new(cameras: Vec<Camera>) -> Self {
-        let _inner_cameras: Vec<_> = cameras.iter().map(|c| c.inner.clone()).collect();
+        let _inner_cameras: Vec<_> = cameras.iter().map(|c| c.inner).collect();
         let inner = CUVSLAM_CameraRig {
             cameras: _inner_cameras.as_ptr(),
             num_cameras: cameras.len() as i32,
@@ -161,19 +363,177 @@ impl CameraRig {
 
         Self { 
             _inner_cameras,  // Keep the cloned cameras alive
+            time_offsets_ns: vec![0; cameras.len()],
             _cameras: cameras,
             inner,
+            imu_pose: None,
+        }
+    }
+
+    /// Create a new camera rig, rejecting camera poses the tracker can't work with
+    ///
+    /// Returns `Status::InvalidArg` if a camera's rotation fails `is_valid_rotation`, or if a rig
+    /// of several cameras has all of them at the same pose, leaving stereo without a baseline.
+    /// `Status` has no room for the reason, so it is logged as a warning.
+    #[must_use = "the rig or the reason it is invalid is only in the Result"]
+    pub fn try_new(cameras: Vec<Camera>) -> Result<Self, Status> {
+        let reject = |reason: String| {
+            log::warn!("invalid camera rig: {}", reason);
+            Err(Status::InvalidArg)
+        };
+        if let Some(index) = cameras.iter().position(|camera| !is_valid_rotation(&camera.inner.pose.r)) {
+            return reject(format!("camera {} has a rotation that is not orthonormal", index));
+        }
+        let first = cameras.first().map(|camera| camera.inner.pose);
+        let same_pose = |camera: &Camera| {
+            first.is_some_and(|pose| camera.inner.pose.r == pose.r && camera.inner.pose.t == pose.t)
+        };
+        if cameras.len() > 1 && cameras.iter().all(same_pose) {
+            return reject(format!("all {} cameras share the same pose", cameras.len()));
         }
+        Ok(Self::new(cameras))
+    }
+
+    /// Create an ideal stereo pair whose cameras share `params` and differ only in position
+    ///
+    /// The left camera is at the rig origin and the right camera `baseline_m` meters along +x,
+    /// both with identity rotation. Use `new` for rigs whose cameras differ in intrinsics.
+    pub fn stereo_shared_intrinsics(params: Brown5kParameters, width: i32, height: i32, baseline_m: f32) -> Self {
+        let camera = |x: f32| {
+            Camera::new_brown5k(
+                width,
+                height,
+                params,
+                CUVSLAM_Pose {
+                    r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+                    t: [x, 0.0, 0.0],
+                },
+            )
+        };
+        Self::new(vec![camera(0.0), camera(baseline_m)])
     }
 
     /// Get a reference to the underlying CUVSLAM_CameraRig
     pub fn as_inner(&self) -> &CUVSLAM_CameraRig {
         &self.inner
     }
+
+    /// Mount an IMU on the rig, `imu_to_camera0_pose` mapping IMU coordinates into camera 0's frame
+    ///
+    /// `Tracker::new` passes the pose to cuVSLAM as the configuration's
+    /// `imu_calibration.left_from_imu`, overriding whatever the configuration holds. The noise
+    /// parameters and `enable_imu_fusion` still come from the configuration.
+    pub fn with_imu(mut self, imu_to_camera0_pose: CUVSLAM_Pose) -> Self {
+        self.imu_pose = Some(imu_to_camera0_pose);
+        self
+    }
+
+    /// Pose of the IMU in camera 0's frame, if set with `with_imu`
+    pub fn imu_pose(&self) -> Option<&CUVSLAM_Pose> {
+        self.imu_pose.as_ref()
+    }
+
+    /// Compensate a known, fixed sync skew of the camera at `camera_index`
+    ///
+    /// `Tracker::track` adds `offset_ns` to the timestamp of each of the camera's images before
+    /// handing them to cuVSLAM, so a camera whose images are stamped 2ms late takes an offset of
+    /// -2ms. It rejects frames with `Status::InvalidArg` when an offset is half the time since the
+    /// previous frame or more, which would move an image closer to a neighbouring frame than to
+    /// its own. `track_raw` passes timestamps through unchanged. An offset for a camera the rig
+    /// doesn't have fails `validate_rig`.
+    pub fn with_time_offset(mut self, camera_index: usize, offset_ns: i64) -> Self {
+        if self.time_offsets_ns.len() <= camera_index {
+            self.time_offsets_ns.resize(camera_index + 1, 0);
+        }
+        self.time_offsets_ns[camera_index] = offset_ns;
+        self
+    }
+
+    /// Time offset of the camera at `camera_index`, 0 unless set with `with_time_offset`
+    pub fn time_offset_ns(&self, camera_index: usize) -> i64 {
+        self.time_offsets_ns.get(camera_index).copied().unwrap_or(0)
+    }
+
+    /// Swap the cameras at indices `i` and `j`
+    ///
+    /// Images passed to `Tracker::track` select their camera with `camera_index`, so they must
+    /// use the reordered indices. Time offsets move with their cameras. Panics if either index is
+    /// out of range.
+    pub fn swap_cameras(&mut self, i: usize, j: usize) {
+        self._cameras.swap(i, j);
+        self._inner_cameras.swap(i, j);
+        self.time_offsets_ns.swap(i, j);
+        self.inner.cameras = self._inner_cameras.as_ptr();
+    }
+
+    /// Transform rig/world frame points into the frame of the camera at `camera_index`
+    ///
+    /// Camera poses map camera space into rig space, so the inverse pose is applied.
+    /// Returns an empty vector if `camera_index` is out of range.
+    pub fn transform_points(&self, points: &[[f32; 3]], camera_index: usize) -> Vec<[f32; 3]> {
+        let Some(camera) = self._cameras.get(camera_index) else {
+            return Vec::new();
+        };
+        let CUVSLAM_Pose { r, t } = camera.inner.pose;
+
+        points
+            .iter()
+            .map(|p| {
+                let d = [p[0] - t[0], p[1] - t[1], p[2] - t[2]];
+                // Rotation is column-major, so row i of R^T is column i of R
+                [
+                    r[0] * d[0] + r[1] * d[1] + r[2] * d[2],
+                    r[3] * d[0] + r[4] * d[1] + r[5] * d[2],
+                    r[6] * d[0] + r[7] * d[1] + r[8] * d[2],
+                ]
+            })
+            .collect()
+    }
+
+    /// Describe the rig as a Graphviz DOT graph, e.g. for `dot -Tpng rig.dot -o rig.png`
+    ///
+    /// Each camera is a node labelled with its index, distortion model and resolution. Edges
+    /// join camera 0 to every other camera, and to the IMU if one is mounted, labelled with the
+    /// distance between them and the angle of their relative rotation.
+    pub fn to_dot_graph(&self) -> String {
+        let mut dot = String::from("graph rig {\n    node [shape=box];\n");
+        for (index, camera) in self._cameras.iter().enumerate() {
+            dot.push_str(&format!(
+                "    camera{} [label=\"camera {}\\n{} {}x{}\"];\n",
+                index,
+                index,
+                camera._distortion_model.to_string_lossy(),
+                camera.inner.width,
+                camera.inner.height
+            ));
+        }
+        let Some(reference) = self._cameras.first().map(|camera| invert_pose(&camera.inner.pose)) else {
+            return dot + "}\n";
+        };
+        let edge = |pose: &CUVSLAM_Pose| {
+            format!("{:.3} m\\n{:.1} deg", pose_translation_norm(pose), pose_rotation_angle(pose).to_degrees())
+        };
+        for (index, camera) in self._cameras.iter().enumerate().skip(1) {
+            let relative = compose_poses(&reference, &camera.inner.pose);
+            dot.push_str(&format!("    camera0 -- camera{} [label=\"{}\"];\n", index, edge(&relative)));
+        }
+        if let Some(imu_pose) = &self.imu_pose {
+            // The IMU pose is already given in camera 0's frame
+            dot.push_str("    imu [label=\"IMU\", shape=ellipse];\n");
+            dot.push_str(&format!("    camera0 -- imu [label=\"{}\"];\n", edge(imu_pose)));
+        }
+        dot + "}\n"
+    }
 }
 
 /// Status codes returned by CUVSLAM operations
+///
+/// With the `serde` feature, statuses serialize as their variant names
+///
+/// New cuVSLAM releases may add status codes, so matches must include a wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Status {
     /// Operation completed successfully
     Success,
@@ -193,6 +553,8 @@ pub enum Status {
     NotImplemented,
     /// Reading SLAM internals is disabled
     ReadingSlamInternalsDisabled,
+    /// The cuVSLAM library could not be loaded (`runtime-loading` feature only)
+    LibraryNotLoaded,
 }
 
 impl From<cuvslam_lib::bindings::CUVSLAM_Status> for Status {
@@ -207,7 +569,11 @@ impl From<cuvslam_lib::bindings::CUVSLAM_Status> for Status {
             cuvslam_lib::bindings::CUVSLAM_SLAM_IS_NOT_INITIALIZED => Status::SlamNotInitialized,
             cuvslam_lib::bindings::CUVSLAM_NOT_IMPLEMENTED => Status::NotImplemented,
             cuvslam_lib::bindings::CUVSLAM_READING_SLAM_INTERNALS_DISABLED => Status::ReadingSlamInternalsDisabled,
-            _ => Status::GenericError,
+            bindings::CUVSLAM_LIBRARY_NOT_LOADED => Status::LibraryNotLoaded,
+            unknown => {
+                log::warn!("unknown cuVSLAM status code {}, treating it as a generic error", unknown);
+                Status::GenericError
+            }
         }
     }
 }
@@ -224,6 +590,177 @@ impl std::fmt::Display for Status {
             Status::SlamNotInitialized => write!(f, "SLAM Not Initialized"),
             Status::NotImplemented => write!(f, "Not Implemented"),
             Status::ReadingSlamInternalsDisabled => write!(f, "Reading SLAM Internals Disabled"),
+            Status::LibraryNotLoaded => write!(f, "Library Not Loaded"),
+        }
+    }
+}
+
+impl std::error::Error for Status {}
+
+impl From<Status> for std::io::Error {
+    /// Wrap the status in an `io::Error`, recoverable with `get_ref` and `downcast_ref`
+    fn from(status: Status) -> Self {
+        use std::io::ErrorKind;
+        let kind = match status {
+            Status::InvalidArg | Status::UnsupportedNumberOfCameras => ErrorKind::InvalidInput,
+            Status::NotImplemented => ErrorKind::Unsupported,
+            Status::LibraryNotLoaded => ErrorKind::NotFound,
+            _ => ErrorKind::Other,
+        };
+        std::io::Error::new(kind, status)
+    }
+}
+
+/// Parse a status from its `Display` representation, ignoring case
+impl TryFrom<&str> for Status {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "success" => Ok(Status::Success),
+            "tracking lost" => Ok(Status::TrackingLost),
+            "invalid argument" => Ok(Status::InvalidArg),
+            "cannot localize" => Ok(Status::CannotLocalize),
+            "generic error" => Ok(Status::GenericError),
+            "unsupported number of cameras" => Ok(Status::UnsupportedNumberOfCameras),
+            "slam not initialized" => Ok(Status::SlamNotInitialized),
+            "not implemented" => Ok(Status::NotImplemented),
+            "reading slam internals disabled" => Ok(Status::ReadingSlamInternalsDisabled),
+            "library not loaded" => Ok(Status::LibraryNotLoaded),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Whether a tracker is following the camera, as of its last `track` call
+///
+/// Returned by `Tracker::current_state` without tracking another frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackerState {
+    /// No frame has been tracked successfully yet
+    Uninitialized,
+    /// The last frame was tracked
+    Tracking,
+    /// The last frame failed with `Status::TrackingLost` after tracking had started
+    Lost,
+}
+
+/// Errors reported by the safe wrapper in addition to raw cuVSLAM statuses
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A cuVSLAM call failed with the given status
+    Status(Status),
+    /// The camera rig failed validation
+    InvalidRig(String),
+    /// The configuration cannot be used with the camera rig
+    InvalidConfiguration(String),
+    /// CUDA is not usable (`cuda` feature only)
+    Cuda(String),
+    /// A calibration file could not be read or describes an invalid rig (`calibration` feature only)
+    Calibration(String),
+    /// A dataset sequence is missing files or could not be parsed (`datasets` feature only)
+    Dataset(String),
+    /// cuVSLAM failed to create a tracker from inputs that passed validation
+    TrackerCreation {
+        status: Status,
+        summary: TrackerSummary,
+    },
+}
+
+impl From<Status> for Error {
+    fn from(status: Status) -> Self {
+        Error::Status(status)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Status(status) => write!(f, "{}", status),
+            Error::InvalidRig(reason) => write!(f, "Invalid camera rig: {}", reason),
+            Error::InvalidConfiguration(reason) => write!(f, "Invalid configuration: {}", reason),
+            Error::Cuda(reason) => write!(f, "CUDA unavailable: {}", reason),
+            Error::Calibration(reason) => write!(f, "Invalid calibration: {}", reason),
+            Error::Dataset(reason) => write!(f, "Invalid dataset: {}", reason),
+            Error::TrackerCreation { status, summary } => {
+                write!(f, "Failed to create tracker ({}) for {}", status, summary)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Summary of the validated tracker inputs, attached to creation errors for bug reports
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackerSummary {
+    /// Number of cameras in the rig
+    pub num_cameras: usize,
+    /// Width and height of each camera
+    pub resolutions: Vec<(i32, i32)>,
+    pub use_gpu: bool,
+    pub horizontal_stereo_camera: bool,
+    pub enable_imu_fusion: bool,
+    pub enable_localization_n_mapping: bool,
+}
+
+impl TrackerSummary {
+    fn new(rig: &CameraRig, config: &CUVSLAM_Configuration) -> Self {
+        Self {
+            num_cameras: rig._cameras.len(),
+            resolutions: rig._cameras.iter().map(|c| (c.inner.width, c.inner.height)).collect(),
+            use_gpu: config.use_gpu != 0,
+            horizontal_stereo_camera: config.horizontal_stereo_camera != 0,
+            enable_imu_fusion: config.enable_imu_fusion != 0,
+            enable_localization_n_mapping: config.enable_localization_n_mapping != 0,
+        }
+    }
+}
+
+impl std::fmt::Display for TrackerSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let resolutions: Vec<_> = self.resolutions.iter().map(|(w, h)| format!("{}x{}", w, h)).collect();
+        write!(
+            f,
+            "{} camera(s) [{}], use_gpu={}, horizontal_stereo_camera={}, enable_imu_fusion={}, enable_localization_n_mapping={}",
+            self.num_cameras,
+            resolutions.join(", "),
+            self.use_gpu,
+            self.horizontal_stereo_camera,
+            self.enable_imu_fusion,
+            self.enable_localization_n_mapping,
+        )
+    }
+}
+
+/// Maximum number of observations read back from the tracker per frame
+const MAX_OBSERVATIONS: usize = 4096;
+
+/// Maximum number of landmarks read back per frame
+const MAX_LANDMARKS: usize = 4096;
+
+/// Maximum number of landmarks cuVSLAM exports from the SLAM map
+const MAX_MAP_LANDMARKS: u32 = 100_000;
+
+/// Maximum number of nodes and edges cuVSLAM exports from the SLAM pose graph
+const MAX_POSE_GRAPH_ITEMS: u32 = 100_000;
+
+/// Track length of every observed feature, with buffers reused from frame to frame
+struct TrackLengths {
+    current: HashMap<i32, u32>,
+    previous: HashMap<i32, u32>,
+    observations: Vec<CUVSLAM_Observation>,
+}
+
+impl TrackLengths {
+    /// Allocate room for `MAX_OBSERVATIONS` features up front when `enabled`, so tracking doesn't have to
+    fn new(enabled: bool) -> Self {
+        let capacity = if enabled { MAX_OBSERVATIONS } else { 0 };
+        Self {
+            current: HashMap::with_capacity(capacity),
+            previous: HashMap::with_capacity(capacity),
+            observations: Vec::with_capacity(capacity),
         }
     }
 }
@@ -232,56 +769,427 @@ impl std::fmt::Display for Status {
 pub struct Tracker {
     handle: CUVSLAM_TrackerHandle,
     _rig: CameraRig, // Keep rig alive while tracker exists
+    config: CUVSLAM_Configuration, // Copy passed to cuVSLAM, including the IMU calibration
+    _debug_dump_directory: Option<CString>, // Keep buffers referenced by `config` alive
+    track_lengths: RefCell<TrackLengths>, // Consecutive frames each observation id has been seen
+    frame_count: Cell<u64>, // Successful track calls
+    session_span_ns: Cell<Option<(i64, i64)>>, // First and latest successfully tracked timestamps
+    enabled_layers: Cell<u32>, // Bit set of data layers cuVSLAM has been asked to export
+    world_from_odometry: Cell<Option<CUVSLAM_Pose>>, // Set by `localize_in_db`
+    state: Cell<TrackerState>, // Outcome of the last call into cuVSLAM's tracking
 }
 
 impl Tracker {
+    /// Start building a tracker, for options beyond `Tracker::new`
+    pub fn builder() -> TrackerBuilder {
+        TrackerBuilder::default()
+    }
+
     /// Create a new tracker instance
-    pub fn new(rig: CameraRig, config: &CUVSLAM_Configuration) -> Result<Self, Status> {
+    ///
+    /// The rig and configuration are validated first (see `validate_rig` and
+    /// `validate_configuration`), followed by a CUDA device probe when the `cuda` feature is
+    /// enabled, so the returned error names the first stage that failed.
+    ///
+    /// cuVSLAM only reads `enable_reading_slam_internals` here; it cannot be toggled on an
+    /// existing tracker, so it must be set up front if SLAM internals will ever be read.
+    #[must_use = "dropping the Result discards the tracker or the reason it could not be created"]
+    pub fn new(rig: CameraRig, config: &CUVSLAM_Configuration) -> Result<Self, Error> {
+        validate_rig(&rig).map_err(Error::InvalidRig)?;
+        validate_configuration(&rig, config).map_err(Error::InvalidConfiguration)?;
+        #[cfg(feature = "cuda")]
+        if config.use_gpu != 0 {
+            cuda::probe().map_err(Error::Cuda)?;
+        }
+
+        Self::create(rig, config)
+    }
+
+    /// Create the tracker without validating the inputs
+    fn create(rig: CameraRig, config: &CUVSLAM_Configuration) -> Result<Self, Error> {
         let mut handle = std::ptr::null_mut();
-        
+
+        // Hand cuVSLAM a configuration whose buffers are owned by the tracker, so nothing it
+        // was given can dangle once the caller's configuration goes away
+        let debug_dump_directory = (!config.debug_dump_directory.is_null())
+            .then(|| unsafe { std::ffi::CStr::from_ptr(config.debug_dump_directory) }.to_owned());
+        let mut config = *config;
+        config.debug_dump_directory = debug_dump_directory
+            .as_ref()
+            .map_or(std::ptr::null(), |directory| directory.as_ptr());
+        if let Some(imu_pose) = rig.imu_pose() {
+            config.imu_calibration.left_from_imu = *imu_pose;
+        }
+
         unsafe {
-            let status = bindings::CUVSLAM_CreateTracker(&mut handle, rig.as_inner(), config);
+            let status = bindings::CUVSLAM_CreateTracker(&mut handle, rig.as_inner(), &config);
             if status == 0 {
-                Ok(Self { handle, _rig: rig })
+                Ok(Self {
+                    handle,
+                    _rig: rig,
+                    config,
+                    _debug_dump_directory: debug_dump_directory,
+                    track_lengths: RefCell::new(TrackLengths::new(config.enable_observations_export != 0)),
+                    frame_count: Cell::new(0),
+                    session_span_ns: Cell::new(None),
+                    enabled_layers: Cell::new(0),
+                    world_from_odometry: Cell::new(None),
+                    state: Cell::new(TrackerState::Uninitialized),
+                })
             } else {
-                Err(status.into())
+                Err(Error::TrackerCreation {
+                    status: status.into(),
+                    summary: TrackerSummary::new(&rig, &config),
+                })
             }
         }
     }
 
+    /// Get the configuration the tracker was created with
+    pub fn configuration(&self) -> &CUVSLAM_Configuration {
+        &self.config
+    }
+
     /// Track current frame synchronously
-    pub fn track(
+    ///
+    /// A `predicted_pose` replaces the motion model's prediction for this frame (see
+    /// `ConfigurationBuilder::motion_model`).
+    ///
+    /// Returns `Status::InvalidArg` without calling cuVSLAM if `images` is empty, names a camera
+    /// outside the rig or the same camera twice, or an image size doesn't match its camera.
+    #[must_use = "tracking failures such as TrackingLost are only reported through the Result"]
+    pub fn track(&self, images: &[Image], predicted_pose: Option<&PoseEstimate>) -> Result<PoseEstimate, Status> {
+        let mut pose_estimate = MaybeUninit::<PoseEstimate>::uninit();
+        self.track_images(images, predicted_pose, pose_estimate.as_mut_ptr())?;
+        // cuVSLAM fills in the whole estimate on success
+        Ok(unsafe { pose_estimate.assume_init() })
+    }
+
+    /// Track current frame synchronously, writing the estimate into `out`
+    ///
+    /// Validated like `track`. cuVSLAM writes straight into `out`, so repeated calls with up to
+    /// eight images don't allocate or copy. With `enable_observations_export`, each call also
+    /// reads the frame's observations to advance `feature_track_lengths`, into buffers the tracker
+    /// allocated up front. The contents of `out` are unspecified if tracking fails.
+    #[must_use = "tracking failures such as TrackingLost are only reported through the Result"]
+    pub fn track_into(
+        &self,
+        images: &[Image],
+        predicted_pose: Option<&PoseEstimate>,
+        out: &mut PoseEstimate,
+    ) -> Result<(), Status> {
+        self.track_images(images, predicted_pose, out)
+    }
+
+    /// Track a frame, taking ownership of its images
+    ///
+    /// The pixel buffers are released once cuVSLAM returns, so a capture thread can hand frames
+    /// to a tracking thread without keeping them alive on its side.
+    #[must_use = "tracking failures such as TrackingLost are only reported through the Result"]
+    pub fn track_owned(
+        &self,
+        images: Vec<Image>,
+        predicted_pose: Option<&PoseEstimate>,
+    ) -> Result<PoseEstimate, Status> {
+        self.track(&images, predicted_pose)
+    }
+
+    /// Track every remaining frame of `source` in order, without predicted poses
+    ///
+    /// Returns each frame's timestamp with its tracking result, so failed frames stay in place.
+    /// Stops at the first frame that fails to load.
+    #[must_use = "per-frame results and load failures are only reported through the Result"]
+    pub fn track_source<S: FrameSource + ?Sized>(
+        &self,
+        source: &mut S,
+    ) -> Result<Vec<TrackedFrame>, Error> {
+        let mut results = Vec::with_capacity(source.len_hint().unwrap_or(0));
+        while let Some(frame) = source.next_frame() {
+            let frame = frame?;
+            results.push((frame.timestamp_ns, self.track(&frame.images, None)));
+        }
+        Ok(results)
+    }
+
+    /// Track the first frames of a session and return the estimate for the last one
+    ///
+    /// cuVSLAM needs a few frames to triangulate its first landmarks before estimates settle;
+    /// about ten frames (a third of a second at 30 Hz) with the rig still or moving slowly is
+    /// recommended. Each frame is tracked like `track` with its optional predicted pose, and
+    /// the results of all but the last are discarded, failures included. Like `track`, each frame
+    /// is a list of `Image`s, not raw `CUVSLAM_Image`s. Returns `Status::InvalidArg` if `frames`
+    /// is empty.
+    #[must_use = "the estimate for the last frame or its failure is only in the Result"]
+    pub fn warm_up(&self, frames: &[(Vec<Image>, Option<PoseEstimate>)]) -> Result<PoseEstimate, Status> {
+        let ((last_images, last_prediction), rest) = frames.split_last().ok_or(Status::InvalidArg)?;
+        for (images, predicted_pose) in rest {
+            let _ = self.track(images, predicted_pose.as_ref());
+        }
+        self.track(last_images, last_prediction.as_ref())
+    }
+
+    /// Track current frame from raw cuVSLAM images, skipping all validation
+    ///
+    /// Camera time offsets set with `CameraRig::with_time_offset` are neither checked nor applied:
+    /// each timestamp reaches cuVSLAM exactly as given, so offsets must already be included.
+    ///
+    /// # Safety
+    ///
+    /// For every image in `images`:
+    /// - `pixels` must point to at least `pitch * height` readable bytes that stay valid and
+    ///   unmodified until this call returns,
+    /// - `width` and `height` must be positive and match the rig camera selected by `camera_index`,
+    ///   which must be in range and not repeated within `images`,
+    /// - `pitch` must be at least `width` times the bytes per pixel of `image_encoding`.
+    #[must_use = "tracking failures such as TrackingLost are only reported through the Result"]
+    pub unsafe fn track_raw(
         &self,
         images: &[CUVSLAM_Image],
         predicted_pose: Option<&PoseEstimate>,
     ) -> Result<PoseEstimate, Status> {
-        let mut pose_estimate = CUVSLAM_PoseEstimate {
-            pose: CUVSLAM_Pose {
-                r: [0.0; 9],
-                t: [0.0; 3],
-            },
-            timestamp_ns: 0,
-            covariance: [0.0; 36],
+        let mut pose_estimate = MaybeUninit::<PoseEstimate>::uninit();
+        self.track_to(images, predicted_pose, pose_estimate.as_mut_ptr())?;
+        Ok(pose_estimate.assume_init())
+    }
+
+    /// Track current frame from raw cuVSLAM images, as `track` did before it took `Image`s
+    ///
+    /// Kept for one release so existing callers only need a rename. It forwards to `track_raw`
+    /// and skips validation the same way, so the caller must still uphold `track_raw`'s safety
+    /// contract even though this function isn't marked unsafe.
+    #[deprecated(note = "pass `Image`s to `track`, or call the unsafe `track_raw` with the same arguments")]
+    #[must_use = "tracking failures such as TrackingLost are only reported through the Result"]
+    pub fn track_cuvslam_images(
+        &self,
+        images: &[CUVSLAM_Image],
+        predicted_pose: Option<&PoseEstimate>,
+    ) -> Result<PoseEstimate, Status> {
+        unsafe { self.track_raw(images, predicted_pose) }
+    }
+
+    /// Like `track_raw`, writing the estimate into `out`
+    ///
+    /// # Safety
+    ///
+    /// `images` must satisfy the contract of `track_raw`.
+    #[must_use = "tracking failures such as TrackingLost are only reported through the Result"]
+    pub unsafe fn track_raw_into(
+        &self,
+        images: &[CUVSLAM_Image],
+        predicted_pose: Option<&PoseEstimate>,
+        out: &mut PoseEstimate,
+    ) -> Result<(), Status> {
+        self.track_to(images, predicted_pose, out)
+    }
+
+    /// Validate `images` and track them, building the raw views on the stack for small rigs
+    fn track_images(
+        &self,
+        images: &[Image],
+        predicted_pose: Option<&PoseEstimate>,
+        out: *mut PoseEstimate,
+    ) -> Result<(), Status> {
+        const INLINE: usize = 8;
+        validate_images(&self._rig, images)?;
+        self.check_time_offsets(images)?;
+        let first = self.offset_image(&images[0]);
+        if images.len() <= INLINE {
+            let mut raw_images = [first; INLINE];
+            for (raw, image) in raw_images.iter_mut().zip(images) {
+                *raw = self.offset_image(image);
+            }
+            self.track_to(&raw_images[..images.len()], predicted_pose, out)
+        } else {
+            let raw_images: Vec<CUVSLAM_Image> = images.iter().map(|image| self.offset_image(image)).collect();
+            self.track_to(&raw_images, predicted_pose, out)
+        }
+    }
+
+    /// Raw view of a validated image, with its camera's time offset applied
+    fn offset_image(&self, image: &Image) -> CUVSLAM_Image {
+        let mut raw = *image.as_inner();
+        raw.timestamp_ns += self._rig.time_offset_ns(raw.camera_index as usize);
+        raw
+    }
+
+    /// Reject time offsets of half the time since the previous frame or more
+    fn check_time_offsets(&self, images: &[Image]) -> Result<(), Status> {
+        let offset_ns = |image: &Image| self._rig.time_offset_ns(image.camera_index() as usize);
+        let max_offset_ns = images.iter().map(|image| offset_ns(image).unsigned_abs()).max().unwrap_or(0);
+        let Some((_, previous_ns)) = self.session_span_ns.get().filter(|_| max_offset_ns > 0) else {
+            return Ok(());
         };
+        // The session span holds offset-applied timestamps, so compare against those
+        let latest_ns = images.iter().map(|image| image.timestamp_ns().saturating_add(offset_ns(image))).max();
+        let period_ns = latest_ns.unwrap_or(previous_ns).saturating_sub(previous_ns);
+        if offset_exceeds_period(max_offset_ns, period_ns) {
+            log::warn!(
+                "camera time offset of {}ns is not small relative to the {}ns since the previous frame",
+                max_offset_ns,
+                period_ns
+            );
+            return Err(Status::InvalidArg);
+        }
+        Ok(())
+    }
 
-        unsafe {
-            let status = bindings::CUVSLAM_Track(
+    fn track_to(
+        &self,
+        images: &[CUVSLAM_Image],
+        predicted_pose: Option<&PoseEstimate>,
+        out: *mut PoseEstimate,
+    ) -> Result<(), Status> {
+        profile_scope!("track");
+        let world_from_odometry = self.world_from_odometry.get();
+        // Predictions are given in the same frame as the returned poses
+        let predicted_pose = predicted_pose.map(|p| match &world_from_odometry {
+            Some(world_from_odometry) => compose_poses(&invert_pose(world_from_odometry), &p.pose),
+            None => p.pose,
+        });
+        let status = unsafe {
+            bindings::CUVSLAM_Track(
                 self.handle,
                 images.as_ptr(),
                 images.len(),
-                predicted_pose.map_or(std::ptr::null(), |p| &p.pose),
-                &mut pose_estimate,
-            );
+                predicted_pose.as_ref().map_or(std::ptr::null(), |pose| pose),
+                out.cast::<CUVSLAM_PoseEstimate>(),
+            )
+        };
+
+        if self.config.enable_observations_export != 0 {
+            self.update_track_lengths(status == 0);
+        }
+
+        if status == 0 {
+            if let Some(world_from_odometry) = world_from_odometry {
+                // cuVSLAM filled in the estimate
+                unsafe { (*out).pose = compose_poses(&world_from_odometry, &(*out).pose) };
+            }
+            self.frame_count.set(self.frame_count.get() + 1);
+            self.state.set(TrackerState::Tracking);
+            if let Some(timestamp_ns) = images.iter().map(|image| image.timestamp_ns).max() {
+                let first_ns = self.session_span_ns.get().map_or(timestamp_ns, |(first_ns, _)| first_ns);
+                self.session_span_ns.set(Some((first_ns, timestamp_ns)));
+            }
+            Ok(())
+        } else {
+            let status = Status::from(status);
+            if status == Status::TrackingLost && self.state.get() == TrackerState::Tracking {
+                self.state.set(TrackerState::Lost);
+            }
+            Err(status)
+        }
+    }
+
+    /// Whether the tracker is tracking, lost or yet to track a frame, without tracking one
+    ///
+    /// Derived from the last call that reached cuVSLAM: a tracked frame makes it `Tracking` and
+    /// a `Status::TrackingLost` failure after that makes it `Lost`, until a frame is tracked
+    /// again. Frames rejected by validation, and other failures, leave it unchanged. Reading it
+    /// costs a `Cell` load, so it can be polled as often as needed; `Tracker` isn't `Sync`, so a
+    /// watchdog on another thread needs the tracking thread to pass the state on.
+    ///
+    /// cuVSLAM has no way to reset a tracker, so there is no `reset`: the state only returns to
+    /// `Uninitialized` with a new `Tracker`, as `DeviceRecovery` creates after a device loss.
+    pub fn current_state(&self) -> TrackerState {
+        self.state.get()
+    }
+
+    /// Number of frames tracked successfully since the tracker was created
+    ///
+    /// Frames that fail, e.g. with `Status::TrackingLost`, are not counted. cuVSLAM has no way
+    /// to reset a tracker, so the count only restarts with a new `Tracker`.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count.get()
+    }
+
+    /// Sensor time between the first and the latest successfully tracked frame
+    ///
+    /// Measured on image timestamps, so it reflects how much of the recording the map covers
+    /// rather than wall-clock time. Zero until two frames have been tracked. Like `frame_count`,
+    /// it only restarts with a new `Tracker`.
+    pub fn session_duration(&self) -> std::time::Duration {
+        let span_ns = self.session_span_ns.get().map_or(0, |(first_ns, latest_ns)| latest_ns - first_ns);
+        std::time::Duration::from_nanos(span_ns.max(0) as u64)
+    }
+
+    /// Number of frames tracked successfully in this session, the same as `frame_count`
+    pub fn session_frames(&self) -> u64 {
+        self.frame_count.get()
+    }
+
+    /// Get the observations of the left camera from the last tracked frame
+    ///
+    /// Requires `enable_observations_export` to be set in the configuration
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn get_last_observations(&self) -> Result<Vec<CUVSLAM_Observation>, Status> {
+        let mut observations = Vec::new();
+        self.read_last_observations(&mut observations)?;
+        Ok(observations)
+    }
+
+    /// Replace the contents of `observations` with the last left camera observations
+    ///
+    /// Only allocates if `observations` has room for fewer than `MAX_OBSERVATIONS`.
+    fn read_last_observations(&self, observations: &mut Vec<CUVSLAM_Observation>) -> Result<(), Status> {
+        observations.clear();
+        observations.reserve(MAX_OBSERVATIONS);
+        let mut vector = bindings::CUVSLAM_ObservationVector {
+            num: 0,
+            max: MAX_OBSERVATIONS as u32,
+            observations: observations.as_mut_ptr(),
+        };
 
+        unsafe {
+            let status = bindings::CUVSLAM_GetLastLeftObservations(self.handle, &mut vector);
             if status == 0 {
-                Ok(pose_estimate.into())
+                observations.set_len((vector.num as usize).min(MAX_OBSERVATIONS));
+                Ok(())
             } else {
                 Err(status.into())
             }
         }
     }
 
+    /// Get the number of features tracked in the last frame, per camera
+    ///
+    /// cuVSLAM only exports the observations of the first (left) camera, so the result currently
+    /// holds a single count and other cameras can't be compared against it. Requires
+    /// `enable_observations_export` in the configuration and returns `Status::InvalidArg` otherwise.
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn features_per_camera(&self) -> Result<Vec<u32>, Status> {
+        if self.config.enable_observations_export == 0 {
+            return Err(Status::InvalidArg);
+        }
+        Ok(vec![self.get_last_observations()?.len() as u32])
+    }
+
+    /// Get the track length (consecutive frames observed) of every currently tracked feature
+    ///
+    /// Lengths are derived from the left camera observation ids, so this requires
+    /// `enable_observations_export` in the configuration and returns `Status::InvalidArg` otherwise.
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn feature_track_lengths(&self) -> Result<Vec<u32>, Status> {
+        if self.config.enable_observations_export == 0 {
+            return Err(Status::InvalidArg);
+        }
+        Ok(self.track_lengths.borrow().current.values().copied().collect())
+    }
+
+    /// Advance the per-feature track lengths after a track call, reusing the tracker's buffers
+    fn update_track_lengths(&self, tracked: bool) {
+        let mut lengths = self.track_lengths.borrow_mut();
+        let TrackLengths { current, previous, observations } = &mut *lengths;
+        if tracked && self.read_last_observations(observations).is_ok() {
+            std::mem::swap(current, previous);
+            advance_track_lengths(previous, observations.iter().map(|o| o.id), current);
+        } else {
+            current.clear();
+        }
+    }
+
     /// Get current odometry pose
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
     pub fn get_odometry_pose(&self) -> Result<CUVSLAM_Pose, Status> {
         let mut pose = CUVSLAM_Pose {
             r: [0.0; 9],
@@ -298,16 +1206,18 @@ impl Tracker {
         }
     }
 
-    /// Save SLAM database to folder
-    pub fn save_to_slam_db(&self, folder: &str) -> Result<(), Status> {
-        let folder = CString::new(folder).unwrap();
+    /// Register an IMU measurement from the IMU at `sensor_index`
+    ///
+    /// Measurements must be registered in timestamp order, interleaved with the frames passed to
+    /// `track`. Requires `enable_imu_fusion` in the configuration.
+    #[must_use = "a rejected measurement is only reported through the Result"]
+    pub fn register_imu_measurement(
+        &self,
+        sensor_index: i32,
+        measurement: &CUVSLAM_ImuMeasurement,
+    ) -> Result<(), Status> {
         unsafe {
-            let status = bindings::CUVSLAM_SaveToSlamDb(
-                self.handle,
-                folder.as_ptr(),
-                None,
-                std::ptr::null_mut(),
-            );
+            let status = bindings::CUVSLAM_RegisterImuMeasurement(self.handle, sensor_index, measurement);
             if status == 0 {
                 Ok(())
             } else {
@@ -315,152 +1225,2225 @@ impl Tracker {
             }
         }
     }
-}
 
-impl Drop for Tracker {
-    fn drop(&mut self) {
+    /// Get the landmarks tracked in the last frame
+    ///
+    /// Requires `enable_landmarks_export` in the configuration.
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn get_last_landmarks(&self) -> Result<Vec<CUVSLAM_Landmark>, Status> {
+        let mut landmarks = Vec::with_capacity(MAX_LANDMARKS);
+        let mut vector = bindings::CUVSLAM_LandmarkVector {
+            num: 0,
+            max: MAX_LANDMARKS as u32,
+            landmarks: landmarks.as_mut_ptr(),
+        };
+
         unsafe {
-            bindings::CUVSLAM_DestroyTracker(self.handle);
+            let status = bindings::CUVSLAM_GetLastLandmarks(self.handle, &mut vector);
+            if status == 0 {
+                landmarks.set_len((vector.num as usize).min(MAX_LANDMARKS));
+                Ok(landmarks)
+            } else {
+                Err(status.into())
+            }
         }
     }
-}
-
-/// Initialize default CUVSLAM configuration
-pub fn init_default_configuration() -> CUVSLAM_Configuration {
-    unsafe { bindings::CUVSLAM_GetDefaultConfiguration() }
-}
 
-/// Get CUVSLAM version information
-pub fn get_version() -> (i32, i32, Option<String>) {
-    let mut major = 0;
-    let mut minor = 0;
-    let mut version_ptr = std::ptr::null();
+    /// Get the landmarks of the SLAM map
+    ///
+    /// Requires `enable_reading_slam_internals` in the configuration. cuVSLAM starts exporting
+    /// the map on the first call and fills it in while tracking, so the first call may return
+    /// no landmarks.
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn get_map_landmarks(&self) -> Result<Vec<CUVSLAM_LandmarkInfo>, Status> {
+        if self.config.enable_reading_slam_internals == 0 {
+            return Err(Status::ReadingSlamInternalsDisabled);
+        }
+        let layer = bindings::CUVSLAM_DataLayer_LL_MAP;
 
-    unsafe {
-        bindings::CUVSLAM_GetVersion(&mut major, &mut minor, &mut version_ptr);
-        
-        let version = if !version_ptr.is_null() {
-            // Convert C string to Rust String
-            let c_str = std::ffi::CStr::from_ptr(version_ptr);
-            Some(c_str.to_string_lossy().into_owned())
-        } else {
-            None
-        };
+        unsafe {
+            self.enable_layer(layer, MAX_MAP_LANDMARKS)?;
 
-        (major, minor, version)
+            let array = bindings::CUVSLAM_StartReadingLandmarks(self.handle, layer);
+            let landmarks = copy_array(array.landmarks, array.num);
+            bindings::CUVSLAM_FinishReadingLandmarks(self.handle, layer);
+            Ok(landmarks)
+        }
     }
-}
 
-/// Image encoding formats supported by the tracker
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum ImageEncoding {
-    /// 8-bit monochrome image
-    Mono8,
-    /// 8-bit RGB image 
-    Rgb8,
-}
+    /// Get a snapshot of the SLAM pose graph
+    ///
+    /// Requires `enable_reading_slam_internals` in the configuration. As with
+    /// `get_map_landmarks`, the first call may return an empty graph.
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn get_pose_graph(&self) -> Result<PoseGraph, Status> {
+        if self.config.enable_reading_slam_internals == 0 {
+            return Err(Status::ReadingSlamInternalsDisabled);
+        }
+        let layer = bindings::CUVSLAM_DataLayer_LL_POSE_GRAPH;
+        self.enable_layer(layer, MAX_POSE_GRAPH_ITEMS)?;
 
-impl From<cuvslam_lib::bindings::CUVSLAM_ImageEncoding> for ImageEncoding {
-    fn from(encoding: cuvslam_lib::bindings::CUVSLAM_ImageEncoding) -> Self {
+        unsafe {
+            let graph = bindings::CUVSLAM_StartReadingPoseGraph(self.handle, layer);
+            let result = PoseGraph {
+                nodes: copy_array(graph.nodes, graph.num_nodes),
+                edges: copy_array(graph.edges, graph.num_edges),
+            };
+            bindings::CUVSLAM_FinishReadingPoseGraph(self.handle, layer);
+            Ok(result)
+        }
+    }
+
+    /// Write the SLAM pose graph to `path` in g2o format (see `PoseGraph::write_g2o`)
+    ///
+    /// Returns an `io::Result` like the crate's other writers, since creating and writing the
+    /// file can fail in ways a `Status` can't describe. A failure to read the graph from cuVSLAM
+    /// is wrapped as an `io::Error` whose `get_ref` downcasts to the `Status`.
+    #[must_use = "a failed export is only reported through the Result"]
+    pub fn export_pose_graph_g2o(&self, path: &str) -> std::io::Result<()> {
+        let graph = self.get_pose_graph()?;
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        graph.write_g2o(file)
+    }
+
+    /// Ask cuVSLAM to start exporting a data layer, once per tracker
+    fn enable_layer(&self, layer: bindings::CUVSLAM_DataLayer, max_items: u32) -> Result<(), Status> {
+        let bit = 1 << layer;
+        if self.enabled_layers.get() & bit != 0 {
+            return Ok(());
+        }
+        let status = unsafe { bindings::CUVSLAM_EnableReadingDataLayer(self.handle, layer, max_items) };
+        if status != 0 {
+            return Err(status.into());
+        }
+        self.enabled_layers.set(self.enabled_layers.get() | bit);
+        Ok(())
+    }
+
+    /// Estimate the area covered by the SLAM map in square meters
+    ///
+    /// Computed as the area of the convex hull of the map landmarks projected onto the ground
+    /// plane, which is the x-z plane since cuVSLAM's y axis points up. See `get_map_landmarks`
+    /// for requirements.
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn get_map_coverage_m2(&self) -> Result<f32, Status> {
+        let mut points: Vec<_> = self.get_map_landmarks()?.iter().map(|l| [l.x, l.z]).collect();
+        Ok(convex_hull_area(&mut points))
+    }
+
+    /// Number of poses in the SLAM pose graph
+    fn slam_pose_count(&self) -> u32 {
+        fetch_with_growing_buffer::<bindings::CUVSLAM_PoseStamped>(|capacity, poses| unsafe {
+            bindings::CUVSLAM_GetAllSlamPoses(self.handle, capacity, poses)
+        })
+    }
+
+    /// Report map size, GPU memory and process memory usage
+    ///
+    /// Meant to be sampled periodically (see `MemorySampler`) to spot unbounded growth in long
+    /// mapping sessions. GPU memory is only reported with the `cuda` feature.
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn memory_report(&self) -> Result<MemoryReport, Error> {
+        let mut report = MemoryReport {
+            process_rss_bytes: memory::process_rss_bytes(),
+            ..MemoryReport::default()
+        };
+        if self.config.enable_localization_n_mapping != 0 {
+            report.slam_poses = Some(self.slam_pose_count());
+        }
+        if self.config.enable_landmarks_export != 0 {
+            report.landmarks = Some(self.get_last_landmarks()?.len() as u32);
+        }
+        #[cfg(feature = "cuda")]
+        {
+            let (free, total) = cuda::mem_info().map_err(Error::Cuda)?;
+            report.gpu_free_bytes = Some(free);
+            report.gpu_total_bytes = Some(total);
+        }
+        Ok(report)
+    }
+
+    /// Device memory in use, in MiB, for watching GPU memory as the map grows
+    ///
+    /// cuVSLAM does not report its own allocations, so this is the device-wide figure from
+    /// `cudaMemGetInfo`: it includes other processes using the GPU, which on Jetson boards
+    /// share it with the CPU. Returns `None` when the query fails, and always without the `cuda`
+    /// feature.
+    pub fn get_gpu_memory_usage_mb(&self) -> Option<f32> {
+        #[cfg(feature = "cuda")]
+        {
+            let (free, total) = cuda::mem_info().ok()?;
+            Some(total.saturating_sub(free) as f32 / (1024.0 * 1024.0))
+        }
+        #[cfg(not(feature = "cuda"))]
+        None
+    }
+
+    /// Get the odometry pose together with the SLAM pose for the same frame
+    ///
+    /// The SLAM pose is `None` when the tracker was created without
+    /// `enable_localization_n_mapping`.
+    #[must_use = "the requested data or the reason it is unavailable is only in the Result"]
+    pub fn get_poses(&self) -> Result<(CUVSLAM_Pose, Option<CUVSLAM_Pose>), Status> {
+        let odometry = self.get_odometry_pose()?;
+        if self.config.enable_localization_n_mapping == 0 {
+            return Ok((odometry, None));
+        }
+
+        let mut slam = CUVSLAM_Pose {
+            r: [0.0; 9],
+            t: [0.0; 3],
+        };
+        unsafe {
+            let status = bindings::CUVSLAM_GetSlamPose(self.handle, &mut slam);
+            if status == 0 {
+                Ok((odometry, Some(slam)))
+            } else {
+                Err(status.into())
+            }
+        }
+    }
+
+    /// How long `localize_in_db` waits for cuVSLAM to report, loading the map included
+    pub const LOCALIZE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+    /// Relocalize in a map saved with `save_to_slam_db`, and report poses in its frame from now on
+    ///
+    /// cuVSLAM loads the map from `folder` and searches it for `images` within `radius_m` of
+    /// `guess_pose`, a rough rig pose in the saved map's frame. `images` must be the frame most
+    /// recently passed to `track`, and get the rig's time offsets applied the same way. Blocks
+    /// until cuVSLAM reports the result, which on success is the rig's pose in the saved map, or
+    /// for at most `LOCALIZE_TIMEOUT`, after which it fails with `Status::CannotLocalize`.
+    ///
+    /// After a successful call the tracker keeps the transform from its odometry frame, which
+    /// started at identity, to the saved map's world frame. Poses returned by `track` and its
+    /// variants, and predicted poses passed to them, are then expressed in the map's frame, so
+    /// trajectories of separate sessions line up. `get_odometry_pose` and `get_poses` still
+    /// report cuVSLAM's own frames, and covariances are not rotated. Relocalizing again replaces
+    /// the transform. Requires `enable_localization_n_mapping` in the configuration.
+    #[must_use = "the pose in the saved map or the reason localization failed is only in the Result"]
+    pub fn localize_in_db(
+        &self,
+        folder: &str,
+        guess_pose: &CUVSLAM_Pose,
+        radius_m: f32,
+        images: &[Image],
+    ) -> Result<CUVSLAM_Pose, Status> {
+        validate_images(&self._rig, images)?;
+        self.check_time_offsets(images)?;
+        let folder = CString::new(folder).map_err(|_| Status::InvalidArg)?;
+        let raw_images: Vec<CUVSLAM_Image> = images.iter().map(|image| self.offset_image(image)).collect();
+        let odometry = self.get_odometry_pose()?;
+
+        let pose_in_db = await_localization(Self::LOCALIZE_TIMEOUT, |context| unsafe {
+            bindings::CUVSLAM_LocalizeInExistDb(
+                self.handle,
+                folder.as_ptr(),
+                guess_pose,
+                radius_m,
+                raw_images.as_ptr(),
+                raw_images.len(),
+                Some(respond_localized),
+                context,
+            )
+        })?;
+
+        self.world_from_odometry.set(Some(compose_poses(&pose_in_db, &invert_pose(&odometry))));
+        Ok(pose_in_db)
+    }
+
+    /// Transform from the odometry frame to the saved map's frame, once `localize_in_db` succeeded
+    pub fn world_from_odometry(&self) -> Option<CUVSLAM_Pose> {
+        self.world_from_odometry.get()
+    }
+
+    /// Save SLAM database to folder
+    #[must_use = "a failed save is only reported through the Result"]
+    pub fn save_to_slam_db(&self, folder: &str) -> Result<(), Status> {
+        let folder = CString::new(folder).unwrap();
+        unsafe {
+            let status = bindings::CUVSLAM_SaveToSlamDb(
+                self.handle,
+                folder.as_ptr(),
+                None,
+                std::ptr::null_mut(),
+            );
+            if status == 0 {
+                Ok(())
+            } else {
+                Err(status.into())
+            }
+        }
+    }
+}
+
+/// Builder for a `Tracker` with optional startup steps
+#[derive(Default)]
+pub struct TrackerBuilder {
+    rig: Option<CameraRig>,
+    config: Option<CUVSLAM_Configuration>,
+    warm_up: bool,
+    gpu_required: bool,
+}
+
+impl TrackerBuilder {
+    /// Set the camera rig (required)
+    pub fn rig(mut self, rig: CameraRig) -> Self {
+        self.rig = Some(rig);
+        self
+    }
+
+    /// Set the configuration, defaulting to `init_default_configuration()`
+    pub fn configuration(mut self, config: &CUVSLAM_Configuration) -> Self {
+        self.config = Some(*config);
+        self
+    }
+
+    /// Compile and load the GPU kernels before the tracker is returned
+    ///
+    /// Otherwise this happens during the first `track` call, which then takes much longer than
+    /// the following ones. Warming up moves that cost, typically hundreds of milliseconds to a
+    /// few seconds depending on the device, into `build`. Has no effect when `use_gpu` is off.
+    pub fn warm_up(mut self, warm_up: bool) -> Self {
+        self.warm_up = warm_up;
+        self
+    }
+
+    /// Refuse to create a tracker that might run on the CPU
+    ///
+    /// cuVSLAM falls back to its CPU path, at a few frames per second, when `use_gpu` is off
+    /// and on some systems when it cannot use the GPU, without reporting it. With this set,
+    /// `build` fails with `Error::InvalidConfiguration` if `use_gpu` is off, which this doesn't
+    /// change, and otherwise with `Error::Cuda` when no CUDA device is usable. The device check
+    /// needs the `cuda` feature; without it the GPU can't be confirmed, so `build` always fails
+    /// with `Error::Cuda`, and the fallback shows up only as `cuvslam-doctor` reporting no GPU and
+    /// `track` taking hundreds of milliseconds per frame instead of a few (see `LatencyTracker`).
+    pub fn require_gpu(mut self, required: bool) -> Self {
+        self.gpu_required = required;
+        self
+    }
+
+    /// Validate the inputs and create the tracker, as `Tracker::new` does
+    #[must_use = "dropping the Result discards the tracker or the reason it could not be created"]
+    pub fn build(self) -> Result<Tracker, Error> {
+        let rig = self.rig.ok_or_else(|| Error::InvalidRig("no camera rig given".to_string()))?;
+        let config = self.config.unwrap_or_else(init_default_configuration);
+        if self.gpu_required {
+            check_gpu_usable(&config)?;
+        }
+        let tracker = Tracker::new(rig, &config)?;
+        if self.warm_up && config.use_gpu != 0 {
+            warm_up_gpu();
+        }
+        Ok(tracker)
+    }
+}
+
+/// Check that `config` runs on the GPU and that a CUDA device is usable, for `require_gpu`
+fn check_gpu_usable(config: &CUVSLAM_Configuration) -> Result<(), Error> {
+    if config.use_gpu == 0 {
+        return Err(Error::InvalidConfiguration("a GPU is required but use_gpu is off".to_string()));
+    }
+    #[cfg(feature = "cuda")]
+    let usable = cuda::probe().map(drop).map_err(Error::Cuda);
+    #[cfg(not(feature = "cuda"))]
+    let usable = Err(Error::Cuda("a GPU is required but the cuda feature needed to find one is off".to_string()));
+    usable
+}
+
+/// Check that a rotation matrix is orthonormal with determinant +1
+pub fn is_valid_rotation(r: &[f32; 9]) -> bool {
+    const TOLERANCE: f32 = 1e-3;
+    // Columns of the (column-major) matrix must be orthonormal
+    let col = |i: usize| [r[3 * i], r[3 * i + 1], r[3 * i + 2]];
+    let dot = |a: [f32; 3], b: [f32; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    let (c0, c1, c2) = (col(0), col(1), col(2));
+    let cross = [
+        c0[1] * c1[2] - c0[2] * c1[1],
+        c0[2] * c1[0] - c0[0] * c1[2],
+        c0[0] * c1[1] - c0[1] * c1[0],
+    ];
+
+    r.iter().all(|v| v.is_finite())
+        && (dot(c0, c0) - 1.0).abs() < TOLERANCE
+        && (dot(c1, c1) - 1.0).abs() < TOLERANCE
+        && (dot(c2, c2) - 1.0).abs() < TOLERANCE
+        && dot(c0, c1).abs() < TOLERANCE
+        && dot(c0, c2).abs() < TOLERANCE
+        && dot(c1, c2).abs() < TOLERANCE
+        && (dot(cross, c2) - 1.0).abs() < TOLERANCE
+}
+
+/// Validate a camera rig before handing it to cuVSLAM
+///
+/// The rig must contain at least one camera, and every camera needs a positive resolution,
+/// positive focal lengths, finite parameters and a proper rotation in its pose.
+pub fn validate_rig(rig: &CameraRig) -> Result<(), String> {
+    if rig._cameras.is_empty() {
+        return Err("rig has no cameras".to_string());
+    }
+
+    for (index, camera) in rig._cameras.iter().enumerate() {
+        let inner = &camera.inner;
+        if inner.width <= 0 || inner.height <= 0 {
+            return Err(format!(
+                "camera {} has invalid resolution {}x{}",
+                index, inner.width, inner.height
+            ));
+        }
+        if camera._parameters.iter().any(|p| !p.is_finite()) {
+            return Err(format!("camera {} has non-finite intrinsics", index));
+        }
+        // All distortion models start with cx, cy, fx, fy
+        if camera._parameters[2] <= 0.0 || camera._parameters[3] <= 0.0 {
+            return Err(format!("camera {} has non-positive focal length", index));
+        }
+        if !is_valid_rotation(&inner.pose.r) || inner.pose.t.iter().any(|t| !t.is_finite()) {
+            return Err(format!("camera {} has an invalid pose", index));
+        }
+    }
+    if let Some(index) = (rig._cameras.len()..rig.time_offsets_ns.len()).find(|&i| rig.time_offsets_ns[i] != 0) {
+        return Err(format!(
+            "time offset given for camera {}, rig has {} cameras",
+            index,
+            rig._cameras.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check that `images` form a valid frame for `rig`
+///
+/// The frame must be non-empty, each image must select a distinct camera of the rig, and image
+/// sizes must match their cameras. Buffer and pitch checks are done when an `Image` is built.
+fn validate_images(rig: &CameraRig, images: &[Image]) -> Result<(), Status> {
+    if images.is_empty() {
+        return Err(Status::InvalidArg);
+    }
+    let mut seen = vec![false; rig._inner_cameras.len()];
+    for image in images {
+        let index = usize::try_from(image.camera_index()).map_err(|_| Status::InvalidArg)?;
+        let camera = rig._inner_cameras.get(index).ok_or(Status::InvalidArg)?;
+        if std::mem::replace(&mut seen[index], true) {
+            return Err(Status::InvalidArg);
+        }
+        if (image.width(), image.height()) != (camera.width, camera.height) {
+            return Err(Status::InvalidArg);
+        }
+    }
+    Ok(())
+}
+
+/// Validate a configuration against the rig it will be used with
+///
+/// cuVSLAM reports most invalid configurations only as a generic error from tracker creation,
+/// so known-bad combinations are rejected here with a description instead. Rules:
+/// - `horizontal_stereo_camera` requires at least two cameras
+/// - `enable_imu_fusion` requires an IMU calibration with a positive frequency, noise densities
+///   and random walks
+/// - `enable_reading_slam_internals` requires `enable_localization_n_mapping`
+/// - `map_cell_size` and `max_frame_delta_s` must not be negative
+///
+/// The rules that do not depend on the rig are also available as `ConfigurationBuilder::validate`.
+pub fn validate_configuration(rig: &CameraRig, config: &CUVSLAM_Configuration) -> Result<(), String> {
+    if config.horizontal_stereo_camera != 0 && rig._cameras.len() < 2 {
+        return Err(format!(
+            "horizontal_stereo_camera requires at least two cameras, rig has {}",
+            rig._cameras.len()
+        ));
+    }
+    configuration::validate(config)?;
+
+    Ok(())
+}
+
+/// Area of the convex hull of 2D points, reordering them in the process
+fn convex_hull_area(points: &mut [[f32; 2]]) -> f32 {
+    points.sort_unstable_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    let cross = |o: [f32; 2], a: [f32; 2], b: [f32; 2]| {
+        (a[0] - o[0]) as f64 * (b[1] - o[1]) as f64 - (a[1] - o[1]) as f64 * (b[0] - o[0]) as f64
+    };
+
+    // Andrew's monotone chain: lower hull left to right, then upper hull right to left
+    let half_hull = |points: &mut dyn Iterator<Item = &[f32; 2]>| {
+        let mut hull: Vec<[f32; 2]> = Vec::new();
+        for &p in points {
+            while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last point starts the other half
+        hull.pop();
+        hull
+    };
+    let mut hull = half_hull(&mut points.iter());
+    hull.extend(half_hull(&mut points.iter().rev()));
+
+    let twice_area: f64 = (0..hull.len())
+        .map(|i| cross([0.0, 0.0], hull[i], hull[(i + 1) % hull.len()]))
+        .sum();
+    (twice_area.abs() / 2.0) as f32
+}
+
+/// Copy an array exported by cuVSLAM, which may be null when empty
+unsafe fn copy_array<T: Copy>(ptr: *const T, len: u32) -> Vec<T> {
+    if ptr.is_null() {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(ptr, len as usize).to_vec()
+    }
+}
+
+/// Call `fetch` with ever larger buffers until it returns fewer items than the buffer holds
+///
+/// `fetch` gets the buffer's capacity and a pointer to it and returns the number of items
+/// available. Each retry allocates at least that many, and at least twice the previous capacity.
+fn fetch_with_growing_buffer<T>(mut fetch: impl FnMut(u32, *mut T) -> u32) -> u32 {
+    let mut buffer = Vec::<T>::with_capacity(1024);
+    loop {
+        let capacity = buffer.capacity().min(u32::MAX as usize) as u32;
+        let count = fetch(capacity, buffer.as_mut_ptr());
+        if count < capacity || capacity == u32::MAX {
+            return count;
+        }
+        buffer = Vec::with_capacity((count as usize).max(buffer.capacity() * 2));
+    }
+}
+
+/// Whether a time offset of `max_offset_ns` either way is half of `period_ns` or more
+fn offset_exceeds_period(max_offset_ns: u64, period_ns: i64) -> bool {
+    period_ns > 0 && max_offset_ns.saturating_mul(2) >= period_ns.unsigned_abs()
+}
+
+/// Fill `next` with new track lengths given the previous lengths and the ids observed in the
+/// latest frame, keeping its capacity
+fn advance_track_lengths(
+    previous: &HashMap<i32, u32>,
+    ids: impl Iterator<Item = i32>,
+    next: &mut HashMap<i32, u32>,
+) {
+    next.clear();
+    next.extend(ids.map(|id| (id, previous.get(&id).copied().unwrap_or(0) + 1)));
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::CUVSLAM_DestroyTracker(self.handle);
+        }
+    }
+}
+
+/// Iterator adapter that tracks each frame and pairs the result with the frame timestamp
+///
+/// The timestamp of the first image in each frame is kept even when tracking fails, so the
+/// output can be collected straight into a timestamp to pose map.
+pub struct PoseStream<'a, I> {
+    tracker: &'a Tracker,
+    frames: I,
+}
+
+impl<'a, I> PoseStream<'a, I> {
+    /// Create a pose stream over frames of images
+    pub fn new(tracker: &'a Tracker, frames: I) -> Self {
+        Self { tracker, frames }
+    }
+}
+
+impl<I, F> Iterator for PoseStream<'_, I>
+where
+    I: Iterator<Item = F>,
+    F: AsRef<[Image]>,
+{
+    type Item = (i64, Result<PoseEstimate, Status>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        let images = frame.as_ref();
+        let timestamp_ns = images.first().map_or(0, |image| image.timestamp_ns());
+        Some((timestamp_ns, self.tracker.track(images, None)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+/// Sender of the response to `CUVSLAM_LocalizeInExistDb`, which `respond_localized` takes ownership of
+type LocalizeResponse = std::sync::mpsc::Sender<(CUVSLAM_Status, Option<CUVSLAM_Pose>)>;
+
+unsafe extern "C" fn respond_localized(
+    context: *mut std::ffi::c_void,
+    status: CUVSLAM_Status,
+    pose_in_db: *const CUVSLAM_Pose,
+) {
+    let response = Box::from_raw(context.cast::<LocalizeResponse>());
+    let _ = response.send((status, pose_in_db.as_ref().copied()));
+}
+
+/// Start a localization with `call`, which passes its context on with `respond_localized`, and
+/// wait up to `timeout` for the response
+///
+/// cuVSLAM calls back exactly once when the call is accepted, possibly from another thread. The
+/// context is a boxed sender freed by that callback, so a response arriving after the timeout
+/// goes nowhere instead of into freed memory, and one that never arrives leaks the sender.
+fn await_localization(
+    timeout: std::time::Duration,
+    call: impl FnOnce(*mut std::ffi::c_void) -> CUVSLAM_Status,
+) -> Result<CUVSLAM_Pose, Status> {
+    let (response, result): (LocalizeResponse, _) = std::sync::mpsc::channel();
+    let context = Box::into_raw(Box::new(response));
+    let status = call(context.cast());
+    if status != 0 {
+        // Rejected, so there is no callback to free the sender
+        drop(unsafe { Box::from_raw(context) });
+        return Err(status.into());
+    }
+    let (status, pose_in_db) = result.recv_timeout(timeout).map_err(|error| match error {
+        std::sync::mpsc::RecvTimeoutError::Timeout => {
+            log::warn!("no localization result after {:?}", timeout);
+            Status::CannotLocalize
+        }
+        std::sync::mpsc::RecvTimeoutError::Disconnected => Status::GenericError,
+    })?;
+    if status != 0 {
+        return Err(status.into());
+    }
+    pose_in_db.ok_or(Status::GenericError)
+}
+
+/// Compile and load cuVSLAM's GPU kernels ahead of the first `track` call
+pub fn warm_up_gpu() {
+    unsafe { bindings::CUVSLAM_WarmUpGPU() }
+}
+
+/// Initialize default CUVSLAM configuration
+///
+/// With the `runtime-loading` feature and no library loaded, there are no defaults to read: this
+/// logs a warning and returns an all-zero configuration, and `Tracker::new` then fails with
+/// `Status::LibraryNotLoaded`. Check `is_available` first to handle that case up front.
+pub fn init_default_configuration() -> CUVSLAM_Configuration {
+    if !is_available() {
+        log::warn!("libcuvslam is not loaded, using an all-zero configuration instead of its defaults");
+    }
+    unsafe { bindings::CUVSLAM_GetDefaultConfiguration() }
+}
+
+/// Merge SLAM databases saved with `Tracker::save_to_slam_db` into one database in `output`
+///
+/// `inputs` are two or more databases recorded with `rig`. Their paths must be valid UTF-8, and
+/// fewer than two inputs fail with `Status::InvalidArg`. With the `runtime-loading` feature, a
+/// library that doesn't export the merge entry point fails with `Status::NotImplemented`.
+#[must_use = "a failed merge is only reported through the Result"]
+pub fn merge_slam_dbs(rig: &CameraRig, inputs: &[&std::path::Path], output: &std::path::Path) -> Result<(), Status> {
+    let c_path = |path: &std::path::Path| path.to_str().and_then(|path| CString::new(path).ok());
+    let inputs: Vec<CString> = inputs.iter().map(|path| c_path(path)).collect::<Option<_>>().ok_or(Status::InvalidArg)?;
+    let output = c_path(output).ok_or(Status::InvalidArg)?;
+    if inputs.len() < 2 {
+        return Err(Status::InvalidArg);
+    }
+    let pointers: Vec<_> = inputs.iter().map(|input| input.as_ptr()).collect();
+    let status = unsafe {
+        bindings::CUVSLAM_MergeDatabases(rig.as_inner(), pointers.as_ptr(), pointers.len() as i32, output.as_ptr())
+    };
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(status.into())
+    }
+}
+
+/// Check whether the cuVSLAM library is available
+///
+/// Always true when linking at build time. With the `runtime-loading` feature this tries to
+/// load `libcuvslam.so` from the dynamic linker search path if it hasn't been loaded yet.
+pub fn is_available() -> bool {
+    bindings::is_available()
+}
+
+/// Load the cuVSLAM library from an explicit path (`runtime-loading` feature only)
+#[cfg(feature = "runtime-loading")]
+pub fn load_library<P: AsRef<std::ffi::OsStr>>(path: P) -> Result<(), libloading::Error> {
+    bindings::load(path.as_ref())
+}
+
+/// Locate the `libcuvslam` shared library loaded into this process
+///
+/// Meant for diagnosing deployment problems such as a stale copy earlier in `LD_LIBRARY_PATH`.
+/// Reads `/proc/self/maps`, so it returns `None` on platforms other than Linux, and also when the
+/// library could not be loaded.
+pub fn get_cuvslam_library_path() -> Option<std::path::PathBuf> {
+    if !is_available() {
+        return None;
+    }
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    find_mapped_library(&maps, "libcuvslam")
+}
+
+/// Path of the first mapping in `/proc/<pid>/maps` contents whose file name starts with `name`
+fn find_mapped_library(maps: &str, name: &str) -> Option<std::path::PathBuf> {
+    maps.lines()
+        // address, perms, offset, dev and inode precede the path, which may contain spaces
+        .filter_map(|line| line.splitn(6, ' ').nth(5))
+        .map(|path| std::path::Path::new(path.trim_start()))
+        .find(|path| {
+            path.is_absolute()
+                && path.file_name().is_some_and(|file| file.to_string_lossy().starts_with(name))
+        })
+        .map(std::path::Path::to_path_buf)
+}
+
+/// Get CUVSLAM version information
+pub fn get_version() -> (i32, i32, Option<String>) {
+    let mut major = 0;
+    let mut minor = 0;
+    let mut version_ptr = std::ptr::null();
+
+    unsafe {
+        bindings::CUVSLAM_GetVersion(&mut major, &mut minor, &mut version_ptr);
+        
+        let version = if !version_ptr.is_null() {
+            // Convert C string to Rust String
+            let c_str = std::ffi::CStr::from_ptr(version_ptr);
+            Some(c_str.to_string_lossy().into_owned())
+        } else {
+            None
+        };
+
+        (major, minor, version)
+    }
+}
+
+/// Image encoding formats supported by the tracker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageEncoding {
+    /// 8-bit monochrome image
+    Mono8,
+    /// 8-bit RGB image 
+    Rgb8,
+}
+
+impl From<cuvslam_lib::bindings::CUVSLAM_ImageEncoding> for ImageEncoding {
+    fn from(encoding: cuvslam_lib::bindings::CUVSLAM_ImageEncoding) -> Self {
         match encoding {
             cuvslam_lib::bindings::CUVSLAM_ImageEncoding_MONO8 => ImageEncoding::Mono8,
             cuvslam_lib::bindings::CUVSLAM_ImageEncoding_RGB8 => ImageEncoding::Rgb8,
             _ => panic!("Unknown image encoding"),
         }
     }
-}
+}
+
+impl From<ImageEncoding> for cuvslam_lib::bindings::CUVSLAM_ImageEncoding {
+    fn from(encoding: ImageEncoding) -> Self {
+        match encoding {
+            ImageEncoding::Mono8 => cuvslam_lib::bindings::CUVSLAM_ImageEncoding_MONO8,
+            ImageEncoding::Rgb8 => cuvslam_lib::bindings::CUVSLAM_ImageEncoding_RGB8,
+        }
+    }
+}
+
+impl From<ImageEncoding> for &'static str {
+    /// Name of the encoding as used by ROS `sensor_msgs/Image`, such as `"mono8"`
+    fn from(encoding: ImageEncoding) -> Self {
+        match encoding {
+            ImageEncoding::Mono8 => "mono8",
+            ImageEncoding::Rgb8 => "rgb8",
+        }
+    }
+}
+
+/// Parse an encoding from its ROS name, ignoring case
+impl TryFrom<&str> for ImageEncoding {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "mono8" => Ok(ImageEncoding::Mono8),
+            "rgb8" => Ok(ImageEncoding::Rgb8),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ImageEncoding {
+    /// Number of bytes used by a single pixel
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            ImageEncoding::Mono8 => 1,
+            ImageEncoding::Rgb8 => 3,
+        }
+    }
+}
+
+/// Owned pixel storage of an `Image`
+enum Pixels {
+    Heap(Vec<u8>),
+    #[cfg(feature = "cuda")]
+    Pinned(PinnedBuffer),
+}
+
+impl std::ops::Deref for Pixels {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Pixels::Heap(pixels) => pixels,
+            #[cfg(feature = "cuda")]
+            Pixels::Pinned(pixels) => pixels,
+        }
+    }
+}
+
+/// Safe wrapper around an image with owned pixel data
+///
+/// cuVSLAM takes no per-frame exposure or gain metadata: `CUVSLAM_Image` carries only pixels,
+/// size, encoding, camera and timestamp, and the configuration has no related options. For
+/// scenes with large exposure swings, keep auto-exposure fast and avoid saturating the image
+/// rather than passing exposure settings along.
+pub struct Image {
+    pixels: Pixels,
+    encoding: ImageEncoding,
+    inner: CUVSLAM_Image,
+}
+
+// `inner.pixels` points into the owned buffer, whose heap allocation moves with the image and is
+// never written through a shared reference
+unsafe impl Send for Image {}
+unsafe impl Sync for Image {}
+
+impl Image {
+    /// Create a new image from tightly packed pixel data
+    ///
+    /// Returns `Status::InvalidArg` if the dimensions are not positive or `pixels` is too small.
+    #[must_use = "the image or the reason it is invalid is only in the Result"]
+    pub fn new(
+        pixels: Vec<u8>,
+        width: i32,
+        height: i32,
+        encoding: ImageEncoding,
+        camera_index: i32,
+        timestamp_ns: i64,
+    ) -> Result<Self, Status> {
+        let pitch = width.saturating_mul(encoding.bytes_per_pixel() as i32);
+        Self::new_with_pitch(pixels, width, height, pitch, encoding, camera_index, timestamp_ns)
+    }
+
+    /// Create a new image whose rows are `pitch` bytes apart, e.g. padded for alignment
+    ///
+    /// Returns `Status::InvalidArg` if the dimensions are not positive, `pitch` is shorter than a
+    /// row of pixels, or `pixels` holds fewer than `pitch * height` bytes.
+    #[must_use = "the image or the reason it is invalid is only in the Result"]
+    pub fn new_with_pitch(
+        pixels: Vec<u8>,
+        width: i32,
+        height: i32,
+        pitch: i32,
+        encoding: ImageEncoding,
+        camera_index: i32,
+        timestamp_ns: i64,
+    ) -> Result<Self, Status> {
+        Self::from_pixels(Pixels::Heap(pixels), width, height, pitch, encoding, camera_index, timestamp_ns)
+    }
+
+    /// Create a new image backed by a `PinnedBuffer`, validated like `new_with_pitch`
+    ///
+    /// Page-locked pixels let cuVSLAM upload the frame to the GPU without a staging copy.
+    #[cfg(feature = "cuda")]
+    #[must_use = "the image or the reason it is invalid is only in the Result"]
+    pub fn new_pinned(
+        pixels: PinnedBuffer,
+        width: i32,
+        height: i32,
+        pitch: i32,
+        encoding: ImageEncoding,
+        camera_index: i32,
+        timestamp_ns: i64,
+    ) -> Result<Self, Status> {
+        Self::from_pixels(Pixels::Pinned(pixels), width, height, pitch, encoding, camera_index, timestamp_ns)
+    }
+
+    fn from_pixels(
+        pixels: Pixels,
+        width: i32,
+        height: i32,
+        pitch: i32,
+        encoding: ImageEncoding,
+        camera_index: i32,
+        timestamp_ns: i64,
+    ) -> Result<Self, Status> {
+        if width <= 0 || height <= 0 {
+            return Err(Status::InvalidArg);
+        }
+        if (pitch as i64) < width as i64 * encoding.bytes_per_pixel() as i64 {
+            return Err(Status::InvalidArg);
+        }
+        if (pixels.len() as u64) < pitch as u64 * height as u64 {
+            return Err(Status::InvalidArg);
+        }
+
+        let inner = CUVSLAM_Image {
+            pixels: pixels.as_ptr(),
+            timestamp_ns,
+            width,
+            height,
+            pitch,
+            camera_index,
+            image_encoding: encoding.into(),
+        };
+
+        Ok(Self {
+            pixels,
+            encoding,
+            inner,
+        })
+    }
+
+    /// Width in pixels
+    pub fn width(&self) -> i32 {
+        self.inner.width
+    }
+
+    /// Height in pixels
+    pub fn height(&self) -> i32 {
+        self.inner.height
+    }
+
+    /// Number of bytes between the starts of consecutive rows
+    pub fn pitch(&self) -> i32 {
+        self.inner.pitch
+    }
+
+    /// Pixel encoding
+    pub fn encoding(&self) -> ImageEncoding {
+        self.encoding
+    }
+
+    /// Index of the camera in the rig that captured this image
+    pub fn camera_index(&self) -> i32 {
+        self.inner.camera_index
+    }
+
+    /// Capture timestamp in nanoseconds
+    pub fn timestamp_ns(&self) -> i64 {
+        self.inner.timestamp_ns
+    }
+
+    /// Raw pixel data, at least `pitch() * height()` bytes long
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Take back the pixel buffer, or `None` if it is a `PinnedBuffer`
+    pub fn into_pixels(self) -> Option<Vec<u8>> {
+        match self.pixels {
+            Pixels::Heap(pixels) => Some(pixels),
+            #[cfg(feature = "cuda")]
+            Pixels::Pinned(_) => None,
+        }
+    }
+
+    /// Copy a region of interest into a new image
+    ///
+    /// Returns `Status::InvalidArg` unless the region lies entirely within this image.
+    #[must_use = "the image or the reason it is invalid is only in the Result"]
+    pub fn crop(&self, x: i32, y: i32, width: i32, height: i32) -> Result<Image, Status> {
+        profile_scope!("image_crop");
+        // Sums are taken in i64 so offsets near i32::MAX can't overflow past the check
+        if x < 0 || y < 0 || width <= 0 || height <= 0
+            || x as i64 + width as i64 > self.width() as i64 || y as i64 + height as i64 > self.height() as i64
+        {
+            return Err(Status::InvalidArg);
+        }
+
+        let bytes_per_pixel = self.encoding.bytes_per_pixel();
+        let row_start = x as usize * bytes_per_pixel;
+        let row_len = width as usize * bytes_per_pixel;
+        let mut pixels = Vec::with_capacity(row_len * height as usize);
+        for row in self.pixels.chunks(self.pitch() as usize).skip(y as usize).take(height as usize) {
+            pixels.extend_from_slice(&row[row_start..row_start + row_len]);
+        }
+
+        Image::new(pixels, width, height, self.encoding, self.camera_index(), self.timestamp_ns())
+    }
+
+    /// Get a reference to the underlying CUVSLAM_Image
+    pub fn as_inner(&self) -> &CUVSLAM_Image {
+        &self.inner
+    }
+}
+
+/// A pose estimate with timestamp and covariance information
+///
+/// Laid out like `CUVSLAM_PoseEstimate` so cuVSLAM can write into it directly.
+///
+/// With the `serde` feature the covariance serializes as an array of numbers, or as a base64
+/// string of little-endian `f32`s when the `compact` feature is also enabled.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct PoseEstimate {
+    /// The estimated pose
+    #[cfg_attr(feature = "serde", serde(with = "serialization::PoseDef"))]
+    pub pose: CUVSLAM_Pose,
+    /// Timestamp in nanoseconds
+    pub timestamp_ns: i64,
+    /// 6x6 covariance matrix in row-major format
+    /// The parameters are: (rotation_x, rotation_y, rotation_z, x, y, z)
+    /// Rotations are in radians, translations in meters
+    #[cfg_attr(feature = "serde", serde(with = "serialization::covariance"))]
+    pub covariance: [f32; 36],
+}
+
+const _: () = assert!(
+    std::mem::size_of::<PoseEstimate>() == std::mem::size_of::<CUVSLAM_PoseEstimate>()
+        && std::mem::align_of::<PoseEstimate>() == std::mem::align_of::<CUVSLAM_PoseEstimate>()
+);
+
+impl PoseEstimate {
+    /// Homogeneous 4x4 transform in row-major order (`m[row][col]`)
+    pub fn to_affine_matrix_4x4(&self) -> [[f32; 4]; 4] {
+        // cuVSLAM stores the rotation column-major
+        let r = &self.pose.r;
+        let t = &self.pose.t;
+        [
+            [r[0], r[3], r[6], t[0]],
+            [r[1], r[4], r[7], t[1]],
+            [r[2], r[5], r[8], t[2]],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+
+    /// Homogeneous 4x4 transform flattened in column-major order (OpenGL convention)
+    pub fn to_column_major_array(&self) -> [f32; 16] {
+        let r = &self.pose.r;
+        let t = &self.pose.t;
+        [
+            r[0], r[1], r[2], 0.0,
+            r[3], r[4], r[5], 0.0,
+            r[6], r[7], r[8], 0.0,
+            t[0], t[1], t[2], 1.0,
+        ]
+    }
+
+    /// Scalar position uncertainty: the trace of the translational covariance block, in m²
+    ///
+    /// This is the sum of the x, y and z position variances, i.e. the expected squared position
+    /// error. Its square root is an RMS position error in meters, convenient for thresholds.
+    /// Rotational uncertainty is not included.
+    pub fn uncertainty_scalar(&self) -> f32 {
+        (3..6).map(|i| self.covariance[i * 6 + i]).sum()
+    }
+}
+
+impl From<PoseEstimate> for CUVSLAM_PoseEstimate {
+    fn from(est: PoseEstimate) -> Self {
+        CUVSLAM_PoseEstimate {
+            pose: est.pose,
+            timestamp_ns: est.timestamp_ns,
+            covariance: est.covariance,
+        }
+    }
+}
+
+impl From<CUVSLAM_PoseEstimate> for PoseEstimate {
+    fn from(est: CUVSLAM_PoseEstimate) -> Self {
+        PoseEstimate {
+            pose: est.pose,
+            timestamp_ns: est.timestamp_ns,
+            covariance: est.covariance,
+        }
+    }
+}
+
+/// Estimates compare equal when their timestamps are equal; pose and covariance are ignored
+impl PartialEq for PoseEstimate {
+    fn eq(&self, other: &Self) -> bool {
+        self.timestamp_ns == other.timestamp_ns
+    }
+}
+
+impl Eq for PoseEstimate {}
+
+/// Orders estimates by timestamp only, so collections of estimates can be sorted chronologically
+impl PartialOrd for PoseEstimate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PoseEstimate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.timestamp_ns.cmp(&other.timestamp_ns)
+    }
+}
+
+/// A 3D vector `[x, y, z]`
+pub type Vec3 = [f32; 3];
+
+/// Linear and angular velocity between two consecutive pose estimates
+///
+/// Linear velocity is in m/s in the world frame. Angular velocity is in rad/s, taken from the
+/// axis-angle of the relative rotation and expressed in the frame of `prev`. Returns zeros if
+/// the timestamps are not increasing.
+pub fn velocity(prev: &PoseEstimate, curr: &PoseEstimate) -> (Vec3, Vec3) {
+    let dt = (curr.timestamp_ns - prev.timestamp_ns) as f64 * 1e-9;
+    if dt <= 0.0 {
+        return ([0.0; 3], [0.0; 3]);
+    }
+
+    let linear = std::array::from_fn(|i| ((curr.pose.t[i] - prev.pose.t[i]) as f64 / dt) as f32);
+
+    // Relative rotation prev^T * curr, both stored column-major
+    let a = &prev.pose.r;
+    let b = &curr.pose.r;
+    let rel = |row: usize, col: usize| -> f64 {
+        (0..3).map(|k| a[row * 3 + k] as f64 * b[col * 3 + k] as f64).sum()
+    };
+    let angular = rotation_vector(rel).map(|w| (w / dt) as f32);
+
+    (linear, angular)
+}
+
+/// Length of the translation of `pose`, in meters
+pub fn pose_translation_norm(pose: &CUVSLAM_Pose) -> f32 {
+    let t = &pose.t;
+    (t[0] * t[0] + t[1] * t[1] + t[2] * t[2]).sqrt()
+}
+
+/// Rotation angle of `pose` in radians, in `[0, pi]`, from its axis-angle representation
+pub fn pose_rotation_angle(pose: &CUVSLAM_Pose) -> f32 {
+    let r = &pose.r;
+    let w = rotation_vector(|row, col| r[col * 3 + row] as f64);
+    (w[0] * w[0] + w[1] * w[1] + w[2] * w[2]).sqrt() as f32
+}
+
+/// Rotation of `pose` as a unit quaternion `[x, y, z, w]` with `w >= 0`
+pub fn pose_rotation_quaternion(pose: &CUVSLAM_Pose) -> [f32; 4] {
+    // Column-major storage
+    let m = |row: usize, col: usize| pose.r[col * 3 + row] as f64;
+    let trace = m(0, 0) + m(1, 1) + m(2, 2);
+
+    // Shepperd's method: divide by the largest of the four candidate components
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [(m(2, 1) - m(1, 2)) / s, (m(0, 2) - m(2, 0)) / s, (m(1, 0) - m(0, 1)) / s, s / 4.0]
+    } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+        let s = (1.0 + m(0, 0) - m(1, 1) - m(2, 2)).sqrt() * 2.0;
+        [s / 4.0, (m(0, 1) + m(1, 0)) / s, (m(0, 2) + m(2, 0)) / s, (m(2, 1) - m(1, 2)) / s]
+    } else if m(1, 1) > m(2, 2) {
+        let s = (1.0 + m(1, 1) - m(0, 0) - m(2, 2)).sqrt() * 2.0;
+        [(m(0, 1) + m(1, 0)) / s, s / 4.0, (m(1, 2) + m(2, 1)) / s, (m(0, 2) - m(2, 0)) / s]
+    } else {
+        let s = (1.0 + m(2, 2) - m(0, 0) - m(1, 1)).sqrt() * 2.0;
+        [(m(0, 2) + m(2, 0)) / s, (m(1, 2) + m(2, 1)) / s, s / 4.0, (m(1, 0) - m(0, 1)) / s]
+    };
+
+    let sign = if q[3] < 0.0 { -1.0 } else { 1.0 };
+    let norm = q.iter().map(|v| v * v).sum::<f64>().sqrt() * sign;
+    q.map(|v| (v / norm) as f32)
+}
+
+/// Pose with the rotation of the unit quaternion `[x, y, z, w]` and translation `t`
+///
+/// The quaternion is normalized first, so any non-zero multiple gives the same rotation.
+pub fn pose_from_quaternion(q: [f32; 4], t: [f32; 3]) -> CUVSLAM_Pose {
+    let norm = q.iter().map(|&v| v as f64 * v as f64).sum::<f64>().sqrt();
+    let [x, y, z, w] = q.map(|v| v as f64 / norm);
+    let m = [
+        [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+        [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+        [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+    ];
+    // Column-major storage
+    CUVSLAM_Pose { r: std::array::from_fn(|i| m[i % 3][i / 3] as f32), t }
+}
+
+/// Pose of a camera at `from` looking at `target`, with `up` pointing towards the top of the image
+///
+/// Uses cuVSLAM's axes, x right, y up and z backward, which `Camera::projection_matrix` and
+/// `Camera::pixel_to_bearing_vector` share: the camera looks down its -z axis, so
+/// `z = normalize(from - target)`, `x = normalize(cross(up, z))` and `y = cross(z, x)`. `up` only
+/// needs to be roughly up; it must not be parallel to the viewing direction.
+pub fn pose_look_at(from: [f32; 3], target: [f32; 3], up: [f32; 3]) -> CUVSLAM_Pose {
+    let normalize = |v: [f32; 3]| {
+        let norm = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+        v.map(|value| value / norm)
+    };
+    let cross = |a: [f32; 3], b: [f32; 3]| {
+        [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+    };
+    let z = normalize(std::array::from_fn(|i| from[i] - target[i]));
+    let x = normalize(cross(up, z));
+    let y = cross(z, x);
+    // Column-major storage, one axis per column
+    CUVSLAM_Pose { r: [x, y, z].concat().try_into().unwrap(), t: from }
+}
+
+/// Axis-angle vector of a rotation matrix given as `m(row, col)`
+fn rotation_vector(m: impl Fn(usize, usize) -> f64) -> [f64; 3] {
+    let cos = ((m(0, 0) + m(1, 1) + m(2, 2) - 1.0) / 2.0).clamp(-1.0, 1.0);
+    let skew = [
+        (m(2, 1) - m(1, 2)) / 2.0,
+        (m(0, 2) - m(2, 0)) / 2.0,
+        (m(1, 0) - m(0, 1)) / 2.0,
+    ];
+    let sin = skew.iter().map(|v| v * v).sum::<f64>().sqrt();
+    let angle = sin.atan2(cos);
+
+    if sin > 1e-6 {
+        return skew.map(|v| v * angle / sin);
+    }
+    if cos > 0.0 {
+        // Small angle: the skew-symmetric part is already the rotation vector
+        return skew;
+    }
+
+    // Rotation by pi: recover the axis from the symmetric part
+    let i = (0..3).max_by(|&x, &y| m(x, x).total_cmp(&m(y, y))).unwrap();
+    let mut axis = [0.0; 3];
+    axis[i] = ((m(i, i) + 1.0) / 2.0).max(0.0).sqrt();
+    for j in (0..3).filter(|&j| j != i) {
+        axis[j] = (m(i, j) + m(j, i)) / (4.0 * axis[i]);
+    }
+    axis.map(|v| v * angle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version() {
+        let (major, minor, version) = get_version();
+        println!("Version info - major: {}, minor: {}, version: {:?}", major, minor, version);
+        assert!(major >= 0);
+        assert!(minor >= 0);
+        assert!(version.is_some());
+    }
+
+    #[test]
+    fn test_tracker_initialization() {
+        let config = init_default_configuration();
+        
+        // Create left camera
+        let left_cam = Camera::new_brown5k(
+            640, 480,
+            Brown5kParameters {
+                cx: 320.0, cy: 240.0,
+                fx: 500.0, fy: 500.0,
+                k1: 0.0, k2: 0.0, k3: 0.0,
+                p1: 0.0, p2: 0.0
+            },
+            CUVSLAM_Pose {
+                r: [1.0, 0.0, 0.0,
+                    0.0, 1.0, 0.0,
+                    0.0, 0.0, 1.0],
+                t: [0.0, 0.0, 0.0],
+            }
+        );
+
+        // Create right camera
+        let right_cam = Camera::new_brown5k(
+            640, 480,
+            Brown5kParameters {
+                cx: 320.0, cy: 240.0,
+                fx: 500.0, fy: 500.0,
+                k1: 0.0, k2: 0.0, k3: 0.0,
+                p1: 0.0, p2: 0.0
+            },
+            CUVSLAM_Pose {
+                r: [1.0, 0.0, 0.0,
+                    0.0, 1.0, 0.0,
+                    0.0, 0.0, 1.0],
+                t: [0.1, 0.0, 0.0],
+            }
+        );
+
+        let rig = CameraRig::new(vec![left_cam, right_cam]);
+        let tracker = Tracker::new(rig, &config);
+        
+        match &tracker {
+            Ok(_) => println!("Tracker initialized successfully"),
+            Err(status) => println!("Failed to initialize tracker with status: {}", status),
+        }
+        assert!(tracker.is_ok());
+    }
+
+    #[test]
+    fn test_rig_transform_points() {
+        let params = || PinholeParameters {
+            cx: 320.0, cy: 240.0,
+            fx: 500.0, fy: 500.0,
+        };
+        let left_cam = Camera::new_pinhole(
+            640, 480,
+            params(),
+            CUVSLAM_Pose {
+                r: [1.0, 0.0, 0.0,
+                    0.0, 1.0, 0.0,
+                    0.0, 0.0, 1.0],
+                t: [0.0, 0.0, 0.0],
+            }
+        );
+        // Rotated 90 degrees about z (column-major) and offset along x
+        let right_cam = Camera::new_pinhole(
+            640, 480,
+            params(),
+            CUVSLAM_Pose {
+                r: [0.0, 1.0, 0.0,
+                    -1.0, 0.0, 0.0,
+                    0.0, 0.0, 1.0],
+                t: [0.1, 0.0, 0.0],
+            }
+        );
+        let rig = CameraRig::new(vec![left_cam, right_cam]);
+        let points = [[0.1, 1.0, 2.0]];
+
+        assert_eq!(rig.transform_points(&points, 0), vec![[0.1, 1.0, 2.0]]);
+        assert_eq!(rig.transform_points(&points, 1), vec![[1.0, 0.0, 2.0]]);
+        assert!(rig.transform_points(&points, 2).is_empty());
+    }
+
+    #[test]
+    fn test_advance_track_lengths() {
+        let mut first = HashMap::new();
+        advance_track_lengths(&HashMap::new(), [1, 2, 3].into_iter(), &mut first);
+        assert_eq!(first, HashMap::from([(1, 1), (2, 1), (3, 1)]));
+
+        // Feature 1 is lost, 2 and 3 continue and 4 is new; stale entries in `next` are dropped
+        let mut second = HashMap::from([(9, 5)]);
+        advance_track_lengths(&first, [2, 3, 4].into_iter(), &mut second);
+        assert_eq!(second, HashMap::from([(2, 2), (3, 2), (4, 1)]));
+    }
+
+    #[cfg(feature = "runtime-loading")]
+    #[test]
+    fn test_load_library_missing_path() {
+        assert!(load_library("/nonexistent/libcuvslam.so").is_err());
+
+        if !is_available() {
+            let rig = CameraRig::new(vec![test_camera(640, identity_pose([0.0; 3]))]);
+            let config = init_default_configuration();
+            assert!(matches!(
+                Tracker::new(rig, &config),
+                Err(Error::TrackerCreation { status: Status::LibraryNotLoaded, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_pose_estimate_matrices() {
+        let estimate = PoseEstimate {
+            // 90 degrees about z, column-major
+            pose: CUVSLAM_Pose {
+                r: [0.0, 1.0, 0.0,
+                    -1.0, 0.0, 0.0,
+                    0.0, 0.0, 1.0],
+                t: [1.0, 2.0, 3.0],
+            },
+            timestamp_ns: 0,
+            covariance: [0.0; 36],
+        };
+
+        assert_eq!(
+            estimate.to_affine_matrix_4x4(),
+            [
+                [0.0, -1.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0, 2.0],
+                [0.0, 0.0, 1.0, 3.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]
+        );
+
+        let matrix = estimate.to_affine_matrix_4x4();
+        let flat = estimate.to_column_major_array();
+        for col in 0..4 {
+            for row in 0..4 {
+                assert_eq!(flat[col * 4 + row], matrix[row][col]);
+            }
+        }
+    }
+
+    fn identity_pose(t: [f32; 3]) -> CUVSLAM_Pose {
+        CUVSLAM_Pose {
+            r: [1.0, 0.0, 0.0,
+                0.0, 1.0, 0.0,
+                0.0, 0.0, 1.0],
+            t,
+        }
+    }
+
+    fn test_camera(width: i32, pose: CUVSLAM_Pose) -> Camera {
+        Camera::new_pinhole(
+            width, 480,
+            PinholeParameters {
+                cx: 320.0, cy: 240.0,
+                fx: 500.0, fy: 500.0,
+            },
+            pose,
+        )
+    }
+
+    /// Two 640x480 mono images sharing one pixel buffer
+    fn blank_stereo_frame(pixels: &[u8], timestamp_ns: i64) -> Vec<Image> {
+        (0..2)
+            .map(|camera_index| {
+                Image::new(pixels.to_vec(), 640, 480, ImageEncoding::Mono8, camera_index, timestamp_ns).unwrap()
+            })
+            .collect()
+    }
+
+    /// Frame `index` of a textured 640x480 scene panning left, for `self_test::stereo_rig`
+    fn textured_stereo_frame(index: i64) -> Vec<Image> {
+        self_test::stereo_frame(640, 480, index as i32, 8, index * 33_000_000).into()
+    }
+
+    fn zeroed_configuration() -> CUVSLAM_Configuration {
+        // Plain data with nullable pointers, so all-zero is a valid value
+        unsafe { std::mem::zeroed() }
+    }
+
+    #[test]
+    fn test_tracker_new_rig_validation() {
+        let config = zeroed_configuration();
+
+        let empty = CameraRig::new(Vec::new());
+        assert!(matches!(Tracker::new(empty, &config), Err(Error::InvalidRig(_))));
+
+        let zero_width = CameraRig::new(vec![test_camera(0, identity_pose([0.0; 3]))]);
+        assert!(matches!(Tracker::new(zero_width, &config), Err(Error::InvalidRig(_))));
+
+        let mut skewed = identity_pose([0.0; 3]);
+        skewed.r[1] = 0.5;
+        let bad_rotation = CameraRig::new(vec![test_camera(640, skewed)]);
+        assert!(matches!(Tracker::new(bad_rotation, &config), Err(Error::InvalidRig(_))));
+    }
+
+    #[test]
+    fn test_tracker_new_configuration_validation() {
+        let mono = || CameraRig::new(vec![test_camera(640, identity_pose([0.0; 3]))]);
+
+        let mut stereo_config = zeroed_configuration();
+        stereo_config.horizontal_stereo_camera = 1;
+        assert!(matches!(
+            Tracker::new(mono(), &stereo_config),
+            Err(Error::InvalidConfiguration(_))
+        ));
+
+        let mut imu_config = zeroed_configuration();
+        imu_config.enable_imu_fusion = 1;
+        assert!(matches!(
+            Tracker::new(mono(), &imu_config),
+            Err(Error::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn test_tracker_creation_failure_summary() {
+        // Bypass validation so cuVSLAM itself rejects the empty rig
+        let config = init_default_configuration();
+        match Tracker::create(CameraRig::new(Vec::new()), &config) {
+            Err(error @ Error::TrackerCreation { .. }) => {
+                assert!(error.to_string().contains("0 camera(s)"));
+            }
+            _ => panic!("expected a tracker creation error"),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_status_serde_round_trip() {
+        let json = serde_json::to_string(&Status::TrackingLost).unwrap();
+        assert_eq!(json, "\"TrackingLost\"");
+        assert_eq!(serde_json::from_str::<Status>(&json).unwrap(), Status::TrackingLost);
+    }
+
+    #[test]
+    #[ignore = "needs a CUDA device"]
+    fn test_pose_stream_keeps_timestamps() {
+        let rig = CameraRig::new(vec![
+            test_camera(640, identity_pose([0.0; 3])),
+            test_camera(640, identity_pose([0.1, 0.0, 0.0])),
+        ]);
+        let Ok(tracker) = Tracker::new(rig, &init_default_configuration()) else {
+            panic!("tracker creation failed");
+        };
+
+        let pixels = vec![0u8; 640 * 480];
+        let frames = [blank_stereo_frame(&pixels, 1_000), blank_stereo_frame(&pixels, 34_000)];
+
+        let timestamps: Vec<i64> = PoseStream::new(&tracker, frames.into_iter())
+            .map(|(timestamp_ns, _)| timestamp_ns)
+            .collect();
+        assert_eq!(timestamps, vec![1_000, 34_000]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_pose_estimate_serde_round_trip() {
+        let mut covariance = [0.0; 36];
+        for (i, value) in covariance.iter_mut().enumerate() {
+            *value = i as f32 * 0.25 - 3.0;
+        }
+        let estimate = PoseEstimate {
+            pose: identity_pose([1.0, 2.0, 3.0]),
+            timestamp_ns: 42,
+            covariance,
+        };
+
+        let json = serde_json::to_value(&estimate).unwrap();
+        if cfg!(feature = "compact") {
+            assert!(json["covariance"].is_string());
+        } else {
+            assert_eq!(json["covariance"].as_array().map(Vec::len), Some(36));
+        }
+
+        let decoded: PoseEstimate = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.pose.t, estimate.pose.t);
+        assert_eq!(decoded.timestamp_ns, estimate.timestamp_ns);
+        assert_eq!(decoded.covariance, estimate.covariance);
+    }
+
+    #[test]
+    fn test_status_string_round_trip() {
+        let statuses = [
+            Status::Success,
+            Status::TrackingLost,
+            Status::InvalidArg,
+            Status::CannotLocalize,
+            Status::GenericError,
+            Status::UnsupportedNumberOfCameras,
+            Status::SlamNotInitialized,
+            Status::NotImplemented,
+            Status::ReadingSlamInternalsDisabled,
+            Status::LibraryNotLoaded,
+        ];
+        for status in statuses {
+            assert_eq!(Status::try_from(status.to_string().as_str()), Ok(status));
+            assert_eq!(Status::try_from(status.to_string().to_uppercase().as_str()), Ok(status));
+        }
+        assert_eq!(Status::try_from("not a status"), Err(()));
+    }
+
+    #[test]
+    fn test_image_encoding_string_round_trip() {
+        for encoding in [ImageEncoding::Mono8, ImageEncoding::Rgb8] {
+            let name: &'static str = encoding.into();
+            assert_eq!(ImageEncoding::try_from(name), Ok(encoding));
+            assert_eq!(ImageEncoding::try_from(name.to_uppercase().as_str()), Ok(encoding));
+        }
+        assert_eq!(<&str>::from(ImageEncoding::Mono8), "mono8");
+        assert_eq!(ImageEncoding::try_from("bgr8"), Err(()));
+    }
+
+    #[test]
+    #[ignore = "needs a CUDA device"]
+    fn test_tracker_owns_configuration_buffers() {
+        let rig = CameraRig::new(vec![
+            test_camera(640, identity_pose([0.0; 3])),
+            test_camera(640, identity_pose([0.1, 0.0, 0.0])),
+        ])
+        .with_imu(identity_pose([0.0, 0.0, 0.05]));
+
+        let tracker = {
+            let debug_dump_directory = CString::new(std::env::temp_dir().to_string_lossy().as_ref()).unwrap();
+            let mut config = init_default_configuration();
+            config.imu_calibration.frequency = 200.0;
+            config.imu_calibration.gyroscope_noise_density = 1.6968e-4;
+            config.debug_dump_directory = debug_dump_directory.as_ptr();
+            let Ok(tracker) = Tracker::new(rig, &config) else {
+                panic!("tracker creation failed");
+            };
+            tracker
+        };
+
+        // The caller's configuration and its buffers are gone, the tracker's copy is not
+        assert_eq!(tracker.configuration().imu_calibration.frequency, 200.0);
+        assert_eq!(tracker.configuration().imu_calibration.left_from_imu.t, [0.0, 0.0, 0.05]);
+        let directory = unsafe { std::ffi::CStr::from_ptr(tracker.configuration().debug_dump_directory) };
+        assert_eq!(directory.to_string_lossy(), std::env::temp_dir().to_string_lossy());
+
+        let pixels = vec![0u8; 640 * 480];
+        let _ = tracker.track(&blank_stereo_frame(&pixels, 0), None);
+    }
 
-/// A pose estimate with timestamp and covariance information
-#[derive(Debug, Clone)]
-pub struct PoseEstimate {
-    /// The estimated pose
-    pub pose: CUVSLAM_Pose,
-    /// Timestamp in nanoseconds
-    pub timestamp_ns: i64,
-    /// 6x6 covariance matrix in row-major format
-    /// The parameters are: (rotation_x, rotation_y, rotation_z, x, y, z)
-    /// Rotations are in radians, translations in meters
-    pub covariance: [f32; 36],
-}
+    #[test]
+    #[ignore = "needs a CUDA device"]
+    fn test_track_into_reuses_estimate() {
+        let Ok(tracker) = Tracker::new(self_test::stereo_rig(640, 480), &init_default_configuration()) else {
+            panic!("tracker creation failed");
+        };
 
-impl From<PoseEstimate> for CUVSLAM_PoseEstimate {
-    fn from(est: PoseEstimate) -> Self {
-        CUVSLAM_PoseEstimate {
-            pose: est.pose,
-            timestamp_ns: est.timestamp_ns,
-            covariance: est.covariance,
+        let mut estimate = PoseEstimate {
+            pose: identity_pose([0.0; 3]),
+            timestamp_ns: -1,
+            covariance: [0.0; 36],
+        };
+        for i in 0..2 {
+            assert_eq!(tracker.track_into(&textured_stereo_frame(i), None, &mut estimate), Ok(()));
+            // Overwritten in place with this frame's result
+            assert_eq!(estimate.timestamp_ns, i * 33_000_000);
+            estimate.timestamp_ns = -1;
         }
+
+        // Next frame through the unchecked path, into the same buffer
+        let images = textured_stereo_frame(2);
+        let raw_images = [*images[0].as_inner(), *images[1].as_inner()];
+        assert_eq!(unsafe { tracker.track_raw_into(&raw_images, None, &mut estimate) }, Ok(()));
+        assert_eq!(estimate.timestamp_ns, 66_000_000);
     }
-}
 
-impl From<CUVSLAM_PoseEstimate> for PoseEstimate {
-    fn from(est: CUVSLAM_PoseEstimate) -> Self {
+    #[test]
+    fn test_validate_images() {
+        let rig = CameraRig::new(vec![
+            test_camera(640, identity_pose([0.0; 3])),
+            test_camera(640, identity_pose([0.1, 0.0, 0.0])),
+        ]);
+        let image = |camera_index, width| {
+            Image::new(vec![0; width as usize * 480], width, 480, ImageEncoding::Mono8, camera_index, 0).unwrap()
+        };
+
+        assert_eq!(validate_images(&rig, &[image(0, 640), image(1, 640)]), Ok(()));
+        assert_eq!(validate_images(&rig, &[image(1, 640)]), Ok(()));
+        assert_eq!(validate_images(&rig, &[]), Err(Status::InvalidArg));
+        assert_eq!(validate_images(&rig, &[image(0, 640), image(0, 640)]), Err(Status::InvalidArg));
+        assert_eq!(validate_images(&rig, &[image(2, 640)]), Err(Status::InvalidArg));
+        assert_eq!(validate_images(&rig, &[image(-1, 640)]), Err(Status::InvalidArg));
+        assert_eq!(validate_images(&rig, &[image(0, 320)]), Err(Status::InvalidArg));
+    }
+
+    #[test]
+    fn test_image_crop() {
+        // 4x3 RGB image where every byte encodes its own offset
+        let pixels: Vec<u8> = (0..36).collect();
+        let image = Image::new(pixels.clone(), 4, 3, ImageEncoding::Rgb8, 1, 99).unwrap();
+
+        let full = image.crop(0, 0, 4, 3).unwrap();
+        assert_eq!(full.pixels(), image.pixels());
+        assert_eq!((full.width(), full.height(), full.pitch()), (4, 3, 12));
+        assert_eq!((full.camera_index(), full.timestamp_ns()), (1, 99));
+
+        let region = image.crop(1, 1, 2, 2).unwrap();
+        assert_eq!(region.pitch(), 6);
+        assert_eq!(region.pixels(), &[15, 16, 17, 18, 19, 20, 27, 28, 29, 30, 31, 32]);
+
+        assert_eq!(image.crop(3, 0, 2, 1).err(), Some(Status::InvalidArg));
+        assert_eq!(image.crop(-1, 0, 1, 1).err(), Some(Status::InvalidArg));
+        assert_eq!(image.crop(0, 0, 0, 1).err(), Some(Status::InvalidArg));
+        assert_eq!(image.crop(i32::MAX, 0, 1, 1).err(), Some(Status::InvalidArg));
+        assert_eq!(image.crop(0, 1, 1, i32::MAX).err(), Some(Status::InvalidArg));
+    }
+
+    fn estimate_at(timestamp_ns: i64, pose: CUVSLAM_Pose) -> PoseEstimate {
         PoseEstimate {
-            pose: est.pose,
-            timestamp_ns: est.timestamp_ns,
-            covariance: est.covariance,
+            pose,
+            timestamp_ns,
+            covariance: [0.0; 36],
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_velocity() {
+        let prev = estimate_at(1_000_000_000, identity_pose([1.0, 2.0, 3.0]));
+
+        // Half a second later, moved 1m along x and yawed by 0.5 rad
+        let (s, c) = 0.5f32.sin_cos();
+        let curr = estimate_at(
+            1_500_000_000,
+            CUVSLAM_Pose {
+                r: [c, s, 0.0, -s, c, 0.0, 0.0, 0.0, 1.0],
+                t: [2.0, 2.0, 3.0],
+            },
+        );
+
+        let (linear, angular) = velocity(&prev, &curr);
+        let expected_linear = [2.0, 0.0, 0.0];
+        let expected_angular = [0.0, 0.0, 1.0];
+        for i in 0..3 {
+            assert!((linear[i] - expected_linear[i]).abs() < 1e-5, "{:?}", linear);
+            assert!((angular[i] - expected_angular[i]).abs() < 1e-5, "{:?}", angular);
+        }
+
+        // Half turn about y
+        let flipped = estimate_at(
+            2_000_000_000,
+            CUVSLAM_Pose {
+                r: [-1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, -1.0],
+                t: [1.0, 2.0, 3.0],
+            },
+        );
+        let (_, angular) = velocity(&prev, &flipped);
+        assert!((angular[1].abs() - std::f32::consts::PI).abs() < 1e-5, "{:?}", angular);
+
+        assert_eq!(velocity(&prev, &prev), ([0.0; 3], [0.0; 3]));
+    }
 
     #[test]
-    fn test_version() {
-        let (major, minor, version) = get_version();
-        println!("Version info - major: {}, minor: {}, version: {:?}", major, minor, version);
-        assert!(major >= 0);
-        assert!(minor >= 0);
-        assert!(version.is_some());
+    fn test_image_new_with_pitch() {
+        // 3x2 mono image with rows padded to 4 bytes
+        let pixels = vec![1, 2, 3, 0, 4, 5, 6, 0];
+        let image = Image::new_with_pitch(pixels.clone(), 3, 2, 4, ImageEncoding::Mono8, 0, 0).unwrap();
+        assert_eq!(image.pitch(), 4);
+        assert_eq!(image.as_inner().pitch, 4);
+
+        // Cropping drops the padding
+        let region = image.crop(1, 0, 2, 2).unwrap();
+        assert_eq!(region.pitch(), 2);
+        assert_eq!(region.pixels(), &[2, 3, 5, 6]);
+
+        // Pitch shorter than a row, and a buffer shorter than pitch * height
+        assert_eq!(
+            Image::new_with_pitch(pixels.clone(), 3, 2, 2, ImageEncoding::Mono8, 0, 0).err(),
+            Some(Status::InvalidArg)
+        );
+        assert_eq!(
+            Image::new_with_pitch(pixels, 3, 2, 12, ImageEncoding::Rgb8, 0, 0).err(),
+            Some(Status::InvalidArg)
+        );
     }
 
     #[test]
-    fn test_tracker_initialization() {
-        let config = init_default_configuration();
-        
-        // Create left camera
-        let left_cam = Camera::new_brown5k(
-            640, 480,
-            Brown5kParameters {
-                cx: 320.0, cy: 240.0,
-                fx: 500.0, fy: 500.0,
-                k1: 0.0, k2: 0.0, k3: 0.0,
-                p1: 0.0, p2: 0.0
-            },
-            CUVSLAM_Pose {
-                r: [1.0, 0.0, 0.0,
-                    0.0, 1.0, 0.0,
-                    0.0, 0.0, 1.0],
-                t: [0.0, 0.0, 0.0],
-            }
+    fn test_image_thread_handoff() {
+        let image = Image::new(vec![7; 12], 4, 3, ImageEncoding::Mono8, 1, 42).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let consumer = std::thread::spawn(move || {
+            let image: Image = receiver.recv().unwrap();
+            // The raw view still points at the moved buffer
+            let inner = image.as_inner();
+            (inner.pixels == image.pixels().as_ptr(), inner.camera_index, inner.timestamp_ns)
+        });
+        sender.send(image).unwrap();
+        assert_eq!(consumer.join().unwrap(), (true, 1, 42));
+    }
+
+    #[test]
+    fn test_camera_projection_matrix() {
+        let p = test_camera(640, identity_pose([0.1, 0.0, 0.0])).projection_matrix();
+        assert_eq!(p, [[500.0, 0.0, -320.0, -50.0], [0.0, -500.0, -240.0, 0.0], [0.0, 0.0, -1.0, 0.0]]);
+
+        // Rotated 90 degrees about y, P agrees with transform_points followed by the pinhole model
+        let mut pose = identity_pose([0.1, 0.2, 0.3]);
+        pose.r = [0.0, 0.0, -1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0];
+        let rig = CameraRig::new(vec![test_camera(640, pose)]);
+        let p = rig._cameras[0].projection_matrix();
+        let point = [2.0, 0.5, 0.6];
+        let [x, y, z] = rig.transform_points(&[point], 0)[0];
+        let [u, v, w] = p.map(|row| row[0] * point[0] + row[1] * point[1] + row[2] * point[2] + row[3]);
+        assert!((u / w - (320.0 - 500.0 * x / z)).abs() < 1e-3);
+        assert!((v / w - (240.0 + 500.0 * y / z)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_look_at_projects_in_front() {
+        let project = |camera: &Camera, point: [f32; 3]| {
+            camera.projection_matrix().map(|row| row[0] * point[0] + row[1] * point[1] + row[2] * point[2] + row[3])
+        };
+        let camera = test_camera(640, pose_look_at([0.0; 3], [0.0, 0.0, -5.0], [0.0, 1.0, 0.0]));
+        let [u, v, w] = project(&camera, [0.0, 0.0, -5.0]);
+        assert!(w > 0.0);
+        assert_eq!((u / w, v / w), (320.0, 240.0));
+        let [u, _, w] = project(&camera, [1.0, 0.0, -5.0]);
+        assert!(w > 0.0 && u / w > 320.0);
+        let [_, v, w] = project(&camera, [0.0, 1.0, -5.0]);
+        assert!(w > 0.0 && v / w < 240.0);
+
+        // Off-axis, the target lands on the principal point and the central ray points at it
+        let (from, target) = ([1.0, 2.0, 3.0], [4.0, 0.0, -2.0]);
+        let pose = pose_look_at(from, target, [0.0, 1.0, 0.0]);
+        let camera = test_camera(640, pose);
+        let [u, v, w] = project(&camera, target);
+        assert!(w > 0.0 && (u / w - 320.0).abs() < 1e-3 && (v / w - 240.0).abs() < 1e-3);
+        let ray = camera.pixel_to_bearing_vector(320.0, 240.0);
+        let ray: [f32; 3] = std::array::from_fn(|i| (0..3).map(|k| pose.r[k * 3 + i] * ray[k]).sum());
+        let direction: [f32; 3] = std::array::from_fn(|i| target[i] - from[i]);
+        let length = (direction.iter().map(|v| v * v).sum::<f32>()).sqrt();
+        for (value, expected) in ray.iter().zip(direction) {
+            assert!((value - expected / length).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_rig_to_dot_graph() {
+        let mut rotated = identity_pose([0.0, 0.0, 0.1]);
+        // 90 degrees about z
+        rotated.r = [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+        let rig = CameraRig::new(vec![
+            test_camera(640, identity_pose([0.1, 0.0, 0.0])),
+            test_camera(320, identity_pose([0.22, 0.0, 0.0])),
+            test_camera(640, rotated),
+        ])
+        .with_imu(identity_pose([0.0, 0.03, 0.04]));
+        let dot = rig.to_dot_graph();
+        assert!(dot.starts_with("graph rig {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("camera1 [label=\"camera 1\\npinhole 320x480\"];"));
+        assert!(dot.contains("camera0 -- camera1 [label=\"0.120 m\\n0.0 deg\"];"));
+        // sqrt(0.1^2 + 0.1^2)
+        assert!(dot.contains("camera0 -- camera2 [label=\"0.141 m\\n90.0 deg\"];"));
+        assert!(dot.contains("camera0 -- imu [label=\"0.050 m\\n0.0 deg\"];"));
+
+        assert_eq!(CameraRig::new(Vec::new()).to_dot_graph(), "graph rig {\n    node [shape=box];\n}\n");
+    }
+
+    #[test]
+    fn test_camera_into_fisheye4() {
+        let camera = test_camera(640, identity_pose([0.1, 0.0, 0.0])).into_fisheye4(0.1, 0.2, 0.3, 0.4);
+        let inner = camera.as_inner();
+        assert_eq!((inner.width, inner.height), (640, 480));
+        assert_eq!(inner.pose.t, [0.1, 0.0, 0.0]);
+        assert_eq!(inner.num_parameters, 8);
+
+        let model = unsafe { std::ffi::CStr::from_ptr(inner.distortion_model) };
+        assert_eq!(model.to_str(), Ok("fisheye4"));
+        let parameters = unsafe { std::slice::from_raw_parts(inner.parameters, 8) };
+        assert_eq!(parameters, &[320.0, 240.0, 500.0, 500.0, 0.1, 0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    #[ignore = "needs a CUDA device"]
+    fn test_get_poses_without_slam() {
+        let mut config = init_default_configuration();
+        config.enable_localization_n_mapping = 0;
+        let Ok(tracker) = Tracker::new(self_test::stereo_rig(640, 480), &config) else {
+            panic!("tracker creation failed");
+        };
+
+        assert_eq!(tracker.track(&textured_stereo_frame(0), None).err(), None);
+        let Ok((_, slam)) = tracker.get_poses() else {
+            panic!("get_poses failed without SLAM");
+        };
+        assert!(slam.is_none());
+    }
+
+    #[test]
+    fn test_rig_swap_cameras() {
+        let mut rig = CameraRig::new(vec![
+            test_camera(640, identity_pose([0.1, 0.0, 0.0])),
+            test_camera(320, identity_pose([0.0; 3])),
+        ]);
+        rig.swap_cameras(0, 1);
+
+        let inner = rig.as_inner();
+        let cameras = unsafe { std::slice::from_raw_parts(inner.cameras, inner.num_cameras as usize) };
+        assert_eq!(cameras[0].width, 320);
+        assert_eq!(cameras[1].width, 640);
+        assert_eq!(rig.transform_points(&[[0.1, 0.0, 0.0]], 1), vec![[0.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_rig_try_new() {
+        let stereo = CameraRig::try_new(vec![
+            test_camera(640, identity_pose([0.0; 3])),
+            test_camera(640, identity_pose([0.1, 0.0, 0.0])),
+        ]);
+        assert_eq!(stereo.map(|rig| rig.as_inner().num_cameras).ok(), Some(2));
+        assert!(CameraRig::try_new(vec![test_camera(640, identity_pose([0.0; 3]))]).is_ok());
+
+        let coincident = vec![test_camera(640, identity_pose([0.0; 3])), test_camera(640, identity_pose([0.0; 3]))];
+        assert!(matches!(CameraRig::try_new(coincident), Err(Status::InvalidArg)));
+        let mut skewed = identity_pose([0.1, 0.0, 0.0]);
+        skewed.r[1] = 0.5;
+        let degenerate = vec![test_camera(640, identity_pose([0.0; 3])), test_camera(640, skewed)];
+        assert!(matches!(CameraRig::try_new(degenerate), Err(Status::InvalidArg)));
+    }
+
+    #[test]
+    fn test_rig_with_imu() {
+        let rig = CameraRig::new(vec![test_camera(640, identity_pose([0.0; 3]))]);
+        assert!(rig.imu_pose().is_none());
+        let rig = rig.with_imu(identity_pose([0.0, -0.02, 0.01]));
+        assert_eq!(rig.imu_pose().map(|pose| pose.t), Some([0.0, -0.02, 0.01]));
+        assert_eq!(rig.as_inner().num_cameras, 1);
+    }
+
+    #[test]
+    fn test_rig_time_offsets() {
+        let mut rig = CameraRig::new(vec![
+            test_camera(640, identity_pose([0.0; 3])),
+            test_camera(640, identity_pose([0.1, 0.0, 0.0])),
+        ])
+        .with_time_offset(1, -2_000_000);
+        assert_eq!((rig.time_offset_ns(0), rig.time_offset_ns(1), rig.time_offset_ns(5)), (0, -2_000_000, 0));
+        rig.swap_cameras(0, 1);
+        assert_eq!((rig.time_offset_ns(0), rig.time_offset_ns(1)), (-2_000_000, 0));
+        assert_eq!(validate_rig(&rig), Ok(()));
+
+        let rig = rig.with_time_offset(2, 1_000);
+        assert_eq!(validate_rig(&rig), Err("time offset given for camera 2, rig has 2 cameras".to_string()));
+    }
+
+    #[test]
+    fn test_fetch_with_growing_buffer() {
+        // cuVSLAM-style: fills at most `capacity` and reports how many it wrote
+        let mut capacities = Vec::new();
+        let count = fetch_with_growing_buffer::<u64>(|capacity, _| {
+            capacities.push(capacity);
+            capacity.min(5000)
+        });
+        assert_eq!(count, 5000);
+        assert_eq!(capacities, [1024, 2048, 4096, 8192]);
+
+        // Reporting the total regardless of capacity sizes the next buffer to fit it
+        let mut capacities = Vec::new();
+        let count = fetch_with_growing_buffer::<u64>(|capacity, _| {
+            capacities.push(capacity);
+            3000
+        });
+        assert_eq!(count, 3000);
+        assert_eq!(capacities, [1024, 3000, 6000]);
+    }
+
+    #[test]
+    fn test_offset_exceeds_period() {
+        assert!(!offset_exceeds_period(4_000_000, 10_000_000));
+        assert!(offset_exceeds_period(5_000_000, 10_000_000));
+        // Frames out of order or at the same time are left to cuVSLAM
+        assert!(!offset_exceeds_period(5_000_000, 0));
+        assert!(!offset_exceeds_period(5_000_000, -10_000_000));
+        // Extreme offsets saturate instead of overflowing
+        assert!(offset_exceeds_period(i64::MIN.unsigned_abs(), i64::MAX));
+    }
+
+    #[test]
+    fn test_await_localization() {
+        let timeout = std::time::Duration::from_secs(5);
+        let pose = CUVSLAM_Pose { r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], t: [1.0, 2.0, 3.0] };
+        let respond = |context: usize, status: CUVSLAM_Status, pose: Option<&CUVSLAM_Pose>| unsafe {
+            respond_localized(context as *mut _, status, pose.map_or(std::ptr::null(), |pose| pose as *const _))
+        };
+
+        // Responses on the calling thread and from another one
+        let localized = await_localization(timeout, |context| {
+            respond(context as usize, 0, Some(&pose));
+            0
+        });
+        assert_eq!(localized.map(|pose| pose.t), Ok([1.0, 2.0, 3.0]));
+        let localized = await_localization(timeout, |context| {
+            let context = context as usize;
+            std::thread::spawn(move || respond(context, 0, Some(&pose)));
+            0
+        });
+        assert_eq!(localized.map(|pose| pose.t), Ok([1.0, 2.0, 3.0]));
+
+        // Failures reported by the call itself, through the callback, or not at all
+        let rejected = await_localization(timeout, |_| cuvslam_lib::bindings::CUVSLAM_INVALID_ARG);
+        assert_eq!(rejected.map(|pose| pose.t), Err(Status::InvalidArg));
+        let failed = await_localization(timeout, |context| {
+            respond(context as usize, cuvslam_lib::bindings::CUVSLAM_CAN_NOT_LOCALIZE, None);
+            0
+        });
+        assert_eq!(failed.map(|pose| pose.t), Err(Status::CannotLocalize));
+        let mut late = None;
+        let timed_out = await_localization(std::time::Duration::from_millis(10), |context| {
+            let context = context as usize;
+            late = Some(std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                respond(context, 0, Some(&pose));
+            }));
+            0
+        });
+        assert_eq!(timed_out.map(|pose| pose.t), Err(Status::CannotLocalize));
+        // A response after the timeout is dropped rather than written to the returned call's stack
+        late.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_rig_stereo_shared_intrinsics() {
+        let params = Brown5kParameters {
+            cx: 320.5,
+            cy: 240.5,
+            fx: 385.0,
+            fy: 386.0,
+            k1: -0.05,
+            k2: 0.04,
+            k3: 0.0,
+            p1: 0.001,
+            p2: -0.0005,
+        };
+        let rig = CameraRig::stereo_shared_intrinsics(params, 640, 480, 0.055);
+
+        let inner = rig.as_inner();
+        assert_eq!(inner.num_cameras, 2);
+        let cameras = unsafe { std::slice::from_raw_parts(inner.cameras, 2) };
+        for camera in cameras {
+            assert_eq!((camera.width, camera.height), (640, 480));
+            let parameters = unsafe { std::slice::from_raw_parts(camera.parameters, camera.num_parameters as usize) };
+            assert_eq!(parameters, [320.5, 240.5, 385.0, 386.0, -0.05, 0.04, 0.0, 0.001, -0.0005]);
+            assert_eq!(camera.pose.r, identity_pose([0.0; 3]).r);
+        }
+        assert_eq!(cameras[0].pose.t, [0.0; 3]);
+        assert_eq!(cameras[1].pose.t, [0.055, 0.0, 0.0]);
+        assert_eq!(validate_rig(&rig), Ok(()));
+    }
+
+    #[test]
+    fn test_pose_norms() {
+        let pose = identity_pose([3.0, 0.0, -4.0]);
+        assert_eq!(pose_translation_norm(&pose), 5.0);
+        assert_eq!(pose_rotation_angle(&pose), 0.0);
+
+        // 120 degrees about (1, 1, 1) permutes the axes
+        let pose = CUVSLAM_Pose {
+            r: [0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0],
+            t: [0.0; 3],
+        };
+        assert!((pose_rotation_angle(&pose) - 2.0 * std::f32::consts::FRAC_PI_3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_status_into_io_error() {
+        let error = std::io::Error::from(Status::InvalidArg);
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), Status::InvalidArg.to_string());
+        let inner = error.get_ref().and_then(|e| e.downcast_ref::<Status>());
+        assert_eq!(inner, Some(&Status::InvalidArg));
+
+        assert_eq!(std::io::Error::from(Status::TrackingLost).kind(), std::io::ErrorKind::Other);
+    }
+
+    #[test]
+    #[ignore = "needs a CUDA device"]
+    fn test_frame_count_counts_successful_frames() {
+        let Ok(tracker) = Tracker::new(self_test::stereo_rig(640, 480), &init_default_configuration()) else {
+            panic!("tracker creation failed");
+        };
+        assert_eq!(tracker.frame_count(), 0);
+        assert_eq!(tracker.session_duration(), std::time::Duration::ZERO);
+        // Rejected before reaching cuVSLAM, so not counted
+        assert_eq!(tracker.track(&[], None).err(), Some(Status::InvalidArg));
+
+        for i in 0..3 {
+            assert_eq!(tracker.track(&textured_stereo_frame(i), None).err(), None);
+        }
+        assert_eq!(tracker.frame_count(), 3);
+        assert_eq!(tracker.session_frames(), 3);
+        assert_eq!(tracker.session_duration(), std::time::Duration::from_millis(66));
+    }
+
+    #[test]
+    fn test_current_state_follows_track_results() {
+        let rig = CameraRig::new(vec![
+            test_camera(640, identity_pose([0.0; 3])),
+            test_camera(640, identity_pose([0.1, 0.0, 0.0])),
+        ]);
+        let Ok(tracker) = Tracker::new(rig, &init_default_configuration()) else {
+            panic!("tracker creation failed");
+        };
+        assert_eq!(tracker.current_state(), TrackerState::Uninitialized);
+        // Rejected before reaching cuVSLAM
+        assert_eq!(tracker.track(&[], None).err(), Some(Status::InvalidArg));
+        assert_eq!(tracker.current_state(), TrackerState::Uninitialized);
+
+        let pixels = vec![0u8; 640 * 480];
+        let mut tracked = false;
+        for timestamp_ns in [0, 33_000_000, 66_000_000] {
+            let expected = match tracker.track(&blank_stereo_frame(&pixels, timestamp_ns), None) {
+                Ok(_) => TrackerState::Tracking,
+                Err(Status::TrackingLost) if tracked => TrackerState::Lost,
+                Err(_) => tracker.current_state(),
+            };
+            tracked |= expected == TrackerState::Tracking;
+            assert_eq!(tracker.current_state(), expected);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a CUDA device"]
+    fn test_warm_up() {
+        let Ok(tracker) = Tracker::new(self_test::stereo_rig(640, 480), &init_default_configuration()) else {
+            panic!("tracker creation failed");
+        };
+        assert_eq!(tracker.warm_up(&[]).err(), Some(Status::InvalidArg));
+
+        let frames: Vec<_> = (0..3).map(|i| (textured_stereo_frame(i), None)).collect();
+        assert_eq!(tracker.warm_up(&frames).map(|estimate| estimate.timestamp_ns), Ok(66_000_000));
+    }
+
+    #[test]
+    fn test_convex_hull_area() {
+        let mut square = vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0], [1.0, 1.0], [0.5, 1.5], [2.0, 1.0]];
+        assert_eq!(convex_hull_area(&mut square), 4.0);
+
+        let mut triangle = vec![[0.0, 0.0], [4.0, 0.0], [0.0, 3.0]];
+        assert_eq!(convex_hull_area(&mut triangle), 6.0);
+
+        let mut collinear = vec![[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]];
+        assert_eq!(convex_hull_area(&mut collinear), 0.0);
+        assert_eq!(convex_hull_area(&mut [[1.0, 1.0]]), 0.0);
+        assert_eq!(convex_hull_area(&mut []), 0.0);
+    }
+
+    #[test]
+    fn test_builder_requires_rig() {
+        assert!(matches!(Tracker::builder().warm_up(true).build(), Err(Error::InvalidRig(_))));
+    }
+
+    #[test]
+    fn test_builder_require_gpu() {
+        let builder = || Tracker::builder().rig(CameraRig::new(vec![test_camera(640, identity_pose([0.0; 3]))]));
+        let mut config: CUVSLAM_Configuration = unsafe { std::mem::zeroed() };
+        let cpu = builder().configuration(&config).require_gpu(true).build();
+        assert!(matches!(cpu, Err(Error::InvalidConfiguration(_))));
+
+        // Without the cuda feature there is no way to confirm the GPU
+        config.use_gpu = 1;
+        #[cfg(not(feature = "cuda"))]
+        assert!(matches!(builder().configuration(&config).require_gpu(true).build(), Err(Error::Cuda(_))));
+    }
+
+    #[test]
+    #[ignore = "needs a CUDA device"]
+    fn test_builder_warm_up() {
+        let Ok(tracker) = Tracker::builder().rig(self_test::stereo_rig(640, 480)).warm_up(true).build() else {
+            panic!("tracker creation failed");
+        };
+
+        let frames: Vec<_> = (0..3).map(|i| (textured_stereo_frame(i), None)).collect();
+        assert_eq!(tracker.warm_up(&frames).map(|estimate| estimate.timestamp_ns), Ok(66_000_000));
+    }
+
+    #[test]
+    fn test_pose_rotation_quaternion() {
+        assert_eq!(pose_rotation_quaternion(&identity_pose([0.0; 3])), [0.0, 0.0, 0.0, 1.0]);
+
+        // Half turn about x has a zero trace
+        let pose = CUVSLAM_Pose {
+            r: [1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0],
+            t: [0.0; 3],
+        };
+        assert_eq!(pose_rotation_quaternion(&pose), [1.0, 0.0, 0.0, 0.0]);
+
+        // 120 degrees about (1, 1, 1)
+        let pose = CUVSLAM_Pose {
+            r: [0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0],
+            t: [0.0; 3],
+        };
+        for (value, expected) in pose_rotation_quaternion(&pose).iter().zip([0.5; 4]) {
+            assert!((value - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_pose_from_quaternion() {
+        let identity = pose_from_quaternion([0.0, 0.0, 0.0, 2.0], [1.0, 2.0, 3.0]);
+        assert_eq!(identity.r, identity_pose([0.0; 3]).r);
+        assert_eq!(identity.t, [1.0, 2.0, 3.0]);
+
+        // Quarter turn about z maps x onto y
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let pose = pose_from_quaternion([0.0, 0.0, half, half], [0.0; 3]);
+        for (value, expected) in pose.r.iter().zip([0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0]) {
+            assert!((value - expected).abs() < 1e-6);
+        }
+
+        let q = [0.1, -0.5, 0.3, 0.8];
+        let norm = q.iter().map(|v: &f32| v * v).sum::<f32>().sqrt();
+        let round_trip = pose_rotation_quaternion(&pose_from_quaternion(q, [0.0; 3]));
+        for (value, expected) in round_trip.iter().zip(q) {
+            assert!((value - expected / norm).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_pose_look_at() {
+        let identity = pose_look_at([0.0; 3], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]);
+        assert_eq!(identity.r, identity_pose([0.0; 3]).r);
+        assert_eq!(identity.t, [0.0; 3]);
+
+        // From +x looking back at the origin, with a tilted up vector: the camera's -z points to -x
+        let pose = pose_look_at([2.0, 0.0, 0.0], [0.0; 3], [0.3, 1.0, 0.0]);
+        for (value, expected) in pose.r.iter().zip([0.0, 0.0, -1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0]) {
+            assert!((value - expected).abs() < 1e-6);
+        }
+        assert_eq!(pose.t, [2.0, 0.0, 0.0]);
+        assert!((pose_rotation_angle(&pose) - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_find_mapped_library() {
+        let maps = "\
+7f1c2a000000-7f1c2a021000 r--p 00000000 08:01 1234    /usr/lib/x86_64-linux-gnu/libc.so.6
+7f1c2b000000-7f1c2b400000 r-xp 00000000 08:01 5678    /opt/my libs/libcuvslam.so
+7ffd4a000000-7ffd4a021000 rw-p 00000000 00:00 0       [stack]
+";
+        assert_eq!(
+            find_mapped_library(maps, "libcuvslam"),
+            Some(std::path::PathBuf::from("/opt/my libs/libcuvslam.so"))
         );
+        assert_eq!(find_mapped_library(maps, "libnvinfer"), None);
+    }
 
-        // Create right camera
-        let right_cam = Camera::new_brown5k(
-            640, 480,
+    #[test]
+    fn test_pose_estimate_orders_by_timestamp() {
+        let mut estimates = [
+            estimate_at(3, identity_pose([0.0; 3])),
+            estimate_at(1, identity_pose([5.0; 3])),
+            estimate_at(2, identity_pose([0.0; 3])),
+        ];
+        estimates.sort();
+        let timestamps: Vec<_> = estimates.iter().map(|estimate| estimate.timestamp_ns).collect();
+        assert_eq!(timestamps, [1, 2, 3]);
+
+        // Equality ignores the pose
+        assert_eq!(estimate_at(1, identity_pose([0.0; 3])), estimate_at(1, identity_pose([5.0; 3])));
+    }
+
+    #[test]
+    fn test_pixel_to_bearing_vector() {
+        let pinhole = test_camera(640, identity_pose([0.0; 3]));
+        let principal_point = (pinhole._parameters[0], pinhole._parameters[1]);
+        let brown5k = Camera::new_brown5k(
+            640,
+            480,
             Brown5kParameters {
-                cx: 320.0, cy: 240.0,
-                fx: 500.0, fy: 500.0,
-                k1: 0.0, k2: 0.0, k3: 0.0,
-                p1: 0.0, p2: 0.0
+                cx: 320.0, cy: 240.0, fx: 500.0, fy: 500.0,
+                k1: -0.2, k2: 0.05, k3: 0.0, p1: 1e-3, p2: -1e-3,
             },
-            CUVSLAM_Pose {
-                r: [1.0, 0.0, 0.0,
-                    0.0, 1.0, 0.0,
-                    0.0, 0.0, 1.0],
-                t: [0.1, 0.0, 0.0],
-            }
+            identity_pose([0.0; 3]),
         );
+        let fisheye = test_camera(640, identity_pose([0.0; 3])).into_fisheye4(0.05, -0.01, 0.002, 0.0);
+        for camera in [&pinhole, &brown5k, &fisheye] {
+            assert_eq!(camera.pixel_to_bearing_vector(principal_point.0, principal_point.1), [0.0, 0.0, -1.0]);
+        }
 
-        let rig = CameraRig::new(vec![left_cam, right_cam]);
-        let tracker = Tracker::new(rig, &config);
-        
-        match &tracker {
-            Ok(_) => println!("Tracker initialized successfully"),
-            Err(status) => println!("Failed to initialize tracker with status: {}", status),
+        // One focal length right of the principal point is 45 degrees off-axis without distortion
+        let ray = pinhole.pixel_to_bearing_vector(820.0, 240.0);
+        let expected = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((ray[0] - expected).abs() < 1e-6 && ray[1] == 0.0 && (ray[2] + expected).abs() < 1e-6);
+
+        // Project a ray 30 degrees off-axis with each distortion model and invert it
+        let theta = 30f64.to_radians();
+        let (x, y) = (theta.tan() * 0.6, theta.tan() * 0.8);
+        let r2 = x * x + y * y;
+        let radial = 1.0 + r2 * (-0.2 + r2 * 0.05);
+        let brown_pixel = (
+            320.0 + 500.0 * (x * radial + 2.0 * 1e-3 * x * y - 1e-3 * (r2 + 2.0 * x * x)),
+            240.0 + 500.0 * (y * radial + 1e-3 * (r2 + 2.0 * y * y) - 2.0 * 1e-3 * x * y),
+        );
+        let t2 = theta * theta;
+        let theta_d = theta * (1.0 + t2 * (0.05 + t2 * (-0.01 + t2 * 0.002)));
+        let fisheye_pixel = (320.0 + 500.0 * theta_d * 0.6, 240.0 + 500.0 * theta_d * 0.8);
+
+        // Pixels below the principal point look down, -y
+        let expected = [theta.sin() * 0.6, -theta.sin() * 0.8, -theta.cos()];
+        for (camera, (px, py)) in [(&brown5k, brown_pixel), (&fisheye, fisheye_pixel)] {
+            let ray = camera.pixel_to_bearing_vector(px as f32, py as f32);
+            for (value, expected) in ray.iter().zip(expected) {
+                assert!((*value as f64 - expected).abs() < 1e-5, "{:?} != {:?}", ray, expected);
+            }
         }
-        assert!(tracker.is_ok());
+    }
+
+    #[test]
+    fn test_uncertainty_scalar() {
+        let mut estimate = estimate_at(0, identity_pose([0.0; 3]));
+        for (i, variance) in [1.0, 1.0, 1.0, 0.01, 0.02, 0.04].into_iter().enumerate() {
+            estimate.covariance[i * 6 + i] = variance;
+        }
+        // Off-diagonal terms and rotational variances are ignored
+        estimate.covariance[3 * 6 + 4] = 0.5;
+        assert!((estimate.uncertainty_scalar() - 0.07).abs() < 1e-7);
     }
 }
@@ -0,0 +1,77 @@
+//! Chunk-at-a-time reading of MCAP files, shared by the `rosbag2` and `mcap` features
+
+use mcap::records::ChunkIndex;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+/// An MCAP file, memory-mapped and read a chunk at a time through its summary
+pub(crate) struct McapFile {
+    mapped: memmap2::Mmap,
+    /// Message encoding of the channels read, channels in other encodings are ignored
+    message_encoding: &'static str,
+    summary: mcap::read::Summary,
+    /// Chunks in recording order
+    chunks: Vec<ChunkIndex>,
+    next_chunk: usize,
+    page: VecDeque<(String, Vec<u8>)>,
+}
+
+impl McapFile {
+    pub(crate) fn open(path: &Path, message_encoding: &'static str) -> Result<Self, String> {
+        let file = std::fs::File::open(path).map_err(|error| error.to_string())?;
+        // Safety: the mapping is only read, and recordings are not written to while they are played
+        let mapped = unsafe { memmap2::Mmap::map(&file) }.map_err(|error| error.to_string())?;
+        let summary = mcap::read::Summary::read(&mapped)
+            .map_err(|error| error.to_string())?
+            .ok_or("no summary section, the recording may have been cut short (`mcap recover` can rebuild it)")?;
+        let mut chunks = summary.chunk_indexes.clone();
+        chunks.sort_by_key(|chunk| (chunk.message_start_time, chunk.chunk_start_offset));
+        Ok(Self { mapped, message_encoding, summary, chunks, next_chunk: 0, page: VecDeque::new() })
+    }
+
+    /// Schema name of every topic, such as `sensor_msgs/msg/Image`
+    pub(crate) fn topic_schemas(&self) -> HashMap<String, String> {
+        self.summary
+            .channels
+            .values()
+            .filter(|channel| channel.message_encoding == self.message_encoding)
+            .filter_map(|channel| Some((channel.topic.clone(), channel.schema.as_ref()?.name.clone())))
+            .collect()
+    }
+
+    /// Messages on `topics` in the chunk at `index`, in log time order
+    fn chunk_messages(&self, index: usize, topics: &[&str]) -> mcap::McapResult<Vec<(u64, String, Vec<u8>)>> {
+        let mut messages = Vec::new();
+        for message in self.summary.stream_chunk(&self.mapped, &self.chunks[index])? {
+            let message = message?;
+            if message.channel.message_encoding == self.message_encoding
+                && topics.contains(&message.channel.topic.as_str())
+            {
+                messages.push((message.log_time, message.channel.topic.clone(), message.data.into_owned()));
+            }
+        }
+        messages.sort_by_key(|(log_time, _, _)| *log_time);
+        Ok(messages)
+    }
+
+    /// First message on `topic`
+    #[cfg(feature = "rosbag2")]
+    pub(crate) fn first_message(&self, topic: &str) -> mcap::McapResult<Option<Vec<u8>>> {
+        for index in 0..self.chunks.len() {
+            if let Some((_, _, data)) = self.chunk_messages(index, &[topic])?.into_iter().next() {
+                return Ok(Some(data));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Next message on one of `topics`, in log time order
+    pub(crate) fn next_message(&mut self, topics: &[&str]) -> mcap::McapResult<Option<(String, Vec<u8>)>> {
+        while self.page.is_empty() && self.next_chunk < self.chunks.len() {
+            let messages = self.chunk_messages(self.next_chunk, topics)?;
+            self.page.extend(messages.into_iter().map(|(_, topic, data)| (topic, data)));
+            self.next_chunk += 1;
+        }
+        Ok(self.page.pop_front())
+    }
+}
@@ -0,0 +1,159 @@
+//! Memory usage reporting for long-running sessions
+
+use std::time::{Duration, Instant};
+
+/// Snapshot of tracker, GPU and process memory usage
+///
+/// Fields are `None` when the value is unavailable, e.g. GPU figures without the `cuda` feature
+/// or the SLAM pose count when mapping is disabled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// Poses (keyframes) in the SLAM pose graph
+    pub slam_poses: Option<u32>,
+    /// Landmarks exported for the last tracked frame
+    pub landmarks: Option<u32>,
+    /// Free device memory in bytes
+    pub gpu_free_bytes: Option<u64>,
+    /// Total device memory in bytes
+    pub gpu_total_bytes: Option<u64>,
+    /// Resident set size of the process in bytes
+    pub process_rss_bytes: Option<u64>,
+}
+
+impl MemoryReport {
+    /// Device memory in use, in bytes
+    pub fn gpu_used_bytes(&self) -> Option<u64> {
+        Some(self.gpu_total_bytes?.saturating_sub(self.gpu_free_bytes?))
+    }
+
+    /// Fraction of device memory that is free, between 0 and 1
+    pub fn gpu_free_fraction(&self) -> Option<f64> {
+        let total = self.gpu_total_bytes.filter(|&total| total > 0)?;
+        Some(self.gpu_free_bytes? as f64 / total as f64)
+    }
+}
+
+impl std::fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn mib(bytes: Option<u64>) -> String {
+            bytes.map_or("n/a".to_string(), |bytes| format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)))
+        }
+        fn count(value: Option<u32>) -> String {
+            value.map_or("n/a".to_string(), |value| value.to_string())
+        }
+        write!(
+            f,
+            "slam poses: {}, landmarks: {}, gpu used: {} of {}, process rss: {}",
+            count(self.slam_poses),
+            count(self.landmarks),
+            mib(self.gpu_used_bytes()),
+            mib(self.gpu_total_bytes),
+            mib(self.process_rss_bytes)
+        )
+    }
+}
+
+/// Resident set size of this process, read from `/proc/self/status`
+pub(crate) fn process_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Schedules periodic memory sampling and flags low free GPU memory
+///
+/// Call `poll` from the tracking loop; it samples the source at most once per interval.
+pub struct MemorySampler {
+    interval: Duration,
+    gpu_free_threshold_bytes: Option<u64>,
+    next_sample: Option<Instant>,
+}
+
+impl MemorySampler {
+    /// Sample every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            gpu_free_threshold_bytes: None,
+            next_sample: None,
+        }
+    }
+
+    /// Report samples whose free GPU memory is below `bytes` as low
+    pub fn with_gpu_free_threshold(mut self, bytes: u64) -> Self {
+        self.gpu_free_threshold_bytes = Some(bytes);
+        self
+    }
+
+    /// Take a sample from `source` if one is due at `now`
+    ///
+    /// Returns the sample and whether free GPU memory is below the threshold.
+    pub fn poll<E>(
+        &mut self,
+        now: Instant,
+        source: impl FnOnce() -> Result<MemoryReport, E>,
+    ) -> Option<Result<(MemoryReport, bool), E>> {
+        if self.next_sample.is_some_and(|next| now < next) {
+            return None;
+        }
+        self.next_sample = Some(now + self.interval);
+
+        Some(source().map(|report| {
+            let low = matches!(
+                (report.gpu_free_bytes, self.gpu_free_threshold_bytes),
+                (Some(free), Some(threshold)) if free < threshold
+            );
+            (report, low)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn gpu_report(free_mib: u64) -> MemoryReport {
+        MemoryReport {
+            gpu_free_bytes: Some(free_mib * MIB),
+            gpu_total_bytes: Some(1024 * MIB),
+            ..MemoryReport::default()
+        }
+    }
+
+    #[test]
+    fn test_memory_report_math() {
+        let report = gpu_report(256);
+        assert_eq!(report.gpu_used_bytes(), Some(768 * MIB));
+        assert_eq!(report.gpu_free_fraction(), Some(0.25));
+        assert_eq!(
+            report.to_string(),
+            "slam poses: n/a, landmarks: n/a, gpu used: 768.0 MiB of 1024.0 MiB, process rss: n/a"
+        );
+
+        let empty = MemoryReport::default();
+        assert_eq!(empty.gpu_used_bytes(), None);
+        assert_eq!(empty.gpu_free_fraction(), None);
+    }
+
+    #[test]
+    fn test_memory_sampler_schedule() {
+        let mut sampler = MemorySampler::new(Duration::from_secs(10)).with_gpu_free_threshold(100 * MIB);
+        let start = Instant::now();
+        let mut free = [512, 64, 32].into_iter();
+        let mut source = || Ok::<_, ()>(gpu_report(free.next().unwrap()));
+
+        assert_eq!(sampler.poll(start, &mut source), Some(Ok((gpu_report(512), false))));
+        assert_eq!(sampler.poll(start + Duration::from_secs(5), &mut source), None);
+        assert_eq!(sampler.poll(start + Duration::from_secs(10), &mut source), Some(Ok((gpu_report(64), true))));
+        assert_eq!(sampler.poll(start + Duration::from_secs(19), &mut source), None);
+        assert!(sampler.poll(start + Duration::from_secs(25), &mut source).is_some());
+    }
+
+    #[test]
+    fn test_process_rss() {
+        assert!(process_rss_bytes().is_some_and(|rss| rss > 0));
+    }
+}
@@ -0,0 +1,144 @@
+//! 2D occupancy grids from sparse landmarks
+
+use crate::{Status, CUVSLAM_Landmark};
+
+/// Ground-plane grid of cells marked occupied by landmarks
+///
+/// The ground plane is cuVSLAM's x-z plane, since its y axis points up. Cells are stored row by
+/// row, with rows along z and columns along x. Cells without landmarks are not known to be
+/// free, only to have no observed structure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OccupancyGrid {
+    /// World (x, z) coordinates of the outer corner of cell (0, 0), in meters
+    pub origin: [f32; 2],
+    /// Side length of a cell, in meters
+    pub resolution_m: f32,
+    /// Number of cells along x
+    pub width: usize,
+    /// Number of cells along z
+    pub height: usize,
+    /// Occupancy of each cell, `width * height` values in row-major order
+    pub cells: Vec<bool>,
+}
+
+impl OccupancyGrid {
+    /// Column and row of the cell containing world point (x, z), if it is inside the grid
+    pub fn cell_at(&self, x: f32, z: f32) -> Option<(usize, usize)> {
+        let column = ((x - self.origin[0]) / self.resolution_m).floor();
+        let row = ((z - self.origin[1]) / self.resolution_m).floor();
+        let inside = column >= 0.0 && row >= 0.0 && (column as usize) < self.width && (row as usize) < self.height;
+        inside.then_some((column as usize, row as usize))
+    }
+
+    /// Whether the cell containing world point (x, z) is occupied, false outside the grid
+    pub fn is_occupied(&self, x: f32, z: f32) -> bool {
+        self.cell_at(x, z).is_some_and(|(column, row)| self.cells[row * self.width + column])
+    }
+
+    /// Number of occupied cells
+    pub fn occupied_count(&self) -> usize {
+        self.cells.iter().filter(|&&occupied| occupied).count()
+    }
+}
+
+/// Project the landmarks whose height is within `height_range` onto an occupancy grid
+///
+/// Height is the y coordinate, in meters, and both ends of the range are included, so a band
+/// from just above the floor to the robot's height keeps obstacles and drops the floor and
+/// ceiling. The grid covers the bounding box of the kept landmarks with `resolution_m` cells,
+/// its origin aligned to a multiple of the resolution. With no landmarks in the band, or a
+/// non-positive resolution, the grid is empty.
+///
+/// A single outlier far from the rest stretches the bounding box, so the grid is limited to
+/// `max_cells` cells: a larger one returns `Status::InvalidArg` rather than being allocated.
+pub fn landmarks_to_occupancy(
+    landmarks: &[CUVSLAM_Landmark],
+    resolution_m: f32,
+    height_range: (f32, f32),
+    max_cells: usize,
+) -> Result<OccupancyGrid, Status> {
+    let (low, high) = height_range;
+    let points: Vec<[f32; 2]> = landmarks
+        .iter()
+        .filter(|landmark| (low..=high).contains(&landmark.y) && landmark.x.is_finite() && landmark.z.is_finite())
+        .map(|landmark| [landmark.x, landmark.z])
+        .collect();
+    if points.is_empty() || resolution_m <= 0.0 || !resolution_m.is_finite() {
+        return Ok(OccupancyGrid { origin: [0.0; 2], resolution_m, width: 0, height: 0, cells: Vec::new() });
+    }
+
+    // Cell indices relative to the world origin, so grids of overlapping areas line up
+    let index = |value: f32| (value / resolution_m).floor() as i64;
+    let min = |axis: usize| points.iter().map(|point| index(point[axis])).min().unwrap_or(0);
+    let max = |axis: usize| points.iter().map(|point| index(point[axis])).max().unwrap_or(0);
+    let (min_column, min_row) = (min(0), min(1));
+    // Indices saturate at the i64 range, so the spans can't overflow i128
+    let span = |min: i64, max: i64| usize::try_from(max as i128 - min as i128 + 1).ok();
+    let (width, height) = (span(min_column, max(0)), span(min_row, max(1)));
+    let (width, height, count) = match (width, height) {
+        (Some(width), Some(height)) => match width.checked_mul(height) {
+            Some(count) if count <= max_cells => (width, height, count),
+            _ => return Err(Status::InvalidArg),
+        },
+        _ => return Err(Status::InvalidArg),
+    };
+
+    let mut cells = vec![false; count];
+    for point in &points {
+        let column = (index(point[0]) - min_column) as usize;
+        let row = (index(point[1]) - min_row) as usize;
+        cells[row * width + column] = true;
+    }
+    Ok(OccupancyGrid {
+        origin: [min_column as f32 * resolution_m, min_row as f32 * resolution_m],
+        resolution_m,
+        width,
+        height,
+        cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn landmark(x: f32, y: f32, z: f32) -> CUVSLAM_Landmark {
+        CUVSLAM_Landmark { id: 0, x, y, z }
+    }
+
+    #[test]
+    fn test_landmarks_to_occupancy() {
+        let landmarks = [
+            landmark(-0.9, 0.5, 0.1),
+            landmark(-0.8, 1.0, 0.2),
+            landmark(0.75, 0.2, 1.1),
+            // Floor and ceiling
+            landmark(5.0, 0.0, 5.0),
+            landmark(-5.0, 2.5, -5.0),
+        ];
+        let grid = landmarks_to_occupancy(&landmarks, 0.5, (0.2, 1.0), 12).unwrap();
+        assert_eq!((grid.origin, grid.width, grid.height), ([-1.0, 0.0], 4, 3));
+        assert_eq!(grid.occupied_count(), 2);
+        assert!(grid.is_occupied(-0.9, 0.1) && grid.is_occupied(-0.6, 0.4));
+        assert!(grid.is_occupied(0.75, 1.1));
+        assert!(!grid.is_occupied(0.0, 0.0));
+        assert_eq!(grid.cell_at(0.9, 1.4), Some((3, 2)));
+        assert_eq!(grid.cell_at(1.0, 1.4), None);
+        assert!(!grid.is_occupied(5.0, 5.0));
+
+        assert_eq!(landmarks_to_occupancy(&landmarks, 0.5, (3.0, 4.0), 12).unwrap().width, 0);
+        assert!(landmarks_to_occupancy(&landmarks, 0.0, (0.0, 3.0), 12).unwrap().cells.is_empty());
+        // One cell short of the 4x3 bounding box
+        assert_eq!(landmarks_to_occupancy(&landmarks, 0.5, (0.2, 1.0), 11), Err(Status::InvalidArg));
+    }
+
+    #[test]
+    fn test_occupancy_rejects_huge_grids() {
+        // An outlier 1 km away at 5 cm resolution would need 4e8 cells
+        let landmarks = [landmark(0.0, 0.5, 0.0), landmark(1000.0, 0.5, 1000.0)];
+        assert_eq!(landmarks_to_occupancy(&landmarks, 0.05, (0.0, 1.0), 1 << 24), Err(Status::InvalidArg));
+        // Spans whose cell count overflows usize
+        let landmarks = [landmark(-3e38, 0.5, -3e38), landmark(3e38, 0.5, 3e38)];
+        assert_eq!(landmarks_to_occupancy(&landmarks, 1e-30, (0.0, 1.0), usize::MAX), Err(Status::InvalidArg));
+    }
+}
@@ -0,0 +1,154 @@
+//! Latest-pose cache shared between a tracking thread and its consumers
+
+use crate::PoseEstimate;
+use arc_swap::ArcSwapOption;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Cache holding the most recently published pose estimate
+///
+/// `latest` is a lock-free load of an immutable snapshot, so readers never contend with the
+/// tracking thread and can never observe a partially written estimate.
+#[derive(Clone, Default)]
+pub struct PoseCache {
+    shared: Arc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    pose: ArcSwapOption<PoseEstimate>,
+    version: AtomicU64,
+    lock: Mutex<()>,
+    changed: Condvar,
+}
+
+impl PoseCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish a new estimate, replacing the previous one
+    pub fn publish(&self, estimate: PoseEstimate) {
+        self.shared.pose.store(Some(Arc::new(estimate)));
+
+        // Bump the version under the lock so waiting receivers can't miss the wakeup
+        let _guard = self.shared.lock.lock().unwrap();
+        self.shared.version.fetch_add(1, Ordering::Release);
+        self.shared.changed.notify_all();
+    }
+
+    /// Get the most recently published estimate, if any
+    pub fn latest(&self) -> Option<PoseEstimate> {
+        self.shared.pose.load_full().map(|pose| (*pose).clone())
+    }
+
+    /// Create a receiver that is notified of every newly published estimate
+    pub fn subscribe(&self) -> PoseReceiver {
+        PoseReceiver {
+            shared: self.shared.clone(),
+            seen: self.shared.version.load(Ordering::Acquire),
+        }
+    }
+}
+
+/// Watch-style receiver for poses published to a `PoseCache`
+///
+/// Only the latest estimate is retained; a slow receiver skips intermediate poses.
+pub struct PoseReceiver {
+    shared: Arc<Shared>,
+    seen: u64,
+}
+
+impl PoseReceiver {
+    /// Check whether an estimate was published since this receiver last looked
+    pub fn has_changed(&self) -> bool {
+        self.shared.version.load(Ordering::Acquire) != self.seen
+    }
+
+    /// Get the latest estimate and mark it as seen
+    pub fn borrow_and_update(&mut self) -> Option<PoseEstimate> {
+        self.seen = self.shared.version.load(Ordering::Acquire);
+        self.shared.pose.load_full().map(|pose| (*pose).clone())
+    }
+
+    /// Block until a new estimate is published, returning `None` if `timeout` expires first
+    pub fn wait_for_change(&mut self, timeout: Duration) -> Option<PoseEstimate> {
+        let deadline = Instant::now() + timeout;
+        let mut guard = self.shared.lock.lock().unwrap();
+        while !self.has_changed() {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            guard = self.shared.changed.wait_timeout(guard, remaining).unwrap().0;
+        }
+        drop(guard);
+        self.borrow_and_update()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CUVSLAM_Pose;
+    use std::thread;
+
+    /// Estimate whose every field encodes `value`, so a torn read is detectable
+    fn checksum_estimate(value: i64) -> PoseEstimate {
+        PoseEstimate {
+            pose: CUVSLAM_Pose {
+                r: [value as f32; 9],
+                t: [value as f32; 3],
+            },
+            timestamp_ns: value,
+            covariance: [value as f32; 36],
+        }
+    }
+
+    #[test]
+    fn test_pose_cache_no_torn_reads() {
+        const PUBLISHES: i64 = 20_000;
+        let cache = PoseCache::new();
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    let mut last = -1;
+                    while last < PUBLISHES - 1 {
+                        if let Some(estimate) = cache.latest() {
+                            let expected = estimate.timestamp_ns as f32;
+                            assert!(estimate.covariance.iter().all(|&c| c == expected));
+                            assert!(estimate.pose.r.iter().chain(&estimate.pose.t).all(|&v| v == expected));
+                            assert!(estimate.timestamp_ns >= last);
+                            last = estimate.timestamp_ns;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for value in 0..PUBLISHES {
+            cache.publish(checksum_estimate(value));
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_pose_receiver_wait_for_change() {
+        let cache = PoseCache::new();
+        let mut receiver = cache.subscribe();
+        assert!(!receiver.has_changed());
+        assert!(receiver.wait_for_change(Duration::from_millis(10)).is_none());
+
+        let publisher = {
+            let cache = cache.clone();
+            thread::spawn(move || cache.publish(checksum_estimate(7)))
+        };
+        let estimate = receiver.wait_for_change(Duration::from_secs(5)).unwrap();
+        assert_eq!(estimate.timestamp_ns, 7);
+        assert!(!receiver.has_changed());
+        publisher.join().unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! SLAM pose graph snapshot and g2o export
+
+use crate::bindings::{CUVSLAM_PoseGraphEdge, CUVSLAM_PoseGraphNode};
+use crate::pose_rotation_quaternion;
+use std::io::{self, Write};
+
+/// Snapshot of the SLAM pose graph
+#[derive(Debug, Clone, Default)]
+pub struct PoseGraph {
+    /// Keyframe poses in the world frame
+    pub nodes: Vec<CUVSLAM_PoseGraphNode>,
+    /// Relative transforms from `node_from` to `node_to` with their covariances
+    pub edges: Vec<CUVSLAM_PoseGraphEdge>,
+}
+
+impl PoseGraph {
+    /// Write the graph in g2o text format, as `VERTEX_SE3:QUAT` and `EDGE_SE3:QUAT` records
+    ///
+    /// Edge information matrices are the inverse of cuVSLAM's covariances, reordered to g2o's
+    /// (translation, quaternion vector) parameterization. Edges whose covariance is singular
+    /// get an identity information matrix.
+    pub fn write_g2o<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for node in &self.nodes {
+            let t = &node.node_pose.t;
+            let q = pose_rotation_quaternion(&node.node_pose);
+            writeln!(
+                writer,
+                "VERTEX_SE3:QUAT {} {} {} {} {} {} {} {}",
+                node.id, t[0], t[1], t[2], q[0], q[1], q[2], q[3]
+            )?;
+        }
+
+        for edge in &self.edges {
+            let t = &edge.transform.t;
+            let q = pose_rotation_quaternion(&edge.transform);
+            write!(
+                writer,
+                "EDGE_SE3:QUAT {} {} {} {} {} {} {} {} {}",
+                edge.node_from, edge.node_to, t[0], t[1], t[2], q[0], q[1], q[2], q[3]
+            )?;
+            let information = g2o_information(&edge.covariance);
+            for (row, values) in information.iter().enumerate() {
+                for value in &values[row..] {
+                    write!(writer, " {}", value)?;
+                }
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Information matrix in g2o's (x, y, z, qx, qy, qz) order from a row-major cuVSLAM covariance
+/// in (rotation_x, rotation_y, rotation_z, x, y, z) order
+fn g2o_information(covariance: &[f32; 36]) -> [[f64; 6]; 6] {
+    // Index into cuVSLAM's order, and the scale from rotation angle to quaternion vector part
+    let source = |i: usize| if i < 3 { (i + 3, 1.0) } else { (i - 3, 0.5) };
+    let mut matrix = [[0.0; 6]; 6];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            let ((si, ki), (sj, kj)) = (source(i), source(j));
+            *value = covariance[si * 6 + sj] as f64 * ki * kj;
+        }
+    }
+    invert(matrix).unwrap_or_else(|| std::array::from_fn(|i| std::array::from_fn(|j| (i == j) as u8 as f64)))
+}
+
+/// Gauss-Jordan inversion with partial pivoting, `None` if the matrix is singular
+fn invert(mut a: [[f64; 6]; 6]) -> Option<[[f64; 6]; 6]> {
+    let mut inverse: [[f64; 6]; 6] = std::array::from_fn(|i| std::array::from_fn(|j| (i == j) as u8 as f64));
+    for col in 0..6 {
+        let pivot = (col..6).max_by(|&x, &y| a[x][col].abs().total_cmp(&a[y][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let scale = a[col][col];
+        for k in 0..6 {
+            a[col][k] /= scale;
+            inverse[col][k] /= scale;
+        }
+        for row in (0..6).filter(|&row| row != col) {
+            let factor = a[row][col];
+            for k in 0..6 {
+                a[row][k] -= factor * a[col][k];
+                inverse[row][k] -= factor * inverse[col][k];
+            }
+        }
+    }
+    Some(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CUVSLAM_Pose;
+
+    fn pose(r: [f32; 9], t: [f32; 3]) -> CUVSLAM_Pose {
+        CUVSLAM_Pose { r, t }
+    }
+
+    #[test]
+    fn test_pose_graph_g2o() {
+        const IDENTITY: [f32; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        // Quarter turn about z, column-major
+        const YAW_90: [f32; 9] = [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+
+        // Variances of 0.04 for rotations and 0.25 for translations
+        let mut covariance = [0.0; 36];
+        for i in 0..6 {
+            covariance[i * 6 + i] = if i < 3 { 0.04 } else { 0.25 };
+        }
+
+        let graph = PoseGraph {
+            nodes: vec![
+                CUVSLAM_PoseGraphNode { id: 0, node_pose: pose(IDENTITY, [0.0; 3]) },
+                CUVSLAM_PoseGraphNode { id: 1, node_pose: pose(YAW_90, [1.0, 0.0, 0.0]) },
+            ],
+            edges: vec![
+                CUVSLAM_PoseGraphEdge { node_from: 0, node_to: 1, transform: pose(YAW_90, [1.0, 0.0, 0.0]), covariance },
+                CUVSLAM_PoseGraphEdge { node_from: 1, node_to: 0, transform: pose(IDENTITY, [0.0; 3]), covariance: [0.0; 36] },
+            ],
+        };
+
+        let mut output = Vec::new();
+        graph.write_g2o(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+
+        assert_eq!(lines[0], "VERTEX_SE3:QUAT 0 0 0 0 0 0 0 1");
+        let vertex: Vec<f64> = lines[1].split(' ').skip(1).map(|v| v.parse().unwrap()).collect();
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        for (value, expected) in vertex.iter().zip([1.0, 1.0, 0.0, 0.0, 0.0, 0.0, half, half]) {
+            assert!((value - expected).abs() < 1e-6, "{}", lines[1]);
+        }
+
+        // 7 pose values and 21 upper-triangular information entries after the two ids
+        let edge: Vec<f64> = lines[2].split(' ').skip(3).map(|v| v.parse().unwrap()).collect();
+        assert_eq!(edge.len(), 28);
+        let information = &edge[7..];
+        // Diagonal entries sit at the start of each upper-triangular row
+        let diagonal: Vec<_> = [0, 6, 11, 15, 18, 20].iter().map(|&i| information[i]).collect();
+        for (value, expected) in diagonal.iter().zip([4.0, 4.0, 4.0, 100.0, 100.0, 100.0]) {
+            assert!((value - expected).abs() < 1e-3, "{}", lines[2]);
+        }
+        assert!(lines[3].ends_with(" 1 0 0 0 0 0 1 0 0 0 0 1 0 0 0 1 0 0 1 0 1"));
+    }
+}
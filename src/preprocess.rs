@@ -0,0 +1,366 @@
+//! Per-camera image preprocessing ahead of `Tracker::track`
+
+use crate::{profile_scope, Image, ImageEncoding, Status};
+use std::time::{Duration, Instant};
+
+/// Pixel layout of frames handed to a `Preprocessor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormat {
+    /// 8-bit grayscale
+    Mono8,
+    /// 8-bit interleaved RGB
+    Rgb8,
+    /// 8-bit Bayer mosaic with an RGGB pattern
+    BayerRggb8,
+    /// 8-bit YUYV 4:2:2, as delivered by most UVC webcams
+    Yuyv,
+}
+
+/// A frame as delivered by a camera driver, borrowed for preprocessing
+#[derive(Debug, Clone, Copy)]
+pub struct RawFrame<'a> {
+    /// Rows of pixels `pitch` bytes apart; the last row may end right after its pixels, so a
+    /// region of a larger buffer such as one half of a side-by-side frame can be passed as is
+    pub pixels: &'a [u8],
+    pub width: i32,
+    pub height: i32,
+    /// Bytes between the starts of consecutive rows
+    pub pitch: i32,
+    pub format: RawFormat,
+    pub camera_index: i32,
+    pub timestamp_ns: i64,
+}
+
+/// Exposure summary of a preprocessed frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CameraHealth {
+    /// Mean intensity, 0 to 255
+    pub mean_intensity: f32,
+    /// Fraction of pixels at or below 5
+    pub dark_fraction: f32,
+    /// Fraction of pixels at or above 250
+    pub saturated_fraction: f32,
+}
+
+/// Time spent in each preprocessing stage, summed over cameras and frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreprocessStats {
+    /// Calls to `Preprocessor::process`
+    pub frames: u64,
+    /// Format conversion to mono
+    pub conversion: Duration,
+    /// Downscaling
+    pub downscale: Duration,
+    /// Health checks
+    pub health_check: Duration,
+    /// Wall-clock time of `process`, which is less than the stage sum when run in parallel
+    pub wall: Duration,
+}
+
+/// Converts each camera's frame to downscaled mono images, optionally one thread per camera
+///
+/// Output buffers handed back with `recycle` are reused for later frames, so steady-state
+/// processing does not allocate.
+pub struct Preprocessor {
+    downscale: u32,
+    parallel: bool,
+    pools: Vec<Vec<Vec<u8>>>,
+    stats: PreprocessStats,
+}
+
+/// Pixels and stage timings of one camera
+struct CameraOutput {
+    pixels: Result<(Vec<u8>, i32, i32), Status>,
+    health: CameraHealth,
+    conversion: Duration,
+    downscale: Duration,
+    health_check: Duration,
+}
+
+impl Preprocessor {
+    /// Create a preprocessor reducing resolution by `downscale` (at least 1) in each direction
+    pub fn new(downscale: u32, parallel: bool) -> Self {
+        Self {
+            downscale: downscale.max(1),
+            parallel,
+            pools: Vec::new(),
+            stats: PreprocessStats::default(),
+        }
+    }
+
+    /// Preprocess one frame per camera, returning the images and their health in input order
+    #[must_use = "the images or the reason they are invalid are only in the Result"]
+    pub fn process(&mut self, frames: &[RawFrame]) -> Result<Vec<(Image, CameraHealth)>, Status> {
+        profile_scope!("preprocess");
+        let start = Instant::now();
+        if self.pools.len() < frames.len() {
+            self.pools.resize_with(frames.len(), Vec::new);
+        }
+        let buffers: Vec<_> = self.pools.iter_mut().map(|pool| pool.pop()).collect();
+        let downscale = self.downscale;
+
+        let outputs: Vec<CameraOutput> = if self.parallel && frames.len() > 1 {
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = frames
+                    .iter()
+                    .zip(buffers)
+                    .map(|(frame, buffer)| scope.spawn(move || process_camera(frame, downscale, buffer)))
+                    .collect();
+                workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+            })
+        } else {
+            frames
+                .iter()
+                .zip(buffers)
+                .map(|(frame, buffer)| process_camera(frame, downscale, buffer))
+                .collect()
+        };
+
+        self.stats.frames += 1;
+        for output in &outputs {
+            self.stats.conversion += output.conversion;
+            self.stats.downscale += output.downscale;
+            self.stats.health_check += output.health_check;
+        }
+        self.stats.wall += start.elapsed();
+
+        frames
+            .iter()
+            .zip(outputs)
+            .map(|(frame, output)| {
+                let (pixels, width, height) = output.pixels?;
+                let image = Image::new(
+                    pixels,
+                    width,
+                    height,
+                    ImageEncoding::Mono8,
+                    frame.camera_index,
+                    frame.timestamp_ns,
+                )?;
+                Ok((image, output.health))
+            })
+            .collect()
+    }
+
+    /// Return images from `process` so their buffers are reused for the same cameras
+    pub fn recycle(&mut self, images: impl IntoIterator<Item = Image>) {
+        for (camera, image) in images.into_iter().enumerate() {
+            if let (Some(pool), Some(pixels)) = (self.pools.get_mut(camera), image.into_pixels()) {
+                pool.push(pixels);
+            }
+        }
+    }
+
+    /// Stage timings since the preprocessor was created
+    pub fn stats(&self) -> PreprocessStats {
+        self.stats
+    }
+}
+
+fn process_camera(frame: &RawFrame, downscale: u32, buffer: Option<Vec<u8>>) -> CameraOutput {
+    let mut output = CameraOutput {
+        pixels: Err(Status::InvalidArg),
+        health: CameraHealth::default(),
+        conversion: Duration::ZERO,
+        downscale: Duration::ZERO,
+        health_check: Duration::ZERO,
+    };
+    let bytes_per_pixel = match frame.format {
+        RawFormat::Mono8 | RawFormat::BayerRggb8 => 1,
+        RawFormat::Rgb8 => 3,
+        RawFormat::Yuyv => 2,
+    };
+    if frame.width <= 0
+        || frame.height <= 0
+        || (frame.pitch as i64) < frame.width as i64 * bytes_per_pixel
+        || (frame.pixels.len() as i64) < frame.pitch as i64 * (frame.height as i64 - 1) + frame.width as i64 * bytes_per_pixel
+    {
+        return output;
+    }
+    let (width, height) = (frame.width as usize, frame.height as usize);
+
+    let start = Instant::now();
+    let mono = to_mono(frame, width, height);
+    output.conversion = start.elapsed();
+
+    let start = Instant::now();
+    let factor = downscale as usize;
+    let (out_width, out_height) = (width / factor, height / factor);
+    let mut pixels = buffer.unwrap_or_default();
+    pixels.clear();
+    pixels.reserve(out_width * out_height);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let sum: u32 = mono[y * factor * width..][..factor * width]
+                .chunks(width)
+                .flat_map(|row| &row[x * factor..][..factor])
+                .map(|&p| p as u32)
+                .sum();
+            pixels.push((sum / (factor * factor) as u32) as u8);
+        }
+    }
+    output.downscale = start.elapsed();
+
+    let start = Instant::now();
+    output.health = health(&pixels);
+    output.health_check = start.elapsed();
+
+    output.pixels = Ok((pixels, out_width as i32, out_height as i32));
+    output
+}
+
+/// Tightly packed mono copy of `frame`
+fn to_mono(frame: &RawFrame, width: usize, height: usize) -> Vec<u8> {
+    let rows = frame.pixels.chunks(frame.pitch as usize).take(height);
+    match frame.format {
+        RawFormat::Mono8 => rows.flat_map(|row| &row[..width]).copied().collect(),
+        // Integer approximation of Rec. 601 luma
+        RawFormat::Rgb8 => rows
+            .flat_map(|row| row[..width * 3].chunks_exact(3))
+            .map(|rgb| ((77 * rgb[0] as u32 + 150 * rgb[1] as u32 + 29 * rgb[2] as u32) >> 8) as u8)
+            .collect(),
+        // Luma is every other byte, chroma is dropped
+        RawFormat::Yuyv => rows.flat_map(|row| row[..width * 2].iter().step_by(2)).copied().collect(),
+        // Each pixel takes the mean of the RGGB cell it belongs to
+        RawFormat::BayerRggb8 => {
+            let pixel = |x: usize, y: usize| frame.pixels[y * frame.pitch as usize + x] as u32;
+            let mut mono = Vec::with_capacity(width * height);
+            for y in 0..height {
+                let y0 = (y & !1).min(height.saturating_sub(2));
+                for x in 0..width {
+                    let x0 = (x & !1).min(width.saturating_sub(2));
+                    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+                    let sum = pixel(x0, y0) + pixel(x1, y0) + pixel(x0, y1) + pixel(x1, y1);
+                    mono.push((sum / 4) as u8);
+                }
+            }
+            mono
+        }
+    }
+}
+
+fn health(pixels: &[u8]) -> CameraHealth {
+    if pixels.is_empty() {
+        return CameraHealth::default();
+    }
+    let (mut sum, mut dark, mut saturated) = (0u64, 0usize, 0usize);
+    for &p in pixels {
+        sum += p as u64;
+        dark += (p <= 5) as usize;
+        saturated += (p >= 250) as usize;
+    }
+    let n = pixels.len() as f32;
+    CameraHealth {
+        mean_intensity: sum as f32 / n,
+        dark_fraction: dark as f32 / n,
+        saturated_fraction: saturated as f32 / n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random bytes
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        let formats = [RawFormat::BayerRggb8, RawFormat::Rgb8, RawFormat::Mono8, RawFormat::BayerRggb8];
+        let data: Vec<_> = formats
+            .iter()
+            .enumerate()
+            .map(|(i, format)| {
+                let pitch = if *format == RawFormat::Rgb8 { 64 * 3 } else { 70 };
+                noise(pitch * 48, i as u32)
+            })
+            .collect();
+        let frames: Vec<_> = formats
+            .iter()
+            .zip(&data)
+            .enumerate()
+            .map(|(i, (&format, pixels))| RawFrame {
+                pixels,
+                width: 64,
+                height: 48,
+                pitch: if format == RawFormat::Rgb8 { 64 * 3 } else { 70 },
+                format,
+                camera_index: i as i32,
+                timestamp_ns: 1000,
+            })
+            .collect();
+
+        let mut serial = Preprocessor::new(2, false);
+        let mut parallel = Preprocessor::new(2, true);
+        for _ in 0..3 {
+            let expected = serial.process(&frames).unwrap();
+            let actual = parallel.process(&frames).unwrap();
+            for ((a, health_a), (b, health_b)) in expected.iter().zip(&actual) {
+                assert_eq!(a.pixels(), b.pixels());
+                assert_eq!((a.width(), a.height(), a.camera_index()), (32, 24, b.camera_index()));
+                assert_eq!(health_a, health_b);
+            }
+            serial.recycle(expected.into_iter().map(|(image, _)| image));
+            parallel.recycle(actual.into_iter().map(|(image, _)| image));
+        }
+        assert_eq!(parallel.stats().frames, 3);
+    }
+
+    #[test]
+    fn test_conversion_and_health() {
+        // Uniform RGGB cells convert to their mean, then 2x2 boxes average
+        let bayer = [10, 20, 10, 20, 30, 40, 30, 40];
+        let frame = RawFrame {
+            pixels: &bayer,
+            width: 4,
+            height: 2,
+            pitch: 4,
+            format: RawFormat::BayerRggb8,
+            camera_index: 0,
+            timestamp_ns: 0,
+        };
+        let mut preprocessor = Preprocessor::new(1, false);
+        let (image, health) = preprocessor.process(&[frame]).unwrap().remove(0);
+        assert_eq!(image.pixels(), &[25; 8]);
+        assert_eq!(health.mean_intensity, 25.0);
+
+        let short = RawFrame { pixels: &bayer[..4], ..frame };
+        assert_eq!(preprocessor.process(&[short]).err(), Some(Status::InvalidArg));
+    }
+
+    #[test]
+    fn test_yuyv_to_mono() {
+        // Two rows of 3 pixels with a padded pitch, chroma bytes set to 128
+        let yuyv = [10, 128, 20, 128, 30, 128, 0, 0, 40, 128, 50, 128, 60, 128, 0, 0];
+        let frame = RawFrame {
+            pixels: &yuyv,
+            width: 3,
+            height: 2,
+            pitch: 8,
+            format: RawFormat::Yuyv,
+            camera_index: 1,
+            timestamp_ns: 5,
+        };
+        let mut preprocessor = Preprocessor::new(1, false);
+        let (image, _) = preprocessor.process(&[frame]).unwrap().remove(0);
+        assert_eq!(image.pixels(), &[10, 20, 30, 40, 50, 60]);
+        assert_eq!((image.encoding(), image.camera_index(), image.timestamp_ns()), (ImageEncoding::Mono8, 1, 5));
+
+        // The right half of the frame, as for a side-by-side camera
+        let right = RawFrame { pixels: &yuyv[2..], width: 2, ..frame };
+        assert_eq!(preprocessor.process(&[right]).unwrap()[0].0.pixels(), &[20, 30, 50, 60]);
+
+        let narrow = RawFrame { pitch: 5, ..frame };
+        assert_eq!(preprocessor.process(&[narrow]).err(), Some(Status::InvalidArg));
+        let short = RawFrame { pixels: &yuyv[..13], ..frame };
+        assert_eq!(preprocessor.process(&[short]).err(), Some(Status::InvalidArg));
+    }
+}
@@ -0,0 +1,187 @@
+//! Chrome trace-event recorder for the `profiling` feature
+//!
+//! Spans are recorded as fixed-size begin/end events in a ring buffer, so profiling a long run
+//! keeps only its most recent events and never allocates after start-up. Traces written by
+//! `Profiler::save` open in `chrome://tracing` and Perfetto.
+
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// Number of events kept by the global profiler
+const GLOBAL_CAPACITY: usize = 1 << 16;
+
+#[derive(Clone, Copy)]
+struct Event {
+    name: &'static str,
+    begin: bool,
+    timestamp_us: f64,
+    thread_id: u64,
+}
+
+struct Ring {
+    events: Vec<Event>,
+    // Index of the oldest event once the buffer has wrapped
+    next: usize,
+}
+
+/// Records timed spans and writes them as Chrome trace-event JSON
+pub struct Profiler {
+    start: Instant,
+    capacity: usize,
+    ring: Mutex<Ring>,
+}
+
+/// Ends its span when dropped
+pub struct SpanGuard<'a> {
+    profiler: &'a Profiler,
+    name: &'static str,
+}
+
+impl Profiler {
+    /// Create a profiler keeping the most recent `capacity` events (at least one)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            capacity: capacity.max(1),
+            ring: Mutex::new(Ring {
+                events: Vec::with_capacity(capacity.max(1)),
+                next: 0,
+            }),
+        }
+    }
+
+    /// The process-wide profiler used by `profile_scope!`
+    pub fn global() -> &'static Profiler {
+        static GLOBAL: OnceLock<Profiler> = OnceLock::new();
+        GLOBAL.get_or_init(|| Profiler::new(GLOBAL_CAPACITY))
+    }
+
+    /// Start a span on the current thread, ending when the guard is dropped
+    pub fn span(&self, name: &'static str) -> SpanGuard<'_> {
+        self.record(name, true);
+        SpanGuard { profiler: self, name }
+    }
+
+    /// Write the recorded events to `path` as Chrome trace-event JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_json(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Write the recorded events as Chrome trace-event JSON
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let ring = self.ring.lock().unwrap();
+        let (newer, older) = ring.events.split_at(ring.next);
+
+        write!(writer, "{{\"traceEvents\":[")?;
+        for (i, event) in older.iter().chain(newer).enumerate() {
+            if i > 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                "{{\"name\":\"{}\",\"ph\":\"{}\",\"ts\":{:.3},\"pid\":{},\"tid\":{}}}",
+                event.name.escape_default(),
+                if event.begin { "B" } else { "E" },
+                event.timestamp_us,
+                std::process::id(),
+                event.thread_id
+            )?;
+        }
+        write!(writer, "],\"displayTimeUnit\":\"ms\"}}")
+    }
+
+    fn record(&self, name: &'static str, begin: bool) {
+        let event = Event {
+            name,
+            begin,
+            timestamp_us: self.start.elapsed().as_secs_f64() * 1e6,
+            thread_id: thread_id(),
+        };
+        let mut ring = self.ring.lock().unwrap();
+        if ring.events.len() < self.capacity {
+            ring.events.push(event);
+        } else {
+            let next = ring.next;
+            ring.events[next] = event;
+            ring.next = (next + 1) % self.capacity;
+        }
+    }
+}
+
+impl Drop for SpanGuard<'_> {
+    fn drop(&mut self) {
+        self.profiler.record(self.name, false);
+    }
+}
+
+/// Small sequential id for the current thread
+fn thread_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: Cell<u64> = const { Cell::new(0) };
+    }
+    ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn trace_events(profiler: &Profiler) -> Vec<serde_json::Value> {
+        let mut json = Vec::new();
+        profiler.write_json(&mut json).unwrap();
+        let trace: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        trace["traceEvents"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn test_profiler_chrome_trace() {
+        let profiler = Profiler::new(64);
+        {
+            let _track = profiler.span("track");
+            let _convert = profiler.span("convert");
+        }
+        thread::scope(|scope| {
+            scope.spawn(|| drop(profiler.span("capture")));
+        });
+
+        let events = trace_events(&profiler);
+        let summary: Vec<_> = events
+            .iter()
+            .map(|e| (e["name"].as_str().unwrap(), e["ph"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            summary,
+            [("track", "B"), ("convert", "B"), ("convert", "E"), ("track", "E"), ("capture", "B"), ("capture", "E")]
+        );
+        assert_ne!(events[0]["tid"], events[4]["tid"]);
+        let timestamps: Vec<_> = events[..4].iter().map(|e| e["ts"].as_f64().unwrap()).collect();
+        assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_profiler_ring_keeps_latest() {
+        let profiler = Profiler::new(4);
+        for name in ["a", "b", "c"] {
+            drop(profiler.span(name));
+        }
+        let names: Vec<_> = trace_events(&profiler)
+            .iter()
+            .map(|e| e["name"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, ["b", "b", "c", "c"]);
+    }
+}
@@ -0,0 +1,258 @@
+//! Recreating the tracker after a GPU driver reset
+
+use crate::{Error, Status};
+use std::time::{Duration, Instant};
+
+/// Whether the supervised tracker is usable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryState {
+    /// The tracker exists and calls are succeeding
+    Healthy,
+    /// The tracker was destroyed after repeated device failures and has not been recreated yet
+    DeviceLost {
+        /// Recreation attempts made so far
+        attempts: u32,
+    },
+}
+
+/// Step taken by `DeviceRecovery`, passed to the event callback
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecoveryEvent {
+    /// Too many consecutive calls failed and the tracker was destroyed
+    DeviceLost { consecutive_failures: u32 },
+    /// Recreating the tracker is being attempted
+    RecreationStarted { attempt: u32 },
+    /// Recreation failed and will be retried after `retry_in`
+    RecreationFailed { attempt: u32, error: Error, retry_in: Duration },
+    /// A new tracker was created
+    Recovered { attempts: u32 },
+}
+
+/// Supervises a tracker, recreating it when the GPU stops responding
+///
+/// After a driver reset every cuVSLAM call fails with `Status::GenericError` until the tracker is
+/// recreated. Once `failure_threshold` consecutive calls reported through `report` fail that way,
+/// the tracker is dropped (destroying its handle) and `poll` calls the factory again with
+/// exponential backoff. With the `cuda` feature `Tracker::new` re-probes the device first, so a
+/// factory building a `Tracker` only succeeds once CUDA is usable again.
+///
+/// The factory must rebuild the tracker from scratch, e.g.
+/// `move || Tracker::new(make_rig(), &config)`, since a `CameraRig` is consumed by the tracker.
+pub struct DeviceRecovery<T> {
+    factory: Box<dyn FnMut() -> Result<T, Error>>,
+    tracker: Option<T>,
+    failure_threshold: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    auto_recover: bool,
+    consecutive_failures: u32,
+    attempts: u32,
+    next_attempt: Option<Instant>,
+    on_event: Option<Box<dyn FnMut(RecoveryEvent)>>,
+}
+
+impl<T> DeviceRecovery<T> {
+    /// Create the initial tracker with `factory`
+    ///
+    /// Defaults to declaring the device lost after 5 consecutive failures and retrying after 1s,
+    /// doubling up to 60s.
+    #[must_use = "dropping the Result discards the tracker or the reason it could not be created"]
+    pub fn new<F: FnMut() -> Result<T, Error> + 'static>(mut factory: F) -> Result<Self, Error> {
+        let tracker = factory()?;
+        Ok(Self {
+            factory: Box::new(factory),
+            tracker: Some(tracker),
+            failure_threshold: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            auto_recover: true,
+            consecutive_failures: 0,
+            attempts: 0,
+            next_attempt: None,
+            on_event: None,
+        })
+    }
+
+    /// Declare the device lost after `failures` consecutive failed calls (at least one)
+    pub fn with_failure_threshold(mut self, failures: u32) -> Self {
+        self.failure_threshold = failures.max(1);
+        self
+    }
+
+    /// Wait `initial` before the first recreation attempt, doubling after each failure up to `max`
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Whether `poll` recreates the tracker, on by default
+    ///
+    /// When disabled the supervisor stays in `RecoveryState::DeviceLost` once the device is lost.
+    pub fn with_auto_recovery(mut self, enabled: bool) -> Self {
+        self.auto_recover = enabled;
+        self
+    }
+
+    /// Call `on_event` with every step of detection and recovery
+    pub fn with_event_callback<F: FnMut(RecoveryEvent) + 'static>(mut self, on_event: F) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    /// The supervised tracker, or `None` while the device is lost
+    pub fn tracker(&self) -> Option<&T> {
+        self.tracker.as_ref()
+    }
+
+    /// Current state of the supervisor
+    pub fn state(&self) -> RecoveryState {
+        match self.tracker {
+            Some(_) => RecoveryState::Healthy,
+            None => RecoveryState::DeviceLost { attempts: self.attempts },
+        }
+    }
+
+    /// Record the outcome of a call made on the tracker
+    ///
+    /// Only `Status::GenericError` counts as a device failure; other statuses such as
+    /// `TrackingLost` show the device is still responding and reset the count.
+    pub fn report<R>(&mut self, now: Instant, result: &Result<R, Status>) {
+        if self.tracker.is_none() {
+            return;
+        }
+        if !matches!(result, Err(Status::GenericError)) {
+            self.consecutive_failures = 0;
+            return;
+        }
+
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= self.failure_threshold {
+            self.tracker = None;
+            self.attempts = 0;
+            self.next_attempt = Some(now + self.initial_backoff);
+            let consecutive_failures = std::mem::take(&mut self.consecutive_failures);
+            self.emit(RecoveryEvent::DeviceLost { consecutive_failures });
+        }
+    }
+
+    /// Attempt to recreate a lost tracker if auto-recovery is enabled and the backoff has passed
+    pub fn poll(&mut self, now: Instant) -> RecoveryState {
+        let due = self.next_attempt.is_some_and(|next| now >= next);
+        if self.tracker.is_some() || !self.auto_recover || !due {
+            return self.state();
+        }
+
+        self.attempts += 1;
+        let attempt = self.attempts;
+        self.emit(RecoveryEvent::RecreationStarted { attempt });
+        match (self.factory)() {
+            Ok(tracker) => {
+                self.tracker = Some(tracker);
+                self.next_attempt = None;
+                self.emit(RecoveryEvent::Recovered { attempts: attempt });
+            }
+            Err(error) => {
+                let retry_in = self
+                    .initial_backoff
+                    .saturating_mul(2u32.saturating_pow(attempt))
+                    .min(self.max_backoff);
+                self.next_attempt = Some(now + retry_in);
+                self.emit(RecoveryEvent::RecreationFailed { attempt, error, retry_in });
+            }
+        }
+        self.state()
+    }
+
+    fn emit(&mut self, event: RecoveryEvent) {
+        if let Some(on_event) = &mut self.on_event {
+            on_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    /// Supervisor over numbered stand-in trackers whose factory fails while `device_down` is set
+    fn supervisor(
+        device_down: Rc<Cell<bool>>,
+    ) -> (DeviceRecovery<u32>, Rc<RefCell<Vec<RecoveryEvent>>>) {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut created = 0;
+        let recovery = DeviceRecovery::new(move || {
+            if device_down.get() {
+                return Err(Error::Cuda("no CUDA devices found".to_string()));
+            }
+            created += 1;
+            Ok(created)
+        })
+        .unwrap()
+        .with_failure_threshold(3)
+        .with_backoff(Duration::from_secs(1), Duration::from_secs(3))
+        .with_event_callback({
+            let events = events.clone();
+            move |event| events.borrow_mut().push(event)
+        });
+        (recovery, events)
+    }
+
+    #[test]
+    fn test_recovery_after_device_reset() {
+        let device_down = Rc::new(Cell::new(false));
+        let (mut recovery, events) = supervisor(device_down.clone());
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let failed: Result<(), Status> = Err(Status::GenericError);
+
+        // Tracking failures and interleaved successes don't count towards the threshold
+        recovery.report(at(0), &failed);
+        recovery.report(at(0), &failed);
+        recovery.report(at(0), &Err::<(), _>(Status::TrackingLost));
+        recovery.report(at(0), &failed);
+        recovery.report(at(0), &failed);
+        assert_eq!(recovery.state(), RecoveryState::Healthy);
+
+        device_down.set(true);
+        recovery.report(at(0), &failed);
+        assert_eq!(recovery.state(), RecoveryState::DeviceLost { attempts: 0 });
+        assert!(recovery.tracker().is_none());
+
+        // Backoff of 1s, then 2s, capped at 3s
+        assert_eq!(recovery.poll(at(0)), RecoveryState::DeviceLost { attempts: 0 });
+        assert_eq!(recovery.poll(at(1)), RecoveryState::DeviceLost { attempts: 1 });
+        assert_eq!(recovery.poll(at(2)), RecoveryState::DeviceLost { attempts: 1 });
+        assert_eq!(recovery.poll(at(3)), RecoveryState::DeviceLost { attempts: 2 });
+        device_down.set(false);
+        assert_eq!(recovery.poll(at(5)), RecoveryState::DeviceLost { attempts: 2 });
+        assert_eq!(recovery.poll(at(6)), RecoveryState::Healthy);
+        assert_eq!(recovery.tracker(), Some(&2));
+
+        let cuda_error = || Error::Cuda("no CUDA devices found".to_string());
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                RecoveryEvent::DeviceLost { consecutive_failures: 3 },
+                RecoveryEvent::RecreationStarted { attempt: 1 },
+                RecoveryEvent::RecreationFailed { attempt: 1, error: cuda_error(), retry_in: Duration::from_secs(2) },
+                RecoveryEvent::RecreationStarted { attempt: 2 },
+                RecoveryEvent::RecreationFailed { attempt: 2, error: cuda_error(), retry_in: Duration::from_secs(3) },
+                RecoveryEvent::RecreationStarted { attempt: 3 },
+                RecoveryEvent::Recovered { attempts: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_auto_recovery_disabled() {
+        let (recovery, events) = supervisor(Rc::new(Cell::new(false)));
+        let mut recovery = recovery.with_auto_recovery(false).with_failure_threshold(1);
+        let now = Instant::now();
+        recovery.report(now, &Err::<(), _>(Status::GenericError));
+        assert_eq!(recovery.poll(now + Duration::from_secs(60)), RecoveryState::DeviceLost { attempts: 0 });
+        assert_eq!(events.borrow().len(), 1);
+    }
+}
@@ -0,0 +1,470 @@
+//! ROS message decoding (`rosbag1` and `rosbag2` features)
+//!
+//! rosbag2 stores messages in their CDR serialization: a 4-byte encapsulation header followed by
+//! the fields, each aligned to its own size relative to the end of the header. ROS 1 bags store the
+//! ROS 1 serialization, with the same fields little-endian and unaligned, no encapsulation header
+//! and a sequence number leading every `Header`. Only the fields the tracker needs are kept.
+
+use crate::image_io::convert_pixels;
+#[cfg(feature = "rosbag2")]
+use crate::CUVSLAM_ImuMeasurement;
+use crate::{Calibration, CameraCalibration, DistortionModel, Error, Extrinsics, Image};
+
+/// `sensor_msgs/msg/CameraInfo`, the calibration ROS drivers publish next to each image topic
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraInfo {
+    pub width: u32,
+    pub height: u32,
+    /// `plumb_bob`, `rational_polynomial` or `equidistant`
+    pub distortion_model: String,
+    /// Distortion coefficients in ROS's order, `k1, k2, p1, p2, k3, ...` for `plumb_bob`
+    pub d: Vec<f64>,
+    /// Row-major intrinsic matrix of the raw image
+    pub k: [f64; 9],
+    /// Row-major rotation from the camera frame to the rectified stereo frame
+    pub r: [f64; 9],
+    /// Row-major projection matrix of the rectified image, holding the stereo baseline
+    pub p: [f64; 12],
+}
+
+impl CameraInfo {
+    /// Decode a CDR-serialized `CameraInfo` message
+    #[must_use = "the message or the reason it could not be decoded is only in the Result"]
+    pub fn from_cdr(message: &[u8]) -> Result<Self, Error> {
+        Self::read(MessageReader::new(message, Serialization::Cdr)?)
+    }
+
+    /// Decode a ROS 1 `sensor_msgs/CameraInfo` message, as stored in ROS 1 bags
+    #[must_use = "the message or the reason it could not be decoded is only in the Result"]
+    pub fn from_ros1(message: &[u8]) -> Result<Self, Error> {
+        Self::read(MessageReader::new(message, Serialization::Ros1)?)
+    }
+
+    fn read(mut reader: MessageReader) -> Result<Self, Error> {
+        reader.header()?;
+        let height = reader.u32()?;
+        let width = reader.u32()?;
+        let distortion_model = reader.string()?;
+        let count = reader.u32()? as usize;
+        let d = (0..count).map(|_| reader.f64()).collect::<Result<_, _>>()?;
+        Ok(Self {
+            width,
+            height,
+            distortion_model,
+            d,
+            k: reader.f64_array()?,
+            r: reader.f64_array()?,
+            p: reader.f64_array()?,
+        })
+    }
+
+    /// Intrinsics and distortion of the camera, with identity extrinsics
+    ///
+    /// `plumb_bob` becomes `Brown5k` (or `Pinhole` when all coefficients are zero), `equidistant`
+    /// becomes `Fisheye4`. `rational_polynomial` is accepted as long as its rational terms are
+    /// zero, since cuVSLAM has no model for them.
+    #[must_use = "the calibration or the reason it could not be converted is only in the Result"]
+    pub fn to_calibration(&self) -> Result<CameraCalibration, Error> {
+        let d = |index: usize| self.d.get(index).copied().unwrap_or(0.0) as f32;
+        let (model, distortion) = match self.distortion_model.as_str() {
+            _ if self.d.iter().all(|&value| value == 0.0) => (DistortionModel::Pinhole, Vec::new()),
+            "plumb_bob" | "rational_polynomial" if self.d.iter().skip(5).all(|&value| value == 0.0) => {
+                (DistortionModel::Brown5k, vec![d(0), d(1), d(4), d(2), d(3)])
+            }
+            "equidistant" => (DistortionModel::Fisheye4, vec![d(0), d(1), d(2), d(3)]),
+            model => {
+                return Err(Error::Calibration(format!(
+                    "unsupported camera_info distortion model {} with coefficients {:?}",
+                    model, self.d
+                )))
+            }
+        };
+        Ok(CameraCalibration {
+            width: self.width as i32,
+            height: self.height as i32,
+            model,
+            cx: self.k[2] as f32,
+            cy: self.k[5] as f32,
+            fx: self.k[0] as f32,
+            fy: self.k[4] as f32,
+            distortion,
+            extrinsics: Extrinsics::default(),
+        })
+    }
+}
+
+impl Calibration {
+    /// Build a rig calibration from the `camera_info` of each camera, in camera index order
+    ///
+    /// Camera 0 defines the rig frame. The other cameras are placed from the stereo rectification:
+    /// their `r` rotates them into a frame shared with camera 0, and the translation term of `p`
+    /// (`-fx * baseline`) gives their offset in it.
+    #[must_use = "the calibration or the reason it could not be converted is only in the Result"]
+    pub fn from_ros_camera_info(infos: &[CameraInfo]) -> Result<Self, Error> {
+        let first = infos.first().ok_or_else(|| Error::Calibration("no camera_info".to_string()))?;
+        let cameras = infos
+            .iter()
+            .enumerate()
+            .map(|(index, info)| {
+                let mut camera = info.to_calibration()?;
+                if index > 0 {
+                    camera.extrinsics = stereo_extrinsics(first, info)?;
+                }
+                Ok(camera)
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { cameras })
+    }
+}
+
+/// Pose of `other` in the frame of `reference`, from their rectification and projection matrices
+fn stereo_extrinsics(reference: &CameraInfo, other: &CameraInfo) -> Result<Extrinsics, Error> {
+    if other.p[0] == 0.0 || other.p[5] == 0.0 {
+        return Err(Error::Calibration("camera_info has no projection matrix".to_string()));
+    }
+    // Position in the shared rectified frame, then rotated back into the reference camera
+    let rectified = [-other.p[3] / other.p[0], -other.p[7] / other.p[5], 0.0];
+    let (r0, r1) = (&reference.r, &other.r);
+    Ok(Extrinsics {
+        rotation: std::array::from_fn(|row| {
+            std::array::from_fn(|col| (0..3).map(|i| r0[i * 3 + row] * r1[i * 3 + col]).sum::<f64>() as f32)
+        }),
+        translation: std::array::from_fn(|row| (0..3).map(|i| r0[i * 3 + row] * rectified[i]).sum::<f64>() as f32),
+    })
+}
+
+/// `camera_info` topic published alongside `image_topic` by `image_transport`
+pub(crate) fn info_topic(image_topic: &str) -> String {
+    let topic = image_topic.strip_suffix("/compressed").unwrap_or(image_topic);
+    match topic.rsplit_once('/') {
+        Some((namespace, _)) => format!("{}/camera_info", namespace),
+        None => "camera_info".to_string(),
+    }
+}
+
+/// Name the topic a message came from in its decoding error
+pub(crate) fn on_topic(topic: &str, error: Error) -> Error {
+    match error {
+        Error::Dataset(reason) => Error::Dataset(format!("{}: {}", topic, reason)),
+        error => error,
+    }
+}
+
+/// Wire format of a serialized message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Serialization {
+    /// ROS 2's CDR, as stored by rosbag2
+    Cdr,
+    /// ROS 1's own serialization, as stored in ROS 1 bags
+    Ros1,
+}
+
+/// Header stamp of a message that starts with a `std_msgs/msg/Header`, in nanoseconds
+pub(crate) fn header_stamp_ns(message: &[u8], serialization: Serialization) -> Result<i64, Error> {
+    MessageReader::new(message, serialization)?.header()
+}
+
+/// Decode a `sensor_msgs/msg/Image` as a tracker input stamped with `timestamp_ns`
+pub(crate) fn decode_image(
+    message: &[u8],
+    serialization: Serialization,
+    camera_index: i32,
+    timestamp_ns: i64,
+) -> Result<Image, Error> {
+    let mut reader = MessageReader::new(message, serialization)?;
+    reader.header()?;
+    let height = reader.u32()? as usize;
+    let width = reader.u32()? as usize;
+    let encoding = reader.string()?;
+    let big_endian = reader.u8()? != 0;
+    let step = reader.u32()? as usize;
+    let data = reader.bytes()?;
+    let (pixels, image_encoding) =
+        convert_pixels(data, &encoding, width, height, step, big_endian).map_err(Error::Dataset)?;
+    Image::new(pixels, width as i32, height as i32, image_encoding, camera_index, timestamp_ns).map_err(Error::Status)
+}
+
+/// Decode a `sensor_msgs/msg/CompressedImage` (PNG or JPEG) stamped with `timestamp_ns`
+pub(crate) fn decode_compressed_image(
+    message: &[u8],
+    serialization: Serialization,
+    camera_index: i32,
+    timestamp_ns: i64,
+) -> Result<Image, Error> {
+    let mut reader = MessageReader::new(message, serialization)?;
+    reader.header()?;
+    let format = reader.string()?;
+    Image::decode(reader.bytes()?, camera_index, timestamp_ns)
+        .map_err(|error| Error::Dataset(format!("{} image: {}", format, error)))
+}
+
+/// Decode a CDR-serialized `sensor_msgs/msg/Imu`, stamped with its header
+#[cfg(feature = "rosbag2")]
+pub(crate) fn decode_imu(message: &[u8]) -> Result<CUVSLAM_ImuMeasurement, Error> {
+    let mut reader = MessageReader::new(message, Serialization::Cdr)?;
+    let timestamp_ns = reader.header()?;
+    // Orientation and its covariance
+    reader.f64_array::<4>()?;
+    reader.f64_array::<9>()?;
+    let angular_velocity: [f64; 3] = reader.f64_array()?;
+    reader.f64_array::<9>()?;
+    let linear_acceleration: [f64; 3] = reader.f64_array()?;
+    Ok(CUVSLAM_ImuMeasurement {
+        timestamp_ns,
+        linear_accelerations: linear_acceleration.map(|value| value as f32),
+        angular_velocities: angular_velocity.map(|value| value as f32),
+    })
+}
+
+/// Cursor over the body of a serialized message
+struct MessageReader<'a> {
+    body: &'a [u8],
+    position: usize,
+    little_endian: bool,
+    serialization: Serialization,
+}
+
+impl<'a> MessageReader<'a> {
+    fn new(message: &'a [u8], serialization: Serialization) -> Result<Self, Error> {
+        if serialization == Serialization::Ros1 {
+            return Ok(Self { body: message, position: 0, little_endian: true, serialization });
+        }
+        let little_endian = match message.get(..2) {
+            Some([0, 0]) => false,
+            Some([0, 1]) => true,
+            Some(kind) => return Err(Error::Dataset(format!("unsupported CDR encapsulation {:?}", kind))),
+            None => return Err(Error::Dataset("empty CDR message".to_string())),
+        };
+        Ok(Self { body: &message[4.min(message.len())..], position: 0, little_endian, serialization })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .body
+            .get(self.position..self.position.saturating_add(len))
+            .ok_or_else(|| Error::Dataset("truncated message".to_string()))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    /// The next `N` bytes of a primitive, aligned to its size in CDR
+    fn aligned<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        if self.serialization == Serialization::Cdr {
+            self.position = self.position.next_multiple_of(N);
+        }
+        let mut bytes: [u8; N] = self.take(N)?.try_into().unwrap();
+        if !self.little_endian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        self.aligned().map(u32::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Result<f64, Error> {
+        self.aligned().map(f64::from_le_bytes)
+    }
+
+    fn f64_array<const N: usize>(&mut self) -> Result<[f64; N], Error> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = self.f64()?;
+        }
+        Ok(values)
+    }
+
+    /// A `sequence<uint8>`
+    fn bytes(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    /// A string, whose length counts its null terminator in CDR
+    fn string(&mut self) -> Result<String, Error> {
+        let bytes = self.bytes()?;
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::Dataset("message string is not UTF-8".to_string()))
+    }
+
+    /// A `std_msgs/msg/Header`, returning its stamp in nanoseconds
+    fn header(&mut self) -> Result<i64, Error> {
+        if self.serialization == Serialization::Ros1 {
+            // Sequence number, dropped in ROS 2
+            self.u32()?;
+        }
+        let sec = self.u32()? as i32;
+        let nanosec = self.u32()?;
+        self.string()?;
+        Ok(i64::from(sec) * 1_000_000_000 + i64::from(nanosec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageEncoding;
+
+    /// Little-endian CDR writer for the messages above
+    #[derive(Default)]
+    struct CdrWriter(Vec<u8>);
+
+    impl CdrWriter {
+        fn align(&mut self, n: usize) {
+            self.0.resize(4 + (self.0.len() - 4).next_multiple_of(n), 0);
+        }
+
+        fn u32(&mut self, value: u32) -> &mut Self {
+            self.align(4);
+            self.0.extend(value.to_le_bytes());
+            self
+        }
+
+        fn f64s(&mut self, values: &[f64]) -> &mut Self {
+            for value in values {
+                self.align(8);
+                self.0.extend(value.to_le_bytes());
+            }
+            self
+        }
+
+        fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+            self.u32(bytes.len() as u32);
+            self.0.extend(bytes);
+            self
+        }
+
+        fn string(&mut self, text: &str) -> &mut Self {
+            self.bytes(&[text.as_bytes(), &[0]].concat())
+        }
+
+        fn header(stamp_ns: i64) -> Self {
+            let mut writer = CdrWriter(vec![0, 1, 0, 0]);
+            writer.u32((stamp_ns / 1_000_000_000) as u32).u32((stamp_ns % 1_000_000_000) as u32).string("cam");
+            writer
+        }
+    }
+
+    fn camera_info(d: &[f64], r: [f64; 9], tx: f64) -> Vec<u8> {
+        let mut writer = CdrWriter::header(0);
+        writer.u32(480).u32(640).string("plumb_bob").u32(d.len() as u32).f64s(d);
+        writer.f64s(&[400.0, 0.0, 320.0, 0.0, 410.0, 240.0, 0.0, 0.0, 1.0]).f64s(&r);
+        writer.f64s(&[400.0, 0.0, 320.0, tx, 0.0, 400.0, 240.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        writer.u32(0).u32(0).u32(0).u32(0).u32(0).u32(0);
+        writer.0.push(0);
+        writer.0
+    }
+
+    #[test]
+    fn test_info_topic() {
+        assert_eq!(info_topic("/stereo/left/image_raw"), "/stereo/left/camera_info");
+        assert_eq!(info_topic("/stereo/left/image_raw/compressed"), "/stereo/left/camera_info");
+        assert_eq!(info_topic("image"), "camera_info");
+    }
+
+    #[test]
+    fn test_camera_info_to_calibration() {
+        let identity = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        // Right camera rotated 90 degrees about y relative to the rectified frame
+        let rotated = [0.0, 0.0, 1.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0];
+        let left = CameraInfo::from_cdr(&camera_info(&[0.1, -0.2, 0.001, 0.002, 0.05], identity, 0.0)).unwrap();
+        let right = CameraInfo::from_cdr(&camera_info(&[0.0; 5], rotated, -400.0 * 0.12)).unwrap();
+        assert_eq!((left.width, left.height, left.distortion_model.as_str()), (640, 480, "plumb_bob"));
+
+        let calibration = Calibration::from_ros_camera_info(&[left, right]).unwrap();
+        let [left, right] = &calibration.cameras[..] else { panic!("expected two cameras") };
+        assert_eq!(
+            (left.model, left.fx, left.fy, left.cx, left.cy),
+            (DistortionModel::Brown5k, 400.0, 410.0, 320.0, 240.0)
+        );
+        assert_eq!(left.distortion, [0.1, -0.2, 0.05, 0.001, 0.002]);
+        assert_eq!(left.extrinsics, Extrinsics::default());
+        assert_eq!(right.model, DistortionModel::Pinhole);
+        assert_eq!(right.extrinsics.rotation, [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]]);
+        assert!((right.extrinsics.translation[0] - 0.12).abs() < 1e-6);
+        assert_eq!(right.extrinsics.translation[1..], [0.0, 0.0]);
+
+        let mut fisheye = CameraInfo::from_cdr(&camera_info(&[0.1, 0.2, 0.3, 0.4], identity, 0.0)).unwrap();
+        fisheye.distortion_model = "equidistant".to_string();
+        assert_eq!(fisheye.to_calibration().unwrap().model, DistortionModel::Fisheye4);
+        fisheye.distortion_model = "unknown".to_string();
+        assert!(matches!(fisheye.to_calibration(), Err(Error::Calibration(_))));
+        assert!(CameraInfo::from_cdr(&camera_info(&[0.1], identity, 0.0)[..40]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "rosbag2")]
+    fn test_decode_messages() {
+        let stamp_ns = 12_000_000_345;
+        let mut image = CdrWriter::header(stamp_ns);
+        // 2x2 bgr8 image with rows padded to 8 bytes
+        image.u32(2).u32(2).string("bgr8");
+        image.0.push(0);
+        image.u32(8).bytes(&[1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0]);
+        assert_eq!(header_stamp_ns(&image.0, Serialization::Cdr).unwrap(), stamp_ns);
+        let decoded = decode_image(&image.0, Serialization::Cdr, 1, stamp_ns).unwrap();
+        assert_eq!((decoded.width(), decoded.height(), decoded.encoding()), (2, 2, ImageEncoding::Rgb8));
+        assert_eq!((decoded.camera_index(), decoded.timestamp_ns()), (1, stamp_ns));
+        assert_eq!(decoded.pixels(), [3, 2, 1, 6, 5, 4, 9, 8, 7, 12, 11, 10]);
+
+        let png = Image::new(vec![7; 6], 3, 2, ImageEncoding::Mono8, 0, 0).unwrap().to_png_bytes().unwrap();
+        let mut compressed = CdrWriter::header(stamp_ns);
+        compressed.string("png").bytes(&png);
+        let decoded = decode_compressed_image(&compressed.0, Serialization::Cdr, 0, stamp_ns).unwrap();
+        assert_eq!((decoded.width(), decoded.timestamp_ns(), decoded.pixels()), (3, stamp_ns, &[7; 6][..]));
+
+        let mut imu = CdrWriter::header(stamp_ns);
+        imu.f64s(&[0.0, 0.0, 0.0, 1.0]).f64s(&[0.0; 9]).f64s(&[0.1, 0.2, 0.3]).f64s(&[0.0; 9]).f64s(&[0.0, 0.0, 9.8]);
+        imu.f64s(&[0.0; 9]);
+        let measurement = decode_imu(&imu.0).unwrap();
+        assert_eq!(measurement.timestamp_ns, stamp_ns);
+        assert_eq!(measurement.angular_velocities, [0.1, 0.2, 0.3]);
+        assert_eq!(measurement.linear_accelerations, [0.0, 0.0, 9.8]);
+
+        assert!(matches!(decode_image(&[0, 1, 0, 0, 1], Serialization::Cdr, 0, 0), Err(Error::Dataset(_))));
+        assert!(matches!(MessageReader::new(&[0, 2, 0, 0], Serialization::Cdr), Err(Error::Dataset(_))));
+    }
+
+    /// ROS 1 serialization of a `Header`: sequence number, stamp and frame id, unaligned
+    fn ros1_header(stamp_ns: i64) -> Vec<u8> {
+        let mut message = Vec::new();
+        for value in [7, (stamp_ns / 1_000_000_000) as u32, (stamp_ns % 1_000_000_000) as u32, 3] {
+            message.extend(u32::to_le_bytes(value));
+        }
+        message.extend(b"cam");
+        message
+    }
+
+    #[test]
+    fn test_decode_ros1_messages() {
+        let stamp_ns = 12_000_000_345;
+        let mut image = ros1_header(stamp_ns);
+        // 3x1 mono8 image, where CDR would pad the encoding string and the byte before step
+        image.extend([1, 0, 0, 0, 3, 0, 0, 0, 5, 0, 0, 0]);
+        image.extend(b"mono8");
+        image.push(0);
+        image.extend([3, 0, 0, 0, 3, 0, 0, 0, 10, 20, 30]);
+        assert_eq!(header_stamp_ns(&image, Serialization::Ros1).unwrap(), stamp_ns);
+        let decoded = decode_image(&image, Serialization::Ros1, 1, stamp_ns).unwrap();
+        assert_eq!((decoded.width(), decoded.height(), decoded.pixels()), (3, 1, &[10, 20, 30][..]));
+
+        let mut info = ros1_header(0);
+        info.extend([2, 0, 0, 0, 4, 0, 0, 0, 9, 0, 0, 0]);
+        info.extend(b"plumb_bob");
+        info.extend([0, 0, 0, 0]);
+        let k = [400.0, 0.0, 2.0, 0.0, 410.0, 1.0, 0.0, 0.0, 1.0];
+        let identity = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let p = [400.0, 0.0, 2.0, -48.0, 0.0, 400.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        for value in k.iter().chain(&identity).chain(&p) {
+            info.extend(f64::to_le_bytes(*value));
+        }
+        let info = CameraInfo::from_ros1(&info).unwrap();
+        assert_eq!((info.width, info.height, info.distortion_model.as_str()), (4, 2, "plumb_bob"));
+        assert_eq!((info.k, info.p[3]), (k, -48.0));
+    }
+}
@@ -0,0 +1,357 @@
+//! ROS 1 bag files (`rosbag1` feature)
+//!
+//! Bags in format 2.0 are a sequence of records, each a header of `name=value` fields and a
+//! data block. Messages are grouped in chunks, uncompressed or LZ4 compressed, and an index at
+//! the end of the file lists the connections, which map the connection ids of messages to topics
+//! and message types. Only the records needed to play image topics are interpreted.
+
+use crate::frame_source::StampMatcher;
+use crate::ros::{decode_compressed_image, decode_image, header_stamp_ns, info_topic, on_topic, Serialization};
+use crate::{Calibration, CameraInfo, CameraRig, Error, FrameSet, FrameSource, Image};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8] = b"#ROSBAG V2.0\n";
+
+const OP_MESSAGE_DATA: u8 = 0x02;
+const OP_BAG_HEADER: u8 = 0x03;
+const OP_CHUNK: u8 = 0x05;
+const OP_CONNECTION: u8 = 0x07;
+
+const IMAGE_TYPE: &str = "sensor_msgs/Image";
+const COMPRESSED_IMAGE_TYPE: &str = "sensor_msgs/CompressedImage";
+const CAMERA_INFO_TYPE: &str = "sensor_msgs/CameraInfo";
+
+/// Left and right images of one stereo frame, matched by header stamp
+pub struct StereoImagePair {
+    /// Header stamp of the left image
+    pub timestamp_ns: i64,
+    /// Camera 0 image
+    pub left: Image,
+    /// Camera 1 image
+    pub right: Image,
+}
+
+/// Reader of a stereo pair of image topics in a ROS 1 bag
+///
+/// Iterating yields one `StereoImagePair` per matched frame; it is also a `FrameSource` for
+/// `Tracker::track_source`. Topics may hold `sensor_msgs/Image` or `CompressedImage` messages.
+/// Images are paired by header stamp and each frame is stamped with the left image; unpaired
+/// images are skipped. Chunks may be uncompressed or LZ4 compressed; bz2 bags need
+/// `rosbag decompress` first, and bags left unindexed by an interrupted recording
+/// `rosbag reindex`.
+///
+/// The rig is built with `Calibration::from_ros_camera_info` from the first message on each
+/// image topic's `camera_info` sibling, e.g. `/stereo/left/camera_info` for
+/// `/stereo/left/image_raw`. Messages are streamed from disk one chunk at a time.
+pub struct RosBagReader {
+    bag: BagFile,
+    /// Camera index of each connection that publishes one of the image topics
+    cameras: HashMap<u32, usize>,
+    image_topics: [String; 2],
+    compressed: [bool; 2],
+    /// Undecoded messages of each camera, waiting for a match
+    matcher: StampMatcher<Vec<u8>>,
+    calibration: Option<Calibration>,
+}
+
+impl RosBagReader {
+    /// Open the bag at `path` to play `image_topics` as cameras 0 and 1
+    ///
+    /// Images whose header stamps differ by more than `max_skew_ns` are not paired.
+    #[must_use = "the reader or the reason it could not be opened is only in the Result"]
+    pub fn open<P: AsRef<Path>>(path: P, image_topics: [&str; 2], max_skew_ns: i64) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bag = BagFile::open(path)?;
+        let topic_types: HashMap<&str, &str> =
+            bag.connections.values().map(|(topic, kind)| (topic.as_str(), kind.as_str())).collect();
+
+        let mut compressed = [false; 2];
+        for (topic, is_compressed) in image_topics.iter().zip(&mut compressed) {
+            *is_compressed = match topic_types.get(topic) {
+                Some(&IMAGE_TYPE) => false,
+                Some(&COMPRESSED_IMAGE_TYPE) => true,
+                Some(other) => return Err(Error::Dataset(format!("{}: {} is not an image topic", topic, other))),
+                None => return Err(Error::Dataset(format!("{}: no topic {}", path.display(), topic))),
+            };
+        }
+
+        let info_topics = image_topics.map(info_topic);
+        let has_info = info_topics.iter().all(|topic| topic_types.get(topic.as_str()) == Some(&CAMERA_INFO_TYPE));
+        let calibration = if has_info {
+            let [left, right] = first_messages(path, &bag.connections_on(&info_topics))?;
+            match (left, right) {
+                (Some(left), Some(right)) => Some(Calibration::from_ros_camera_info(&[
+                    CameraInfo::from_ros1(&left).map_err(|error| on_topic(&info_topics[0], error))?,
+                    CameraInfo::from_ros1(&right).map_err(|error| on_topic(&info_topics[1], error))?,
+                ])?),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        if calibration.is_none() {
+            log::warn!("{}: no camera_info for {:?}, the reader has no calibration", path.display(), image_topics);
+        }
+
+        Ok(Self {
+            cameras: bag.connections_on(&image_topics),
+            image_topics: image_topics.map(str::to_string),
+            compressed,
+            matcher: StampMatcher::new(2, max_skew_ns),
+            calibration,
+            bag,
+        })
+    }
+
+    /// Calibration read from the `camera_info` topics, if the bag has them
+    pub fn camera_calibration(&self) -> Option<&Calibration> {
+        self.calibration.as_ref()
+    }
+
+    /// Decode a matched pair of image messages
+    fn decode_pair(&self, timestamp_ns: i64, messages: Vec<Vec<u8>>) -> Result<FrameSet, Error> {
+        let images = messages
+            .iter()
+            .enumerate()
+            .map(|(camera, message)| {
+                let decode = if self.compressed[camera] { decode_compressed_image } else { decode_image };
+                decode(message, Serialization::Ros1, camera as i32, timestamp_ns)
+                    .map_err(|error| on_topic(&self.image_topics[camera], error))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(FrameSet { timestamp_ns, images })
+    }
+}
+
+impl FrameSource for RosBagReader {
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>> {
+        loop {
+            if let Some((timestamp_ns, messages)) = self.matcher.pop() {
+                return Some(self.decode_pair(timestamp_ns, messages));
+            }
+
+            let (connection, message) = match self.bag.next_message() {
+                Ok(Some(message)) => message,
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error)),
+            };
+            let Some(&camera) = self.cameras.get(&connection) else {
+                continue;
+            };
+            match header_stamp_ns(&message, Serialization::Ros1) {
+                Ok(stamp_ns) => self.matcher.push(camera, stamp_ns, message),
+                Err(error) => return Some(Err(on_topic(&self.image_topics[camera], error))),
+            }
+        }
+    }
+
+    fn calibration(&self) -> Option<CameraRig> {
+        self.calibration.as_ref()?.to_rig().ok()
+    }
+}
+
+impl Iterator for RosBagReader {
+    type Item = Result<StereoImagePair, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.next_frame()?;
+        Some(frame.map(|frame| {
+            let [left, right] = <[Image; 2]>::try_from(frame.images).unwrap_or_else(|_| unreachable!());
+            StereoImagePair { timestamp_ns: frame.timestamp_ns, left, right }
+        }))
+    }
+}
+
+/// First message on each of the two sets of connections, reading the bag from the start
+fn first_messages(path: &Path, connections: &HashMap<u32, usize>) -> Result<[Option<Vec<u8>>; 2], Error> {
+    let mut bag = BagFile::open(path)?;
+    let mut found = [None, None];
+    while found.iter().any(Option::is_none) {
+        let Some((connection, message)) = bag.next_message()? else { break };
+        if let Some(&index) = connections.get(&connection) {
+            found[index].get_or_insert(message);
+        }
+    }
+    Ok(found)
+}
+
+/// A record's header fields and data
+struct Record {
+    fields: HashMap<String, Vec<u8>>,
+    data: Vec<u8>,
+}
+
+impl Record {
+    /// Read the record at the reader's position, or `None` at the end of the input
+    fn read(reader: &mut impl Read) -> std::io::Result<Option<Self>> {
+        let mut len = [0; 4];
+        match reader.read_exact(&mut len) {
+            Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let header = read_block(reader, u32::from_le_bytes(len))?;
+        reader.read_exact(&mut len)?;
+        let data = read_block(reader, u32::from_le_bytes(len))?;
+        Ok(Some(Self { fields: parse_fields(&header)?, data }))
+    }
+
+    fn op(&self) -> Option<u8> {
+        self.fields.get("op")?.first().copied()
+    }
+
+    fn field(&self, name: &str) -> std::io::Result<&[u8]> {
+        self.fields.get(name).map(Vec::as_slice).ok_or_else(|| invalid_data(format!("record has no {} field", name)))
+    }
+
+    fn u32(&self, name: &str) -> std::io::Result<u32> {
+        let bytes = self.field(name)?.try_into().map_err(|_| invalid_data(format!("{} is not 4 bytes", name)))?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&self, name: &str) -> std::io::Result<u64> {
+        let bytes = self.field(name)?.try_into().map_err(|_| invalid_data(format!("{} is not 8 bytes", name)))?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+fn read_block(reader: &mut impl Read, len: u32) -> std::io::Result<Vec<u8>> {
+    let mut block = Vec::new();
+    reader.take(len as u64).read_to_end(&mut block)?;
+    if block.len() != len as usize {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(block)
+}
+
+/// Split a record header or connection header into its `name=value` fields
+fn parse_fields(mut bytes: &[u8]) -> std::io::Result<HashMap<String, Vec<u8>>> {
+    let mut fields = HashMap::new();
+    while !bytes.is_empty() {
+        let len = bytes.get(..4).ok_or_else(|| invalid_data("truncated record header".to_string()))?;
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        let field = bytes.get(4..4 + len).ok_or_else(|| invalid_data("truncated record header".to_string()))?;
+        let separator = field.iter().position(|&byte| byte == b'=');
+        let separator = separator.ok_or_else(|| invalid_data("record header field has no '='".to_string()))?;
+        fields.insert(String::from_utf8_lossy(&field[..separator]).into_owned(), field[separator + 1..].to_vec());
+        bytes = &bytes[4 + len..];
+    }
+    Ok(fields)
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Message records of a bag file in the order they were written
+struct BagFile {
+    path: PathBuf,
+    reader: BufReader<File>,
+    /// Topic and message type of each connection id
+    connections: HashMap<u32, (String, String)>,
+    /// Where the index starts, which holds no messages
+    index_position: u64,
+    /// Decompressed records of the current chunk and the position in them
+    chunk: Vec<u8>,
+    chunk_position: usize,
+}
+
+impl BagFile {
+    fn open(path: &Path) -> Result<Self, Error> {
+        let mut bag = Self {
+            path: path.to_path_buf(),
+            reader: BufReader::new(File::open(path).map_err(|error| dataset_error(path, error))?),
+            connections: HashMap::new(),
+            index_position: 0,
+            chunk: Vec::new(),
+            chunk_position: 0,
+        };
+        bag.read_index().map_err(|error| dataset_error(path, error))?;
+        Ok(bag)
+    }
+
+    /// Check the version, then read the connections from the index and return to the first chunk
+    fn read_index(&mut self) -> std::io::Result<()> {
+        let mut magic = [0; MAGIC.len()];
+        self.reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not a ROS 1 bag in format 2.0".to_string()));
+        }
+        let header = Record::read(&mut self.reader)?.filter(|record| record.op() == Some(OP_BAG_HEADER));
+        let header = header.ok_or_else(|| invalid_data("bag header record is missing".to_string()))?;
+        self.index_position = header.u64("index_pos")?;
+        if self.index_position == 0 {
+            return Err(invalid_data("bag is not indexed, run `rosbag reindex` on it".to_string()));
+        }
+
+        let first_chunk = self.reader.stream_position()?;
+        self.reader.seek(SeekFrom::Start(self.index_position))?;
+        while let Some(record) = Record::read(&mut self.reader)? {
+            if record.op() == Some(OP_CONNECTION) {
+                let topic = String::from_utf8_lossy(record.field("topic")?).into_owned();
+                let kind = parse_fields(&record.data)?.remove("type").unwrap_or_default();
+                self.connections.insert(record.u32("conn")?, (topic, String::from_utf8_lossy(&kind).into_owned()));
+            }
+        }
+        self.reader.seek(SeekFrom::Start(first_chunk))?;
+        Ok(())
+    }
+
+    /// Index into `topics` of each connection publishing on one of them
+    fn connections_on<T: AsRef<str>>(&self, topics: &[T; 2]) -> HashMap<u32, usize> {
+        self.connections
+            .iter()
+            .filter_map(|(&id, (topic, _))| Some((id, topics.iter().position(|t| t.as_ref() == topic)?)))
+            .collect()
+    }
+
+    /// Read the next message as its connection id and serialized bytes
+    fn next_message(&mut self) -> Result<Option<(u32, Vec<u8>)>, Error> {
+        self.read_message().map_err(|error| dataset_error(&self.path, error))
+    }
+
+    fn read_message(&mut self) -> std::io::Result<Option<(u32, Vec<u8>)>> {
+        loop {
+            let mut rest = &self.chunk[self.chunk_position..];
+            if let Some(record) = Record::read(&mut rest)? {
+                self.chunk_position = self.chunk.len() - rest.len();
+                if record.op() == Some(OP_MESSAGE_DATA) {
+                    return Ok(Some((record.u32("conn")?, record.data)));
+                }
+                continue;
+            }
+
+            if self.reader.stream_position()? >= self.index_position {
+                return Ok(None);
+            }
+            let Some(record) = Record::read(&mut self.reader)? else {
+                return Ok(None);
+            };
+            if record.op() == Some(OP_CHUNK) {
+                self.chunk = decompress(record)?;
+                self.chunk_position = 0;
+            }
+        }
+    }
+}
+
+/// Decompress the records held by a chunk record
+fn decompress(chunk: Record) -> std::io::Result<Vec<u8>> {
+    let size = chunk.u32("size")?;
+    match chunk.field("compression")? {
+        b"none" => Ok(chunk.data),
+        b"lz4" => {
+            let mut records = Vec::with_capacity(size as usize);
+            lz4_flex::frame::FrameDecoder::new(chunk.data.as_slice()).read_to_end(&mut records)?;
+            Ok(records)
+        }
+        b"bz2" => Err(invalid_data("bz2 chunks are not supported, run `rosbag decompress` on the bag".to_string())),
+        other => Err(invalid_data(format!("unknown chunk compression {}", String::from_utf8_lossy(other)))),
+    }
+}
+
+fn dataset_error(path: &Path, error: std::io::Error) -> Error {
+    Error::Dataset(format!("{}: {}", path.display(), error))
+}
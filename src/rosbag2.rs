@@ -0,0 +1,330 @@
+//! rosbag2 recordings (`rosbag2` feature)
+
+use crate::frame_source::StampMatcher;
+use crate::mcap_file::McapFile;
+use crate::ros::{
+    decode_compressed_image, decode_image, decode_imu, header_stamp_ns, info_topic, on_topic, Serialization,
+};
+use crate::{Calibration, CameraInfo, CameraRig, Error, FrameSet, FrameSource, CUVSLAM_ImuMeasurement};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+const IMAGE_TYPE: &str = "sensor_msgs/msg/Image";
+const COMPRESSED_IMAGE_TYPE: &str = "sensor_msgs/msg/CompressedImage";
+const CAMERA_INFO_TYPE: &str = "sensor_msgs/msg/CameraInfo";
+const IMU_TYPE: &str = "sensor_msgs/msg/Imu";
+
+/// `FrameSource` over a stereo pair of image topics in a rosbag2 recording
+///
+/// Reads `sqlite3` (`.db3`) and `mcap` storage, given either the bag directory or one of its
+/// files. Topics may hold `sensor_msgs/msg/Image` or `CompressedImage` messages. Images are paired
+/// by header stamp and each frame is stamped with the camera 0 image; unpaired images are skipped.
+///
+/// The rig is built with `Calibration::from_ros_camera_info` from the first message on each
+/// image topic's `camera_info` sibling, e.g. `/stereo/left/camera_info` for
+/// `/stereo/left/image_raw/compressed`. Messages are streamed from disk in recording order, so
+/// bags larger than memory can be played.
+pub struct Rosbag2Source {
+    files: std::vec::IntoIter<PathBuf>,
+    reader: Option<BagFile>,
+    /// Message type of every topic in the bag
+    topic_types: HashMap<String, String>,
+    image_topics: [String; 2],
+    compressed: [bool; 2],
+    imu_topic: Option<String>,
+    /// Undecoded messages of each camera, waiting for a match
+    matcher: StampMatcher<Vec<u8>>,
+    imu: Vec<CUVSLAM_ImuMeasurement>,
+    last_timestamp_ns: Option<i64>,
+    calibration: Option<Calibration>,
+}
+
+impl Rosbag2Source {
+    /// Open the bag at `path` to play `image_topics` as cameras 0 and 1
+    ///
+    /// Images whose header stamps differ by more than `max_skew_ns` are not paired.
+    #[must_use = "the source or the reason it could not be opened is only in the Result"]
+    pub fn open<P: AsRef<Path>>(path: P, image_topics: [&str; 2], max_skew_ns: i64) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let files = bag_files(path)?;
+        let mut topic_types = HashMap::new();
+        for file in &files {
+            topic_types.extend(BagFile::open(file)?.topic_types()?);
+        }
+
+        let mut compressed = [false; 2];
+        for (topic, compressed) in image_topics.iter().zip(&mut compressed) {
+            *compressed = match topic_types.get(*topic).map(String::as_str) {
+                Some(IMAGE_TYPE) => false,
+                Some(COMPRESSED_IMAGE_TYPE) => true,
+                Some(other) => return Err(Error::Dataset(format!("{}: {} is not an image topic", topic, other))),
+                None => return Err(Error::Dataset(format!("{}: no topic {}", path.display(), topic))),
+            };
+        }
+
+        let infos = image_topics
+            .iter()
+            .map(|topic| info_topic(topic))
+            .filter(|topic| topic_types.get(topic).map(String::as_str) == Some(CAMERA_INFO_TYPE))
+            .map(|topic| first_message(&files, &topic).map(|message| (topic, message)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let calibration = match infos.as_slice() {
+            [(_, Some(left)), (_, Some(right))] => {
+                Some(Calibration::from_ros_camera_info(&[CameraInfo::from_cdr(left)?, CameraInfo::from_cdr(right)?])?)
+            }
+            _ => {
+                log::warn!("{}: no camera_info for {:?}, the source has no calibration", path.display(), image_topics);
+                None
+            }
+        };
+
+        Ok(Self {
+            files: files.into_iter(),
+            reader: None,
+            topic_types,
+            image_topics: image_topics.map(str::to_string),
+            compressed,
+            imu_topic: None,
+            matcher: StampMatcher::new(2, max_skew_ns),
+            imu: Vec::new(),
+            last_timestamp_ns: None,
+            calibration,
+        })
+    }
+
+    /// Also read `sensor_msgs/msg/Imu` messages from `topic`, to be collected with `drain_imu`
+    #[must_use = "the source or the reason the topic can't be read is only in the Result"]
+    pub fn with_imu_topic(mut self, topic: &str) -> Result<Self, Error> {
+        match self.topic_types.get(topic).map(String::as_str) {
+            Some(IMU_TYPE) => {}
+            Some(other) => return Err(Error::Dataset(format!("{}: {} is not an IMU topic", topic, other))),
+            None => return Err(Error::Dataset(format!("no topic {}", topic))),
+        }
+        self.imu_topic = Some(topic.to_string());
+        Ok(self)
+    }
+
+    /// Take the IMU measurements read so far that are no later than the last frame returned
+    ///
+    /// Call it after each `next_frame` and register the measurements before tracking the frame.
+    pub fn drain_imu(&mut self) -> Vec<CUVSLAM_ImuMeasurement> {
+        let Some(until_ns) = self.last_timestamp_ns else { return Vec::new() };
+        self.imu.sort_by_key(|measurement| measurement.timestamp_ns);
+        let split = self.imu.partition_point(|measurement| measurement.timestamp_ns <= until_ns);
+        let later = self.imu.split_off(split);
+        std::mem::replace(&mut self.imu, later)
+    }
+
+    /// Read the next message on one of the topics being played, moving on to the bag's next file
+    fn next_message(&mut self) -> Result<Option<(String, Vec<u8>)>, Error> {
+        let topics: Vec<&str> = self.image_topics.iter().chain(&self.imu_topic).map(String::as_str).collect();
+        loop {
+            if let Some(reader) = &mut self.reader {
+                if let Some(message) = reader.next_message(&topics)? {
+                    return Ok(Some(message));
+                }
+            }
+            match self.files.next() {
+                Some(file) => self.reader = Some(BagFile::open(&file)?),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Decode a matched pair of image messages
+    fn decode_pair(&mut self, timestamp_ns: i64, messages: Vec<Vec<u8>>) -> Result<FrameSet, Error> {
+        self.last_timestamp_ns = Some(timestamp_ns);
+        let images = messages
+            .iter()
+            .enumerate()
+            .map(|(camera, message)| {
+                let decode = if self.compressed[camera] { decode_compressed_image } else { decode_image };
+                decode(message, Serialization::Cdr, camera as i32, timestamp_ns)
+                    .map_err(|error| on_topic(&self.image_topics[camera], error))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(FrameSet { timestamp_ns, images })
+    }
+}
+
+impl FrameSource for Rosbag2Source {
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>> {
+        loop {
+            if let Some((timestamp_ns, messages)) = self.matcher.pop() {
+                return Some(self.decode_pair(timestamp_ns, messages));
+            }
+
+            let (topic, message) = match self.next_message() {
+                Ok(Some(message)) => message,
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error)),
+            };
+            let result = match self.image_topics.iter().position(|image_topic| *image_topic == topic) {
+                Some(camera) => header_stamp_ns(&message, Serialization::Cdr)
+                    .map(|stamp_ns| self.matcher.push(camera, stamp_ns, message)),
+                None => decode_imu(&message).map(|measurement| self.imu.push(measurement)),
+            };
+            if let Err(error) = result {
+                return Some(Err(on_topic(&topic, error)));
+            }
+        }
+    }
+
+    fn calibration(&self) -> Option<CameraRig> {
+        self.calibration.as_ref()?.to_rig().ok()
+    }
+}
+
+/// Storage files of the bag at `path`, in recording order
+fn bag_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let read_error = |error: std::io::Error| Error::Dataset(format!("{}: {}", path.display(), error));
+    let metadata_path = path.join("metadata.yaml");
+    let files = if metadata_path.is_file() {
+        let metadata: serde_yaml::Value =
+            serde_yaml::from_str(&std::fs::read_to_string(&metadata_path).map_err(read_error)?)
+                .map_err(|error| Error::Dataset(format!("{}: {}", metadata_path.display(), error)))?;
+        let relative_paths = metadata["rosbag2_bagfile_information"]["relative_file_paths"].as_sequence();
+        relative_paths
+            .into_iter()
+            .flatten()
+            .filter_map(serde_yaml::Value::as_str)
+            .map(|relative| {
+                // Older rosbag2 versions prefix the paths with the bag directory's name
+                let file = path.join(relative);
+                match Path::new(relative).file_name() {
+                    Some(name) if !file.is_file() => path.join(name),
+                    _ => file,
+                }
+            })
+            .collect()
+    } else {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .map_err(read_error)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|file| matches!(file.extension().and_then(|extension| extension.to_str()), Some("db3" | "mcap")))
+            .collect();
+        files.sort();
+        files
+    };
+    if files.is_empty() {
+        return Err(Error::Dataset(format!("{}: no .db3 or .mcap files", path.display())));
+    }
+    Ok(files)
+}
+
+/// First message on `topic` across the bag's files
+fn first_message(files: &[PathBuf], topic: &str) -> Result<Option<Vec<u8>>, Error> {
+    for file in files {
+        if let Some(message) = BagFile::open(file)?.first_message(topic)? {
+            return Ok(Some(message));
+        }
+    }
+    Ok(None)
+}
+
+/// One storage file of a bag
+enum BagFile {
+    Sqlite(SqliteFile),
+    Mcap(McapFile),
+}
+
+impl BagFile {
+    fn open(path: &Path) -> Result<Self, Error> {
+        let error = |reason: String| Error::Dataset(format!("{}: {}", path.display(), reason));
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("db3") => SqliteFile::open(path).map(BagFile::Sqlite).map_err(|sqlite| error(sqlite.to_string())),
+            Some("mcap") => McapFile::open(path, "cdr").map(BagFile::Mcap).map_err(error),
+            _ => Err(error("unknown storage, expected a .db3 or .mcap file".to_string())),
+        }
+    }
+
+    /// Message type of every topic, such as `sensor_msgs/msg/Image`
+    fn topic_types(&self) -> Result<HashMap<String, String>, Error> {
+        match self {
+            BagFile::Sqlite(file) => file.topic_types().map_err(sqlite_error),
+            BagFile::Mcap(file) => Ok(file.topic_schemas()),
+        }
+    }
+
+    fn first_message(&self, topic: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            BagFile::Sqlite(file) => file.first_message(topic).map_err(sqlite_error),
+            BagFile::Mcap(file) => file.first_message(topic).map_err(mcap_error),
+        }
+    }
+
+    /// Next message on one of `topics`, in recording order
+    fn next_message(&mut self, topics: &[&str]) -> Result<Option<(String, Vec<u8>)>, Error> {
+        match self {
+            BagFile::Sqlite(file) => file.next_message(topics).map_err(sqlite_error),
+            BagFile::Mcap(file) => file.next_message(topics).map_err(mcap_error),
+        }
+    }
+}
+
+fn sqlite_error(error: rusqlite::Error) -> Error {
+    Error::Dataset(format!("rosbag2 sqlite3 storage: {}", error))
+}
+
+fn mcap_error(error: mcap::McapError) -> Error {
+    Error::Dataset(format!("rosbag2 mcap storage: {}", error))
+}
+
+/// The `topics` and `messages` tables of the `sqlite3` storage plugin
+struct SqliteFile {
+    connection: rusqlite::Connection,
+    /// Receive time and id of the last message read, as messages are read a page at a time
+    after: (i64, i64),
+    page: VecDeque<(String, Vec<u8>)>,
+}
+
+impl SqliteFile {
+    const PAGE_SIZE: usize = 64;
+
+    fn open(path: &Path) -> rusqlite::Result<Self> {
+        let connection = rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(Self { connection, after: (i64::MIN, i64::MIN), page: VecDeque::new() })
+    }
+
+    fn topic_types(&self) -> rusqlite::Result<HashMap<String, String>> {
+        let mut statement = self.connection.prepare("SELECT name, type FROM topics")?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    fn first_message(&self, topic: &str) -> rusqlite::Result<Option<Vec<u8>>> {
+        let mut statement = self.connection.prepare(
+            "SELECT data FROM messages JOIN topics ON messages.topic_id = topics.id \
+             WHERE topics.name = ?1 ORDER BY timestamp, messages.id LIMIT 1",
+        )?;
+        let mut rows = statement.query_map([topic], |row| row.get(0))?;
+        rows.next().transpose()
+    }
+
+    fn next_message(&mut self, topics: &[&str]) -> rusqlite::Result<Option<(String, Vec<u8>)>> {
+        if self.page.is_empty() {
+            let placeholders = (0..topics.len()).map(|index| format!("?{}", index + 3)).collect::<Vec<_>>().join(", ");
+            let mut statement = self.connection.prepare_cached(&format!(
+                "SELECT topics.name, timestamp, messages.id, data \
+                 FROM messages JOIN topics ON messages.topic_id = topics.id \
+                 WHERE (timestamp, messages.id) > (?1, ?2) AND topics.name IN ({}) \
+                 ORDER BY timestamp, messages.id LIMIT {}",
+                placeholders,
+                Self::PAGE_SIZE
+            ))?;
+            let (after_timestamp, after_id) = self.after;
+            let parameters = [&after_timestamp as &dyn rusqlite::ToSql, &after_id]
+                .into_iter()
+                .chain(topics.iter().map(|topic| topic as &dyn rusqlite::ToSql));
+            let mut rows = statement.query(rusqlite::params_from_iter(parameters))?;
+            while let Some(row) = rows.next()? {
+                self.after = (row.get(1)?, row.get(2)?);
+                self.page.push_back((row.get(0)?, row.get(3)?));
+            }
+        }
+        Ok(self.page.pop_front())
+    }
+}
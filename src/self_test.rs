@@ -0,0 +1,338 @@
+//! Installation check that exercises cuVSLAM without real cameras
+
+use crate::{
+    get_version, init_default_configuration, is_available, validate_configuration, validate_rig, Camera, CameraRig,
+    Error, FrameSet, FrameSource, Image, ImageEncoding, PinholeParameters, Status, Tracker, CUVSLAM_Pose,
+};
+use std::time::{Duration, Instant};
+
+const WIDTH: i32 = 640;
+const HEIGHT: i32 = 480;
+/// Time between `SyntheticSource` frames, 30 Hz
+const FRAME_INTERVAL_NS: i64 = 33_333_333;
+
+/// Outcome of one stage of `self_test`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestStage {
+    /// Short stage name, e.g. `create_tracker`
+    pub name: &'static str,
+    /// Time the stage took
+    pub duration: Duration,
+    /// Why the stage failed, if it did
+    pub error: Option<Error>,
+}
+
+impl SelfTestStage {
+    /// Whether the stage succeeded
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Stages run by `self_test`, in order, up to and including the first failure
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestReport {
+    /// Version string reported by the library, `None` if it could not be loaded
+    pub version: Option<String>,
+    /// Stages that ran; stages after a failed one are skipped
+    pub stages: Vec<SelfTestStage>,
+}
+
+impl SelfTestReport {
+    /// Whether every stage ran and succeeded
+    pub fn passed(&self) -> bool {
+        self.stages.iter().all(SelfTestStage::passed)
+    }
+
+    /// Whether the CUDA probe found a usable device, `None` if it didn't run
+    ///
+    /// The probe needs the `cuda` feature and only runs once the library has loaded.
+    pub fn gpu_available(&self) -> Option<bool> {
+        self.stage("cuda_probe").map(SelfTestStage::passed)
+    }
+
+    /// Whether a tracker was created with the canned rig and the default configuration
+    pub fn tracker_created(&self) -> bool {
+        self.stage("create_tracker").is_some_and(SelfTestStage::passed)
+    }
+
+    fn stage(&self, name: &str) -> Option<&SelfTestStage> {
+        self.stages.iter().find(|stage| stage.name == name)
+    }
+}
+
+impl std::fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "cuVSLAM {}", self.version.as_deref().unwrap_or("(not loaded)"))?;
+        for stage in &self.stages {
+            match &stage.error {
+                None => writeln!(f, "  pass  {:<16} {:>10.1?}", stage.name, stage.duration)?,
+                Some(error) => writeln!(f, "  FAIL  {:<16} {:>10.1?}  {}", stage.name, stage.duration, error)?,
+            }
+        }
+        write!(f, "{}", if self.passed() { "self-test passed" } else { "self-test failed" })
+    }
+}
+
+/// Check that cuVSLAM works on this machine, without cameras
+///
+/// Loads the library, validates a canned 640x480 stereo rig, probes CUDA (`cuda` feature only),
+/// creates a tracker with the default configuration, tracks one synthetic stereo frame and
+/// destroys the tracker, timing each stage. Tracking passes if cuVSLAM accepts the frame, even if
+/// it reports `TrackingLost`. A library that can't be loaded fails the first stage with
+/// `Status::LibraryNotLoaded`.
+pub fn self_test() -> SelfTestReport {
+    let mut report = SelfTestReport { version: None, stages: Vec::new() };
+    let loaded = run_stage(&mut report, "load_library", || {
+        if is_available() {
+            Ok(())
+        } else {
+            Err(Error::Status(Status::LibraryNotLoaded))
+        }
+    });
+    if loaded.is_none() {
+        return report;
+    }
+    let (major, minor, version) = get_version();
+    report.version = Some(version.unwrap_or_else(|| format!("{}.{}", major, minor)));
+    let config = init_default_configuration();
+
+    let validated = run_stage(&mut report, "validate_rig", || {
+        let rig = canned_rig();
+        validate_rig(&rig).map_err(Error::InvalidRig)?;
+        validate_configuration(&rig, &config).map_err(Error::InvalidConfiguration)
+    });
+    if validated.is_none() {
+        return report;
+    }
+
+    #[cfg(feature = "cuda")]
+    if run_stage(&mut report, "cuda_probe", || crate::cuda::probe().map(drop).map_err(Error::Cuda)).is_none() {
+        return report;
+    }
+
+    let Some(tracker) = run_stage(&mut report, "create_tracker", || Tracker::new(canned_rig(), &config)) else {
+        return report;
+    };
+
+    let frame = synthetic_frame(0);
+    let tracked = run_stage(&mut report, "track", || match tracker.track(&frame, None) {
+        Ok(_) | Err(Status::TrackingLost) => Ok(()),
+        Err(status) => Err(Error::Status(status)),
+    });
+    if tracked.is_none() {
+        return report;
+    }
+
+    run_stage(&mut report, "destroy_tracker", || {
+        drop(tracker);
+        Ok(())
+    });
+    report
+}
+
+/// Time `stage` and record its outcome, returning its value if it succeeded
+fn run_stage<T>(
+    report: &mut SelfTestReport,
+    name: &'static str,
+    stage: impl FnOnce() -> Result<T, Error>,
+) -> Option<T> {
+    let start = Instant::now();
+    let result = stage();
+    let duration = start.elapsed();
+    let (value, error) = match result {
+        Ok(value) => (Some(value), None),
+        Err(error) => (None, Some(error)),
+    };
+    report.stages.push(SelfTestStage { name, duration, error });
+    value
+}
+
+/// Distance between the two cameras of `stereo_rig`, in meters
+pub const BASELINE: f32 = 0.1;
+
+/// Pinhole stereo pair with a horizontal baseline and a focal length of roughly 60° FOV
+///
+/// This and the frame generators below are public for the crate's integration tests and
+/// benchmarks, which share them with `self_test` and `SyntheticSource`.
+pub fn stereo_rig(width: i32, height: i32) -> CameraRig {
+    let camera = |x: f32| {
+        Camera::new_pinhole(
+            width,
+            height,
+            PinholeParameters {
+                cx: width as f32 / 2.0,
+                cy: height as f32 / 2.0,
+                fx: width as f32 * 0.87,
+                fy: width as f32 * 0.87,
+            },
+            CUVSLAM_Pose {
+                r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+                t: [x, 0.0, 0.0],
+            },
+        )
+    };
+    CameraRig::new(vec![camera(0.0), camera(BASELINE)])
+}
+
+/// Mono8 frame of random 8x8 blocks, shifted left by `shift` pixels to simulate motion
+///
+/// The texture is deterministic, so frames with the same `shift` are identical.
+pub fn textured_frame(width: i32, height: i32, shift: i32) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            pixels.push(block_intensity((x + shift) / 8, y / 8));
+        }
+    }
+    pixels
+}
+
+/// Left and right images of one synthetic stereo frame, with the right view offset by `disparity`
+pub fn stereo_frame(width: i32, height: i32, shift: i32, disparity: i32, timestamp_ns: i64) -> [Image; 2] {
+    [(0, shift), (1, shift + disparity)].map(|(camera_index, shift)| {
+        Image::new(
+            textured_frame(width, height, shift),
+            width,
+            height,
+            ImageEncoding::Mono8,
+            camera_index,
+            timestamp_ns,
+        )
+        .unwrap()
+    })
+}
+
+/// Hash a block coordinate into an intensity
+fn block_intensity(bx: i32, by: i32) -> u8 {
+    let mut h = (bx as u32).wrapping_mul(0x9e37_79b9) ^ (by as u32).wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h as u8
+}
+
+/// Stereo rig `self_test` and `SyntheticSource` run on
+fn canned_rig() -> CameraRig {
+    stereo_rig(WIDTH, HEIGHT)
+}
+
+/// `SyntheticSource` frame `index`, the first of which `self_test` tracks
+fn synthetic_frame(index: i64) -> [Image; 2] {
+    stereo_frame(WIDTH, HEIGHT, index as i32, 8, index * FRAME_INTERVAL_NS)
+}
+
+/// `FrameSource` of synthetic stereo frames, for running the tracker where no dataset or camera
+/// is at hand
+///
+/// Plays the frame `self_test` tracks at 30 Hz on the same 640x480 pinhole stereo rig, reported
+/// by `calibration`, with the texture sliding one pixel left per frame as if the rig panned. The
+/// images are generated as they are played. Good for timing and smoke tests, not for accuracy.
+pub struct SyntheticSource {
+    next: usize,
+    frames: usize,
+}
+
+impl SyntheticSource {
+    /// Play `frames` frames
+    pub fn new(frames: usize) -> Self {
+        Self { next: 0, frames }
+    }
+}
+
+impl FrameSource for SyntheticSource {
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>> {
+        if self.next == self.frames {
+            return None;
+        }
+        let images = synthetic_frame(self.next as i64);
+        self.next += 1;
+        Some(Ok(FrameSet { timestamp_ns: images[0].timestamp_ns(), images: images.into() }))
+    }
+
+    fn calibration(&self) -> Option<CameraRig> {
+        Some(canned_rig())
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        Some(self.frames - self.next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canned_inputs_are_valid() {
+        let rig = canned_rig();
+        assert_eq!(validate_rig(&rig), Ok(()));
+
+        let frame = synthetic_frame(0);
+        assert_eq!(crate::validate_images(&rig, &frame), Ok(()));
+        // Textured rather than flat, so features can be found
+        let distinct: std::collections::HashSet<u8> = frame[0].pixels().iter().copied().collect();
+        assert!(distinct.len() > 64);
+    }
+
+    #[test]
+    fn test_synthetic_source() {
+        let mut source = SyntheticSource::new(3);
+        assert_eq!(source.len_hint(), Some(3));
+        let rig = source.calibration().unwrap();
+        let first = source.next_frame().unwrap().unwrap();
+        let second = source.next_frame().unwrap().unwrap();
+        assert_eq!(crate::validate_images(&rig, &second.images), Ok(()));
+        assert_eq!((first.timestamp_ns, second.timestamp_ns), (0, FRAME_INTERVAL_NS));
+        assert_eq!(second.images[1].timestamp_ns(), FRAME_INTERVAL_NS);
+        // The texture moves one pixel left per frame
+        let row = WIDTH as usize;
+        assert_eq!(first.images[0].pixels()[1..row], second.images[0].pixels()[..row - 1]);
+        assert_eq!(source.len_hint(), Some(1));
+        assert!(source.next_frame().unwrap().is_ok());
+        assert!(source.next_frame().is_none());
+    }
+
+    #[test]
+    fn test_report_display() {
+        let report = SelfTestReport {
+            version: Some("12.5".to_string()),
+            stages: vec![
+                SelfTestStage { name: "load_library", duration: Duration::from_micros(5), error: None },
+                SelfTestStage { name: "validate_rig", duration: Duration::from_micros(20), error: None },
+                SelfTestStage {
+                    name: "create_tracker",
+                    duration: Duration::from_millis(300),
+                    error: Some(Error::Cuda("no CUDA devices found".to_string())),
+                },
+            ],
+        };
+        assert!(!report.passed());
+        assert!(!report.tracker_created());
+        assert_eq!(report.gpu_available(), None);
+        let text = report.to_string();
+        assert!(text.starts_with("cuVSLAM 12.5\n  pass  load_library"));
+        assert!(text.contains("FAIL  create_tracker"));
+        assert!(text.ends_with("self-test failed"));
+
+        let not_loaded = SelfTestReport {
+            version: None,
+            stages: vec![SelfTestStage {
+                name: "load_library",
+                duration: Duration::ZERO,
+                error: Some(Error::Status(Status::LibraryNotLoaded)),
+            }],
+        };
+        assert!(not_loaded.to_string().starts_with("cuVSLAM (not loaded)\n  FAIL  load_library"));
+    }
+
+    #[test]
+    #[ignore = "needs a CUDA device"]
+    fn test_self_test_on_gpu() {
+        let report = self_test();
+        assert!(report.passed(), "{}", report);
+        assert!(report.tracker_created());
+        assert_ne!(report.gpu_available(), Some(false));
+        assert_eq!(report.stages.last().unwrap().name, "destroy_tracker");
+    }
+}
@@ -0,0 +1,59 @@
+//! Serde helpers for the FFI types embedded in wrapper types (`serde` feature)
+
+use crate::CUVSLAM_Pose;
+use serde::{Deserialize, Serialize};
+
+/// Serde definition mirroring `CUVSLAM_Pose`
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "CUVSLAM_Pose")]
+pub(crate) struct PoseDef {
+    r: [f32; 9],
+    t: [f32; 3],
+}
+
+/// Covariance matrices serialize as a list of 36 numbers, or with the `compact` feature as a
+/// base64 string of their little-endian bytes
+pub(crate) mod covariance {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[cfg(not(feature = "compact"))]
+    pub(crate) fn serialize<S: Serializer>(covariance: &[f32; 36], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(covariance)
+    }
+
+    #[cfg(not(feature = "compact"))]
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f32; 36], D::Error> {
+        let values = Vec::<f32>::deserialize(deserializer)?;
+        values
+            .try_into()
+            .map_err(|values: Vec<f32>| D::Error::invalid_length(values.len(), &"36 covariance values"))
+    }
+
+    #[cfg(feature = "compact")]
+    pub(crate) fn serialize<S: Serializer>(covariance: &[f32; 36], serializer: S) -> Result<S::Ok, S::Error> {
+        use base64::Engine;
+
+        let bytes: Vec<u8> = covariance.iter().flat_map(|v| v.to_le_bytes()).collect();
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    #[cfg(feature = "compact")]
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f32; 36], D::Error> {
+        use base64::Engine;
+
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(D::Error::custom)?;
+        if bytes.len() != 36 * 4 {
+            return Err(D::Error::invalid_length(bytes.len(), &"144 covariance bytes"));
+        }
+
+        let mut covariance = [0.0; 36];
+        for (value, chunk) in covariance.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        Ok(covariance)
+    }
+}
@@ -0,0 +1,123 @@
+//! Online statistics of scalar streams
+
+/// Mean, variance and range of a stream of values, updated one value at a time
+///
+/// Uses Welford's algorithm with `f64` accumulators, so long streams of large values with a
+/// small spread keep their precision. The variance is the population variance (numpy's default
+/// `ddof=0`). Before the first update the mean, minimum and maximum are NaN and the variance is
+/// zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunningStatistics {
+    count: u64,
+    mean: f64,
+    m2: f64,
+    min: f32,
+    max: f32,
+}
+
+impl Default for RunningStatistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunningStatistics {
+    /// Create statistics over no values
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            min: f32::NAN,
+            max: f32::NAN,
+        }
+    }
+
+    /// Add a value to the stream
+    pub fn update(&mut self, value: f32) {
+        self.count += 1;
+        let value_f64 = value as f64;
+        let delta = value_f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value_f64 - self.mean);
+        // f32::min and max ignore the initial NaNs
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Number of values seen
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Arithmetic mean of the values seen
+    pub fn mean(&self) -> f32 {
+        if self.count == 0 {
+            f32::NAN
+        } else {
+            self.mean as f32
+        }
+    }
+
+    /// Population variance of the values seen
+    pub fn variance(&self) -> f32 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.m2 / self.count as f64) as f32
+        }
+    }
+
+    /// Population standard deviation of the values seen
+    pub fn std_dev(&self) -> f32 {
+        self.variance().sqrt()
+    }
+
+    /// Smallest value seen
+    pub fn min(&self) -> f32 {
+        self.min
+    }
+
+    /// Largest value seen
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+}
+
+impl Extend<f32> for RunningStatistics {
+    fn extend<I: IntoIterator<Item = f32>>(&mut self, values: I) {
+        for value in values {
+            self.update(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_running_statistics() {
+        let mut stats = RunningStatistics::new();
+        assert_eq!((stats.count(), stats.variance()), (0, 0.0));
+        assert!(stats.mean().is_nan() && stats.min().is_nan() && stats.max().is_nan());
+
+        stats.extend([2.5, -1.25, 7.0, 3.5, 0.0, 10.75, -4.5, 6.25]);
+        // np.mean, np.var and np.std of the same values
+        assert_eq!(stats.count(), 8);
+        assert_eq!(stats.mean(), 3.03125);
+        assert_eq!(stats.variance(), 21.303_71);
+        assert!((stats.std_dev() - 4.6155943).abs() < 1e-6);
+        assert_eq!((stats.min(), stats.max()), (-4.5, 10.75));
+    }
+
+    #[test]
+    fn test_running_statistics_large_offset() {
+        // A naive sum of squares loses the spread entirely in f32
+        let mut stats = RunningStatistics::default();
+        stats.extend([1_000_004.0, 1_000_007.0, 1_000_013.0, 1_000_016.0]);
+        assert_eq!(stats.mean(), 1_000_010.0);
+        assert_eq!(stats.variance(), 22.5);
+        assert!((stats.std_dev() - 4.7434165).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,42 @@
+//! Stereo geometry helpers for using cuVSLAM alongside disparity images
+
+/// Metric depth of a point with the given disparity, `baseline * focal_length / disparity`
+///
+/// `baseline_m` is the distance between the rectified cameras in meters and `focal_length_px`
+/// their shared focal length in pixels. Returns `f32::INFINITY` unless `disparity` is positive,
+/// which is how matchers such as OpenCV's `StereoSGBM` mark pixels without a match.
+pub fn stereo_disparity_to_depth(disparity: f32, baseline_m: f32, focal_length_px: f32) -> f32 {
+    if disparity > 0.0 {
+        baseline_m * focal_length_px / disparity
+    } else {
+        f32::INFINITY
+    }
+}
+
+/// Disparity in pixels of a point at `depth_m`, the inverse of `stereo_disparity_to_depth`
+///
+/// Returns 0 unless `depth_m` is positive and finite.
+pub fn stereo_depth_to_disparity(depth_m: f32, baseline_m: f32, focal_length_px: f32) -> f32 {
+    if depth_m > 0.0 && depth_m.is_finite() {
+        baseline_m * focal_length_px / depth_m
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disparity_depth_round_trip() {
+        // 10cm baseline at 500px focal length: 25px of disparity is 2m away
+        assert_eq!(stereo_disparity_to_depth(25.0, 0.1, 500.0), 2.0);
+        assert_eq!(stereo_depth_to_disparity(2.0, 0.1, 500.0), 25.0);
+
+        assert_eq!(stereo_disparity_to_depth(0.0, 0.1, 500.0), f32::INFINITY);
+        assert_eq!(stereo_disparity_to_depth(-1.0, 0.1, 500.0), f32::INFINITY);
+        assert_eq!(stereo_disparity_to_depth(f32::NAN, 0.1, 500.0), f32::INFINITY);
+        assert_eq!(stereo_depth_to_disparity(f32::INFINITY, 0.1, 500.0), 0.0);
+    }
+}
@@ -0,0 +1,111 @@
+//! Soak tests for tracker lifecycle leaks, usable from downstream CI
+
+use crate::{memory, CameraRig, Error, Tracker, CUVSLAM_Configuration};
+use std::time::{Duration, Instant};
+
+/// Memory before and after repeatedly creating and destroying trackers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeakReport {
+    /// Resident set size after the warm-up cycle, in bytes
+    pub rss_before_bytes: Option<u64>,
+    /// Resident set size after the last cycle, in bytes
+    pub rss_after_bytes: Option<u64>,
+    /// Free device memory after the warm-up cycle, in bytes (`cuda` feature only)
+    pub gpu_free_before_bytes: Option<u64>,
+    /// Free device memory after the last cycle, in bytes (`cuda` feature only)
+    pub gpu_free_after_bytes: Option<u64>,
+    /// Time to create and destroy the tracker in each measured cycle
+    pub cycle_times: Vec<Duration>,
+}
+
+impl LeakReport {
+    /// Growth of the resident set size over the measured cycles, negative if it shrank
+    pub fn rss_growth_bytes(&self) -> Option<i64> {
+        Some(self.rss_after_bytes? as i64 - self.rss_before_bytes? as i64)
+    }
+
+    /// Device memory lost over the measured cycles, negative if more is free afterwards
+    pub fn gpu_growth_bytes(&self) -> Option<i64> {
+        Some(self.gpu_free_before_bytes? as i64 - self.gpu_free_after_bytes? as i64)
+    }
+
+    /// Whether neither host nor device memory grew by more than its tolerance
+    ///
+    /// Measurements that are unavailable, e.g. GPU memory without the `cuda` feature, pass.
+    pub fn passed(&self, rss_tolerance_bytes: u64, gpu_tolerance_bytes: u64) -> bool {
+        self.rss_growth_bytes().map_or(true, |growth| growth <= rss_tolerance_bytes as i64)
+            && self.gpu_growth_bytes().map_or(true, |growth| growth <= gpu_tolerance_bytes as i64)
+    }
+
+    /// Mean create and destroy time per cycle
+    pub fn mean_cycle_time(&self) -> Option<Duration> {
+        let cycles = u32::try_from(self.cycle_times.len()).ok().filter(|&cycles| cycles > 0)?;
+        Some(self.cycle_times.iter().sum::<Duration>() / cycles)
+    }
+}
+
+/// Create and destroy a tracker `n` times, measuring memory before and after
+///
+/// One extra warm-up cycle runs before the baseline is taken, so one-off allocations such as the
+/// CUDA context and kernel loading are not counted as leaks. `rig_factory` is called once per
+/// cycle since each tracker consumes its rig. Stops at the first tracker that can't be created.
+#[must_use = "the report or the reason a tracker could not be created is only in the Result"]
+pub fn create_destroy_cycles(
+    n: usize,
+    mut rig_factory: impl FnMut() -> CameraRig,
+    config: &CUVSLAM_Configuration,
+) -> Result<LeakReport, Error> {
+    drop(Tracker::new(rig_factory(), config)?);
+
+    let mut report = LeakReport {
+        rss_before_bytes: memory::process_rss_bytes(),
+        gpu_free_before_bytes: gpu_free_bytes(),
+        cycle_times: Vec::with_capacity(n),
+        ..LeakReport::default()
+    };
+    for _ in 0..n {
+        let rig = rig_factory();
+        let start = Instant::now();
+        drop(Tracker::new(rig, config)?);
+        report.cycle_times.push(start.elapsed());
+    }
+    report.rss_after_bytes = memory::process_rss_bytes();
+    report.gpu_free_after_bytes = gpu_free_bytes();
+    Ok(report)
+}
+
+#[cfg(feature = "cuda")]
+fn gpu_free_bytes() -> Option<u64> {
+    crate::cuda::mem_info().ok().map(|(free, _)| free)
+}
+
+#[cfg(not(feature = "cuda"))]
+fn gpu_free_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leak_report_tolerances() {
+        let report = LeakReport {
+            rss_before_bytes: Some(100_000),
+            rss_after_bytes: Some(104_096),
+            gpu_free_before_bytes: Some(1 << 30),
+            gpu_free_after_bytes: Some((1 << 30) + 512),
+            cycle_times: vec![Duration::from_millis(10), Duration::from_millis(30)],
+        };
+        assert_eq!(report.rss_growth_bytes(), Some(4096));
+        assert_eq!(report.gpu_growth_bytes(), Some(-512));
+        assert_eq!(report.mean_cycle_time(), Some(Duration::from_millis(20)));
+        assert!(report.passed(4096, 0));
+        assert!(!report.passed(4095, 0));
+
+        // Missing measurements don't fail the check
+        let empty = LeakReport::default();
+        assert!(empty.passed(0, 0));
+        assert_eq!(empty.mean_cycle_time(), None);
+    }
+}
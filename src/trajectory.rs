@@ -0,0 +1,693 @@
+//! Trajectory export and evaluation against ground truth
+
+use crate::{pose_from_quaternion, pose_rotation_quaternion, CUVSLAM_Landmark, CUVSLAM_Pose, PoseEstimate};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// Write estimates in the TUM RGB-D format, one `timestamp tx ty tz qx qy qz qw` line per pose
+///
+/// Timestamps are written in seconds, which is what the TUM and EuRoC evaluation tools expect.
+pub fn write_tum<W: Write>(mut writer: W, estimates: &[PoseEstimate]) -> std::io::Result<()> {
+    for estimate in estimates {
+        let t = estimate.pose.t;
+        let [qx, qy, qz, qw] = pose_rotation_quaternion(&estimate.pose);
+        writeln!(
+            writer,
+            "{:.9} {} {} {} {} {} {} {}",
+            estimate.timestamp_ns as f64 * 1e-9,
+            t[0],
+            t[1],
+            t[2],
+            qx,
+            qy,
+            qz,
+            qw
+        )?;
+    }
+    Ok(())
+}
+
+/// Write poses in the KITTI odometry format, one row-major 3x4 `[R | t]` matrix per line
+///
+/// The devkit expects exactly one line per frame of the sequence, in order.
+pub fn write_kitti<W: Write>(mut writer: W, poses: &[CUVSLAM_Pose]) -> std::io::Result<()> {
+    for pose in poses {
+        let row = |row: usize| [pose.r[row], pose.r[3 + row], pose.r[6 + row], pose.t[row]];
+        let values: Vec<String> = (0..3).flat_map(row).map(|value| value.to_string()).collect();
+        writeln!(writer, "{}", values.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Write estimates as CSV matching the fields of `geometry_msgs/PoseStamped`, converted to ROS axes
+///
+/// After a `stamp,x,y,z,qx,qy,qz,qw` header, each line holds the timestamp in seconds and the
+/// position and orientation of `pose_to_ros(&estimate.pose)`.
+pub fn write_ros_poses_csv<W: Write>(mut writer: W, estimates: &[PoseEstimate]) -> std::io::Result<()> {
+    writeln!(writer, "stamp,x,y,z,qx,qy,qz,qw")?;
+    for estimate in estimates {
+        let pose = pose_to_ros(&estimate.pose);
+        let [qx, qy, qz, qw] = pose_rotation_quaternion(&pose);
+        let stamp = estimate.timestamp_ns;
+        writeln!(
+            writer,
+            "{}.{:09},{},{},{},{},{},{},{}",
+            stamp.div_euclid(1_000_000_000),
+            stamp.rem_euclid(1_000_000_000),
+            pose.t[0],
+            pose.t[1],
+            pose.t[2],
+            qx,
+            qy,
+            qz,
+            qw
+        )?;
+    }
+    Ok(())
+}
+
+/// Save a trajectory and any landmarks to a Rerun recording file, to inspect without a viewer open
+///
+/// Each pose is logged as the `world/camera` transform at its timestamp on the `capture`
+/// timeline. The whole trajectory is logged as the `world/trajectory` line strip and the
+/// landmarks, if any, as `world/landmarks` points, both static so they show at every time.
+/// Open the file with `rerun <path>`.
+pub fn log_trajectory_to_rrd<P: AsRef<Path>>(
+    poses: &[PoseEstimate],
+    landmarks: &[CUVSLAM_Landmark],
+    path: P,
+) -> rerun::RecordingStreamResult<()> {
+    let rec = rerun::RecordingStreamBuilder::new("cuvslam").save(path.as_ref())?;
+    for estimate in poses {
+        rec.set_time_nanos("capture", estimate.timestamp_ns);
+        rec.log(
+            "world/camera",
+            &rerun::Transform3D::from_translation_rotation(
+                estimate.pose.t,
+                rerun::Quaternion::from_xyzw(pose_rotation_quaternion(&estimate.pose)),
+            ),
+        )?;
+    }
+    rec.log_static("world/trajectory", &rerun::LineStrips3D::new([poses.iter().map(|estimate| estimate.pose.t)]))?;
+    if !landmarks.is_empty() {
+        let points = landmarks.iter().map(|landmark| [landmark.x, landmark.y, landmark.z]);
+        rec.log_static("world/landmarks", &rerun::Points3D::new(points))?;
+    }
+    rec.flush_blocking();
+    Ok(())
+}
+
+/// Express a pose in ROS axes (REP 103: x forward, y left, z up) instead of cuVSLAM's
+///
+/// cuVSLAM's world and rig frames have x right, y up and z backward. Both sides of the pose are
+/// converted, so the result maps a ROS-convention body frame into a ROS-convention world frame
+/// sharing the tracker's origin.
+pub fn pose_to_ros(pose: &CUVSLAM_Pose) -> CUVSLAM_Pose {
+    // Rows (0, 0, -1), (-1, 0, 0), (0, 1, 0), stored column-major
+    const ROS_FROM_CUVSLAM: CUVSLAM_Pose = CUVSLAM_Pose {
+        r: [0.0, -1.0, 0.0, 0.0, 0.0, 1.0, -1.0, 0.0, 0.0],
+        t: [0.0; 3],
+    };
+    compose_poses(&compose_poses(&ROS_FROM_CUVSLAM, pose), &invert_pose(&ROS_FROM_CUVSLAM))
+}
+
+/// Compose two poses, `a * b`, as maps from `b`'s source frame into `a`'s target frame
+pub fn compose_poses(a: &CUVSLAM_Pose, b: &CUVSLAM_Pose) -> CUVSLAM_Pose {
+    // Column-major storage
+    let (ra, rb) = (|row: usize, col: usize| a.r[col * 3 + row], |row: usize, col: usize| b.r[col * 3 + row]);
+    let mut r = [0.0; 9];
+    for col in 0..3 {
+        for row in 0..3 {
+            r[col * 3 + row] = (0..3).map(|k| ra(row, k) * rb(k, col)).sum();
+        }
+    }
+    let t = std::array::from_fn(|row| (0..3).map(|k| ra(row, k) * b.t[k]).sum::<f32>() + a.t[row]);
+    CUVSLAM_Pose { r, t }
+}
+
+/// Inverse of a rigid pose
+pub fn invert_pose(pose: &CUVSLAM_Pose) -> CUVSLAM_Pose {
+    let m = |row: usize, col: usize| pose.r[col * 3 + row];
+    // The inverse rotation is the transpose
+    let r = std::array::from_fn(|i| m(i / 3, i % 3));
+    let t = std::array::from_fn(|row| -(0..3).map(|k| m(k, row) * pose.t[k]).sum::<f32>());
+    CUVSLAM_Pose { r, t }
+}
+
+/// Absolute trajectory error of estimated positions after rigid alignment to ground truth
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AteReport {
+    /// Estimates matched to a ground truth position
+    pub matched: usize,
+    /// Root mean square position error, in meters
+    pub rmse_m: f64,
+    /// Mean position error, in meters
+    pub mean_m: f64,
+    /// Largest position error, in meters
+    pub max_m: f64,
+}
+
+/// Compute the ATE of timestamped positions against ground truth
+///
+/// Each estimate is matched to the ground truth sample closest in time, if within
+/// `max_time_difference_ns`. The estimates are then rotated and translated onto the ground truth
+/// with Horn's closed-form method (no scale), since the tracker's world frame is arbitrary.
+/// Returns `None` with fewer than three matches. `ground_truth` must be sorted by timestamp.
+pub fn absolute_trajectory_error(
+    estimated: &[(i64, [f64; 3])],
+    ground_truth: &[(i64, [f64; 3])],
+    max_time_difference_ns: i64,
+) -> Option<AteReport> {
+    let pairs: Vec<PositionPair> = estimated
+        .iter()
+        .filter_map(|&(timestamp_ns, position)| {
+            nearest_position(ground_truth, timestamp_ns, max_time_difference_ns).map(|truth| (position, truth))
+        })
+        .collect();
+    if pairs.len() < 3 {
+        return None;
+    }
+    Some(report(&pairs, &align(&pairs)))
+}
+
+/// ATE updated as estimates arrive, for watching the error of a run while it plays
+///
+/// Estimates are matched to ground truth as in `absolute_trajectory_error`. The alignment is
+/// recomputed over all matches once three are available and then every `realign_every` matches,
+/// and errors in between use the latest one, so right after a realignment `report` equals the
+/// batch result. Estimates with no ground truth within the tolerance, such as those after the
+/// ground truth ends, are left out, and frames that failed to track are simply never pushed.
+#[derive(Debug, Clone)]
+pub struct RunningAte {
+    ground_truth: Vec<(i64, [f64; 3])>,
+    max_time_difference_ns: i64,
+    realign_every: usize,
+    pairs: Vec<PositionPair>,
+    alignment: Option<Alignment>,
+}
+
+impl RunningAte {
+    /// Evaluate against `ground_truth`, which must be sorted by timestamp
+    pub fn new(ground_truth: Vec<(i64, [f64; 3])>, max_time_difference_ns: i64, realign_every: usize) -> Self {
+        Self {
+            ground_truth,
+            max_time_difference_ns,
+            realign_every: realign_every.max(1),
+            pairs: Vec::new(),
+            alignment: None,
+        }
+    }
+
+    /// Add an estimated position, returning its error under the current alignment
+    ///
+    /// Returns `None` when the estimate has no ground truth match or fewer than three estimates
+    /// have been matched so far.
+    pub fn push(&mut self, timestamp_ns: i64, position: [f64; 3]) -> Option<f64> {
+        let truth = nearest_position(&self.ground_truth, timestamp_ns, self.max_time_difference_ns)?;
+        self.pairs.push((position, truth));
+        let matched = self.pairs.len();
+        if matched >= 3 && (self.alignment.is_none() || matched % self.realign_every == 0) {
+            self.alignment = Some(align(&self.pairs));
+        }
+        self.alignment.as_ref().map(|alignment| error(alignment, &(position, truth)))
+    }
+
+    /// Estimates matched to ground truth so far
+    pub fn matched(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Timestamp of the last ground truth sample, after which estimates can no longer be matched
+    pub fn ground_truth_end_ns(&self) -> Option<i64> {
+        self.ground_truth.last().map(|&(timestamp_ns, _)| timestamp_ns)
+    }
+
+    /// ATE of all matched estimates under the current alignment
+    pub fn report(&self) -> Option<AteReport> {
+        self.alignment.as_ref().map(|alignment| report(&self.pairs, alignment))
+    }
+
+    /// The ground truth positions moved into the estimates' world frame by the current alignment
+    pub fn aligned_ground_truth(&self) -> Option<Vec<[f64; 3]>> {
+        let (rotation, translation) = self.alignment.as_ref()?;
+        // The alignment maps estimates onto ground truth, so ground truth goes back through its inverse
+        let to_estimate = |truth: &[f64; 3]| -> [f64; 3] {
+            std::array::from_fn(|i| (0..3).map(|k| rotation[k][i] * (truth[k] - translation[k])).sum())
+        };
+        Some(self.ground_truth.iter().map(|(_, truth)| to_estimate(truth)).collect())
+    }
+}
+
+/// Ground truth poses resampled at arbitrary timestamps
+///
+/// References such as motion capture or a LiDAR map are rarely sampled at the camera's
+/// timestamps. A query between two samples interpolates the translation linearly and the
+/// rotation by SLERP, taking the covariance of the nearer sample; a query matching a sample
+/// returns it as is, and one outside the sampled range returns `None`.
+#[derive(Debug, Clone)]
+pub struct GroundTruthInterpolator {
+    gt: Vec<PoseEstimate>,
+}
+
+impl GroundTruthInterpolator {
+    /// Interpolate between the poses of `gt`, in any order
+    pub fn new(mut gt: Vec<PoseEstimate>) -> Self {
+        gt.sort_by_key(|sample| sample.timestamp_ns);
+        Self { gt }
+    }
+
+    /// The ground truth pose at `timestamp_ns`
+    pub fn query(&self, timestamp_ns: i64) -> Option<PoseEstimate> {
+        let index = self.gt.partition_point(|sample| sample.timestamp_ns < timestamp_ns);
+        let after = self.gt.get(index)?;
+        if after.timestamp_ns == timestamp_ns {
+            return Some(after.clone());
+        }
+        let before = self.gt.get(index.checked_sub(1)?)?;
+
+        let s = (timestamp_ns - before.timestamp_ns) as f64 / (after.timestamp_ns - before.timestamp_ns) as f64;
+        let t = std::array::from_fn(|i| (before.pose.t[i] as f64 * (1.0 - s) + after.pose.t[i] as f64 * s) as f32);
+        let rotation = slerp(pose_rotation_quaternion(&before.pose), pose_rotation_quaternion(&after.pose), s);
+        let nearer = if s < 0.5 { before } else { after };
+        Some(PoseEstimate {
+            pose: pose_from_quaternion(rotation, t),
+            timestamp_ns,
+            covariance: nearer.covariance,
+        })
+    }
+}
+
+/// Spherical linear interpolation between unit quaternions `[x, y, z, w]`, along the shorter arc
+fn slerp(a: [f32; 4], b: [f32; 4], s: f64) -> [f32; 4] {
+    let (a, mut b) = (a.map(f64::from), b.map(f64::from));
+    let mut cos = (0..4).map(|i| a[i] * b[i]).sum::<f64>();
+    if cos < 0.0 {
+        b = b.map(|v| -v);
+        cos = -cos;
+    }
+    // Nearly identical rotations: the linear blend is accurate and avoids dividing by sin ~ 0
+    let (wa, wb) = if cos > 0.9995 {
+        (1.0 - s, s)
+    } else {
+        let angle = cos.acos();
+        (((1.0 - s) * angle).sin() / angle.sin(), (s * angle).sin() / angle.sin())
+    };
+    std::array::from_fn(|i| (wa * a[i] + wb * b[i]) as f32)
+}
+
+/// Read the positions of a TUM trajectory, such as one written by `write_tum`
+///
+/// Blank lines and `#` comments are skipped. Timestamps are converted from seconds and the
+/// samples are returned sorted by timestamp.
+pub fn read_tum_positions<R: BufRead>(reader: R) -> std::io::Result<Vec<(i64, [f64; 3])>> {
+    let mut positions = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<f64> = line.split_whitespace().filter_map(|field| field.parse().ok()).collect();
+        let [seconds, x, y, z, ..] = fields[..] else {
+            return Err(invalid_line(index, line));
+        };
+        positions.push(((seconds * 1e9).round() as i64, [x, y, z]));
+    }
+    positions.sort_by_key(|&(timestamp_ns, _)| timestamp_ns);
+    Ok(positions)
+}
+
+/// Read a KITTI pose file, such as one written by `write_kitti`, one pose per line
+pub fn read_kitti<R: BufRead>(reader: R) -> std::io::Result<Vec<CUVSLAM_Pose>> {
+    read_kitti_rows(reader).map(|rows| {
+        rows.iter()
+            .map(|row| CUVSLAM_Pose {
+                // Column-major storage
+                r: std::array::from_fn(|i| row[(i % 3) * 4 + i / 3] as f32),
+                t: [row[3], row[7], row[11]].map(|value| value as f32),
+            })
+            .collect()
+    })
+}
+
+/// Read the positions of a KITTI pose file, one per line, at full precision
+pub fn read_kitti_positions<R: BufRead>(reader: R) -> std::io::Result<Vec<[f64; 3]>> {
+    read_kitti_rows(reader).map(|rows| rows.iter().map(|row| [row[3], row[7], row[11]]).collect())
+}
+
+/// The 3x4 row-major matrix on each non-blank line
+fn read_kitti_rows<R: BufRead>(reader: R) -> std::io::Result<Vec<[f64; 12]>> {
+    let mut rows = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<f64> = line.split_whitespace().filter_map(|field| field.parse().ok()).collect();
+        rows.push(fields.try_into().map_err(|_| invalid_line(index, &line))?);
+    }
+    Ok(rows)
+}
+
+fn invalid_line(index: usize, line: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("line {}: malformed pose {:?}", index + 1, line))
+}
+
+/// The ground truth position closest in time to `timestamp_ns`, if within `max_time_difference_ns`
+fn nearest_position(
+    ground_truth: &[(i64, [f64; 3])],
+    timestamp_ns: i64,
+    max_time_difference_ns: i64,
+) -> Option<[f64; 3]> {
+    let index = ground_truth.partition_point(|&(t, _)| t < timestamp_ns);
+    [index.checked_sub(1), Some(index)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| ground_truth.get(i))
+        .min_by_key(|(t, _)| (t - timestamp_ns).abs())
+        .filter(|(t, _)| (t - timestamp_ns).abs() <= max_time_difference_ns)
+        .map(|&(_, truth)| truth)
+}
+
+/// Error statistics of `pairs` under `alignment`
+fn report(pairs: &[PositionPair], alignment: &Alignment) -> AteReport {
+    let errors: Vec<f64> = pairs.iter().map(|pair| error(alignment, pair)).collect();
+    let n = errors.len() as f64;
+    AteReport {
+        matched: errors.len(),
+        rmse_m: (errors.iter().map(|e| e * e).sum::<f64>() / n).sqrt(),
+        mean_m: errors.iter().sum::<f64>() / n,
+        max_m: errors.iter().copied().fold(0.0, f64::max),
+    }
+}
+
+/// Distance between an aligned estimate and its ground truth
+fn error((rotation, translation): &Alignment, (estimate, truth): &PositionPair) -> f64 {
+    let aligned: [f64; 3] =
+        std::array::from_fn(|i| (0..3).map(|k| rotation[i][k] * estimate[k]).sum::<f64>() + translation[i]);
+    (0..3).map(|i| (aligned[i] - truth[i]).powi(2)).sum::<f64>().sqrt()
+}
+
+/// An estimated position and the ground truth position it was matched to
+type PositionPair = ([f64; 3], [f64; 3]);
+
+/// Rotation (row-major) and translation taking estimated positions onto ground truth
+type Alignment = ([[f64; 3]; 3], [f64; 3]);
+
+/// Rotation and translation taking the first points of `pairs` onto the second
+fn align(pairs: &[PositionPair]) -> Alignment {
+    let n = pairs.len() as f64;
+    let centroid = |select: fn(&PositionPair) -> [f64; 3]| -> [f64; 3] {
+        std::array::from_fn(|i| pairs.iter().map(|pair| select(pair)[i]).sum::<f64>() / n)
+    };
+    let (ce, cg) = (centroid(|pair| pair.0), centroid(|pair| pair.1));
+
+    // Cross-covariance s[a][b] = sum of e_a * g_b over centered points
+    let mut s = [[0.0; 3]; 3];
+    for (e, g) in pairs {
+        for a in 0..3 {
+            for b in 0..3 {
+                s[a][b] += (e[a] - ce[a]) * (g[b] - cg[b]);
+            }
+        }
+    }
+
+    // Horn: the optimal rotation is the eigenvector of n with the largest eigenvalue
+    let [[xx, xy, xz], [yx, yy, yz], [zx, zy, zz]] = s;
+    let n = [
+        [xx + yy + zz, yz - zy, zx - xz, xy - yx],
+        [yz - zy, xx - yy - zz, xy + yx, zx + xz],
+        [zx - xz, xy + yx, -xx + yy - zz, yz + zy],
+        [xy - yx, zx + xz, yz + zy, -xx - yy + zz],
+    ];
+    let (values, vectors) = symmetric_eigen(n);
+    let best = (0..4).max_by(|&a, &b| values[a].total_cmp(&values[b])).unwrap();
+    let [w, x, y, z] = std::array::from_fn(|i| vectors[i][best]);
+
+    let rotation = [
+        [w * w + x * x - y * y - z * z, 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+        [2.0 * (x * y + w * z), w * w - x * x + y * y - z * z, 2.0 * (y * z - w * x)],
+        [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), w * w - x * x - y * y + z * z],
+    ];
+    let translation = std::array::from_fn(|i| cg[i] - (0..3).map(|k| rotation[i][k] * ce[k]).sum::<f64>());
+    (rotation, translation)
+}
+
+/// Eigenvalues and eigenvectors (as columns) of a symmetric 4x4 matrix, by cyclic Jacobi rotations
+fn symmetric_eigen(mut a: [[f64; 4]; 4]) -> ([f64; 4], [[f64; 4]; 4]) {
+    let mut v: [[f64; 4]; 4] = std::array::from_fn(|i| std::array::from_fn(|j| (i == j) as u8 as f64));
+    for _ in 0..50 {
+        let off_diagonal: f64 = (0..4)
+            .flat_map(|i| (0..4).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off_diagonal < 1e-30 {
+            break;
+        }
+        for p in 0..3 {
+            for q in p + 1..4 {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                // Rotate columns p and q of a and v, then rows p and q of a
+                for row in a.iter_mut().chain(v.iter_mut()) {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+                let (row_p, row_q) = (a[p], a[q]);
+                for k in 0..4 {
+                    a[p][k] = c * row_p[k] - s * row_q[k];
+                    a[q][k] = s * row_p[k] + c * row_q[k];
+                }
+            }
+        }
+    }
+    (std::array::from_fn(|i| a[i][i]), v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ate_aligns_rotated_trajectory() {
+        let truth: Vec<(i64, [f64; 3])> = (0..20)
+            .map(|i| {
+                let s = i as f64 * 0.3;
+                (i * 50_000_000, [s.cos() * 2.0, s.sin(), 0.1 * s])
+            })
+            .collect();
+
+        // Same path seen from a world frame rotated 90 degrees about z and shifted
+        let estimated: Vec<(i64, [f64; 3])> = truth
+            .iter()
+            .map(|&(t, [x, y, z])| (t + 1_000_000, [-y + 5.0, x - 1.0, z + 0.5]))
+            .collect();
+        let report = absolute_trajectory_error(&estimated, &truth, 5_000_000).unwrap();
+        assert_eq!(report.matched, 20);
+        assert!(report.rmse_m < 1e-9, "{:?}", report);
+
+        // A 10cm error on one point is mostly left on that point after alignment
+        let mut offset = estimated.clone();
+        offset[7].1[2] += 0.1;
+        let report = absolute_trajectory_error(&offset, &truth, 5_000_000).unwrap();
+        assert!(report.max_m > 0.05 && report.max_m < 0.1);
+
+        assert!(absolute_trajectory_error(&estimated, &truth, 0).is_none());
+    }
+
+    #[test]
+    fn test_running_ate_matches_batch() {
+        // Ground truth ends at 1.5s, before the estimates do
+        let truth: Vec<(i64, [f64; 3])> = (0..30)
+            .map(|i| {
+                let s = i as f64 * 0.2;
+                (i * 50_000_000, [s.cos() * 2.0, s.sin(), 0.1 * s])
+            })
+            .collect();
+        let estimated: Vec<(i64, [f64; 3])> = (0..40)
+            .map(|i| {
+                let s = i as f64 * 0.2;
+                let drift = 0.01 * i as f64;
+                (i * 50_000_000 + 1_000_000, [-s.sin() + 5.0 + drift, s.cos() * 2.0 - 1.0, 0.1 * s + 0.5])
+            })
+            .collect();
+
+        let mut running = RunningAte::new(truth.clone(), 5_000_000, 5);
+        let mut errors = Vec::new();
+        for (index, &(timestamp_ns, position)) in estimated.iter().enumerate() {
+            // Tracking lost for a few frames
+            if (12..15).contains(&index) {
+                continue;
+            }
+            errors.push(running.push(timestamp_ns, position));
+            if running.matched() % 5 == 0 && timestamp_ns < 1_500_000_000 {
+                let kept: Vec<_> = estimated[..=index]
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| !(12..15).contains(index))
+                    .map(|(_, &estimate)| estimate)
+                    .collect();
+                let batch = absolute_trajectory_error(&kept, &truth, 5_000_000).unwrap();
+                let report = running.report().unwrap();
+                assert_eq!(report.matched, batch.matched);
+                assert!((report.rmse_m - batch.rmse_m).abs() < 1e-9, "{:?} vs {:?}", report, batch);
+                assert!((report.max_m - batch.max_m).abs() < 1e-9);
+            }
+        }
+        assert_eq!(&errors[..2], &[None, None]);
+        assert!(errors[2..27].iter().all(Option::is_some));
+        // Nothing after the ground truth ends is evaluated
+        assert!(errors[27..].iter().all(Option::is_none));
+        assert_eq!(running.matched(), 27);
+        assert_eq!(running.ground_truth_end_ns(), Some(1_450_000_000));
+
+        // The aligned ground truth lies on the estimated path, up to the drift
+        let aligned = running.aligned_ground_truth().unwrap();
+        let distance = (0..3).map(|i| (aligned[0][i] - estimated[0].1[i]).powi(2)).sum::<f64>().sqrt();
+        assert!(distance < 0.2, "{}", distance);
+        assert!(RunningAte::new(truth, 5_000_000, 5).report().is_none());
+    }
+
+    #[test]
+    fn test_ground_truth_interpolator() {
+        let sample = |timestamp_ns, degrees: f32, t| {
+            let half = degrees.to_radians() / 2.0;
+            PoseEstimate {
+                pose: pose_from_quaternion([0.0, 0.0, half.sin(), half.cos()], t),
+                timestamp_ns,
+                covariance: [timestamp_ns as f32; 36],
+            }
+        };
+        let gt = GroundTruthInterpolator::new(vec![
+            sample(200, 170.0, [2.0, 0.0, 4.0]),
+            sample(100, 0.0, [0.0, 0.0, 0.0]),
+            sample(300, -170.0, [2.0, 0.0, 4.0]),
+        ]);
+
+        let exact = gt.query(100).unwrap();
+        assert_eq!((exact.pose.t, exact.covariance[0]), ([0.0; 3], 100.0));
+
+        // A quarter of the way from 0 to 170 degrees about z
+        let quarter = gt.query(125).unwrap();
+        assert_eq!(quarter.timestamp_ns, 125);
+        assert_eq!(quarter.pose.t, [0.5, 0.0, 1.0]);
+        assert_eq!(quarter.covariance[0], 100.0);
+        let angle = quarter.pose.r[1].atan2(quarter.pose.r[0]).to_degrees();
+        assert!((angle - 42.5).abs() < 1e-3, "{}", angle);
+
+        // From 170 to -170 degrees the short way, through 180
+        let middle = gt.query(250).unwrap();
+        assert!((middle.pose.r[0] + 1.0).abs() < 1e-5 && middle.pose.r[1].abs() < 1e-5, "{:?}", middle.pose.r);
+        assert_eq!(middle.covariance[0], 300.0);
+
+        assert!(gt.query(99).is_none() && gt.query(301).is_none());
+        assert!(gt.query(300).is_some());
+        assert!(GroundTruthInterpolator::new(Vec::new()).query(0).is_none());
+    }
+
+    #[test]
+    fn test_log_trajectory_to_rrd() {
+        let poses: Vec<PoseEstimate> = (0..5)
+            .map(|i| PoseEstimate {
+                pose: CUVSLAM_Pose {
+                    r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+                    t: [i as f32, 0.0, 0.0],
+                },
+                timestamp_ns: i * 33_000_000,
+                covariance: [0.0; 36],
+            })
+            .collect();
+        let landmarks = [CUVSLAM_Landmark { id: 1, x: 0.5, y: 1.0, z: -2.0 }];
+        let path = std::env::temp_dir().join(format!("cuvslam-trajectory-{}.rrd", std::process::id()));
+        log_trajectory_to_rrd(&poses, &landmarks, &path).unwrap();
+        let size = std::fs::metadata(&path).unwrap().len();
+        std::fs::remove_file(&path).unwrap();
+        assert!(size > 0);
+    }
+
+    #[test]
+    fn test_read_trajectories() {
+        let tum = "# timestamp tx ty tz qx qy qz qw\n2.5 4 5 6 0 0 0 1\n\n1.0 1 2 3 0 0 0 1\n";
+        let positions = read_tum_positions(tum.as_bytes()).unwrap();
+        assert_eq!(positions, vec![(1_000_000_000, [1.0, 2.0, 3.0]), (2_500_000_000, [4.0, 5.0, 6.0])]);
+        assert!(read_tum_positions("1.0 1 2".as_bytes()).is_err());
+
+        let kitti = "1 0 0 1 0 1 0 2 0 0 1 3\n1 0 0 4 0 1 0 5 0 0 1 6\n";
+        assert_eq!(read_kitti_positions(kitti.as_bytes()).unwrap(), vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        assert!(read_kitti_positions("1 0 0 1".as_bytes()).is_err());
+
+        // Quarter turn about z at (1, 2, 3)
+        let pose = CUVSLAM_Pose {
+            r: [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            t: [1.0, 2.0, 3.0],
+        };
+        let mut out = Vec::new();
+        write_kitti(&mut out, &[pose]).unwrap();
+        assert_eq!(read_kitti(out.as_slice()).unwrap(), vec![pose]);
+    }
+
+    #[test]
+    fn test_write_ros_poses_csv() {
+        // 2m forward (-z) after turning left by a quarter turn about cuVSLAM's up axis (+y)
+        let pose = CUVSLAM_Pose {
+            r: [0.0, 0.0, -1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0],
+            t: [0.0, 0.0, -2.0],
+        };
+        let ros = pose_to_ros(&pose);
+        assert_eq!(ros.t, [2.0, 0.0, 0.0]);
+
+        let estimate = PoseEstimate { pose, timestamp_ns: 1_700_000_000_000_000_005, covariance: [0.0; 36] };
+        let mut out = Vec::new();
+        write_ros_poses_csv(&mut out, &[estimate]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("stamp,x,y,z,qx,qy,qz,qw"));
+        let line = lines.next().unwrap();
+        assert!(line.starts_with("1700000000.000000005,2,0,0,"), "{}", line);
+        // A left turn is a positive rotation about ROS's up axis (+z)
+        let fields: Vec<f64> = line.split(',').map(|field| field.parse().unwrap()).collect();
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert!(fields[4].abs() < 1e-6 && fields[5].abs() < 1e-6, "{}", line);
+        assert!((fields[6] - half).abs() < 1e-6 && (fields[7] - half).abs() < 1e-6, "{}", line);
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn test_write_trajectories_and_pose_algebra() {
+        // Quarter turn about z at (1, 2, 3)
+        let pose = CUVSLAM_Pose {
+            r: [0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            t: [1.0, 2.0, 3.0],
+        };
+        let identity = compose_poses(&pose, &invert_pose(&pose));
+        for (value, expected) in identity.r.iter().zip([1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]) {
+            assert!((value - expected).abs() < 1e-6);
+        }
+        assert!(identity.t.iter().all(|t| t.abs() < 1e-6));
+
+        let estimate = PoseEstimate {
+            pose,
+            timestamp_ns: 1_403_636_579_763_555_584,
+            covariance: [0.0; 36],
+        };
+        let mut out = Vec::new();
+        write_tum(&mut out, &[estimate]).unwrap();
+        let line = String::from_utf8(out).unwrap();
+        let fields: Vec<f64> = line.split_whitespace().map(|field| field.parse().unwrap()).collect();
+        assert_eq!(fields.len(), 8);
+        assert!((fields[0] - 1_403_636_579.763_555_6).abs() < 1e-6);
+        assert_eq!(&fields[1..4], &[1.0, 2.0, 3.0]);
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((fields[6] - half).abs() < 1e-6 && (fields[7] - half).abs() < 1e-6);
+
+        let mut out = Vec::new();
+        write_kitti(&mut out, &[pose]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "0 -1 0 1 1 0 0 2 0 0 1 3\n");
+    }
+}
@@ -0,0 +1,253 @@
+//! Video file input through FFmpeg (`video` feature)
+
+use crate::{Calibration, CameraRig, Error, FrameSet, FrameSource, Image, ImageEncoding};
+use ffmpeg_next::{codec, decoder, format, frame, media, software::scaling};
+use std::path::Path;
+use std::time::Duration;
+
+/// How the cameras of a rig are laid out in each video frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoLayout {
+    /// One camera filling the frame
+    Single,
+    /// Two cameras side by side, camera 0 in the left half and camera 1 in the right
+    SideBySide,
+}
+
+/// `FrameSource` decoding the video stream of a file, such as a dashcam recording
+///
+/// Frames are converted to `Mono8`. Timestamps come from the container's presentation times,
+/// relative to the start of the stream, so they line up with the file's timeline rather than the
+/// wall-clock time of the recording. `with_frame_rate` stamps frames at a fixed rate instead.
+pub struct VideoSource {
+    input: format::context::Input,
+    decoder: decoder::Video,
+    scaler: Option<scaling::Context>,
+    stream_index: usize,
+    /// Stream time base in nanoseconds per tick
+    tick_ns: f64,
+    start_pts: i64,
+    frame_period_ns: i64,
+    /// Period of `with_frame_rate`, and the index of the next frame once one has been stamped
+    fixed_period_ns: Option<i64>,
+    next_index: Option<i64>,
+    layout: VideoLayout,
+    skip_until_ns: i64,
+    remaining: Option<usize>,
+    last_timestamp_ns: Option<i64>,
+    flushed: bool,
+    calibration: Option<Calibration>,
+}
+
+impl VideoSource {
+    /// Open the best video stream of the file at `path`
+    #[must_use = "the source or the reason it could not be opened is only in the Result"]
+    pub fn open<P: AsRef<Path>>(path: P, layout: VideoLayout) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let error = |error: ffmpeg_next::Error| Error::Dataset(format!("{}: {}", path.display(), error));
+        ffmpeg_next::init().map_err(error)?;
+        let input = format::input(path).map_err(error)?;
+        let stream = input
+            .streams()
+            .best(media::Type::Video)
+            .ok_or_else(|| Error::Dataset(format!("{}: no video stream", path.display())))?;
+        let decoder = codec::context::Context::from_parameters(stream.parameters())
+            .and_then(|context| context.decoder().video())
+            .map_err(error)?;
+        if layout == VideoLayout::SideBySide && decoder.width() % 2 != 0 {
+            return Err(Error::Dataset(format!(
+                "{}: side-by-side video has an odd width {}",
+                path.display(),
+                decoder.width()
+            )));
+        }
+
+        let tick_ns = f64::from(stream.time_base()) * 1e9;
+        let frame_rate = f64::from(stream.avg_frame_rate());
+        let frame_period_ns = if frame_rate > 0.0 { (1e9 / frame_rate) as i64 } else { 0 };
+        let start_pts = Some(stream.start_time()).filter(|&start| start != ffmpeg_next::ffi::AV_NOPTS_VALUE);
+        let stream_index = stream.index();
+        Ok(Self {
+            input,
+            decoder,
+            scaler: None,
+            stream_index,
+            tick_ns,
+            start_pts: start_pts.unwrap_or(0),
+            frame_period_ns,
+            fixed_period_ns: None,
+            next_index: None,
+            layout,
+            skip_until_ns: 0,
+            remaining: None,
+            last_timestamp_ns: None,
+            flushed: false,
+            calibration: None,
+        })
+    }
+
+    /// Continue from the first frame at or after `offset` from the start of the stream
+    ///
+    /// Seeks to the preceding keyframe and decodes forward from there, dropping frames before
+    /// `offset`. Frames already counted against `with_max_frames` are not given back.
+    #[must_use = "a failed seek is only reported through the Result"]
+    pub fn seek(&mut self, offset: Duration) -> Result<(), Error> {
+        let offset_ns = offset.as_nanos() as i64;
+        // Without a stream index the target is in AV_TIME_BASE units, microseconds
+        let start_us = (self.start_pts as f64 * self.tick_ns / 1e3) as i64;
+        let target_us = start_us + offset_ns / 1_000;
+        self.input
+            .seek(target_us, ..target_us + 1)
+            .map_err(|error| Error::Dataset(format!("seek to {:?}: {}", offset, error)))?;
+        self.decoder.flush();
+        self.skip_until_ns = offset_ns;
+        self.last_timestamp_ns = None;
+        self.next_index = None;
+        self.flushed = false;
+        Ok(())
+    }
+
+    /// Stop after yielding `frames` more frames
+    pub fn with_max_frames(mut self, frames: usize) -> Self {
+        self.remaining = Some(frames);
+        self
+    }
+
+    /// Stamp frame `n` of the stream at `n / fps` seconds instead of its presentation time
+    ///
+    /// For files whose timestamps are missing or wrong, such as screen recordings with a variable
+    /// frame rate. Frame numbers count from the start of the stream; after `seek` they are
+    /// estimated from the presentation time the decoder lands on.
+    pub fn with_frame_rate(mut self, fps: f64) -> Self {
+        self.fixed_period_ns = Some((1e9 / fps).round() as i64);
+        self
+    }
+
+    /// Average frame rate of the stream in frames per second, if the container gives one
+    pub fn frame_rate(&self) -> Option<f64> {
+        (self.frame_period_ns > 0).then(|| 1e9 / self.frame_period_ns as f64)
+    }
+
+    /// Report `calibration` as the rig the video was recorded with
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    /// Decode the next frame of the stream, feeding packets as needed
+    fn decode(&mut self) -> Result<Option<frame::Video>, ffmpeg_next::Error> {
+        let mut decoded = frame::Video::empty();
+        loop {
+            match self.decoder.receive_frame(&mut decoded) {
+                Ok(()) => return Ok(Some(decoded)),
+                Err(ffmpeg_next::Error::Eof) => return Ok(None),
+                Err(ffmpeg_next::Error::Other { errno: ffmpeg_next::error::EAGAIN }) => {}
+                Err(error) => return Err(error),
+            }
+            if self.flushed {
+                return Ok(None);
+            }
+
+            let mut packet = ffmpeg_next::Packet::empty();
+            match packet.read(&mut self.input) {
+                Ok(()) if packet.stream() == self.stream_index => self.decoder.send_packet(&packet)?,
+                Ok(()) => {}
+                Err(ffmpeg_next::Error::Eof) => {
+                    self.decoder.send_eof()?;
+                    self.flushed = true;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Timestamp of a decoded frame, counting on from the previous one if it has none
+    fn timestamp_ns(&self, decoded: &frame::Video) -> i64 {
+        match decoded.timestamp().or(decoded.pts()) {
+            Some(pts) => ((pts - self.start_pts) as f64 * self.tick_ns).round() as i64,
+            None => self.last_timestamp_ns.map_or(0, |last| last + self.frame_period_ns),
+        }
+    }
+
+    /// Number of a decoded frame in the stream, counting on from the previous frame
+    fn frame_index(&self, timestamp_ns: i64) -> i64 {
+        match (self.next_index, self.frame_period_ns) {
+            (Some(index), _) => index,
+            (None, 0) => 0,
+            (None, period_ns) => (timestamp_ns as f64 / period_ns as f64).round() as i64,
+        }
+    }
+
+    /// Convert a decoded frame to one `Mono8` image per camera
+    fn to_images(&mut self, decoded: &frame::Video, timestamp_ns: i64) -> Result<Vec<Image>, Error> {
+        let conversion_error = |error: ffmpeg_next::Error| Error::Dataset(format!("grayscale conversion: {}", error));
+        let (width, height) = (decoded.width(), decoded.height());
+        let scaler = match &mut self.scaler {
+            Some(scaler) => scaler,
+            slot => slot.insert(
+                scaling::Context::get(
+                    decoded.format(),
+                    width,
+                    height,
+                    format::Pixel::GRAY8,
+                    width,
+                    height,
+                    scaling::Flags::BILINEAR,
+                )
+                .map_err(conversion_error)?,
+            ),
+        };
+        let mut gray = frame::Video::empty();
+        scaler.run(decoded, &mut gray).map_err(conversion_error)?;
+
+        let (width, height, stride) = (width as usize, height as usize, gray.stride(0));
+        let data = gray.data(0);
+        let crop = |x: usize, crop_width: usize| -> Vec<u8> {
+            (0..height).flat_map(|y| &data[y * stride + x..y * stride + x + crop_width]).copied().collect()
+        };
+        let halves = match self.layout {
+            VideoLayout::Single => vec![crop(0, width)],
+            VideoLayout::SideBySide => vec![crop(0, width / 2), crop(width / 2, width / 2)],
+        };
+        let camera_width = (width / halves.len()) as i32;
+        halves
+            .into_iter()
+            .zip(0..)
+            .map(|(pixels, camera_index)| {
+                Image::new(pixels, camera_width, height as i32, ImageEncoding::Mono8, camera_index, timestamp_ns)
+                    .map_err(Error::Status)
+            })
+            .collect()
+    }
+}
+
+impl FrameSource for VideoSource {
+    fn next_frame(&mut self) -> Option<Result<FrameSet, Error>> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        loop {
+            let decoded = match self.decode() {
+                Ok(Some(decoded)) => decoded,
+                Ok(None) => return None,
+                Err(error) => return Some(Err(Error::Dataset(format!("decoding: {}", error)))),
+            };
+            let timestamp_ns = self.timestamp_ns(&decoded);
+            self.last_timestamp_ns = Some(timestamp_ns);
+            let index = self.frame_index(timestamp_ns);
+            self.next_index = Some(index + 1);
+            if timestamp_ns < self.skip_until_ns {
+                continue;
+            }
+            let timestamp_ns = self.fixed_period_ns.map_or(timestamp_ns, |period_ns| index * period_ns);
+            if let Some(remaining) = &mut self.remaining {
+                *remaining -= 1;
+            }
+            return Some(self.to_images(&decoded, timestamp_ns).map(|images| FrameSet { timestamp_ns, images }));
+        }
+    }
+
+    fn calibration(&self) -> Option<CameraRig> {
+        self.calibration.as_ref()?.to_rig().ok()
+    }
+}
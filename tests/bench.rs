@@ -0,0 +1,18 @@
+//! Bench binary on synthetic frames
+
+#![cfg(feature = "datasets")]
+
+#[test]
+#[ignore = "needs a CUDA device"]
+fn test_synthetic_json_report() {
+    let result = std::process::Command::new(env!("CARGO_BIN_EXE_bench"))
+        .args(["--synthetic", "--frames", "30", "--json"])
+        .output()
+        .unwrap();
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    assert_eq!(report["frames"], 30);
+    assert_eq!(report["tracked"].as_u64().unwrap() + report["dropped"].as_u64().unwrap(), 30);
+    assert!(report["fps"].as_f64().unwrap() > 0.0);
+    assert!(report["latency_ms"]["p99"].as_f64().unwrap() >= report["latency_ms"]["p50"].as_f64().unwrap());
+}
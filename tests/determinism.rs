@@ -0,0 +1,35 @@
+mod support;
+
+use cuvslam::{ConfigurationBuilder, Tracker};
+
+const WIDTH: i32 = 640;
+const HEIGHT: i32 = 480;
+
+/// Track a synthetic sequence with a fresh tracker, returning the translation of every pose
+fn run_sequence(frames: i32) -> Vec<[f32; 3]> {
+    let config = ConfigurationBuilder::new().deterministic(true).build();
+    let Ok(tracker) = Tracker::new(support::stereo_rig(WIDTH, HEIGHT), &config) else {
+        panic!("tracker creation failed");
+    };
+
+    (0..frames)
+        .filter_map(|i| {
+            let images = support::stereo_frame(WIDTH, HEIGHT, i, 8, i as i64 * 33_333_333);
+            tracker.track(&images, None).ok().map(|estimate| estimate.pose.t)
+        })
+        .collect()
+}
+
+#[test]
+#[ignore = "needs a CUDA device"]
+fn test_deterministic_trajectories_match() {
+    let first = run_sequence(50);
+    let second = run_sequence(50);
+
+    assert_eq!(first.len(), second.len());
+    for (a, b) in first.iter().zip(&second) {
+        for axis in 0..3 {
+            assert!((a[axis] - b[axis]).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+}
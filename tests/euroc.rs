@@ -0,0 +1,236 @@
+//! EuRoC reader and player on a generated 20-frame sequence in the ASL layout
+//!
+//! The fixture is written to a temporary directory rather than checked in: synthetic textured
+//! frames with matching calibration, 200 Hz IMU and ground truth files.
+
+#![cfg(feature = "datasets")]
+
+mod support;
+
+use cuvslam::datasets::euroc::EurocSequence;
+use cuvslam::{absolute_trajectory_error, write_tum, PoseEstimate, Tracker, CUVSLAM_Pose};
+use std::path::{Path, PathBuf};
+
+const WIDTH: i32 = 640;
+const HEIGHT: i32 = 480;
+const FRAMES: i64 = 20;
+const FRAME_PERIOD_NS: i64 = 50_000_000;
+const IMU_PERIOD_NS: i64 = 5_000_000;
+const START_NS: i64 = 1_403_636_579_763_555_584;
+
+/// Write the fixture sequence under a fresh temporary directory, returning the sequence directory
+fn write_fixture(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cuvslam-euroc-{}-{}", name, std::process::id()));
+    let mav0 = dir.join("mav0");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    for (camera, x, disparity) in [("cam0", 0.0, 0), ("cam1", support::BASELINE, 8)] {
+        let camera_dir = mav0.join(camera);
+        std::fs::create_dir_all(camera_dir.join("data")).unwrap();
+        let mut csv = String::from("#timestamp [ns],filename\n");
+        for i in 0..FRAMES {
+            let timestamp_ns = START_NS + i * FRAME_PERIOD_NS;
+            let pixels = support::textured_frame(WIDTH, HEIGHT, i as i32 + disparity);
+            image::GrayImage::from_raw(WIDTH as u32, HEIGHT as u32, pixels)
+                .unwrap()
+                .save(camera_dir.join("data").join(format!("{}.png", timestamp_ns)))
+                .unwrap();
+            csv += &format!("{},{}.png\n", timestamp_ns, timestamp_ns);
+        }
+        std::fs::write(camera_dir.join("data.csv"), csv).unwrap();
+        std::fs::write(
+            camera_dir.join("sensor.yaml"),
+            format!(
+                "%YAML:1.0
+sensor_type: camera
+T_BS:
+  cols: 4
+  rows: 4
+  data: [1.0, 0.0, 0.0, {},
+         0.0, 1.0, 0.0, 0.0,
+         0.0, 0.0, 1.0, -0.05,
+         0.0, 0.0, 0.0, 1.0]
+rate_hz: 20
+resolution: [{}, {}]
+camera_model: pinhole
+intrinsics: [{}, {}, {}, {}]
+distortion_model: radial-tangential
+distortion_coefficients: [0.0, 0.0, 0.0, 0.0]
+",
+                x,
+                WIDTH,
+                HEIGHT,
+                WIDTH as f32 * 0.87,
+                WIDTH as f32 * 0.87,
+                WIDTH as f32 / 2.0,
+                HEIGHT as f32 / 2.0
+            ),
+        )
+        .unwrap();
+    }
+
+    let imu_dir = mav0.join("imu0");
+    std::fs::create_dir_all(&imu_dir).unwrap();
+    std::fs::write(
+        imu_dir.join("sensor.yaml"),
+        "%YAML:1.0
+sensor_type: imu
+T_BS:
+  cols: 4
+  rows: 4
+  data: [1.0, 0.0, 0.0, 0.0,
+         0.0, 1.0, 0.0, 0.0,
+         0.0, 0.0, 1.0, 0.0,
+         0.0, 0.0, 0.0, 1.0]
+rate_hz: 200
+gyroscope_noise_density: 1.6968e-04
+gyroscope_random_walk: 1.9393e-05
+accelerometer_noise_density: 2.0000e-3
+accelerometer_random_walk: 3.0000e-3
+",
+    )
+    .unwrap();
+    let samples = (FRAMES - 1) * FRAME_PERIOD_NS / IMU_PERIOD_NS + 1;
+    let mut imu = String::from("#timestamp [ns],w_RS_S_x [rad s^-1],w_RS_S_y [rad s^-1],w_RS_S_z [rad s^-1],");
+    imu += "a_RS_S_x [m s^-2],a_RS_S_y [m s^-2],a_RS_S_z [m s^-2]\n";
+    let ground_truth_dir = mav0.join("state_groundtruth_estimate0");
+    std::fs::create_dir_all(&ground_truth_dir).unwrap();
+    let mut ground_truth = String::from("#timestamp, p_RS_R_x [m], p_RS_R_y [m], p_RS_R_z [m], q_RS_w []\n");
+    for i in 0..samples {
+        let timestamp_ns = START_NS + i * IMU_PERIOD_NS;
+        imu += &format!("{},0.0,0.0,0.0,0.0,0.0,9.81\n", timestamp_ns);
+        ground_truth += &format!("{},{},0.0,1.0,1.0,0.0,0.0,0.0\n", timestamp_ns, i as f64 / 1000.0);
+    }
+    std::fs::write(imu_dir.join("data.csv"), imu).unwrap();
+    std::fs::write(ground_truth_dir.join("data.csv"), ground_truth).unwrap();
+    dir
+}
+
+fn remove_fixture(dir: &Path) {
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_open_fixture_sequence() {
+    let dir = write_fixture("open");
+    let sequence = EurocSequence::open(&dir).unwrap();
+
+    assert_eq!(sequence.frames.len(), FRAMES as usize);
+    assert_eq!(sequence.frames[3].timestamp_ns, START_NS + 3 * FRAME_PERIOD_NS);
+    assert_eq!(sequence.imu.len(), 191);
+    assert_eq!(sequence.imu[1].linear_accelerations, [0.0, 0.0, 9.81]);
+    assert_eq!(sequence.ground_truth.len(), 191);
+    assert_eq!(sequence.ground_truth[10].1, [0.01, 0.0, 1.0]);
+
+    // cam1 extrinsics are relative to cam0, the IMU's too
+    let cameras = &sequence.calibration.cameras;
+    assert_eq!(cameras[0].extrinsics.translation, [0.0; 3]);
+    assert_eq!(cameras[1].extrinsics.translation, [support::BASELINE, 0.0, 0.0]);
+    assert_eq!(sequence.rig_in_body.t, [0.0, 0.0, -0.05]);
+    let imu_calibration = sequence.imu_calibration.unwrap();
+    // The cameras' OpenCV axes become cuVSLAM's, flipping y and z
+    assert_eq!(imu_calibration.left_from_imu.r, [1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0]);
+    assert_eq!(imu_calibration.left_from_imu.t, [0.0, 0.0, -0.05]);
+    assert_eq!(imu_calibration.frequency, 200.0);
+
+    let rig = sequence.calibration.to_rig().unwrap();
+    assert_eq!(cuvslam::validate_rig(&rig), Ok(()));
+    let config = sequence.configuration(true);
+    assert_eq!(config.enable_imu_fusion, 1);
+    assert_eq!(cuvslam::validate_configuration(&rig, &config), Ok(()));
+
+    let images = sequence.frames[0].load().unwrap();
+    assert_eq!(images.len(), 2);
+    assert_eq!((images[1].width(), images[1].height(), images[1].camera_index()), (WIDTH, HEIGHT, 1));
+    assert_eq!(images[0].pixels(), support::textured_frame(WIDTH, HEIGHT, 0));
+
+    // The body sits 5cm above cam0
+    let identity = CUVSLAM_Pose { r: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], t: [0.0; 3] };
+    assert_eq!(sequence.body_pose(&identity).t, [0.0, 0.0, 0.05]);
+    remove_fixture(&dir);
+}
+
+#[test]
+#[ignore = "needs a CUDA device"]
+fn test_track_fixture_sequence() {
+    let dir = write_fixture("track");
+    let sequence = EurocSequence::open(&dir).unwrap();
+    let tracker = Tracker::new(sequence.calibration.to_rig().unwrap(), &sequence.configuration(false)).unwrap();
+
+    let estimates: Vec<PoseEstimate> = sequence
+        .frames
+        .iter()
+        .filter_map(|frame| {
+            let images = frame.load().unwrap();
+            let estimate = tracker.track(&images, None).ok()?;
+            Some(PoseEstimate { pose: sequence.body_pose(&estimate.pose), ..estimate })
+        })
+        .collect();
+    assert!(estimates.len() >= FRAMES as usize / 2, "only {} frames tracked", estimates.len());
+
+    let mut tum = Vec::new();
+    write_tum(&mut tum, &estimates).unwrap();
+    assert_eq!(String::from_utf8(tum).unwrap().lines().count(), estimates.len());
+
+    let positions: Vec<_> =
+        estimates.iter().map(|estimate| (estimate.timestamp_ns, estimate.pose.t.map(f64::from))).collect();
+    let ate = absolute_trajectory_error(&positions, &sequence.ground_truth, IMU_PERIOD_NS).unwrap();
+    println!("{:?}", ate);
+    assert_eq!(ate.matched, estimates.len());
+    remove_fixture(&dir);
+}
+
+#[test]
+#[ignore = "needs a CUDA device"]
+fn test_build_map_then_localize() {
+    let dir = write_fixture("relocalize");
+    let db = dir.join("map");
+    let run = |phase: &str, output: &str| {
+        let result = std::process::Command::new(env!("CARGO_BIN_EXE_euroc"))
+            .arg(&dir)
+            .args([phase, db.to_str().unwrap(), "--output", dir.join(output).to_str().unwrap()])
+            .output()
+            .unwrap();
+        assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+        String::from_utf8(result.stdout).unwrap()
+    };
+
+    let built = run("--build-map", "first_half.tum");
+    assert!(built.contains("map saved to"), "{}", built);
+    assert!(db.join("handoff_pose.txt").is_file());
+
+    let localized = run("--localize", "second_half.tum");
+    assert!(localized.contains("localized in"), "{}", localized);
+    assert!(localized.contains("ATE over"), "{}", localized);
+    remove_fixture(&dir);
+}
+
+#[test]
+#[ignore = "needs a CUDA device"]
+fn test_map_merge_second_half() {
+    let dir = write_fixture("merge");
+    let base = dir.join("first_half_map");
+    let merged = dir.join("merged_map");
+    let built = std::process::Command::new(env!("CARGO_BIN_EXE_euroc"))
+        .arg(&dir)
+        .args(["--build-map", base.to_str().unwrap(), "--output", dir.join("first_half.tum").to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(built.status.success(), "{}", String::from_utf8_lossy(&built.stderr));
+
+    // The whole sequence relocalizes at its start, inside the first half's map, and extends it
+    // over the second half
+    let result = std::process::Command::new(env!("CARGO_BIN_EXE_map_merge"))
+        .args([&base, &dir])
+        .arg("--output")
+        .arg(&merged)
+        .output()
+        .unwrap();
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert!(stdout.contains("localized at frame"), "{}", stdout);
+    assert!(stdout.contains("merged 1 of 1 sessions"), "{}", stdout);
+    assert!(stdout.contains("map: "), "{}", stdout);
+    assert!(merged.is_dir());
+    remove_fixture(&dir);
+}
@@ -0,0 +1,89 @@
+//! Recording a synthetic session with `McapRecorder` and playing it back with `McapSource`
+
+#![cfg(feature = "mcap")]
+
+mod support;
+
+use cuvslam::{
+    pose_rotation_quaternion, FrameSource, ImageEncoding, McapRecorder, McapSource, PoseEstimate,
+    CUVSLAM_ImuMeasurement, CUVSLAM_Pose,
+};
+use std::path::PathBuf;
+
+const WIDTH: i32 = 64;
+const HEIGHT: i32 = 48;
+const FRAMES: i64 = 4;
+const FRAME_PERIOD_NS: i64 = 50_000_000;
+const IMU_PER_FRAME: i64 = 5;
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("cuvslam-{}-{}.mcap", name, std::process::id()))
+}
+
+/// Pose moving along x while turning about y
+fn pose_at(frame: i64) -> CUVSLAM_Pose {
+    let angle = 0.1 * frame as f32;
+    CUVSLAM_Pose {
+        r: [angle.cos(), 0.0, -angle.sin(), 0.0, 1.0, 0.0, angle.sin(), 0.0, angle.cos()],
+        t: [0.2 * frame as f32, 0.0, -0.05],
+    }
+}
+
+fn record_session(path: &PathBuf) {
+    let mut recorder = McapRecorder::create(path).unwrap();
+    for frame in 0..FRAMES {
+        let timestamp_ns = frame * FRAME_PERIOD_NS;
+        for sample in 0..IMU_PER_FRAME {
+            recorder
+                .record_imu(&CUVSLAM_ImuMeasurement {
+                    timestamp_ns: timestamp_ns - FRAME_PERIOD_NS + (sample + 1) * FRAME_PERIOD_NS / IMU_PER_FRAME,
+                    linear_accelerations: [0.0, 9.81, 0.0],
+                    angular_velocities: [0.0, 2.0, 0.0],
+                })
+                .unwrap();
+        }
+        recorder.record_frame(&support::stereo_frame(WIDTH, HEIGHT, frame as i32, 8, timestamp_ns)).unwrap();
+        let estimate = PoseEstimate { pose: pose_at(frame), timestamp_ns, covariance: [0.0; 36] };
+        recorder.record_pose(&estimate).unwrap();
+    }
+    recorder.finish().unwrap();
+}
+
+#[test]
+fn test_record_and_replay() {
+    let path = temp_file("mcap-round-trip");
+    record_session(&path);
+
+    let topics = [McapRecorder::image_topic(0), McapRecorder::image_topic(1)];
+    let mut source =
+        McapSource::open(&path, &[&topics[0], &topics[1]], 0).unwrap().with_imu_topic(McapRecorder::IMU_TOPIC).unwrap();
+    assert!(source.calibration().is_none());
+
+    let mut frames = 0;
+    while let Some(frame) = source.next_frame() {
+        let frame = frame.unwrap();
+        assert_eq!(frame.timestamp_ns, frames * FRAME_PERIOD_NS);
+        let [left, right] = &frame.images[..] else { panic!("expected two images") };
+        assert_eq!((left.width(), left.height(), left.encoding()), (WIDTH, HEIGHT, ImageEncoding::Mono8));
+        assert_eq!((left.camera_index(), right.camera_index()), (0, 1));
+        assert_eq!(left.pixels(), support::textured_frame(WIDTH, HEIGHT, frames as i32));
+        assert_eq!(right.pixels(), support::textured_frame(WIDTH, HEIGHT, frames as i32 + 8));
+        assert_eq!(source.drain_imu().len() as i64, IMU_PER_FRAME);
+        frames += 1;
+    }
+    assert_eq!(frames, FRAMES);
+
+    let poses = source.read_poses(McapRecorder::POSE_TOPIC).unwrap();
+    assert_eq!(poses.len() as i64, FRAMES);
+    for (frame, (timestamp_ns, pose)) in (0..).zip(&poses) {
+        let expected = pose_at(frame);
+        assert_eq!(*timestamp_ns, frame * FRAME_PERIOD_NS);
+        assert_eq!(pose.t, expected.t);
+        let (q, expected_q) = (pose_rotation_quaternion(pose), pose_rotation_quaternion(&expected));
+        assert!(q.iter().zip(expected_q).all(|(a, b)| (a - b).abs() < 1e-6), "{:?} != {:?}", q, expected_q);
+    }
+
+    assert!(source.read_poses(&topics[0]).is_err());
+    assert!(McapSource::open(&path, &[McapRecorder::POSE_TOPIC], 0).is_err());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,92 @@
+mod support;
+
+use cuvslam::{ConfigurationBuilder, Tracker, CUVSLAM_Pose};
+
+const WIDTH: i32 = 640;
+const HEIGHT: i32 = 480;
+const FRAMES: i32 = 60;
+/// Frame the second session starts from
+const RESTART: i32 = 30;
+
+fn mapping_tracker() -> Tracker {
+    let mut config = ConfigurationBuilder::new().deterministic(true).build();
+    config.enable_localization_n_mapping = 1;
+    let Ok(tracker) = Tracker::new(support::stereo_rig(WIDTH, HEIGHT), &config) else {
+        panic!("tracker creation failed");
+    };
+    tracker
+}
+
+fn timestamp_ns(frame: i32) -> i64 {
+    frame as i64 * 33_333_333
+}
+
+#[test]
+#[ignore = "needs a CUDA device"]
+fn test_poses_after_relocalization_follow_saved_map() {
+    let map_dir = std::env::temp_dir().join(format!("cuvslam-relocalization-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&map_dir);
+    let map_dir = map_dir.to_str().unwrap().to_owned();
+
+    // First session maps the whole sequence
+    let tracker = mapping_tracker();
+    let saved: Vec<CUVSLAM_Pose> = (0..FRAMES)
+        .map(|i| {
+            let images = support::stereo_frame(WIDTH, HEIGHT, i, 8, timestamp_ns(i));
+            tracker.track(&images, None).expect("first session tracks every frame").pose
+        })
+        .collect();
+    tracker.save_to_slam_db(&map_dir).unwrap();
+    drop(tracker);
+
+    // Second session starts halfway, so its odometry frame is offset from the map's
+    let tracker = mapping_tracker();
+    let images = support::stereo_frame(WIDTH, HEIGHT, RESTART, 8, timestamp_ns(RESTART));
+    tracker.track(&images, None).unwrap();
+    assert!(tracker.world_from_odometry().is_none());
+    let pose_in_db = tracker.localize_in_db(&map_dir, &saved[RESTART as usize], 0.5, &images).unwrap();
+    assert!(tracker.world_from_odometry().is_some());
+
+    let close = |a: &CUVSLAM_Pose, b: &CUVSLAM_Pose| (0..3).all(|axis| (a.t[axis] - b.t[axis]).abs() < 0.05);
+    assert!(close(&pose_in_db, &saved[RESTART as usize]), "{:?} != {:?}", pose_in_db, saved[RESTART as usize]);
+    for i in RESTART + 1..FRAMES {
+        let images = support::stereo_frame(WIDTH, HEIGHT, i, 8, timestamp_ns(i));
+        let pose = tracker.track(&images, None).unwrap().pose;
+        assert!(close(&pose, &saved[i as usize]), "frame {}: {:?} != {:?}", i, pose, saved[i as usize]);
+    }
+    std::fs::remove_dir_all(map_dir).unwrap();
+}
+
+#[test]
+#[ignore = "needs a CUDA device"]
+fn test_merged_db_holds_both_halves() {
+    let dir = std::env::temp_dir().join(format!("cuvslam-merge-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let halves = [dir.join("first"), dir.join("second")];
+
+    // Each half is mapped by its own session
+    let mut saved = Vec::new();
+    for (frames, db) in [0..RESTART, RESTART..FRAMES].into_iter().zip(&halves) {
+        let tracker = mapping_tracker();
+        for i in frames {
+            let images = support::stereo_frame(WIDTH, HEIGHT, i, 8, timestamp_ns(i));
+            saved.push(tracker.track(&images, None).expect("mapping sessions track every frame").pose);
+        }
+        tracker.save_to_slam_db(db.to_str().unwrap()).unwrap();
+    }
+    let merged = dir.join("merged");
+    let rig = support::stereo_rig(WIDTH, HEIGHT);
+    cuvslam::merge_slam_dbs(&rig, &[&halves[0], &halves[1]], &merged).unwrap();
+    assert!(matches!(cuvslam::merge_slam_dbs(&rig, &[&halves[0]], &merged), Err(cuvslam::Status::InvalidArg)));
+
+    // The first frame of each half relocalizes in the merged map, searching around the pose its
+    // session tracked it at
+    for frame in [0, RESTART] {
+        let tracker = mapping_tracker();
+        let images = support::stereo_frame(WIDTH, HEIGHT, frame, 8, timestamp_ns(frame));
+        tracker.track(&images, None).unwrap();
+        let result = tracker.localize_in_db(merged.to_str().unwrap(), &saved[frame as usize], 1.0, &images);
+        assert!(result.is_ok(), "frame {}: {:?}", frame, result);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,203 @@
+//! Reading ROS 1 bag files written on the fly, with uncompressed and LZ4 compressed chunks
+//!
+//! The bag holds three stereo frames of 4x2 images, raw `mono8` on the left and PNG compressed
+//! on the right, with `camera_info` for both cameras and an unrelated topic. An extra left image
+//! with no right match checks that unpaired images are skipped.
+
+#![cfg(feature = "rosbag1")]
+
+use cuvslam::{FrameSource, Image, ImageEncoding, RosBagReader};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const LEFT: &str = "/stereo/left/image_raw";
+const RIGHT: &str = "/stereo/right/image_raw/compressed";
+const FRAME_STAMPS_NS: [i64; 3] = [1_100_000_000, 1_200_000_000, 1_300_000_000];
+const UNPAIRED_STAMP_NS: i64 = 1_150_000_000;
+
+/// ROS 1 serialization of the few field types used here: little-endian and unaligned
+struct Ros1(Vec<u8>);
+
+impl Ros1 {
+    fn with_header(stamp_ns: i64) -> Self {
+        let mut message = Ros1(Vec::new());
+        message.u32(0).u32((stamp_ns / 1_000_000_000) as u32).u32((stamp_ns % 1_000_000_000) as u32);
+        message.string("frame");
+        message
+    }
+
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.0.push(value);
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend(value.to_le_bytes());
+        self
+    }
+
+    fn f64s(&mut self, values: &[f64]) -> &mut Self {
+        for value in values {
+            self.0.extend(value.to_le_bytes());
+        }
+        self
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.u32(bytes.len() as u32);
+        self.0.extend(bytes);
+        self
+    }
+
+    fn string(&mut self, text: &str) -> &mut Self {
+        self.bytes(text.as_bytes())
+    }
+}
+
+fn raw_image(stamp_ns: i64, value: u8) -> Vec<u8> {
+    let mut message = Ros1::with_header(stamp_ns);
+    message.u32(2).u32(4).string("mono8").u8(0).u32(4).bytes(&[value; 8]);
+    message.0
+}
+
+fn compressed_image(stamp_ns: i64, value: u8) -> Vec<u8> {
+    let png = Image::new(vec![value; 8], 4, 2, ImageEncoding::Mono8, 0, 0).unwrap().to_png_bytes().unwrap();
+    let mut message = Ros1::with_header(stamp_ns);
+    message.string("png").bytes(&png);
+    message.0
+}
+
+fn camera_info(tx: f64) -> Vec<u8> {
+    let mut message = Ros1::with_header(0);
+    message.u32(2).u32(4).string("plumb_bob").u32(5).f64s(&[0.0; 5]);
+    message.f64s(&[3.0, 0.0, 2.0, 0.0, 3.0, 1.0, 0.0, 0.0, 1.0]);
+    message.f64s(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    message.f64s(&[3.0, 0.0, 2.0, tx, 0.0, 3.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    message.u32(1).u32(1).u32(0).u32(0).u32(0).u32(0).u8(0);
+    message.0
+}
+
+/// A bag record: header fields, each a length and `name=value`, then the data
+fn record(fields: &[(&str, &[u8])], data: &[u8]) -> Vec<u8> {
+    let mut header = Vec::new();
+    for (name, value) in fields {
+        header.extend(((name.len() + 1 + value.len()) as u32).to_le_bytes());
+        header.extend(name.as_bytes());
+        header.push(b'=');
+        header.extend(*value);
+    }
+    let mut record = (header.len() as u32).to_le_bytes().to_vec();
+    record.extend(header);
+    record.extend((data.len() as u32).to_le_bytes());
+    record.extend(data);
+    record
+}
+
+fn connection(id: u32, topic: &str, message_type: &str) -> Vec<u8> {
+    let data = record(&[("topic", topic.as_bytes()), ("type", message_type.as_bytes())], &[]);
+    // The connection header is a field list without the data part
+    let header_len = u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+    record(&[("op", &[0x07]), ("conn", &id.to_le_bytes()), ("topic", topic.as_bytes())], &data[4..4 + header_len])
+}
+
+fn message(connection: u32, stamp_ns: i64, data: &[u8]) -> Vec<u8> {
+    let time = ((stamp_ns / 1_000_000_000) as u64) | ((stamp_ns % 1_000_000_000) as u64) << 32;
+    record(&[("op", &[0x02]), ("conn", &connection.to_le_bytes()), ("time", &time.to_le_bytes())], data)
+}
+
+fn chunk(records: &[u8], lz4: bool) -> Vec<u8> {
+    let size = (records.len() as u32).to_le_bytes();
+    if !lz4 {
+        return record(&[("op", &[0x05]), ("compression", b"none"), ("size", &size)], records);
+    }
+    let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+    encoder.write_all(records).unwrap();
+    record(&[("op", &[0x05]), ("compression", b"lz4"), ("size", &size)], &encoder.finish().unwrap())
+}
+
+/// Write a bag whose first frame is in an uncompressed chunk and later frames in LZ4 chunks
+fn write_bag(path: &Path) {
+    let connections = [
+        connection(0, LEFT, "sensor_msgs/Image"),
+        connection(1, RIGHT, "sensor_msgs/CompressedImage"),
+        connection(2, "/stereo/left/camera_info", "sensor_msgs/CameraInfo"),
+        connection(3, "/stereo/right/camera_info", "sensor_msgs/CameraInfo"),
+        connection(4, "/odom", "nav_msgs/Odometry"),
+    ];
+
+    let mut chunks = Vec::new();
+    for (frame, &stamp_ns) in FRAME_STAMPS_NS.iter().enumerate() {
+        let mut records = if frame == 0 { connections.concat() } else { Vec::new() };
+        records.extend(message(2, stamp_ns, &camera_info(0.0)));
+        records.extend(message(3, stamp_ns, &camera_info(-3.0 * 0.1)));
+        records.extend(message(4, stamp_ns, &[0; 16]));
+        records.extend(message(0, stamp_ns, &raw_image(stamp_ns, 10 + frame as u8)));
+        // The right camera's stamp is a millisecond off
+        records.extend(message(1, stamp_ns, &compressed_image(stamp_ns + 1_000_000, 100 + frame as u8)));
+        if frame == 0 {
+            records.extend(message(0, UNPAIRED_STAMP_NS, &raw_image(UNPAIRED_STAMP_NS, 0)));
+        }
+        chunks.extend(chunk(&records, frame > 0));
+    }
+
+    let bag_header = |index_position: u64| {
+        let counts = [(connections.len() as u32).to_le_bytes(), (FRAME_STAMPS_NS.len() as u32).to_le_bytes()];
+        let fields: [(&str, &[u8]); 4] = [
+            ("op", &[0x03]),
+            ("index_pos", &index_position.to_le_bytes()),
+            ("conn_count", &counts[0]),
+            ("chunk_count", &counts[1]),
+        ];
+        record(&fields, &[])
+    };
+    let index_position = (b"#ROSBAG V2.0\n".len() + bag_header(0).len() + chunks.len()) as u64;
+    let mut bag = b"#ROSBAG V2.0\n".to_vec();
+    bag.extend(bag_header(index_position));
+    bag.extend(chunks);
+    bag.extend(connections.concat());
+    std::fs::write(path, bag).unwrap();
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("cuvslam-ros-bag-{}-{}.bag", name, std::process::id()))
+}
+
+#[test]
+fn test_stereo_pairs() {
+    let path = temp_path("pairs");
+    write_bag(&path);
+
+    let reader = RosBagReader::open(&path, [LEFT, RIGHT], 2_000_000).unwrap();
+    let calibration = reader.camera_calibration().expect("calibration from camera_info");
+    assert_eq!(calibration.cameras.len(), 2);
+    assert_eq!(reader.calibration().expect("rig").as_inner().num_cameras, 2);
+
+    let mut timestamps = Vec::new();
+    for pair in reader {
+        let pair = pair.unwrap();
+        let frame_index = timestamps.len() as u8;
+        assert_eq!((pair.left.width(), pair.left.height(), pair.left.camera_index()), (4, 2, 0));
+        assert_eq!((pair.right.camera_index(), pair.right.timestamp_ns()), (1, pair.timestamp_ns));
+        assert!(pair.left.pixels().iter().all(|&value| value == 10 + frame_index));
+        assert!(pair.right.pixels().iter().all(|&value| value == 100 + frame_index));
+        timestamps.push(pair.timestamp_ns);
+    }
+    assert_eq!(timestamps, FRAME_STAMPS_NS);
+
+    // Images more than a microsecond apart are not paired
+    assert_eq!(RosBagReader::open(&path, [LEFT, RIGHT], 1_000).unwrap().count(), 0);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_rejects_bad_topics_and_files() {
+    let path = temp_path("errors");
+    write_bag(&path);
+    assert!(RosBagReader::open(&path, [LEFT, "/missing"], 0).is_err());
+    assert!(RosBagReader::open(&path, [LEFT, "/odom"], 0).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    std::fs::write(&path, b"#ROSBAG V1.2\n").unwrap();
+    assert!(RosBagReader::open(&path, [LEFT, RIGHT], 0).is_err());
+    std::fs::remove_file(path).unwrap();
+}
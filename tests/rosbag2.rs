@@ -0,0 +1,224 @@
+//! Reading rosbag2 recordings written on the fly, in both sqlite3 and mcap storage
+//!
+//! Each bag holds three stereo frames of 4x2 images, raw `mono8` on the left and PNG compressed
+//! on the right, with `camera_info` for both cameras and an IMU topic. An extra left image with
+//! no right match checks that unpaired images are skipped.
+
+#![cfg(feature = "rosbag2")]
+
+use cuvslam::{FrameSource, Image, ImageEncoding, Rosbag2Source};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const LEFT: &str = "/stereo/left/image_raw";
+const RIGHT: &str = "/stereo/right/image_raw/compressed";
+const IMU: &str = "/imu";
+const FRAME_STAMPS_NS: [i64; 3] = [1_100_000_000, 1_200_000_000, 1_300_000_000];
+const UNPAIRED_STAMP_NS: i64 = 1_150_000_000;
+
+/// Little-endian CDR serialization of the few field types used here
+struct Cdr(Vec<u8>);
+
+impl Cdr {
+    fn with_header(stamp_ns: i64) -> Self {
+        let mut cdr = Cdr(vec![0, 1, 0, 0]);
+        cdr.u32((stamp_ns / 1_000_000_000) as u32).u32((stamp_ns % 1_000_000_000) as u32).string("frame");
+        cdr
+    }
+
+    fn align(&mut self, n: usize) {
+        self.0.resize(4 + (self.0.len() - 4).next_multiple_of(n), 0);
+    }
+
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.0.push(value);
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.align(4);
+        self.0.extend(value.to_le_bytes());
+        self
+    }
+
+    fn f64s(&mut self, values: &[f64]) -> &mut Self {
+        for value in values {
+            self.align(8);
+            self.0.extend(value.to_le_bytes());
+        }
+        self
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.u32(bytes.len() as u32);
+        self.0.extend(bytes);
+        self
+    }
+
+    fn string(&mut self, text: &str) -> &mut Self {
+        self.bytes(&[text.as_bytes(), &[0]].concat())
+    }
+}
+
+fn raw_image(stamp_ns: i64, value: u8) -> Vec<u8> {
+    let mut cdr = Cdr::with_header(stamp_ns);
+    cdr.u32(2).u32(4).string("mono8").u8(0).u32(4).bytes(&[value; 8]);
+    cdr.0
+}
+
+fn compressed_image(stamp_ns: i64, value: u8) -> Vec<u8> {
+    let png = Image::new(vec![value; 8], 4, 2, ImageEncoding::Mono8, 0, 0).unwrap().to_png_bytes().unwrap();
+    let mut cdr = Cdr::with_header(stamp_ns);
+    cdr.string("png").bytes(&png);
+    cdr.0
+}
+
+fn camera_info(tx: f64) -> Vec<u8> {
+    let mut cdr = Cdr::with_header(0);
+    cdr.u32(2).u32(4).string("plumb_bob").u32(5).f64s(&[0.0; 5]);
+    cdr.f64s(&[3.0, 0.0, 2.0, 0.0, 3.0, 1.0, 0.0, 0.0, 1.0]);
+    cdr.f64s(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    cdr.f64s(&[3.0, 0.0, 2.0, tx, 0.0, 3.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    cdr.u32(1).u32(1).u32(0).u32(0).u32(0).u32(0).u8(0);
+    cdr.0
+}
+
+fn imu(stamp_ns: i64) -> Vec<u8> {
+    let mut cdr = Cdr::with_header(stamp_ns);
+    cdr.f64s(&[0.0, 0.0, 0.0, 1.0]).f64s(&[0.0; 9]).f64s(&[0.0, 0.0, 0.5]).f64s(&[0.0; 9]);
+    cdr.f64s(&[0.0, 9.8, 0.0]).f64s(&[0.0; 9]);
+    cdr.0
+}
+
+/// Topic index, receive time and serialized message
+type Message = (usize, i64, Vec<u8>);
+
+/// Topics with their types, and messages in receive order
+fn recording() -> (Vec<(&'static str, &'static str)>, Vec<Message>) {
+    let topics = vec![
+        (LEFT, "sensor_msgs/msg/Image"),
+        (RIGHT, "sensor_msgs/msg/CompressedImage"),
+        ("/stereo/left/camera_info", "sensor_msgs/msg/CameraInfo"),
+        ("/stereo/right/camera_info", "sensor_msgs/msg/CameraInfo"),
+        (IMU, "sensor_msgs/msg/Imu"),
+    ];
+    let mut messages = vec![(4, 1_050_000_000, imu(1_050_000_000))];
+    for (frame, &stamp_ns) in FRAME_STAMPS_NS.iter().enumerate() {
+        let received_ns = stamp_ns + 5_000_000;
+        messages.push((2, received_ns, camera_info(0.0)));
+        messages.push((3, received_ns, camera_info(-3.0 * 0.1)));
+        messages.push((0, received_ns, raw_image(stamp_ns, 10 + frame as u8)));
+        // The right camera's stamp is a millisecond off
+        messages.push((1, received_ns + 1, compressed_image(stamp_ns + 1_000_000, 100 + frame as u8)));
+        messages.push((4, received_ns + 2, imu(stamp_ns + 50_000_000)));
+    }
+    messages.push((0, UNPAIRED_STAMP_NS + 5_000_000, raw_image(UNPAIRED_STAMP_NS, 0)));
+    messages.sort_by_key(|(_, received_ns, _)| *received_ns);
+    (topics, messages)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cuvslam-rosbag2-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write the recording as a bag directory with `sqlite3` storage and a `metadata.yaml`
+fn write_sqlite_bag(dir: &Path) {
+    let (topics, messages) = recording();
+    let connection = rusqlite::Connection::open(dir.join("bag_0.db3")).unwrap();
+    connection
+        .execute_batch(
+            "CREATE TABLE topics(id INTEGER PRIMARY KEY, name TEXT NOT NULL, type TEXT NOT NULL, \
+             serialization_format TEXT NOT NULL, offered_qos_profiles TEXT NOT NULL); \
+             CREATE TABLE messages(id INTEGER PRIMARY KEY, topic_id INTEGER NOT NULL, \
+             timestamp INTEGER NOT NULL, data BLOB NOT NULL);",
+        )
+        .unwrap();
+    for (id, (name, message_type)) in topics.iter().enumerate() {
+        connection
+            .execute("INSERT INTO topics VALUES (?1, ?2, ?3, 'cdr', '')", rusqlite::params![id, name, message_type])
+            .unwrap();
+    }
+    for (topic, received_ns, data) in messages {
+        connection
+            .execute(
+                "INSERT INTO messages (topic_id, timestamp, data) VALUES (?1, ?2, ?3)",
+                rusqlite::params![topic, received_ns, data],
+            )
+            .unwrap();
+    }
+    std::fs::write(
+        dir.join("metadata.yaml"),
+        "rosbag2_bagfile_information:\n  storage_identifier: sqlite3\n  relative_file_paths:\n    - bag_0.db3\n",
+    )
+    .unwrap();
+}
+
+/// Write the recording as a single `mcap` file, with small chunks so it spans several
+fn write_mcap_bag(path: &Path) {
+    let (topics, messages) = recording();
+    let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    let mut writer = mcap::WriteOptions::new().chunk_size(Some(256)).create(file).unwrap();
+    let channels: Vec<u16> = topics
+        .iter()
+        .map(|(name, message_type)| {
+            let schema = writer.add_schema(message_type, "ros2msg", b"").unwrap();
+            writer.add_channel(schema, name, "cdr", &BTreeMap::new()).unwrap()
+        })
+        .collect();
+    for (sequence, (topic, received_ns, data)) in messages.into_iter().enumerate() {
+        let header = mcap::records::MessageHeader {
+            channel_id: channels[topic],
+            sequence: sequence as u32,
+            log_time: received_ns as u64,
+            publish_time: received_ns as u64,
+        };
+        writer.write_to_known_channel(&header, &data).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+fn check_bag(path: &Path) {
+    let mut source = Rosbag2Source::open(path, [LEFT, RIGHT], 2_000_000).unwrap().with_imu_topic(IMU).unwrap();
+    let rig = source.calibration().expect("calibration from camera_info");
+    assert_eq!(rig.as_inner().num_cameras, 2);
+
+    let mut imu_counts = Vec::new();
+    let mut timestamps = Vec::new();
+    while let Some(frame) = source.next_frame() {
+        let frame = frame.unwrap();
+        let frame_index = timestamps.len() as u8;
+        let [left, right] = &frame.images[..] else { panic!("expected two images") };
+        assert_eq!((left.width(), left.height(), left.camera_index()), (4, 2, 0));
+        assert_eq!((right.camera_index(), right.timestamp_ns()), (1, frame.timestamp_ns));
+        assert!(left.pixels().iter().all(|&value| value == 10 + frame_index));
+        assert!(right.pixels().iter().all(|&value| value == 100 + frame_index));
+        timestamps.push(frame.timestamp_ns);
+        imu_counts.push(source.drain_imu().len());
+    }
+    assert_eq!(timestamps, FRAME_STAMPS_NS);
+    // The first frame gets the sample recorded before it, later ones the sample between frames
+    assert_eq!(imu_counts, [1, 1, 1]);
+
+    assert!(Rosbag2Source::open(path, [LEFT, "/missing"], 0).is_err());
+    assert!(Rosbag2Source::open(path, [LEFT, RIGHT], 0).unwrap().with_imu_topic(LEFT).is_err());
+}
+
+#[test]
+fn test_sqlite_bag() {
+    let dir = temp_dir("sqlite");
+    write_sqlite_bag(&dir);
+    check_bag(&dir);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_mcap_bag() {
+    let dir = temp_dir("mcap");
+    let path = dir.join("bag_0.mcap");
+    write_mcap_bag(&path);
+    check_bag(&path);
+    std::fs::remove_dir_all(dir).unwrap();
+}
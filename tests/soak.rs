@@ -0,0 +1,21 @@
+mod support;
+
+use cuvslam::stress::create_destroy_cycles;
+
+const MIB: u64 = 1024 * 1024;
+
+#[test]
+#[ignore = "needs a CUDA device and takes a while"]
+fn test_create_destroy_does_not_leak() {
+    let report = create_destroy_cycles(100, || support::stereo_rig(640, 480), &cuvslam::init_default_configuration())
+        .expect("tracker creation failed");
+
+    println!(
+        "rss growth: {:?} bytes, gpu growth: {:?} bytes, mean cycle: {:?}, slowest cycle: {:?}",
+        report.rss_growth_bytes(),
+        report.gpu_growth_bytes(),
+        report.mean_cycle_time(),
+        report.cycle_times.iter().max()
+    );
+    assert!(report.passed(16 * MIB, 16 * MIB), "{:?}", report);
+}
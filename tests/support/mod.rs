@@ -0,0 +1,7 @@
+//! Synthetic inputs shared by integration tests and benchmarks
+//!
+//! The generators live in the crate, where `self_test` and `SyntheticSource` use them too.
+
+#![allow(unused_imports)]
+
+pub use cuvslam::self_test::{stereo_frame, stereo_rig, textured_frame, BASELINE};
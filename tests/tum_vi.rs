@@ -0,0 +1,170 @@
+//! TUM-VI reader and player on a generated 20-frame excerpt
+//!
+//! Like the EuRoC fixture, it is written to a temporary directory: 16-bit textured frames with a
+//! Kalibr fisheye calibration, 200 Hz IMU and mocap ground truth of a marker offset from the IMU.
+
+#![cfg(feature = "datasets")]
+
+mod support;
+
+use cuvslam::datasets::tum_vi;
+use cuvslam::{absolute_trajectory_error, DistortionModel, PoseEstimate, Tracker};
+use std::path::{Path, PathBuf};
+
+const SIZE: i32 = 512;
+const FRAMES: i64 = 20;
+const FRAME_PERIOD_NS: i64 = 50_000_000;
+const IMU_PERIOD_NS: i64 = 5_000_000;
+const START_NS: i64 = 1_520_530_308_199_447_626;
+
+/// Write the fixture sequence under a fresh temporary directory, returning the sequence directory
+fn write_fixture(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cuvslam-tum-vi-{}-{}", name, std::process::id()));
+    let mav0 = dir.join("mav0");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut camchain = String::new();
+    for (index, (camera, disparity)) in [("cam0", 0), ("cam1", 8)].into_iter().enumerate() {
+        let camera_dir = mav0.join(camera);
+        std::fs::create_dir_all(camera_dir.join("data")).unwrap();
+        let mut csv = String::from("#timestamp [ns],filename\n");
+        for i in 0..FRAMES {
+            let timestamp_ns = START_NS + i * FRAME_PERIOD_NS;
+            let pixels = support::textured_frame(SIZE, SIZE, i as i32 + disparity);
+            let pixels = pixels.into_iter().map(|value| u16::from(value) * 257).collect();
+            image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(SIZE as u32, SIZE as u32, pixels)
+                .unwrap()
+                .save(camera_dir.join("data").join(format!("{}.png", timestamp_ns)))
+                .unwrap();
+            csv += &format!("{},{}.png\n", timestamp_ns, timestamp_ns);
+        }
+        std::fs::write(camera_dir.join("data.csv"), csv).unwrap();
+
+        // Both cameras look along the IMU's z axis, 5cm ahead of it
+        camchain += &format!(
+            "{}:
+  T_cam_imu:
+  - [1.0, 0.0, 0.0, {}]
+  - [0.0, 1.0, 0.0, 0.0]
+  - [0.0, 0.0, 1.0, 0.05]
+  - [0.0, 0.0, 0.0, 1.0]
+  camera_model: pinhole
+  distortion_coeffs: [0.0, 0.0, 0.0, 0.0]
+  distortion_model: equidistant
+  intrinsics: [{}, {}, {}, {}]
+  resolution: [{}, {}]
+  rostopic: /{}/image_raw
+",
+            camera,
+            -(index as f32) * support::BASELINE,
+            SIZE as f32 * 0.37,
+            SIZE as f32 * 0.37,
+            SIZE as f32 / 2.0,
+            SIZE as f32 / 2.0,
+            SIZE,
+            SIZE,
+            camera
+        );
+    }
+    std::fs::create_dir_all(dir.join("dso")).unwrap();
+    std::fs::write(dir.join("dso/camchain.yaml"), camchain).unwrap();
+    std::fs::write(
+        dir.join("dso/imu_config.yaml"),
+        "accelerometer_noise_density: 0.0028
+accelerometer_random_walk: 0.00086
+gyroscope_noise_density: 0.00016
+gyroscope_random_walk: 2.2e-05
+rostopic: /imu0
+update_rate: 200.0
+T_imu_marker:
+- [1.0, 0.0, 0.0, 0.0]
+- [0.0, 1.0, 0.0, 0.0]
+- [0.0, 0.0, 1.0, 0.1]
+- [0.0, 0.0, 0.0, 1.0]
+",
+    )
+    .unwrap();
+
+    let imu_dir = mav0.join("imu0");
+    let mocap_dir = mav0.join("mocap0");
+    std::fs::create_dir_all(&imu_dir).unwrap();
+    std::fs::create_dir_all(&mocap_dir).unwrap();
+    let samples = (FRAMES - 1) * FRAME_PERIOD_NS / IMU_PERIOD_NS + 1;
+    let mut imu = String::from("#timestamp [ns],w_RS_S_x [rad s^-1],w_RS_S_y [rad s^-1],w_RS_S_z [rad s^-1],");
+    imu += "a_RS_S_x [m s^-2],a_RS_S_y [m s^-2],a_RS_S_z [m s^-2]\n";
+    let mut mocap = String::from("#timestamp [ns],p_RS_R_x [m],p_RS_R_y [m],p_RS_R_z [m],");
+    mocap += "q_RS_w [],q_RS_x [],q_RS_y [],q_RS_z []\n";
+    for i in 0..samples {
+        let timestamp_ns = START_NS + i * IMU_PERIOD_NS;
+        imu += &format!("{},0.0,0.0,0.0,0.0,0.0,9.81\n", timestamp_ns);
+        mocap += &format!("{},{},0.0,1.0,1.0,0.0,0.0,0.0\n", timestamp_ns, i as f64 / 1000.0);
+    }
+    std::fs::write(imu_dir.join("data.csv"), imu).unwrap();
+    std::fs::write(mocap_dir.join("data.csv"), mocap).unwrap();
+    dir
+}
+
+fn remove_fixture(dir: &Path) {
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_open_fixture_sequence() {
+    let dir = write_fixture("open");
+    let sequence = tum_vi::open(&dir).unwrap();
+
+    assert_eq!(sequence.frames.len(), FRAMES as usize);
+    assert_eq!(sequence.imu.len(), 191);
+    assert_eq!(sequence.ground_truth.len(), 191);
+    // The marker is 10cm above the IMU, which the ground truth follows instead
+    let [x, y, z] = sequence.ground_truth[10].1;
+    assert_eq!((x, y), (0.01f32 as f64, 0.0));
+    assert!((z - 0.9).abs() < 1e-6, "{}", z);
+
+    let cameras = &sequence.calibration.cameras;
+    assert_eq!(cameras[0].model, DistortionModel::Fisheye4);
+    assert_eq!(cameras[0].extrinsics.translation, [0.0; 3]);
+    assert_eq!(cameras[1].extrinsics.translation, [support::BASELINE, 0.0, 0.0]);
+    assert_eq!(sequence.rig_in_body.t, [0.0, 0.0, -0.05]);
+    let imu_calibration = sequence.imu_calibration.unwrap();
+    // The cameras' OpenCV axes become cuVSLAM's, flipping y and z
+    assert_eq!(imu_calibration.left_from_imu.r, [1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0]);
+    assert_eq!(imu_calibration.left_from_imu.t, [0.0, 0.0, -0.05]);
+    assert_eq!(imu_calibration.frequency, 200.0);
+
+    let rig = sequence.calibration.to_rig().unwrap();
+    assert_eq!(cuvslam::validate_rig(&rig), Ok(()));
+    assert_eq!(cuvslam::validate_configuration(&rig, &sequence.configuration(true)), Ok(()));
+
+    // 16-bit frames are scaled back to the 8-bit texture
+    let images = sequence.frames[0].load().unwrap();
+    assert_eq!((images[0].width(), images[0].height()), (SIZE, SIZE));
+    assert_eq!(images[0].pixels(), support::textured_frame(SIZE, SIZE, 0));
+    remove_fixture(&dir);
+}
+
+#[test]
+#[ignore = "needs a CUDA device"]
+fn test_track_fixture_sequence() {
+    let dir = write_fixture("track");
+    let sequence = tum_vi::open(&dir).unwrap();
+    let tracker = Tracker::new(sequence.calibration.to_rig().unwrap(), &sequence.configuration(false)).unwrap();
+
+    let estimates: Vec<PoseEstimate> = sequence
+        .frames
+        .iter()
+        .filter_map(|frame| {
+            let images = frame.load().unwrap();
+            let estimate = tracker.track(&images, None).ok()?;
+            Some(PoseEstimate { pose: sequence.body_pose(&estimate.pose), ..estimate })
+        })
+        .collect();
+    assert!(estimates.len() >= FRAMES as usize / 2, "only {} frames tracked", estimates.len());
+
+    let positions: Vec<_> =
+        estimates.iter().map(|estimate| (estimate.timestamp_ns, estimate.pose.t.map(f64::from))).collect();
+    let ate = absolute_trajectory_error(&positions, &sequence.ground_truth, IMU_PERIOD_NS).unwrap();
+    println!("{:?}", ate);
+    assert!(ate.rmse_m.is_finite() && ate.max_m.is_finite());
+    remove_fixture(&dir);
+}
@@ -0,0 +1,68 @@
+//! Video decoding on `data/side_by_side.y4m`
+//!
+//! The clip is five 16x8 grayscale frames at 10 fps. The left half of frame `i` is filled with
+//! `10 + i` and the right half with `200 - i`, so each camera's image can be told apart.
+
+#![cfg(feature = "video")]
+
+use cuvslam::{FrameSet, FrameSource, VideoLayout, VideoSource};
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn clip() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/side_by_side.y4m")
+}
+
+fn frames(source: &mut VideoSource) -> Vec<FrameSet> {
+    std::iter::from_fn(|| source.next_frame()).map(Result::unwrap).collect()
+}
+
+#[test]
+fn test_decode_side_by_side_clip() {
+    let mut source = VideoSource::open(clip(), VideoLayout::SideBySide).unwrap();
+    assert!(source.calibration().is_none());
+    let frames = frames(&mut source);
+    assert_eq!(frames.len(), 5);
+
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.timestamp_ns, i as i64 * 100_000_000);
+        let [left, right] = &frame.images[..] else {
+            panic!("expected two images");
+        };
+        assert_eq!((left.width(), left.height(), left.camera_index()), (8, 8, 0));
+        assert_eq!((right.width(), right.height(), right.camera_index()), (8, 8, 1));
+        assert_eq!(right.timestamp_ns(), frame.timestamp_ns);
+        assert!(left.pixels().iter().all(|&value| value == 10 + i as u8));
+        assert!(right.pixels().iter().all(|&value| value == 200 - i as u8));
+    }
+}
+
+#[test]
+fn test_seek_and_max_frames() {
+    let mut single = VideoSource::open(clip(), VideoLayout::Single).unwrap().with_max_frames(2);
+    let frames_read = frames(&mut single);
+    assert_eq!(frames_read.len(), 2);
+    assert_eq!(frames_read[0].images.len(), 1);
+    assert_eq!(frames_read[0].images[0].width(), 16);
+
+    let mut source = VideoSource::open(clip(), VideoLayout::SideBySide).unwrap();
+    source.seek(Duration::from_millis(250)).unwrap();
+    let timestamps: Vec<i64> = frames(&mut source).iter().map(|frame| frame.timestamp_ns).collect();
+    assert_eq!(timestamps, [300_000_000, 400_000_000]);
+}
+
+#[test]
+fn test_fixed_frame_rate_timestamps() {
+    let source = VideoSource::open(clip(), VideoLayout::Single).unwrap();
+    assert_eq!(source.frame_rate(), Some(10.0));
+
+    let mut source = source.with_frame_rate(20.0);
+    let timestamps: Vec<i64> = frames(&mut source).iter().map(|frame| frame.timestamp_ns).collect();
+    assert_eq!(timestamps, [0, 50_000_000, 100_000_000, 150_000_000, 200_000_000]);
+
+    // Frame numbers carry on from where a seek lands
+    let mut source = VideoSource::open(clip(), VideoLayout::Single).unwrap().with_frame_rate(20.0);
+    source.seek(Duration::from_millis(250)).unwrap();
+    let timestamps: Vec<i64> = frames(&mut source).iter().map(|frame| frame.timestamp_ns).collect();
+    assert_eq!(timestamps, [150_000_000, 200_000_000]);
+}